@@ -0,0 +1,71 @@
+//! Time a large import into a SQLite log
+//!
+//! Run with `cargo bench --bench sqlite_import`; `AMLOG_BENCH_QSOS` sets
+//! the number of QSOs (50,000 by default).
+
+use std::time::{Duration, Instant};
+
+use amlog::storage::{AdifStorage, SqliteStorage, Storage, StorageFormat, StorageManager};
+use amlog::app::Frequency;
+use amlog::LogEntry;
+use chrono::Utc;
+
+const MODES: [&str; 4] = ["FT8", "CW", "SSB", "FT4"];
+const BANDS: [(&str, i64); 4] = [("20m", 14_074_000), ("40m", 7_030_000), ("15m", 21_300_000), ("10m", 28_074_000)];
+
+fn qsos(count: usize) -> Vec<LogEntry> {
+    let start = Utc::now();
+    (0..count)
+        .map(|i| {
+            let (band, frequency) = BANDS[i % BANDS.len()];
+            LogEntry {
+                id: uuid::Uuid::new_v4().to_string(),
+                timestamp: start - chrono::Duration::minutes(i as i64),
+                callsign: format!("W{}A{}", i % 10, i).into(),
+                frequency: Frequency::from_hz(frequency),
+                band: Some(band.into()),
+                mode: MODES[i % MODES.len()].into(),
+                rst_sent: Some("599".into()),
+                rst_received: Some("579".into()),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+fn report(what: &str, count: usize, elapsed: Duration) {
+    println!(
+        "{:<32} {:>8} QSOs {:>10.2?} {:>10.0} QSOs/s",
+        what,
+        count,
+        elapsed,
+        count as f64 / elapsed.as_secs_f64()
+    );
+}
+
+#[tokio::main]
+async fn main() {
+    let count = std::env::var("AMLOG_BENCH_QSOS").ok().and_then(|v| v.parse().ok()).unwrap_or(50_000);
+    let entries = qsos(count);
+    let dir = tempfile::tempdir().unwrap();
+
+    let mut storage = SqliteStorage::new(&dir.path().join("one-by-one.db")).await.unwrap();
+    let started = Instant::now();
+    for entry in entries.clone() {
+        storage.save_entry(entry).await.unwrap();
+    }
+    report("save_entry, one at a time", count, started.elapsed());
+
+    let mut storage = SqliteStorage::new(&dir.path().join("batch.db")).await.unwrap();
+    let started = Instant::now();
+    storage.save_entries(entries.clone()).await.unwrap();
+    report("save_entries, one transaction", count, started.elapsed());
+
+    // What importing a file costs, journal and revisions included
+    let adif = AdifStorage::entries_to_adif(&entries);
+    let manager = StorageManager::new(StorageFormat::Sqlite, dir.path().join("import.db")).await.unwrap();
+    let started = Instant::now();
+    manager.import_adif(&adif).await.unwrap();
+    report("StorageManager::import_adif", count, started.elapsed());
+    assert_eq!(manager.list_entries().await.unwrap().len(), count);
+}
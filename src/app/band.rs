@@ -0,0 +1,55 @@
+// src/app/band.rs
+
+/// ADIF band names with their frequency limits in MHz
+pub const BANDS: &[(&str, f64, f64)] = &[
+    ("2190m", 0.1357, 0.1378),
+    ("630m", 0.472, 0.479),
+    ("160m", 1.8, 2.0),
+    ("80m", 3.5, 4.0),
+    ("60m", 5.06, 5.45),
+    ("40m", 7.0, 7.3),
+    ("30m", 10.1, 10.15),
+    ("20m", 14.0, 14.35),
+    ("17m", 18.068, 18.168),
+    ("15m", 21.0, 21.45),
+    ("12m", 24.89, 24.99),
+    ("10m", 28.0, 29.7),
+    ("6m", 50.0, 54.0),
+    ("4m", 70.0, 71.0),
+    ("2m", 144.0, 148.0),
+    ("1.25m", 222.0, 225.0),
+    ("70cm", 420.0, 450.0),
+    ("33cm", 902.0, 928.0),
+    ("23cm", 1240.0, 1300.0),
+    ("13cm", 2300.0, 2450.0),
+    ("9cm", 3300.0, 3500.0),
+    ("6cm", 5650.0, 5925.0),
+    ("3cm", 10000.0, 10500.0),
+];
+
+/// ADIF band name for a frequency in MHz
+pub fn band_for_frequency(mhz: f64) -> Option<&'static str> {
+    BANDS
+        .iter()
+        .find(|(_, lower, upper)| (*lower..=*upper).contains(&mhz))
+        .map(|(name, _, _)| *name)
+}
+
+/// Whether two band names refer to the same band, ignoring case
+pub fn same_band(a: &str, b: &str) -> bool {
+    a.trim().eq_ignore_ascii_case(b.trim())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_band_for_frequency() {
+        assert_eq!(band_for_frequency(14.074), Some("20m"));
+        assert_eq!(band_for_frequency(1.84), Some("160m"));
+        assert_eq!(band_for_frequency(435.8), Some("70cm"));
+        assert_eq!(band_for_frequency(15.0), None);
+        assert!(same_band("20M", "20m"));
+    }
+}
@@ -6,57 +6,63 @@ pub struct FormField {
     pub cursor_position: usize,
 }
 
+impl FormField {
+    fn new(label: &str, required: bool) -> Self {
+        FormField {
+            label: label.to_string(),
+            value: String::new(),
+            required,
+            cursor_position: 0,
+        }
+    }
+}
+
 pub struct Form {
     pub fields: Vec<FormField>,
     pub current_field: usize,
 }
 
+impl Default for Form {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Form {
     pub fn new() -> Self {
-        let fields = vec![
-            FormField {
-                label: "Callsign".to_string(),
-                value: String::new(),
-                required: true,
-                cursor_position: 0,
-            },
-            FormField {
-                label: "Frequency".to_string(),
-                value: String::new(),
-                required: true,
-                cursor_position: 0,
-            },
-            FormField {
-                label: "Mode".to_string(),
-                value: String::new(),
-                required: true,
-                cursor_position: 0,
-            },
-            FormField {
-                label: "RST Sent".to_string(),
-                value: String::new(),
-                required: false,
-                cursor_position: 0,
-            },
-            FormField {
-                label: "RST Rcvd".to_string(),
-                value: String::new(),
-                required: false,
-                cursor_position: 0,
-            },
-            FormField {
-                label: "Notes".to_string(),
-                value: String::new(),
-                required: false,
-                cursor_position: 0,
-            },
-        ];
+        Self::with_fields(&[
+            ("Callsign", true),
+            ("Frequency", true),
+            ("Mode", true),
+            ("RST Sent", false),
+            ("RST Rcvd", false),
+            ("Power", false),
+            ("Name", false),
+            ("QTH", false),
+            ("State", false),
+            ("Country", false),
+            ("Grid", false),
+            ("Park", false),
+            ("Summit", false),
+            ("Satellite", false),
+            ("RX Freq", false),
+            ("Notes", false),
+        ])
+    }
+
+    /// Build a form from `(label, required)` pairs
+    pub fn with_fields(fields: &[(&str, bool)]) -> Self {
         Form {
-            fields,
+            fields: fields.iter().map(|(label, required)| FormField::new(label, *required)).collect(),
             current_field: 0,
         }
     }
 
+    /// Add a field after the others
+    pub fn push_field(&mut self, label: &str, required: bool) {
+        self.fields.push(FormField::new(label, required));
+    }
+
     pub fn reset(&mut self) {
         for field in &mut self.fields {
             field.value.clear();
@@ -65,6 +71,40 @@ impl Form {
         self.current_field = 0;
     }
 
+    /// Get the current value of the field with the given label
+    pub fn value(&self, label: &str) -> &str {
+        self.fields
+            .iter()
+            .find(|field| field.label == label)
+            .map(|field| field.value.as_str())
+            .unwrap_or("")
+    }
+
+    /// Get the trimmed value of a field, or `None` if it is empty
+    pub fn optional(&self, label: &str) -> Option<String> {
+        let value = self.value(label).trim();
+        if value.is_empty() {
+            None
+        } else {
+            Some(value.to_string())
+        }
+    }
+
+    /// Replace the value of the field with the given label
+    pub fn set_value(&mut self, label: &str, value: &str) {
+        if let Some(field) = self.fields.iter_mut().find(|field| field.label == label) {
+            field.value = value.to_string();
+            field.cursor_position = field.value.len();
+        }
+    }
+
+    /// Move the cursor to the field with the given label
+    pub fn focus(&mut self, label: &str) {
+        if let Some(index) = self.fields.iter().position(|field| field.label == label) {
+            self.current_field = index;
+        }
+    }
+
     pub fn next_field(&mut self) {
         self.current_field = (self.current_field + 1) % self.fields.len();
     }
@@ -115,4 +155,24 @@ mod tests {
 
         assert!(form.is_valid());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_form_values_by_label() {
+        let mut form = Form::new();
+        form.set_value("Grid", "FN31pr");
+        assert_eq!(form.value("Grid"), "FN31pr");
+        assert_eq!(form.optional("Grid").as_deref(), Some("FN31pr"));
+        assert_eq!(form.optional("Name"), None);
+        assert_eq!(form.value("No such field"), "");
+    }
+
+    #[test]
+    fn test_custom_form_fields() {
+        let mut form = Form::with_fields(&[("Callsign", true), ("Nr", false)]);
+        form.focus("Nr");
+        form.input('5');
+        assert_eq!(form.value("Nr"), "5");
+        form.set_value("Callsign", "W1AW");
+        assert!(form.is_valid());
+    }
+}
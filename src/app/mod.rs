@@ -1,20 +1,122 @@
 // src/app/mod.rs
+pub mod band;
 mod form;
+pub mod mode;
+pub mod qsl;
 mod state;
+pub mod values;
 
 pub use form::{Form, FormField};
-pub use state::{AppMode, LogEntry, DeletedEntry};
-use chrono::{Utc, DateTime};
-use std::path::PathBuf;
+pub use qsl::{QslConfirmation, QslFilter, QslStatus, QslVia};
+pub use state::{AppMode, LogEntry, Prompt, PromptAction};
+pub use values::{Band, Callsign, Frequency, Mode, Power, Rst, ValueError};
+use chrono::{NaiveDate, Utc};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tokio::runtime::Runtime;
 use uuid::Uuid;
-use std::env;
 use std::fs;
 use dirs;
 
-use crate::storage::{StorageManager, StorageFormat, StorageError};
+use crate::awards::{self, AwardDefinition, AwardProgress};
+use crate::app::mode::ModeCategory;
+use crate::contest::{self, ContestDefinition, ContestSession, ContestState};
+use crate::callbook::{CachedCallbook, CallbookConfig, CallbookRecord};
+use crate::portable::pota::{self, ParkList};
+use crate::portable::sota::{self, SummitList};
+use crate::portable::Activation;
+use crate::satellite::{self, Satellite};
+use crate::storage::{
+    Backup, BackupConfig, BackupReason, FieldDefinition, FsckReport, JournalRecord, Revision, SearchHit,
+    StorageManager, StorageFormat, StorageError,
+};
+use crate::storage::formats::adif::CONTEST_EXCHANGE_FIELDS;
+use crate::storage::{journal, mirror};
+use crate::sync::{self, SyncConfig, SyncEvent, SyncHandle, SyncOp};
+use crate::storage::reconcile::{ConfirmationSource, ReconcileReport};
 
-use std::collections::HashMap;
+/// Saved POTA/SOTA activation in the data directory
+const ACTIVATION_FILE: &str = "activation.json";
+
+/// How often to check the log file for writes by other programs
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The amlog directory under XDG_DATA_HOME, created if missing
+pub fn data_dir() -> Result<PathBuf, StorageError> {
+    // Get the XDG data directory
+    let xdg_data_home = dirs::data_dir()
+        .ok_or_else(|| StorageError::Backend("Failed to get XDG_DATA_HOME directory".to_string()))?;
+
+    // Create the application-specific data directory under XDG_DATA_HOME
+    let app_data_dir = xdg_data_home.join("amlog");
+
+    // Ensure the directory exists, creating it if necessary
+    fs::create_dir_all(&app_data_dir)
+        .map_err(|e| StorageError::Backend(format!("Failed to create data directory: {}", e)))?;
+    Ok(app_data_dir)
+}
+
+/// Path of the log chosen with `AMLOG_STORAGE` in the data directory
+pub fn log_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(StorageFormat::from_env().file_name())
+}
+
+/// Open the log chosen with `AMLOG_STORAGE` in the data directory, with
+/// backups set up from the environment
+pub async fn open_log(data_dir: &Path) -> Result<StorageManager, StorageError> {
+    let format = StorageFormat::from_env();
+    let path = log_path(data_dir);
+    let manager = StorageManager::new(format, path.clone()).await?.with_backups(BackupConfig::from_env(&path));
+    for (format, path) in mirror::from_env(data_dir)? {
+        manager.add_mirror(format, path).await?;
+    }
+    Ok(manager)
+}
+
+/// Custom fields something reads: contest exchanges, awards and the
+/// exchange fields written to ADIF
+fn custom_field_names(contests: &[ContestDefinition], awards: &[AwardDefinition]) -> Vec<String> {
+    let mut names: BTreeSet<String> = CONTEST_EXCHANGE_FIELDS.iter().map(|name| name.to_string()).collect();
+    for contest in contests {
+        names.extend(contest.exchange.iter().chain(&contest.sent).map(|field| field.field.clone()));
+    }
+    names.extend(awards.iter().filter_map(|award| award.key.strip_prefix("custom:").map(str::to_string)));
+    names.into_iter().collect()
+}
+
+/// Checked values from the QSO form
+struct FormValues {
+    callsign: Callsign,
+    frequency: Frequency,
+    mode: Mode,
+    rst_sent: Option<Rst>,
+    rst_received: Option<Rst>,
+    power: Option<Power>,
+    freq_rx: Option<Frequency>,
+}
+
+/// The QSO form, with a field for each custom field defined for the log
+/// below the usual ones
+fn entry_form(definitions: &[FieldDefinition]) -> Form {
+    let mut form = Form::new();
+    for definition in definitions {
+        // A definition named like a usual field is left to that field
+        if form.fields.iter().all(|field| !field.label.eq_ignore_ascii_case(&definition.name)) {
+            form.push_field(&definition.name, false);
+        }
+    }
+    form
+}
+
+/// Custom fields in use with the definitions in the data directory
+pub fn known_custom_fields(data_dir: &Path) -> Vec<String> {
+    let contests = contest::load_definitions(&data_dir.join("contests"))
+        .unwrap_or_else(|_| contest::builtin_definitions());
+    let awards = awards::load_definitions(&data_dir.join("awards.json"))
+        .unwrap_or_else(|_| awards::builtin_definitions());
+    custom_field_names(&contests, &awards)
+}
 
 /// Main application state container
 pub struct App {
@@ -25,8 +127,59 @@ pub struct App {
     storage_manager: StorageManager,
     runtime: Runtime,
     selected_index: Option<usize>,
-    pub deleted_entries: Vec<DeletedEntry>,
     editing_index: Option<usize>, // Track the index of the entry being edited
+    callbook: Option<CachedCallbook>,
+    pub callbook_result: Option<CallbookRecord>,
+    // Earlier versions of the entry shown in the detail view
+    pub revisions: Vec<Revision>,
+    pub revision_cursor: Option<usize>,
+    data_dir: PathBuf,
+    // QSL management view state
+    pub qsl_filter: QslFilter,
+    pub qsl_selected: HashSet<String>,
+    pub qsl_date: NaiveDate,
+    pub qsl_via: QslVia,
+    qsl_cursor: Option<usize>,
+    pub prompt: Option<Prompt>,
+    pub reconcile_result: Option<(ConfirmationSource, ReconcileReport)>,
+    // Awards view state
+    award_definitions: Vec<AwardDefinition>,
+    award_index: usize,
+    pub award_row: usize,
+    pub award_column: usize,
+    pub award_drill_down: bool,
+    // Contest operating
+    contest_definitions: Vec<ContestDefinition>,
+    pending_contest: Option<ContestDefinition>,
+    pub contest: Option<ContestSession>,
+    pub contest_form: Form,
+    // Multi-operator sync
+    station: Option<String>,
+    sync: Option<SyncHandle>,
+    sync_peers: BTreeSet<String>,
+    last_refresh: Instant,
+    // Portable activations
+    parks: ParkList,
+    summits: SummitList,
+    pub activation: Option<Activation>,
+    satellites: Vec<Satellite>,
+    // Operation journal view
+    pub history: Vec<JournalRecord>,
+    pub history_cursor: usize,
+    // Backup view
+    pub backups: Vec<Backup>,
+    pub backup_cursor: usize,
+    // Integrity check view
+    pub fsck_report: Option<FsckReport>,
+    pub fsck_cursor: usize,
+    // Full-text search view
+    pub search_query: String,
+    pub search_results: Vec<SearchHit>,
+    pub search_cursor: usize,
+    // Last mirror problem reported, so it is not repeated every poll
+    mirror_alert: Option<String>,
+    // Typed custom fields on the QSO form
+    field_definitions: Vec<FieldDefinition>,
 }
 
 impl App {
@@ -34,79 +187,247 @@ impl App {
         let runtime = Runtime::new()
             .map_err(|e| StorageError::Backend(format!("Failed to create runtime: {}", e)))?;
 
-        // Get the XDG data directory
-        let xdg_data_home = dirs::data_dir()
-            .ok_or_else(|| StorageError::Backend("Failed to get XDG_DATA_HOME directory".to_string()))?;
+        let app_data_dir = data_dir()?;
+        let storage_manager = runtime.block_on(open_log(&app_data_dir))?;
+        println!("Database path: {:?}", storage_manager.get_path());
+
+        // The terminal is already in raw mode, so anything worth saying
+        // waits for the status bar; problems are listed before good news
+        let mut notices: Vec<(String, bool)> = runtime.block_on(storage_manager.warnings())
+            .into_iter()
+            .map(|warning| (warning, true))
+            .collect();
+
+        if let Err(e) = runtime.block_on(storage_manager.backup(BackupReason::Startup)) {
+            notices.push((format!("Startup backup failed: {}", e), true));
+        }
+        match runtime.block_on(storage_manager.resync_mirrors()) {
+            Ok(0) => {}
+            Ok(count) => notices.push((format!("Brought mirrors up to date: {} QSOs written", count), false)),
+            Err(e) => notices.push((format!("Failed to check mirrors: {}", e), true)),
+        }
+
+        let callbook = CallbookConfig::from_env().and_then(|config| {
+            runtime.block_on(CachedCallbook::from_config(&config, &app_data_dir.join("callbook.db")))
+                .map_err(|e| notices.push((format!("Callbook lookups disabled: {}", e), true)))
+                .ok()
+        });
 
-        // Create the application-specific data directory under XDG_DATA_HOME
-        let app_data_dir = xdg_data_home.join("amlog");
+        let award_definitions = awards::load_definitions(&app_data_dir.join("awards.json"))
+            .unwrap_or_else(|e| {
+                notices.push((format!("Failed to load award definitions: {}. Using built-in awards.", e), true));
+                awards::builtin_definitions()
+            });
 
-        // Ensure the directory exists, creating it if necessary
-        fs::create_dir_all(&app_data_dir)
-            .map_err(|e| StorageError::Backend(format!("Failed to create data directory: {}", e)))?;
+        let contest_definitions = contest::load_definitions(&app_data_dir.join("contests"))
+            .unwrap_or_else(|e| {
+                notices.push((format!("Failed to load contest definitions: {}. Using built-in contests.", e), true));
+                contest::builtin_definitions()
+            });
 
-        // Create the database path
-        let db_path = app_data_dir.join("logbook.db");
+        let parks = ParkList::load(&app_data_dir.join("parks.csv")).unwrap_or_else(|e| {
+            notices.push((format!("Failed to load park list: {}", e), true));
+            ParkList::default()
+        });
 
-        println!("Database path: {:?}", db_path);
+        let summits = SummitList::load(&app_data_dir.join("summits.csv")).unwrap_or_else(|e| {
+            notices.push((format!("Failed to load summits list: {}", e), true));
+            SummitList::default()
+        });
 
-        let storage_manager = runtime.block_on(async {
-            StorageManager::new(
-                StorageFormat::Sqlite,
-                db_path.clone(),
-            ).await
-        })?;
+        let activation = match Activation::load(&app_data_dir.join(ACTIVATION_FILE)) {
+            Ok(activation) => activation.filter(Activation::is_current),
+            Err(e) => {
+                notices.push((format!("Ignoring saved activation: {}", e), true));
+                None
+            }
+        };
+
+        let satellites = satellite::load_satellites(&app_data_dir.join("satellites.json"))
+            .unwrap_or_else(|e| {
+                notices.push((format!("Failed to load satellite catalogue: {}. Using built-in satellites.", e), true));
+                satellite::builtin_satellites()
+            });
 
         let entries = runtime.block_on(async {
             storage_manager.list_entries().await
         }).unwrap_or_else(|e| {
-            eprintln!("Failed to load entries: {}. Starting with empty log.", e);
+            notices.push((format!("Failed to load entries: {}. Starting with empty log.", e), true));
             Vec::new()
         });
 
-        Ok(App {
+        let field_definitions = runtime.block_on(storage_manager.field_definitions())
+            .unwrap_or_else(|e| {
+                notices.push((format!("Failed to load custom field definitions: {}", e), true));
+                Vec::new()
+            });
+
+        let sync = SyncConfig::from_env().and_then(|config| {
+            runtime.block_on(sync::start(&config, entries.clone()))
+                .map_err(|e| notices.push((format!("Network sync disabled: {}", e), true)))
+                .ok()
+        });
+        let station = sync.as_ref().map(|s| s.station.clone()).or_else(sync::station_from_env);
+
+        let mut app = App {
             mode: AppMode::Normal,
-            form: Form::new(),
+            form: entry_form(&field_definitions),
             status_message: Some(("amlog".to_string(), false)),
             storage_manager,
             runtime,
             entries,
             selected_index: None,
-            deleted_entries: Vec::new(),
             editing_index: None,
-        })
+            callbook,
+            callbook_result: None,
+            revisions: Vec::new(),
+            revision_cursor: None,
+            data_dir: app_data_dir,
+            qsl_filter: QslFilter::All,
+            qsl_selected: HashSet::new(),
+            qsl_date: Utc::now().date_naive(),
+            qsl_via: QslVia::Bureau,
+            qsl_cursor: None,
+            prompt: None,
+            reconcile_result: None,
+            award_definitions,
+            award_index: 0,
+            award_row: 0,
+            award_column: 0,
+            award_drill_down: false,
+            contest_definitions,
+            pending_contest: None,
+            contest: None,
+            contest_form: Form::new(),
+            station,
+            sync,
+            sync_peers: BTreeSet::new(),
+            last_refresh: Instant::now(),
+            parks,
+            summits,
+            activation,
+            satellites,
+            history: Vec::new(),
+            history_cursor: 0,
+            backups: Vec::new(),
+            backup_cursor: 0,
+            fsck_report: None,
+            fsck_cursor: 0,
+            search_query: String::new(),
+            search_results: Vec::new(),
+            search_cursor: 0,
+            mirror_alert: None,
+            field_definitions,
+        };
+
+        notices.sort_by_key(|(_, is_error)| !is_error);
+        if let Some(&(_, is_error)) = notices.first() {
+            let message: Vec<&str> = notices.iter().map(|(message, _)| message.as_str()).collect();
+            if is_error {
+                app.set_error(&message.join("; "));
+            } else {
+                app.set_status(&message.join("; "));
+            }
+        }
+        Ok(app)
     }
 
     pub fn save_entry(&mut self) {
-        let frequency = match self.form.fields[1].value.parse::<f64>() {
-            Ok(freq) => freq,
-            Err(_) => {
-                self.set_error("Invalid frequency format");
+        let values = match self.form_values() {
+            Ok(values) => values,
+            Err(e) => {
+                self.set_error(&e.to_string());
                 return;
             }
         };
 
-        let mut entry = LogEntry {
-            id: Uuid::new_v4().to_string(),
-            timestamp: Utc::now(),
-            callsign: self.form.fields[0].value.clone(),
-            frequency,
-            mode: self.form.fields[2].value.clone(),
-            rst_sent: Some(self.form.fields[3].value.clone()),
-            rst_received: Some(self.form.fields[4].value.clone()),
-            notes: Some(self.form.fields[5].value.clone()),
-            // Initialize other fields as needed
-            ..Default::default()
+        // When editing, start from the stored entry so fields that are not
+        // on the form survive the round trip
+        let mut entry = match (&self.mode, self.editing_index) {
+            (AppMode::Edit, Some(idx)) => self.entries.get(idx).cloned().unwrap_or_default(),
+            _ => LogEntry {
+                id: Uuid::new_v4().to_string(),
+                timestamp: Utc::now(),
+                station: self.station.clone(),
+                ..Default::default()
+            },
         };
+        entry.callsign = values.callsign;
+        entry.frequency = values.frequency;
+        entry.mode = values.mode;
+        entry.rst_sent = values.rst_sent;
+        entry.rst_received = values.rst_received;
+        entry.power = values.power;
+        entry.name = self.form.optional("Name");
+        entry.qth = self.form.optional("QTH");
+        entry.state = self.form.optional("State");
+        entry.country = self.form.optional("Country");
+        entry.grid = self.form.optional("Grid");
+        entry.notes = self.form.optional("Notes");
+        let references = pota::parse_references(self.form.value("Park"))
+            .and_then(|parks| Ok((parks, sota::parse_reference(self.form.value("Summit"))?)));
+        match references {
+            Ok((parks, summit)) => {
+                pota::set_hunted_parks(&mut entry, &parks);
+                entry.sota_ref = summit;
+            }
+            Err(e) => {
+                self.set_error(&e.to_string());
+                return;
+            }
+        }
+        if self.mode != AppMode::Edit {
+            if let Some(activation) = &self.activation {
+                activation.stamp(&mut entry);
+            }
+        }
+
+        // Keep the bands in step with the frequencies
+        let freq_rx = values.freq_rx;
+        entry.band = entry.frequency.band().or(entry.band.take());
+        if freq_rx.is_some() || entry.freq_rx.is_some() {
+            entry.band_rx = freq_rx.and_then(|f| f.band());
+        }
+        entry.freq_rx = freq_rx;
+
+        for definition in &self.field_definitions {
+            match self.form.optional(&definition.name).map(|value| definition.normalize(&value)) {
+                Some(Ok(value)) => {
+                    entry.custom_fields.insert(definition.name.clone(), value);
+                }
+                Some(Err(e)) => {
+                    self.set_error(&e.to_string());
+                    return;
+                }
+                None => {
+                    entry.custom_fields.remove(&definition.name);
+                }
+            }
+        }
+
+        let mut warning = None;
+        match self.form.optional("Satellite") {
+            Some(name) => match satellite::find(&self.satellites, &name) {
+                Some(sat) => {
+                    sat.apply(&mut entry);
+                    warning = sat.check(&entry).err();
+                }
+                None => {
+                    self.set_error(&format!("Unknown satellite {} (add it to satellites.json)", name));
+                    return;
+                }
+            },
+            None if satellite::is_satellite_qso(&entry) => {
+                entry.prop_mode = None;
+                entry.sat_name = None;
+                entry.sat_mode = None;
+            }
+            None => {}
+        }
 
         let result = if self.mode == AppMode::Edit {
             // Editing an existing entry
-            if let Some(idx) = self.editing_index {
-                if let Some(existing_entry) = self.entries.get(idx) {
-                    // Keep the same ID and timestamp
-                    entry.id = existing_entry.id.clone();
-                    entry.timestamp = existing_entry.timestamp;
-                }
+            if self.editing_index.is_some() {
 
                 self.runtime.block_on(async {
                     self.storage_manager.save_entry(entry.clone()).await
@@ -123,7 +444,8 @@ impl App {
         };
 
         match result {
-            Ok(_) => {
+            Ok(entry) => {
+                self.publish(SyncOp::upsert(entry.clone()));
                 if self.mode == AppMode::Edit {
                     // Update the existing entry in the entries vector
                     if let Some(idx) = self.editing_index {
@@ -133,9 +455,14 @@ impl App {
                     self.editing_index = None;
                 } else {
                     // Add the new entry to the entries vector
+                    let message = self.activation_message(&entry);
                     self.entries.push(entry);
-                    self.set_status("Entry saved successfully");
+                    self.set_status(&message.unwrap_or_else(|| "Entry saved successfully".to_string()));
                 }
+                if let Some(warning) = warning {
+                    self.set_error(&format!("Saved, but {}", warning));
+                }
+                self.backup_if_due();
                 self.form.reset();
                 self.mode = AppMode::Normal;
             }
@@ -145,6 +472,20 @@ impl App {
         }
     }
 
+    /// The values on the QSO form that have to be checked before saving
+    fn form_values(&self) -> Result<FormValues, ValueError> {
+        let rst = |label| self.form.optional(label).map(|rst| Rst::parse(&rst)).transpose();
+        Ok(FormValues {
+            callsign: Callsign::parse(self.form.value("Callsign"))?,
+            frequency: Frequency::parse(self.form.value("Frequency"))?,
+            mode: Mode::parse(self.form.value("Mode"))?,
+            rst_sent: rst("RST Sent")?,
+            rst_received: rst("RST Rcvd")?,
+            power: self.form.optional("Power").map(|p| Power::parse(&p)).transpose()?,
+            freq_rx: self.form.optional("RX Freq").map(|f| Frequency::parse(&f)).transpose()?,
+        })
+    }
+
     pub fn change_storage_format(&mut self, format: StorageFormat, path: PathBuf) -> Result<(), StorageError> {
         let new_storage = self.runtime.block_on(async {
             StorageManager::new(format, path.clone()).await
@@ -157,6 +498,9 @@ impl App {
         self.storage_manager = new_storage;
 
         self.runtime.block_on(async {
+            for definition in self.field_definitions.iter().cloned() {
+                self.storage_manager.define_field(definition).await?;
+            }
             for entry in entries {
                 self.storage_manager.save_entry(entry).await?;
             }
@@ -170,22 +514,14 @@ impl App {
     pub fn delete_current_entry(&mut self) -> Result<(), StorageError> {
         if let Some(selected_idx) = self.selected_index {
             if selected_idx < self.entries.len() {
-                // Store the deleted entry for potential undo
-                let deleted = DeletedEntry {
-                    entry: self.entries[selected_idx].clone(),
-                    index: selected_idx,
-                    timestamp: Utc::now(),
-                };
-
-                // Remove from storage
+                // Remove from storage; the journal keeps it for undo
                 let entry_id = &self.entries[selected_idx].id;
                 self.runtime.block_on(async {
                     self.storage_manager.delete_entry(entry_id).await
                 })?;
+                self.publish(SyncOp::Delete { id: entry_id.clone() });
 
-                // Remove from UI list and store in undo buffer
                 self.entries.remove(selected_idx);
-                self.deleted_entries.push(deleted);
 
                 // Adjust selection if needed
                 if self.entries.is_empty() {
@@ -200,23 +536,276 @@ impl App {
         Ok(())
     }
 
-    pub fn undo_delete(&mut self) -> Result<(), StorageError> {
-        if let Some(deleted) = self.deleted_entries.pop() {
-            // Add back to storage
-            self.runtime.block_on(async {
-                self.storage_manager.save_entry(deleted.entry.clone()).await
-            })?;
+    /// Undo the latest change of any kind, including ones made before a restart
+    pub fn undo(&mut self) {
+        match self.runtime.block_on(self.storage_manager.undo()) {
+            Ok(Some(record)) => {
+                self.journal_step_applied(&record, record.before.clone());
+                self.set_status(&format!("Undid {} of {}", record.op.as_str(), record.callsign()));
+            }
+            Ok(None) => self.set_status("Nothing to undo"),
+            Err(e) => self.set_error(&format!("Undo failed: {}", e)),
+        }
+    }
 
-            // Insert back into UI list at original position
-            let insert_pos = deleted.index.min(self.entries.len());
-            self.entries.insert(insert_pos, deleted.entry);
-            self.selected_index = Some(insert_pos);
+    /// Redo the earliest undone change
+    pub fn redo(&mut self) {
+        match self.runtime.block_on(self.storage_manager.redo()) {
+            Ok(Some(record)) => {
+                self.journal_step_applied(&record, record.after.clone());
+                self.set_status(&format!("Redid {} of {}", record.op.as_str(), record.callsign()));
+            }
+            Ok(None) => self.set_status("Nothing to redo"),
+            Err(e) => self.set_error(&format!("Redo failed: {}", e)),
+        }
+    }
 
-            self.set_status("Delete undone");
-        } else {
-            self.set_status("No deletions to undo");
+    /// Bring the entry list, other stations and the history view up to date
+    /// after an undo or redo left the entry as `image`
+    fn journal_step_applied(&mut self, record: &JournalRecord, image: Option<LogEntry>) {
+        self.publish(match image {
+            Some(entry) => SyncOp::upsert(entry),
+            None => SyncOp::Delete { id: record.entry_id.clone() },
+        });
+        self.reload_entries();
+        if let Some(idx) = self.entries.iter().position(|entry| entry.id == record.entry_id) {
+            self.selected_index = Some(idx);
         }
-        Ok(())
+        if self.mode == AppMode::History {
+            self.load_history();
+        }
+    }
+
+    // Operation history
+    pub fn enter_history_mode(&mut self) {
+        self.load_history();
+        self.history_cursor = 0;
+        self.mode = AppMode::History;
+    }
+
+    /// Read the journal, newest change first
+    fn load_history(&mut self) {
+        match self.runtime.block_on(self.storage_manager.journal()) {
+            Ok(mut journal) => {
+                journal.reverse();
+                self.history = journal;
+                self.history_cursor = self.history_cursor.min(self.history.len().saturating_sub(1));
+            }
+            Err(e) => self.set_error(&format!("Failed to read history: {}", e)),
+        }
+    }
+
+    pub fn history_select_next(&mut self) {
+        if self.history_cursor + 1 < self.history.len() {
+            self.history_cursor += 1;
+        }
+    }
+
+    pub fn history_select_previous(&mut self) {
+        self.history_cursor = self.history_cursor.saturating_sub(1);
+    }
+
+    /// Show the QSO the highlighted change is about, if it still exists
+    pub fn view_history_entry(&mut self) {
+        let Some(record) = self.history.get(self.history_cursor) else {
+            return;
+        };
+        match self.entries.iter().position(|entry| entry.id == record.entry_id) {
+            Some(idx) => {
+                self.selected_index = Some(idx);
+                self.view_selected_entry();
+            }
+            None => self.set_error(&format!("{} is not in the log any more", record.callsign())),
+        }
+    }
+
+    // Backups
+    pub fn enter_backups_mode(&mut self) {
+        self.load_backups();
+        self.backup_cursor = 0;
+        self.mode = AppMode::Backups;
+    }
+
+    fn load_backups(&mut self) {
+        match self.storage_manager.backups() {
+            Ok(backups) => {
+                self.backups = backups;
+                self.backup_cursor = self.backup_cursor.min(self.backups.len().saturating_sub(1));
+            }
+            Err(e) => self.set_error(&format!("Failed to list backups: {}", e)),
+        }
+    }
+
+    pub fn backup_select_next(&mut self) {
+        if self.backup_cursor + 1 < self.backups.len() {
+            self.backup_cursor += 1;
+        }
+    }
+
+    pub fn backup_select_previous(&mut self) {
+        self.backup_cursor = self.backup_cursor.saturating_sub(1);
+    }
+
+    pub fn backup_now(&mut self) {
+        match self.runtime.block_on(self.storage_manager.backup(BackupReason::Manual)) {
+            Ok(backup) => self.set_status(&format!("Backed up to {}", backup.name())),
+            Err(e) => self.set_error(&format!("Backup failed: {}", e)),
+        }
+        if self.mode == AppMode::Backups {
+            self.load_backups();
+        }
+    }
+
+    fn backup_if_due(&mut self) {
+        if let Err(e) = self.runtime.block_on(self.storage_manager.backup_if_due()) {
+            self.set_error(&format!("Periodic backup failed: {}", e));
+        }
+    }
+
+    /// Ask before restoring the highlighted backup over the log
+    pub fn prompt_restore_backup(&mut self) {
+        let Some(backup) = self.backups.get(self.backup_cursor) else {
+            return;
+        };
+        let label = format!("Type yes to restore {} (the current log is backed up first)", backup.name());
+        self.open_prompt(&label, PromptAction::RestoreBackup);
+    }
+
+    fn restore_selected_backup(&mut self) {
+        let Some(backup) = self.backups.get(self.backup_cursor).cloned() else {
+            return;
+        };
+        let before: HashMap<String, LogEntry> = self.entries.iter()
+            .map(|entry| (entry.id.clone(), entry.clone()))
+            .collect();
+        match self.runtime.block_on(self.storage_manager.restore_backup(&backup)) {
+            Ok(previous) => {
+                self.reload_entries();
+                self.publish_changes_since(before);
+                self.set_status(&format!("Restored {}; the log as it was is in {}", backup.name(), previous.name()));
+            }
+            Err(e) => self.set_error(&format!("Failed to restore backup: {}", e)),
+        }
+        self.load_backups();
+    }
+
+    /// Tell sync peers about every QSO added, changed or removed since
+    /// `before`, after a restore or repair
+    fn publish_changes_since(&mut self, mut before: HashMap<String, LogEntry>) {
+        let changed: Vec<LogEntry> = self.entries.iter()
+            .filter(|entry| !journal::diff_entries(before.remove(&entry.id).as_ref(), Some(entry)).is_empty())
+            .cloned()
+            .collect();
+        for entry in changed {
+            self.publish(SyncOp::upsert(entry));
+        }
+        for id in before.into_keys() {
+            self.publish(SyncOp::Delete { id });
+        }
+    }
+
+    // Integrity check
+    pub fn enter_fsck_mode(&mut self) {
+        self.run_fsck(false);
+        self.fsck_cursor = 0;
+        self.mode = AppMode::Fsck;
+    }
+
+    fn run_fsck(&mut self, repair: bool) {
+        let custom_fields = custom_field_names(&self.contest_definitions, &self.award_definitions);
+        let before: HashMap<String, LogEntry> = self.entries.iter()
+            .map(|entry| (entry.id.clone(), entry.clone()))
+            .collect();
+        match self.runtime.block_on(self.storage_manager.fsck(&custom_fields, repair)) {
+            Ok(report) => {
+                self.set_status(&report.summary());
+                if report.repaired {
+                    self.reload_entries();
+                    self.publish_changes_since(before);
+                }
+                self.fsck_report = Some(report);
+            }
+            Err(e) => self.set_error(&format!("Integrity check failed: {}", e)),
+        }
+    }
+
+    pub fn fsck_select_next(&mut self) {
+        let problems = self.fsck_report.as_ref().map_or(0, |report| report.problems.len());
+        if self.fsck_cursor + 1 < problems {
+            self.fsck_cursor += 1;
+        }
+    }
+
+    pub fn fsck_select_previous(&mut self) {
+        self.fsck_cursor = self.fsck_cursor.saturating_sub(1);
+    }
+
+    /// Ask before repairing the problems found
+    pub fn prompt_repair(&mut self) {
+        let fixable = self.fsck_report.as_ref().map_or(0, FsckReport::fixable);
+        if fixable == 0 {
+            self.set_status("Nothing to fix automatically");
+            return;
+        }
+        let label = format!("Type yes to fix {} problems (the log is backed up first)", fixable);
+        self.open_prompt(&label, PromptAction::RepairLog);
+    }
+
+    // Full-text search
+    pub fn enter_search_mode(&mut self) {
+        self.search_query.clear();
+        self.search_results.clear();
+        self.search_cursor = 0;
+        self.mode = AppMode::Search;
+    }
+
+    pub fn search_input(&mut self, c: char) {
+        self.search_query.push(c);
+        self.run_search();
+    }
+
+    pub fn search_backspace(&mut self) {
+        self.search_query.pop();
+        self.run_search();
+    }
+
+    fn run_search(&mut self) {
+        match self.runtime.block_on(self.storage_manager.search_text(&self.search_query)) {
+            Ok(results) => {
+                self.search_results = results;
+                self.search_cursor = 0;
+            }
+            Err(e) => self.set_error(&format!("Search failed: {}", e)),
+        }
+    }
+
+    pub fn search_select_next(&mut self) {
+        if self.search_cursor + 1 < self.search_results.len() {
+            self.search_cursor += 1;
+        }
+    }
+
+    pub fn search_select_previous(&mut self) {
+        self.search_cursor = self.search_cursor.saturating_sub(1);
+    }
+
+    /// Show the highlighted result in the log and open its details
+    pub fn view_search_result(&mut self) {
+        let Some(hit) = self.search_results.get(self.search_cursor) else {
+            return;
+        };
+        match self.entries.iter().position(|entry| entry.id == hit.entry.id) {
+            Some(idx) => {
+                self.selected_index = Some(idx);
+                self.view_selected_entry();
+            }
+            None => self.set_error(&format!("{} is not in the log any more", hit.entry.callsign)),
+        }
+    }
+
+    /// Back up the log on the way out
+    pub fn shutdown(&mut self) -> Result<Backup, StorageError> {
+        self.runtime.block_on(self.storage_manager.backup(BackupReason::Shutdown))
     }
 
     // Status handling methods
@@ -301,18 +890,1087 @@ impl App {
         if let Some(idx) = self.selected_index {
             if let Some(entry) = self.entries.get(idx) {
                 // Fill form with selected entry's data
-                self.form.fields[0].value = entry.callsign.clone();
-                self.form.fields[1].value = entry.frequency.to_string();
-                self.form.fields[2].value = entry.mode.clone();
-                self.form.fields[3].value = entry.rst_sent.clone().unwrap_or_default();
-                self.form.fields[4].value = entry.rst_received.clone().unwrap_or_default();
-                self.form.fields[5].value = entry.notes.clone().unwrap_or_default();
+                self.form.reset();
+                self.form.set_value("Callsign", &entry.callsign);
+                self.form.set_value("Frequency", &entry.frequency.to_string());
+                self.form.set_value("Mode", &entry.mode);
+                self.form.set_value("RST Sent", &entry.rst_sent.as_ref().map(Rst::to_string).unwrap_or_default());
+                self.form.set_value("RST Rcvd", &entry.rst_received.as_ref().map(Rst::to_string).unwrap_or_default());
+                self.form.set_value("Power", &entry.power.map(|p| p.to_string()).unwrap_or_default());
+                self.form.set_value("Name", entry.name.as_deref().unwrap_or_default());
+                self.form.set_value("QTH", entry.qth.as_deref().unwrap_or_default());
+                self.form.set_value("State", entry.state.as_deref().unwrap_or_default());
+                self.form.set_value("Country", entry.country.as_deref().unwrap_or_default());
+                self.form.set_value("Grid", entry.grid.as_deref().unwrap_or_default());
+                self.form.set_value("Park", &pota::hunted_parks(entry).join(","));
+                self.form.set_value("Summit", entry.sota_ref.as_deref().unwrap_or_default());
+                self.form.set_value("Satellite", entry.sat_name.as_deref().unwrap_or_default());
+                self.form.set_value("RX Freq", &entry.freq_rx.map(|f| f.to_string()).unwrap_or_default());
+                self.form.set_value("Notes", entry.notes.as_deref().unwrap_or_default());
+                for definition in &self.field_definitions {
+                    let value = entry.custom_fields.get(&definition.name).map(String::as_str);
+                    self.form.set_value(&definition.name, value.unwrap_or_default());
+                }
                 self.mode = AppMode::Edit;
                 self.editing_index = Some(idx);
             }
         }
     }
 
+    /// Fill the form from the next satellite in the catalogue
+    pub fn next_satellite_preset(&mut self) {
+        if self.satellites.is_empty() {
+            return;
+        }
+        let current = self.satellites.iter().position(|s| s.matches(self.form.value("Satellite")));
+        let satellite = &self.satellites[current.map_or(0, |i| (i + 1) % self.satellites.len())];
+        self.form.set_value("Satellite", &satellite.name);
+        self.form.set_value("Frequency", &format!("{:.3}", satellite.uplink.center()));
+        self.form.set_value("RX Freq", &format!("{:.3}", satellite.downlink.center()));
+        self.form.set_value("Mode", satellite.default_mode());
+        let message = format!(
+            "{} ({}): uplink {:.3}-{:.3}, downlink {:.3}-{:.3} MHz",
+            satellite.name,
+            satellite.sat_mode,
+            satellite.uplink.low,
+            satellite.uplink.high,
+            satellite.downlink.low,
+            satellite.downlink.high
+        );
+        self.set_status(&message);
+    }
+
+    pub fn view_selected_entry(&mut self) {
+        if self.get_selected_entry().is_some() {
+            self.callbook_result = None;
+            self.load_revisions();
+            self.mode = AppMode::Detail;
+        }
+    }
+
+    // Revision history of the selected entry
+    fn load_revisions(&mut self) {
+        let Some(id) = self.get_selected_entry().map(|entry| entry.id.clone()) else {
+            return;
+        };
+        match self.runtime.block_on(self.storage_manager.revisions(&id)) {
+            Ok(revisions) => {
+                self.revision_cursor = revisions.len().checked_sub(1);
+                self.revisions = revisions;
+            }
+            Err(e) => self.set_error(&format!("Failed to read revisions: {}", e)),
+        }
+    }
+
+    pub fn select_older_revision(&mut self) {
+        if let Some(cursor) = self.revision_cursor {
+            self.revision_cursor = Some(cursor.saturating_sub(1));
+        }
+    }
+
+    pub fn select_newer_revision(&mut self) {
+        if let Some(cursor) = self.revision_cursor {
+            self.revision_cursor = Some((cursor + 1).min(self.revisions.len() - 1));
+        }
+    }
+
+    /// Put the selected revision back in place of the current entry
+    pub fn restore_selected_revision(&mut self) {
+        let (Some(idx), Some(revision)) = (
+            self.selected_index,
+            self.revision_cursor.and_then(|cursor| self.revisions.get(cursor)),
+        ) else {
+            return;
+        };
+        let (id, number) = (revision.entry_id.clone(), revision.number);
+        match self.runtime.block_on(self.storage_manager.restore_revision(&id, number)) {
+            Ok(entry) => {
+                self.publish(SyncOp::upsert(entry.clone()));
+                self.set_status(&format!("Restored revision {} of {}", number, entry.callsign));
+                self.entries[idx] = entry;
+                self.load_revisions();
+                self.backup_if_due();
+            }
+            Err(e) => self.set_error(&format!("Failed to restore revision: {}", e)),
+        }
+    }
+
+    pub fn toggle_help(&mut self) {
+        self.mode = if self.mode == AppMode::Help { AppMode::Normal } else { AppMode::Help };
+    }
+
+    pub fn toggle_stats(&mut self) {
+        self.mode = if self.mode == AppMode::Stats { AppMode::Normal } else { AppMode::Stats };
+    }
+
+    // QSL management
+    pub fn enter_qsl_mode(&mut self) {
+        self.mode = AppMode::Qsl;
+        self.qsl_selected.clear();
+        self.qsl_date = Utc::now().date_naive();
+        self.qsl_cursor = if self.qsl_visible().is_empty() { None } else { Some(0) };
+    }
+
+    /// Indices into the entry list of QSOs matching the current QSL filter
+    pub fn qsl_visible(&self) -> Vec<usize> {
+        self.entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| self.qsl_filter.matches(entry))
+            .map(|(idx, _)| idx)
+            .collect()
+    }
+
+    pub fn qsl_cursor(&self) -> Option<usize> {
+        self.qsl_cursor
+    }
+
+    pub fn qsl_select_next(&mut self) {
+        let len = self.qsl_visible().len();
+        if len > 0 {
+            self.qsl_cursor = Some(self.qsl_cursor.map_or(0, |i| (i + 1) % len));
+        }
+    }
+
+    pub fn qsl_select_previous(&mut self) {
+        let len = self.qsl_visible().len();
+        if len > 0 {
+            self.qsl_cursor = Some(match self.qsl_cursor {
+                Some(0) | None => len - 1,
+                Some(i) => i - 1,
+            });
+        }
+    }
+
+    pub fn cycle_qsl_filter(&mut self) {
+        self.qsl_filter = self.qsl_filter.next();
+        self.qsl_cursor = if self.qsl_visible().is_empty() { None } else { Some(0) };
+    }
+
+    pub fn cycle_qsl_via(&mut self) {
+        self.qsl_via = match self.qsl_via {
+            QslVia::Bureau => QslVia::Direct,
+            QslVia::Direct => QslVia::Manager,
+            QslVia::Manager | QslVia::Electronic => QslVia::Bureau,
+        };
+    }
+
+    pub fn shift_qsl_date(&mut self, days: i64) {
+        self.qsl_date += chrono::Duration::days(days);
+    }
+
+    /// Toggle the QSO under the cursor in the bulk selection
+    pub fn toggle_qsl_selection(&mut self) {
+        let visible = self.qsl_visible();
+        if let Some(idx) = self.qsl_cursor.and_then(|c| visible.get(c)) {
+            let id = self.entries[*idx].id.clone();
+            if !self.qsl_selected.remove(&id) {
+                self.qsl_selected.insert(id);
+            }
+        }
+    }
+
+    /// Select every visible QSO, or clear the selection if all are selected
+    pub fn toggle_qsl_select_all(&mut self) {
+        let ids: Vec<String> = self.qsl_visible().into_iter().map(|idx| self.entries[idx].id.clone()).collect();
+        if ids.iter().all(|id| self.qsl_selected.contains(id)) {
+            self.qsl_selected.clear();
+        } else {
+            self.qsl_selected.extend(ids);
+        }
+    }
+
+    /// Apply `change` to the selected QSOs (or the one under the cursor if
+    /// nothing is selected) and persist them
+    fn update_qsl_selection(&mut self, change: impl Fn(&mut LogEntry)) -> usize {
+        let visible = self.qsl_visible();
+        let targets: Vec<usize> = if self.qsl_selected.is_empty() {
+            self.qsl_cursor.and_then(|c| visible.get(c)).copied().into_iter().collect()
+        } else {
+            (0..self.entries.len()).filter(|idx| self.qsl_selected.contains(&self.entries[*idx].id)).collect()
+        };
+
+        let mut updated = 0;
+        for idx in targets {
+            let mut entry = self.entries[idx].clone();
+            change(&mut entry);
+            let result = self.runtime.block_on(self.storage_manager.save_entry(entry.clone()));
+            match result {
+                Ok(entry) => {
+                    self.publish(SyncOp::upsert(entry.clone()));
+                    self.entries[idx] = entry;
+                    updated += 1;
+                }
+                Err(e) => {
+                    self.set_error(&format!("Failed to update {}: {}", entry.callsign, e));
+                    return updated;
+                }
+            }
+        }
+        self.qsl_selected.clear();
+        let len = self.qsl_visible().len();
+        self.qsl_cursor = match self.qsl_cursor {
+            _ if len == 0 => None,
+            Some(c) => Some(c.min(len - 1)),
+            None => Some(0),
+        };
+        updated
+    }
+
+    pub fn mark_selected_qsl_sent(&mut self) {
+        let (date, via) = (self.qsl_date, self.qsl_via);
+        let updated = self.update_qsl_selection(|entry| qsl::mark_sent(entry, date, via));
+        if updated > 0 {
+            self.set_status(&format!("Marked {} QSL(s) sent via {} on {}", updated, via.label(), date));
+        }
+    }
+
+    pub fn mark_selected_qsl_queued(&mut self) {
+        let via = self.qsl_via;
+        let updated = self.update_qsl_selection(|entry| qsl::mark_queued(entry, via));
+        if updated > 0 {
+            self.set_status(&format!("Queued {} QSL card(s) via {}", updated, via.label()));
+        }
+    }
+
+    /// Write the outgoing card queue to a CSV file in the data directory
+    pub fn export_qsl_queue(&mut self) {
+        let queued = self.entries.iter().filter(|e| qsl::is_in_queue(e)).count();
+        if queued == 0 {
+            self.set_error("The QSL queue is empty");
+            return;
+        }
+        let path = self.data_dir.join(format!("qsl_queue_{}.csv", Utc::now().format("%Y%m%d_%H%M%S")));
+        match fs::write(&path, qsl::qsl_queue_csv(&self.entries)) {
+            Ok(_) => self.set_status(&format!("Exported {} queued card(s) to {}", queued, path.display())),
+            Err(e) => self.set_error(&format!("Failed to export QSL queue: {}", e)),
+        }
+    }
+
+    // Awards
+    pub fn enter_awards_mode(&mut self) {
+        self.mode = AppMode::Awards;
+        self.award_row = 0;
+        self.award_column = 0;
+        self.award_drill_down = false;
+    }
+
+    pub fn next_award(&mut self) {
+        if !self.award_definitions.is_empty() {
+            self.award_index = (self.award_index + 1) % self.award_definitions.len();
+            self.award_row = 0;
+            self.award_drill_down = false;
+        }
+    }
+
+    pub fn previous_award(&mut self) {
+        if !self.award_definitions.is_empty() {
+            self.award_index = self.award_index.checked_sub(1).unwrap_or(self.award_definitions.len() - 1);
+            self.award_row = 0;
+            self.award_drill_down = false;
+        }
+    }
+
+    /// Progress of the award shown in the awards view
+    pub fn current_award(&self) -> Option<AwardProgress> {
+        self.award_definitions
+            .get(self.award_index)
+            .map(|definition| AwardProgress::compute(definition, &self.entries))
+    }
+
+    /// Band and mode of the selected matrix cell; the extra last row and
+    /// column stand for all bands and all modes
+    pub fn award_cell(&self, progress: &AwardProgress) -> (Option<String>, Option<ModeCategory>) {
+        let band = progress.bands().get(self.award_row).cloned();
+        let mode = ModeCategory::ALL.get(self.award_column).copied();
+        (band, mode)
+    }
+
+    /// Move the matrix cursor by rows/columns, wrapping at the edges
+    pub fn move_award_cursor(&mut self, rows: isize, columns: isize) {
+        let Some(progress) = self.current_award() else {
+            return;
+        };
+        let row_count = progress.bands().len() as isize + 1;
+        let column_count = ModeCategory::ALL.len() as isize + 1;
+        self.award_row = (self.award_row as isize + rows).rem_euclid(row_count) as usize;
+        self.award_column = (self.award_column as isize + columns).rem_euclid(column_count) as usize;
+    }
+
+    pub fn toggle_award_drill_down(&mut self) {
+        self.award_drill_down = !self.award_drill_down;
+    }
+
+    pub fn find_entry(&self, id: &str) -> Option<&LogEntry> {
+        self.entries.iter().find(|entry| entry.id == id)
+    }
+
+    // Prompt handling
+    pub fn open_prompt(&mut self, label: &str, action: PromptAction) {
+        self.prompt = Some(Prompt {
+            label: label.to_string(),
+            input: String::new(),
+            action,
+        });
+        self.mode = AppMode::Prompt;
+    }
+
+    pub fn prompt_input(&mut self, c: char) {
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.input.push(c);
+        }
+    }
+
+    pub fn prompt_backspace(&mut self) {
+        if let Some(prompt) = self.prompt.as_mut() {
+            prompt.input.pop();
+        }
+    }
+
+    pub fn cancel_prompt(&mut self) {
+        self.mode = self.prompt_return_mode();
+        self.prompt = None;
+    }
+
+    fn prompt_return_mode(&self) -> AppMode {
+        match &self.prompt {
+            Some(prompt) if prompt.action.returns_to_contest() => AppMode::Contest,
+            Some(prompt) if prompt.action == PromptAction::RestoreBackup => AppMode::Backups,
+            Some(prompt) if prompt.action == PromptAction::RepairLog => AppMode::Fsck,
+            _ => AppMode::Normal,
+        }
+    }
+
+    pub fn submit_prompt(&mut self) {
+        self.mode = self.prompt_return_mode();
+        let Some(prompt) = self.prompt.take() else {
+            return;
+        };
+        let input = prompt.input.trim().to_string();
+        if input.is_empty() {
+            return;
+        }
+        match prompt.action {
+            PromptAction::ReconcileConfirmations => self.reconcile_confirmations(&PathBuf::from(input)),
+            PromptAction::StartContest => self.select_contest(&input),
+            PromptAction::ContestSentExchange => {
+                if let Some(definition) = self.pending_contest.take() {
+                    let values: Vec<&str> = input.split_whitespace().collect();
+                    self.begin_contest(definition, &values);
+                }
+            }
+            PromptAction::ContestBonus => self.claim_contest_bonus(&input),
+            PromptAction::ContestPower => self.set_contest_power(&input),
+            PromptAction::ContestCabrillo => self.export_cabrillo(&input),
+            PromptAction::StartActivation => self.start_activation(&input),
+            PromptAction::PortableUploads => self.export_uploads(&input),
+            PromptAction::RestoreBackup if input.eq_ignore_ascii_case("yes") => self.restore_selected_backup(),
+            PromptAction::RestoreBackup => self.set_status("Restore cancelled"),
+            PromptAction::RepairLog if input.eq_ignore_ascii_case("yes") => {
+                self.run_fsck(true);
+                self.fsck_cursor = 0;
+            }
+            PromptAction::RepairLog => self.set_status("Repair cancelled"),
+        }
+    }
+
+    // Contest operating
+    /// Resume the running contest, or ask which contest to start
+    pub fn start_contest(&mut self) {
+        if self.contest.is_some() {
+            self.mode = AppMode::Contest;
+            return;
+        }
+        let ids: Vec<&str> = self.contest_definitions.iter().map(|d| d.id.as_str()).collect();
+        let label = format!("Contest ({})", ids.join(", "));
+        self.open_prompt(&label, PromptAction::StartContest);
+    }
+
+    fn contest_state_path(&self, id: &str) -> PathBuf {
+        self.data_dir.join("contest-sessions").join(format!("{}.json", id))
+    }
+
+    pub fn select_contest(&mut self, id: &str) {
+        let Some(definition) = self.contest_definitions.iter().find(|d| d.id.eq_ignore_ascii_case(id)).cloned() else {
+            self.set_error(&format!("Unknown contest: {}", id));
+            return;
+        };
+
+        // Pick up where we left off if this running of the contest was
+        // started before
+        match ContestState::load(&self.contest_state_path(&definition.id)) {
+            Ok(Some(state)) if state.is_current(&definition) => {
+                let session = ContestSession::resume(definition, state, &self.entries);
+                self.open_contest(session);
+                return;
+            }
+            Ok(_) => {}
+            Err(e) => self.set_error(&format!("Ignoring saved contest state: {}", e)),
+        }
+
+        let inputs: Vec<&str> = definition.sent_inputs().iter().map(|f| f.label.as_str()).collect();
+        if inputs.is_empty() {
+            self.begin_contest(definition, &[]);
+        } else {
+            let label = format!("Sent exchange ({})", inputs.join(" "));
+            self.pending_contest = Some(definition);
+            self.open_prompt(&label, PromptAction::ContestSentExchange);
+        }
+    }
+
+    pub fn begin_contest(&mut self, definition: ContestDefinition, sent: &[&str]) {
+        let session = match ContestSession::new(definition, sent, &self.entries) {
+            Ok(session) => session,
+            Err(e) => {
+                self.set_error(&format!("Cannot start contest: {}", e));
+                return;
+            }
+        };
+        self.open_contest(session);
+        self.save_contest_state();
+    }
+
+    fn open_contest(&mut self, session: ContestSession) {
+        let mut fields = vec![("Callsign", true), ("Frequency", true), ("Mode", true)];
+        fields.extend(session.definition.exchange.iter().map(|f| (f.label.as_str(), true)));
+        self.contest_form = Form::with_fields(&fields);
+        // Carry on where the last QSO left off
+        if let Some(last) = self.entries.iter().max_by_key(|entry| entry.timestamp) {
+            self.contest_form.set_value("Frequency", &last.frequency.to_string());
+            self.contest_form.set_value("Mode", &last.mode);
+        }
+
+        self.set_status(&format!("{} started, next serial {}", session.definition.name, session.next_serial()));
+        self.contest = Some(session);
+        self.reset_contest_form();
+        self.mode = AppMode::Contest;
+    }
+
+    fn save_contest_state(&mut self) {
+        let Some(session) = &self.contest else {
+            return;
+        };
+        let path = self.contest_state_path(&session.definition.id);
+        if let Err(e) = session.state().save(&path) {
+            self.set_error(&format!("Failed to save contest state: {}", e));
+        }
+    }
+
+    pub fn prompt_contest_bonus(&mut self) {
+        let Some(session) = &self.contest else {
+            return;
+        };
+        if session.definition.bonuses.is_empty() {
+            self.set_error("This contest has no bonus points");
+            return;
+        }
+        self.open_prompt("Bonus id [count], 0 to withdraw", PromptAction::ContestBonus);
+    }
+
+    pub fn claim_contest_bonus(&mut self, input: &str) {
+        let mut parts = input.split_whitespace();
+        let id = parts.next().unwrap_or_default();
+        let Ok(count) = parts.next().map_or(Ok(1), str::parse::<u32>) else {
+            self.set_error("Bonus count must be a number");
+            return;
+        };
+        let Some(session) = self.contest.as_mut() else {
+            return;
+        };
+        match session.claim_bonus(id, count) {
+            Ok(()) => {
+                self.save_contest_state();
+                self.set_status(&format!("Bonus {} set to {}", id, count));
+            }
+            Err(e) => self.set_error(&e.to_string()),
+        }
+    }
+
+    pub fn prompt_contest_power(&mut self) {
+        let Some(session) = &self.contest else {
+            return;
+        };
+        if session.definition.power_levels.is_empty() {
+            self.set_error("This contest has no power multiplier");
+            return;
+        }
+        let levels: Vec<String> = session.definition.power_levels.iter().enumerate()
+            .map(|(i, level)| format!("{}: {} x{}", i + 1, level.label, level.factor))
+            .collect();
+        self.open_prompt(&format!("Power ({})", levels.join(", ")), PromptAction::ContestPower);
+    }
+
+    pub fn set_contest_power(&mut self, input: &str) {
+        let Some(session) = self.contest.as_mut() else {
+            return;
+        };
+        let result = match input.trim().parse::<usize>() {
+            Ok(choice) if choice > 0 => session.set_power_level(choice - 1),
+            _ => Err(contest::ContestError::InvalidExchange("power level".to_string(), input.to_string())),
+        };
+        match result {
+            Ok(()) => {
+                self.save_contest_state();
+                self.set_status("Power level set");
+            }
+            Err(e) => self.set_error(&e.to_string()),
+        }
+    }
+
+    pub fn prompt_cabrillo(&mut self) {
+        if self.contest.is_some() {
+            self.open_prompt("Station callsign for the Cabrillo log", PromptAction::ContestCabrillo);
+        }
+    }
+
+    /// Write the Cabrillo log and dupe sheet to the data directory
+    pub fn export_cabrillo(&mut self, callsign: &str) {
+        let Some(session) = &self.contest else {
+            return;
+        };
+        let name = format!("{}-{}", session.definition.cabrillo_name(), callsign.trim().to_uppercase());
+        let log_path = self.data_dir.join(format!("{}.log", name));
+        let sheet_path = self.data_dir.join(format!("{}-dupes.txt", name));
+
+        let result = fs::write(&log_path, contest::cabrillo_log(session, &self.entries, callsign))
+            .and_then(|_| fs::write(&sheet_path, contest::dupe_sheet(session, &self.entries)));
+        match result {
+            Ok(()) => self.set_status(&format!(
+                "Wrote {} and {}",
+                log_path.display(),
+                sheet_path.display()
+            )),
+            Err(e) => self.set_error(&format!("Failed to write Cabrillo log: {}", e)),
+        }
+    }
+
+    /// Label and value of an exchange entry missing from the contest's
+    /// multiplier list, e.g. a mistyped section
+    pub fn contest_unknown_exchange(&self) -> Option<(String, String)> {
+        let session = self.contest.as_ref()?;
+        session.definition.exchange.iter().find_map(|field| {
+            let value = self.contest_form.value(&field.label).trim().to_uppercase();
+            let unknown = !value.is_empty()
+                && session.definition.multipliers.iter()
+                    .any(|rule| rule.key == field.field && !rule.is_valid(&value));
+            unknown.then(|| (field.label.clone(), value))
+        })
+    }
+
+    /// Stop the running contest; its QSOs stay in the log
+    pub fn end_contest(&mut self) {
+        if let Some(session) = self.contest.take() {
+            self.set_status(&format!("{} ended", session.definition.name));
+        }
+        self.mode = AppMode::Normal;
+    }
+
+    /// Clear the callsign and exchange, keeping frequency and mode
+    fn reset_contest_form(&mut self) {
+        let Some(session) = &self.contest else {
+            return;
+        };
+        self.contest_form.set_value("Callsign", "");
+        for field in &session.definition.exchange {
+            self.contest_form.set_value(&field.label, field.default.as_deref().unwrap_or(""));
+        }
+        self.contest_form.focus("Callsign");
+    }
+
+    /// Contest fields take upper case; space jumps to the next field
+    pub fn contest_input(&mut self, c: char) {
+        if c == ' ' {
+            self.contest_form.next_field();
+        } else {
+            self.contest_form.input(c.to_ascii_uppercase());
+        }
+    }
+
+    /// Earlier QSO that the call in the contest form would duplicate
+    pub fn contest_dupe(&self) -> Option<&LogEntry> {
+        let session = self.contest.as_ref()?;
+        let frequency = Frequency::parse(self.contest_form.value("Frequency")).ok()?;
+        session.find_dupe(
+            &self.entries,
+            self.contest_form.value("Callsign"),
+            frequency.band().map(String::from),
+            ModeCategory::for_mode(self.contest_form.value("Mode")),
+        )
+    }
+
+    pub fn log_contest_qso(&mut self) {
+        let Some(session) = &self.contest else {
+            return;
+        };
+        if !self.contest_form.is_valid() {
+            self.set_error("Please fill in the call and exchange");
+            return;
+        }
+        let (frequency, mode) = match Frequency::parse(self.contest_form.value("Frequency"))
+            .and_then(|frequency| Ok((frequency, Mode::parse(self.contest_form.value("Mode"))?)))
+        {
+            Ok(values) => values,
+            Err(e) => {
+                self.set_error(&e.to_string());
+                return;
+            }
+        };
+
+        let mut entry = LogEntry {
+            id: Uuid::new_v4().to_string(),
+            timestamp: Utc::now(),
+            callsign: self.contest_form.value("Callsign").into(),
+            frequency,
+            mode,
+            band: frequency.band(),
+            station: self.station.clone(),
+            ..Default::default()
+        };
+        if let Some(activation) = &self.activation {
+            activation.stamp(&mut entry);
+        }
+        let stamped = session.stamp(&mut entry).and_then(|_| {
+            session.definition.exchange.iter().try_for_each(|field| {
+                contest::set_exchange_value(&mut entry, field, self.contest_form.value(&field.label))
+            })
+        });
+        if let Err(e) = stamped {
+            self.set_error(&e.to_string());
+            return;
+        }
+        let dupe = self.contest_dupe().is_some();
+        let unknown = self.contest_unknown_exchange();
+
+        let entry = match self.runtime.block_on(self.storage_manager.add_entry(entry)) {
+            Ok(entry) => entry,
+            Err(e) => {
+                self.set_error(&format!("Failed to save entry: {}", e));
+                return;
+            }
+        };
+        self.publish(SyncOp::upsert(entry.clone()));
+        let message = match (dupe, unknown) {
+            (true, _) => format!("Logged {} (DUPE, no points)", entry.callsign),
+            (false, Some((label, value))) => format!("Logged {} (unknown {} {})", entry.callsign, label, value),
+            (false, None) => format!("Logged {}", entry.callsign),
+        };
+        self.entries.push(entry);
+        if let Some(session) = self.contest.as_mut() {
+            session.advance_serial();
+        }
+        self.reset_contest_form();
+        self.set_status(&message);
+        self.backup_if_due();
+    }
+
+    // Portable activations (POTA and SOTA)
+    /// Ask for the park(s) and summit to activate, or end the running activation
+    pub fn toggle_activation(&mut self) {
+        match self.activation.take() {
+            Some(activation) => {
+                let _ = fs::remove_file(self.data_dir.join(ACTIVATION_FILE));
+                self.set_status(&format!(
+                    "Activation ended: {}",
+                    activation.progress_text(&self.entries, Utc::now())
+                ));
+            }
+            None => self.open_prompt(
+                "Park and/or summit reference(s), e.g. K-0001,K-0002 W7W/LC-001",
+                PromptAction::StartActivation,
+            ),
+        }
+    }
+
+    pub fn start_activation(&mut self, input: &str) {
+        let activation = match Activation::parse(input) {
+            Ok(activation) if !activation.is_empty() => activation,
+            Ok(_) => return,
+            Err(e) => {
+                self.set_error(&e.to_string());
+                return;
+            }
+        };
+        let mut unknown: Vec<&str> = activation.parks.iter()
+            .filter(|park| !self.parks.is_empty() && self.parks.get(park).is_none())
+            .map(String::as_str)
+            .collect();
+        unknown.extend(activation.summit.as_deref()
+            .filter(|summit| !self.summits.is_empty() && self.summits.get(summit).is_none()));
+        if !unknown.is_empty() {
+            self.set_error(&format!("Not in the reference lists: {}", unknown.join(", ")));
+            return;
+        }
+
+        let mut names: Vec<String> = activation.parks.iter()
+            .map(|park| match self.parks.get(park) {
+                Some(info) => format!("{} ({})", park, info.name),
+                None => park.clone(),
+            })
+            .collect();
+        names.extend(activation.summit.as_ref().map(|summit| match self.summits.get(summit) {
+            Some(info) => format!("{} ({}, {} pts)", summit, info.name, info.points),
+            None => summit.clone(),
+        }));
+        match activation.save(&self.data_dir.join(ACTIVATION_FILE)) {
+            Ok(()) => self.set_status(&format!("Activating {}", names.join(", "))),
+            Err(e) => self.set_error(&format!("Activation not saved: {}", e)),
+        }
+        self.activation = Some(activation);
+    }
+
+    /// Progress toward the activation thresholds after logging `entry`
+    fn activation_message(&self, entry: &LogEntry) -> Option<String> {
+        let activation = self.activation.as_ref()?;
+        // `entry` is not in the list yet
+        let mut entries = self.entries.clone();
+        entries.push(entry.clone());
+        let mut message = format!("Logged {}", entry.callsign);
+        if pota::is_park_to_park(entry) {
+            message.push_str(&format!(", park-to-park with {}", pota::hunted_parks(entry).join(",")));
+        }
+        if let Some(summit) = sota::chased_summit(entry).filter(|_| sota::is_summit_to_summit(entry)) {
+            message.push_str(&format!(", summit-to-summit with {}", summit));
+        }
+        Some(format!("{} | {}", message, activation.progress_text(&entries, entry.timestamp)))
+    }
+
+    /// Running activation and today's QSO counts for the title bar
+    pub fn activation_summary(&self) -> Option<String> {
+        let activation = self.activation.as_ref()?;
+        Some(format!("activating {}", activation.progress_text(&self.entries, Utc::now())))
+    }
+
+    pub fn prompt_uploads(&mut self) {
+        self.open_prompt("Station callsign for the POTA and SOTA upload files", PromptAction::PortableUploads);
+    }
+
+    /// Write POTA files (one ADIF file per park per UTC day) to `pota/` and
+    /// the SOTA activator and chaser CSV files to `sota/` in the data directory
+    pub fn export_uploads(&mut self, callsign: &str) {
+        let files = pota::upload_files(&self.entries, callsign);
+        let activator = sota::activator_csv(&self.entries, callsign);
+        let chaser = sota::chaser_csv(&self.entries, callsign);
+        if files.is_empty() && activator.is_empty() && chaser.is_empty() {
+            self.set_error("No POTA or SOTA QSOs in the log");
+            return;
+        }
+
+        let pota_dir = self.data_dir.join("pota");
+        let sota_dir = self.data_dir.join("sota");
+        let call = callsign.trim().to_uppercase().replace('/', "-");
+        let result = (|| -> std::io::Result<()> {
+            if !files.is_empty() {
+                fs::create_dir_all(&pota_dir)?;
+                for file in &files {
+                    fs::write(pota_dir.join(file.file_name(callsign)), file.adif())?;
+                }
+            }
+            if !activator.is_empty() || !chaser.is_empty() {
+                fs::create_dir_all(&sota_dir)?;
+            }
+            if !activator.is_empty() {
+                fs::write(sota_dir.join(format!("{}-activator.csv", call)), &activator)?;
+            }
+            if !chaser.is_empty() {
+                fs::write(sota_dir.join(format!("{}-chaser.csv", call)), &chaser)?;
+            }
+            Ok(())
+        })();
+        match result {
+            Ok(()) => {
+                let points = sota::points(&self.entries, &self.summits);
+                self.set_status(&format!(
+                    "Wrote {} POTA files and SOTA CSVs (activator {} pts, chaser {} pts, S2S {} pts)",
+                    files.len(),
+                    points.activator,
+                    points.chaser,
+                    points.summit_to_summit
+                ));
+            }
+            Err(e) => self.set_error(&format!("Failed to write upload files: {}", e)),
+        }
+    }
+
+    // Multi-operator sync
+    fn publish(&self, op: SyncOp) {
+        if let Some(sync) = &self.sync {
+            sync.publish(op);
+        }
+    }
+
+    /// Pick up QSOs other programs wrote to the log file, e.g. WSJT-X
+    /// logging to the same ADIF file, and pass them on to other stations
+    pub fn poll_external_changes(&mut self) {
+        if self.last_refresh.elapsed() < REFRESH_INTERVAL {
+            return;
+        }
+        self.last_refresh = Instant::now();
+        self.check_mirrors();
+
+        let changes = match self.runtime.block_on(self.storage_manager.refresh()) {
+            Ok(changes) if !changes.is_empty() => changes,
+            Ok(_) => return,
+            Err(e) => {
+                self.set_error(&format!("Failed to check log file: {}", e));
+                return;
+            }
+        };
+
+        let selected = self.get_selected_entry().map(|entry| entry.id.clone());
+        self.reload_entries();
+        if let Some(idx) = selected.and_then(|id| self.entries.iter().position(|entry| entry.id == id)) {
+            self.selected_index = Some(idx);
+        }
+        let changed: Vec<LogEntry> = self.entries.iter()
+            .filter(|entry| changes.added.contains(&entry.id) || changes.modified.contains(&entry.id))
+            .cloned()
+            .collect();
+        for entry in changed {
+            self.publish(SyncOp::upsert(entry));
+        }
+        for id in &changes.removed {
+            self.publish(SyncOp::Delete { id: id.clone() });
+        }
+        self.set_status(&format!("Log file changed by another program: {}", changes.summary()));
+    }
+
+    /// Retry mirror writes that failed and report mirrors that fall behind
+    /// or catch up
+    fn check_mirrors(&mut self) {
+        let mirrors = self.runtime.block_on(self.storage_manager.mirrors());
+        if mirrors.iter().all(|mirror| mirror.pending == 0) && self.mirror_alert.is_none() {
+            return;
+        }
+        self.runtime.block_on(self.storage_manager.retry_mirrors());
+        let alert = self.runtime.block_on(self.storage_manager.mirrors()).into_iter()
+            .find(|mirror| mirror.pending > 0)
+            .map(|mirror| format!("Mirror {} is behind by {} QSOs: {}",
+                mirror.path.display(),
+                mirror.pending,
+                mirror.last_error.unwrap_or_default()));
+        if alert == self.mirror_alert {
+            return;
+        }
+        match &alert {
+            Some(message) => self.set_error(message),
+            None => self.set_status("Mirrors are up to date"),
+        }
+        self.mirror_alert = alert;
+    }
+
+    /// Apply changes from other stations; called from the UI loop
+    pub fn poll_sync(&mut self) {
+        let mut events = Vec::new();
+        if let Some(sync) = self.sync.as_mut() {
+            while let Some(event) = sync.try_next() {
+                events.push(event);
+            }
+        }
+        for event in events {
+            match event {
+                SyncEvent::Op(envelope) => self.apply_sync_op(envelope.op),
+                SyncEvent::Connected(peer) => {
+                    self.set_status(&format!("Sync: {} connected", peer));
+                    self.sync_peers.insert(peer);
+                }
+                SyncEvent::Disconnected(peer) => {
+                    self.set_error(&format!("Sync: {} disconnected", peer));
+                    self.sync_peers.remove(&peer);
+                }
+                SyncEvent::Rejected(reason) => self.set_error(&format!("Sync stopped: {}", reason)),
+            }
+        }
+    }
+
+    fn apply_sync_op(&mut self, op: SyncOp) {
+        let position = self.entries.iter().position(|entry| entry.id == op.entry_id());
+        let result = match (&op, position) {
+            (SyncOp::Upsert { entry }, _) => self.runtime.block_on(self.storage_manager.apply_remote((**entry).clone())).map(Some),
+            (SyncOp::Delete { id }, Some(_)) => self.runtime.block_on(self.storage_manager.apply_remote_delete(id)).map(|_| None),
+            (SyncOp::Delete { .. }, None) => return,
+        };
+        let stored = match result {
+            Ok(stored) => stored,
+            Err(e) => {
+                self.set_error(&format!("Failed to apply change from another station: {}", e));
+                return;
+            }
+        };
+
+        match (stored, position) {
+            (Some(entry), Some(idx)) => self.entries[idx] = entry,
+            (Some(entry), None) => self.entries.push(entry),
+            (None, Some(idx)) => {
+                self.entries.remove(idx);
+                self.selected_index = match self.selected_index {
+                    _ if self.entries.is_empty() => None,
+                    Some(selected) if selected > idx => Some(selected - 1),
+                    Some(selected) => Some(selected.min(self.entries.len() - 1)),
+                    None => None,
+                };
+            }
+            (None, None) => {}
+        }
+        self.backup_if_due();
+    }
+
+    /// Station name and connected peers for the title bar
+    pub fn sync_summary(&self) -> Option<String> {
+        let station = self.station.as_deref()?;
+        let Some(sync) = &self.sync else {
+            return Some(format!("station {}", station));
+        };
+        let role = if sync.local_addr.is_some() { "hub" } else { "client" };
+        let mut summary = format!("station {} | sync {}: {} connected", station, role, self.sync_peers.len());
+        if !self.sync_peers.is_empty() {
+            let peers: Vec<&str> = self.sync_peers.iter().map(String::as_str).collect();
+            summary.push_str(&format!(" ({})", peers.join(", ")));
+        }
+        Some(summary)
+    }
+
+    // Confirmation reconciliation
+    pub fn start_reconcile(&mut self) {
+        self.open_prompt("LoTW/eQSL report file (ADIF)", PromptAction::ReconcileConfirmations);
+    }
+
+    /// Match a downloaded LoTW/eQSL report against the log and show the result
+    pub fn reconcile_confirmations(&mut self, path: &PathBuf) {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                self.set_error(&format!("Failed to read {}: {}", path.display(), e));
+                return;
+            }
+        };
+        let source = ConfirmationSource::detect(&content).unwrap_or(ConfirmationSource::Lotw);
+
+        let result = self.runtime.block_on(self.storage_manager.reconcile_confirmations(
+            &content,
+            source,
+            chrono::Duration::minutes(30),
+        ));
+        let report = match result {
+            Ok(report) => report,
+            Err(e) => {
+                self.set_error(&format!("Reconciliation failed: {}", e));
+                return;
+            }
+        };
+
+        self.reload_entries();
+        let confirmed: Vec<LogEntry> = self.entries.iter()
+            .filter(|entry| report.confirmed.contains(&entry.id))
+            .cloned()
+            .collect();
+        for entry in confirmed {
+            self.publish(SyncOp::upsert(entry));
+        }
+        self.set_status(&format!(
+            "{}: {} newly confirmed, {} already confirmed, {} unmatched",
+            source.label(),
+            report.confirmed.len(),
+            report.already_confirmed,
+            report.unmatched.len()
+        ));
+        self.backup_if_due();
+        self.reconcile_result = Some((source, report));
+        self.mode = AppMode::Reconcile;
+    }
+
+    /// Re-read all entries from storage, keeping the selection in range
+    pub fn reload_entries(&mut self) {
+        match self.runtime.block_on(self.storage_manager.list_entries()) {
+            Ok(entries) => {
+                self.entries = entries;
+                self.selected_index = match self.selected_index {
+                    _ if self.entries.is_empty() => None,
+                    Some(idx) => Some(idx.min(self.entries.len() - 1)),
+                    None => None,
+                };
+            }
+            Err(e) => self.set_error(&format!("Failed to reload entries: {}", e)),
+        }
+    }
+
+    // Callbook lookups
+    fn lookup_callsign(&mut self, callsign: &str) -> Option<CallbookRecord> {
+        let Some(callbook) = self.callbook.as_ref() else {
+            self.set_error("No callbook configured (set AMLOG_CALLBOOK, AMLOG_CALLBOOK_USER and AMLOG_CALLBOOK_PASSWORD)");
+            return None;
+        };
+        if callsign.trim().is_empty() {
+            self.set_error("Enter a callsign to look up");
+            return None;
+        }
+
+        let service = callbook.service_name();
+        match self.runtime.block_on(callbook.lookup(callsign)) {
+            Ok(Some(record)) => {
+                self.set_status(&format!("{} found on {}", record.callsign, record.source));
+                Some(record)
+            }
+            Ok(None) => {
+                self.set_error(&format!("{} not found on {}", callsign.trim().to_uppercase(), service));
+                None
+            }
+            Err(e) => {
+                self.set_error(&format!("Callbook lookup failed: {}", e));
+                None
+            }
+        }
+    }
+
+    /// Look up the callsign on the form and fill any empty station fields
+    pub fn lookup_form_callsign(&mut self) {
+        let callsign = self.form.value("Callsign").to_string();
+        if let Some(record) = self.lookup_callsign(&callsign) {
+            let fields = [
+                ("Name", &record.name),
+                ("QTH", &record.qth),
+                ("State", &record.state),
+                ("Country", &record.country),
+                ("Grid", &record.grid),
+            ];
+            for (label, value) in fields {
+                if let Some(value) = value {
+                    if self.form.value(label).trim().is_empty() {
+                        self.form.set_value(label, value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Look up the selected entry's callsign for display in the detail view
+    pub fn lookup_selected_entry(&mut self) {
+        if let Some(callsign) = self.get_selected_entry().map(|e| e.callsign.clone()) {
+            self.callbook_result = self.lookup_callsign(&callsign);
+        }
+    }
+
+    /// Copy the last lookup result into the selected entry's empty fields
+    pub fn apply_callbook_result(&mut self) {
+        let (Some(record), Some(idx)) = (self.callbook_result.clone(), self.selected_index) else {
+            self.set_error("Nothing to apply; press 'l' to look up first");
+            return;
+        };
+        let Some(mut entry) = self.entries.get(idx).cloned() else {
+            return;
+        };
+        record.apply_to(&mut entry);
+
+        let result = self.runtime.block_on(self.storage_manager.save_entry(entry));
+        match result {
+            Ok(entry) => {
+                self.publish(SyncOp::upsert(entry.clone()));
+                self.entries[idx] = entry;
+                self.set_status("Callbook details saved to entry");
+            }
+            Err(e) => self.set_error(&format!("Failed to save entry: {}", e)),
+        }
+    }
+
     pub fn get_selected_entry(&self) -> Option<&LogEntry> {
         self.selected_index.and_then(|idx| self.entries.get(idx))
     }
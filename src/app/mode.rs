@@ -0,0 +1,47 @@
+// src/app/mode.rs
+use serde::{Deserialize, Serialize};
+
+/// Mode groups used by awards and contests
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModeCategory {
+    Cw,
+    Phone,
+    Digital,
+}
+
+impl ModeCategory {
+    pub const ALL: [ModeCategory; 3] = [ModeCategory::Cw, ModeCategory::Phone, ModeCategory::Digital];
+
+    /// Group an ADIF mode (or submode) name
+    pub fn for_mode(mode: &str) -> Self {
+        match mode.trim().to_uppercase().as_str() {
+            "CW" => ModeCategory::Cw,
+            "SSB" | "USB" | "LSB" | "AM" | "FM" | "DIGITALVOICE" | "C4FM" | "DSTAR" | "DMR" | "FREEDV" => {
+                ModeCategory::Phone
+            }
+            _ => ModeCategory::Digital,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ModeCategory::Cw => "CW",
+            ModeCategory::Phone => "Phone",
+            ModeCategory::Digital => "Digital",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mode_categories() {
+        assert_eq!(ModeCategory::for_mode("cw"), ModeCategory::Cw);
+        assert_eq!(ModeCategory::for_mode("USB"), ModeCategory::Phone);
+        assert_eq!(ModeCategory::for_mode("FT8"), ModeCategory::Digital);
+        assert_eq!(ModeCategory::for_mode("RTTY"), ModeCategory::Digital);
+    }
+}
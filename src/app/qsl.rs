@@ -0,0 +1,316 @@
+// src/app/qsl.rs
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::LogEntry;
+
+/// QSL sent/received status, mirroring the ADIF QSL_SENT/QSL_RCVD enumerations
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QslStatus {
+    Yes,
+    No,
+    Requested,
+    Queued,
+    Ignore,
+    Verified,
+}
+
+impl QslStatus {
+    pub fn as_adif(&self) -> &'static str {
+        match self {
+            QslStatus::Yes => "Y",
+            QslStatus::No => "N",
+            QslStatus::Requested => "R",
+            QslStatus::Queued => "Q",
+            QslStatus::Ignore => "I",
+            QslStatus::Verified => "V",
+        }
+    }
+
+    pub fn from_adif(value: &str) -> Option<Self> {
+        match value.trim().to_uppercase().as_str() {
+            "Y" => Some(QslStatus::Yes),
+            "N" => Some(QslStatus::No),
+            "R" => Some(QslStatus::Requested),
+            "Q" => Some(QslStatus::Queued),
+            "I" => Some(QslStatus::Ignore),
+            "V" => Some(QslStatus::Verified),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QslStatus::Yes => "Yes",
+            QslStatus::No => "No",
+            QslStatus::Requested => "Requested",
+            QslStatus::Queued => "Queued",
+            QslStatus::Ignore => "Ignore",
+            QslStatus::Verified => "Verified",
+        }
+    }
+}
+
+/// Route a paper QSL card was sent or received by (ADIF QSL_VIA)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QslVia {
+    Bureau,
+    Direct,
+    Electronic,
+    Manager,
+}
+
+impl QslVia {
+    pub fn as_adif(&self) -> &'static str {
+        match self {
+            QslVia::Bureau => "B",
+            QslVia::Direct => "D",
+            QslVia::Electronic => "E",
+            QslVia::Manager => "M",
+        }
+    }
+
+    pub fn from_adif(value: &str) -> Option<Self> {
+        match value.trim().to_uppercase().as_str() {
+            "B" => Some(QslVia::Bureau),
+            "D" => Some(QslVia::Direct),
+            "E" => Some(QslVia::Electronic),
+            "M" => Some(QslVia::Manager),
+            _ => None,
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QslVia::Bureau => "Bureau",
+            QslVia::Direct => "Direct",
+            QslVia::Electronic => "Electronic",
+            QslVia::Manager => "Manager",
+        }
+    }
+}
+
+/// Sent/received state for one confirmation service (paper, LoTW or eQSL)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct QslConfirmation {
+    #[serde(default)]
+    pub sent: Option<QslStatus>,
+    #[serde(default)]
+    pub sent_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub received: Option<QslStatus>,
+    #[serde(default)]
+    pub received_date: Option<NaiveDate>,
+}
+
+impl QslConfirmation {
+    /// Whether the other station's confirmation has arrived
+    pub fn is_confirmed(&self) -> bool {
+        matches!(self.received, Some(QslStatus::Yes) | Some(QslStatus::Verified))
+    }
+
+    pub fn is_sent(&self) -> bool {
+        self.sent == Some(QslStatus::Yes)
+    }
+}
+
+/// Filters offered by the QSL management view
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QslFilter {
+    All,
+    Requested,
+    Queued,
+    Sent,
+    Received,
+    ViaBureau,
+    ViaDirect,
+    LotwConfirmed,
+    EqslConfirmed,
+    LotwNotPaper,
+    Unconfirmed,
+}
+
+impl QslFilter {
+    /// Order in which the view cycles through filters
+    pub const ALL: [QslFilter; 11] = [
+        QslFilter::All,
+        QslFilter::Requested,
+        QslFilter::Queued,
+        QslFilter::Sent,
+        QslFilter::Received,
+        QslFilter::ViaBureau,
+        QslFilter::ViaDirect,
+        QslFilter::LotwConfirmed,
+        QslFilter::EqslConfirmed,
+        QslFilter::LotwNotPaper,
+        QslFilter::Unconfirmed,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            QslFilter::All => "All QSOs",
+            QslFilter::Requested => "Card requested",
+            QslFilter::Queued => "Card queued",
+            QslFilter::Sent => "Card sent",
+            QslFilter::Received => "Card received",
+            QslFilter::ViaBureau => "Sent or received via bureau",
+            QslFilter::ViaDirect => "Sent or received direct",
+            QslFilter::LotwConfirmed => "Confirmed on LoTW",
+            QslFilter::EqslConfirmed => "Confirmed on eQSL",
+            QslFilter::LotwNotPaper => "Confirmed on LoTW but not paper",
+            QslFilter::Unconfirmed => "Not confirmed anywhere",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index = Self::ALL.iter().position(|f| f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn matches(&self, entry: &LogEntry) -> bool {
+        let via = |via: QslVia| entry.qsl_sent_via == Some(via) || entry.qsl_rcvd_via == Some(via);
+        match self {
+            QslFilter::All => true,
+            QslFilter::Requested => {
+                entry.qsl.sent == Some(QslStatus::Requested) || entry.qsl.received == Some(QslStatus::Requested)
+            }
+            QslFilter::Queued => entry.qsl.sent == Some(QslStatus::Queued),
+            QslFilter::Sent => entry.qsl.is_sent(),
+            QslFilter::Received => entry.qsl.is_confirmed(),
+            QslFilter::ViaBureau => via(QslVia::Bureau),
+            QslFilter::ViaDirect => via(QslVia::Direct),
+            QslFilter::LotwConfirmed => entry.lotw.is_confirmed(),
+            QslFilter::EqslConfirmed => entry.eqsl.is_confirmed(),
+            QslFilter::LotwNotPaper => entry.lotw.is_confirmed() && !entry.qsl.is_confirmed(),
+            QslFilter::Unconfirmed => {
+                !entry.qsl.is_confirmed() && !entry.lotw.is_confirmed() && !entry.eqsl.is_confirmed()
+            }
+        }
+    }
+}
+
+/// Record that a paper card went out on `date`
+pub fn mark_sent(entry: &mut LogEntry, date: NaiveDate, via: QslVia) {
+    entry.qsl.sent = Some(QslStatus::Yes);
+    entry.qsl.sent_date = Some(date);
+    entry.qsl_sent_via = Some(via);
+}
+
+/// Put a card in the outgoing queue without marking it sent
+pub fn mark_queued(entry: &mut LogEntry, via: QslVia) {
+    entry.qsl.sent = Some(QslStatus::Queued);
+    entry.qsl_sent_via = Some(via);
+}
+
+/// Cards waiting to go out: queued or requested and not yet sent
+pub fn is_in_queue(entry: &LogEntry) -> bool {
+    matches!(entry.qsl.sent, Some(QslStatus::Queued) | Some(QslStatus::Requested))
+}
+
+/// Render the outgoing card queue as CSV, one card per line, ready for
+/// label printing or a bureau submission sheet
+pub fn qsl_queue_csv(entries: &[LogEntry]) -> String {
+    let mut queue: Vec<&LogEntry> = entries.iter().filter(|e| is_in_queue(e)).collect();
+    // Bureaus want cards sorted by callsign
+    queue.sort_by(|a, b| a.callsign.cmp(&b.callsign).then(a.timestamp.cmp(&b.timestamp)));
+
+    let mut csv = String::from("callsign,date,time_utc,band,frequency,mode,rst_sent,via\n");
+    for entry in queue {
+        csv.push_str(&format!(
+            "{},{},{},{},{},{},{},{}\n",
+            csv_field(&entry.callsign),
+            entry.timestamp.format("%Y-%m-%d"),
+            entry.timestamp.format("%H%M"),
+            csv_field(entry.band.as_deref().unwrap_or("")),
+            entry.frequency,
+            csv_field(entry.submode.as_deref().unwrap_or(&entry.mode)),
+            csv_field(&entry.rst_sent.as_ref().map(ToString::to_string).unwrap_or_default()),
+            entry.qsl_sent_via.map_or("", |v| v.label()),
+        ));
+    }
+    csv
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Frequency;
+
+    #[test]
+    fn test_qsl_status_adif_round_trip() {
+        for status in [
+            QslStatus::Yes,
+            QslStatus::No,
+            QslStatus::Requested,
+            QslStatus::Queued,
+            QslStatus::Ignore,
+            QslStatus::Verified,
+        ] {
+            assert_eq!(QslStatus::from_adif(status.as_adif()), Some(status));
+        }
+        assert_eq!(QslStatus::from_adif("y"), Some(QslStatus::Yes));
+        assert_eq!(QslStatus::from_adif("X"), None);
+        assert_eq!(QslVia::from_adif("b"), Some(QslVia::Bureau));
+    }
+
+    fn confirmed() -> QslConfirmation {
+        QslConfirmation {
+            received: Some(QslStatus::Yes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_qsl_filters() {
+        let lotw_only = LogEntry {
+            lotw: confirmed(),
+            ..Default::default()
+        };
+        let both = LogEntry {
+            lotw: confirmed(),
+            qsl: confirmed(),
+            qsl_rcvd_via: Some(QslVia::Bureau),
+            ..Default::default()
+        };
+        let nothing = LogEntry::default();
+
+        assert!(QslFilter::LotwNotPaper.matches(&lotw_only));
+        assert!(!QslFilter::LotwNotPaper.matches(&both));
+        assert!(QslFilter::ViaBureau.matches(&both));
+        assert!(QslFilter::Unconfirmed.matches(&nothing));
+        assert!(!QslFilter::Unconfirmed.matches(&lotw_only));
+        assert_eq!(QslFilter::Unconfirmed.next(), QslFilter::All);
+    }
+
+    #[test]
+    fn test_queue_and_mark_sent() {
+        let mut entry = LogEntry {
+            callsign: "DL1ABC".into(),
+            frequency: Frequency::from_hz(14_025_000),
+            mode: "CW".into(),
+            rst_sent: Some("599".into()),
+            ..Default::default()
+        };
+        mark_queued(&mut entry, QslVia::Bureau);
+        let csv = qsl_queue_csv(std::slice::from_ref(&entry));
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with("DL1ABC,"));
+        assert!(csv.ends_with(",CW,599,Bureau\n"));
+
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        mark_sent(&mut entry, date, QslVia::Bureau);
+        assert!(entry.qsl.is_sent());
+        assert_eq!(entry.qsl.sent_date, Some(date));
+        assert_eq!(qsl_queue_csv(&[entry]).lines().count(), 1);
+    }
+}
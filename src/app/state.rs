@@ -1,30 +1,76 @@
 // src/app/state.rs
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use super::qsl::{QslConfirmation, QslVia};
+use super::values::{Band, Callsign, Frequency, Mode, Power, Rst};
+
 #[derive(Debug, PartialEq)]
 pub enum AppMode {
     Normal,
     NewEntry,
     Edit,
+    Detail,
+    Help,
+    Stats,
+    Qsl,
+    Prompt,
+    Reconcile,
+    Awards,
+    Contest,
+    History,
+    Backups,
+    Fsck,
+    Search,
+}
+
+/// What to do with the text entered at a prompt
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PromptAction {
+    ReconcileConfirmations,
+    StartContest,
+    ContestSentExchange,
+    ContestBonus,
+    ContestPower,
+    ContestCabrillo,
+    StartActivation,
+    PortableUploads,
+    RestoreBackup,
+    RepairLog,
+}
+
+impl PromptAction {
+    /// Prompts opened from contest mode go back to it
+    pub fn returns_to_contest(&self) -> bool {
+        matches!(self, PromptAction::ContestBonus | PromptAction::ContestPower | PromptAction::ContestCabrillo)
+    }
+}
+
+/// Single-line text input shown over the current view
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub label: String,
+    pub input: String,
+    pub action: PromptAction,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct LogEntry {
     pub id: String,
     pub timestamp: chrono::DateTime<chrono::Utc>,
-    pub callsign: String,
-    pub frequency: f64,
-    pub mode: String,
-    pub rst_sent: Option<String>,
-    pub rst_received: Option<String>,
+    pub callsign: Callsign,
+    pub frequency: Frequency,
+    pub mode: Mode,
+    pub rst_sent: Option<Rst>,
+    pub rst_received: Option<Rst>,
     pub notes: Option<String>,
     pub name: Option<String>,
     pub qth: Option<String>,
     pub state: Option<String>,
     pub country: Option<String>,
     pub dxcc: Option<u32>,
-    pub band: Option<String>,
+    pub band: Option<Band>,
 
     
     // Optional fields
@@ -33,16 +79,113 @@ pub struct LogEntry {
     #[serde(default)]
     pub grid: Option<String>,
     #[serde(default)]
-    pub power: Option<f32>,
+    pub power: Option<Power>,
+
+    // Timing and mode detail
+    #[serde(default)]
+    pub time_off: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub submode: Option<String>,
+
+    // Split operation: receive frequency/band when different from transmit
+    #[serde(default)]
+    pub freq_rx: Option<Frequency>,
+    #[serde(default)]
+    pub band_rx: Option<Band>,
+
+    // Propagation
+    #[serde(default)]
+    pub prop_mode: Option<String>,
+    #[serde(default)]
+    pub sat_name: Option<String>,
+    #[serde(default)]
+    pub sat_mode: Option<String>,
+
+    // Location of the worked station
+    #[serde(default)]
+    pub cq_zone: Option<u32>,
+    #[serde(default)]
+    pub itu_zone: Option<u32>,
+    #[serde(default)]
+    pub cont: Option<String>,
+    #[serde(default)]
+    pub county: Option<String>,
+    #[serde(default)]
+    pub iota: Option<String>,
+    #[serde(default)]
+    pub sota_ref: Option<String>,
+    #[serde(default)]
+    pub pota_ref: Option<String>,
+
+    // Special interest activities such as POTA: the worked station's
+    // (SIG/SIG_INFO) and our own (MY_SIG/MY_SIG_INFO)
+    #[serde(default)]
+    pub sig: Option<String>,
+    #[serde(default)]
+    pub sig_info: Option<String>,
+    #[serde(default)]
+    pub my_sig: Option<String>,
+    #[serde(default)]
+    pub my_sig_info: Option<String>,
+    // Our own summit when activating SOTA; `sota_ref` is the one worked
+    #[serde(default)]
+    pub my_sota_ref: Option<String>,
+
+    // Contest exchange
+    #[serde(default)]
+    pub srx: Option<u32>,
+    #[serde(default)]
+    pub stx: Option<u32>,
+    #[serde(default)]
+    pub contest_id: Option<String>,
+
+    // Multi-operator: the station that logged the QSO
+    #[serde(default)]
+    pub station: Option<String>,
+
+    // Confirmations
+    #[serde(default)]
+    pub qsl: QslConfirmation,
+    #[serde(default)]
+    pub qsl_sent_via: Option<QslVia>,
+    #[serde(default)]
+    pub qsl_rcvd_via: Option<QslVia>,
+    #[serde(default)]
+    pub lotw: QslConfirmation,
+    #[serde(default)]
+    pub eqsl: QslConfirmation,
+
+    // Bookkeeping: when the entry was first saved and last changed
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
 
     // Extensible fields for plugins
     #[serde(default)]
     pub custom_fields: HashMap<String, String>,
 }
 
-#[derive(Clone)]
-pub struct DeletedEntry {
-    pub entry: LogEntry,
-    pub index: usize,
-    pub timestamp: chrono::DateTime<chrono::Utc>,
+impl LogEntry {
+    /// The band as logged, falling back to the band the frequency lies in
+    pub fn band_name(&self) -> Option<Band> {
+        self.band
+            .clone()
+            .filter(|b| !b.is_empty())
+            .or_else(|| self.frequency.band())
+    }
+
+    /// Receive band for split and satellite QSOs, falling back to the band
+    /// `freq_rx` lies in
+    pub fn band_rx_name(&self) -> Option<Band> {
+        self.band_rx
+            .clone()
+            .filter(|b| !b.is_empty())
+            .or_else(|| self.freq_rx.and_then(|f| f.band()))
+    }
+
+    /// Mode group of the QSO, preferring the submode when present
+    pub fn mode_category(&self) -> super::mode::ModeCategory {
+        super::mode::ModeCategory::for_mode(self.submode.as_deref().unwrap_or(&self.mode))
+    }
 }
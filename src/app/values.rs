@@ -0,0 +1,456 @@
+// src/app/values.rs
+//! Checked values of a QSO: frequency, power, signal reports, callsign,
+//! mode and band.
+//!
+//! `parse` checks what the operator typed and rejects what does not fit.
+//! Values read back from a log are taken as they are, only tidied up (case,
+//! rounding to whole hertz), so older logs and other programs' files still
+//! load and `fsck` can find what is wrong with them. Each serializes the way
+//! the plain field it replaced did: MHz and watts as numbers, the rest as
+//! strings.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::ops::Deref;
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ValueError {
+    #[error("Invalid frequency '{0}' (MHz, or a number with Hz, kHz or MHz)")]
+    Frequency(String),
+
+    #[error("Invalid power '{0}' (watts, or a number with mW, W or kW)")]
+    Power(String),
+
+    #[error("Invalid signal report '{0}' (RS such as 59, RST such as 599, or dB such as -10)")]
+    Rst(String),
+
+    #[error("Invalid callsign '{0}'")]
+    Callsign(String),
+
+    #[error("Invalid mode '{0}'")]
+    Mode(String),
+}
+
+/// Split a number from a unit after it, e.g. `1.5kW` into 1.5 and `kw`
+fn number_and_unit(input: &str) -> Option<(f64, String)> {
+    let input = input.trim();
+    let split = input.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(input.len());
+    let number: f64 = input[..split].trim().parse().ok()?;
+    number.is_finite().then(|| (number, input[split..].trim().to_lowercase()))
+}
+
+/// A number without trailing zeros, keeping at least `min` decimals
+fn trimmed(value: f64, min: usize, max: usize) -> String {
+    let text = format!("{:.*}", max, value);
+    let Some(dot) = text.find('.') else {
+        return text;
+    };
+    let keep = text.trim_end_matches('0').len().max(dot + 1 + min);
+    text[..keep].trim_end_matches('.').to_string()
+}
+
+/// A frequency in whole hertz. It is signed so that a negative frequency
+/// read from a log is kept for `fsck` to report.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Frequency(i64);
+
+impl Frequency {
+    pub fn from_hz(hz: i64) -> Self {
+        Frequency(hz)
+    }
+
+    /// Rounded to the nearest hertz, sign and all; not-a-number and
+    /// infinity are not frequencies
+    pub fn from_mhz(mhz: f64) -> Result<Self, ValueError> {
+        if !mhz.is_finite() {
+            return Err(ValueError::Frequency(mhz.to_string()));
+        }
+        Ok(Frequency((mhz * 1e6).round() as i64))
+    }
+
+    pub fn hz(&self) -> i64 {
+        self.0
+    }
+
+    pub fn mhz(&self) -> f64 {
+        self.0 as f64 / 1e6
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn abs(&self) -> Self {
+        Frequency(self.0.abs())
+    }
+
+    /// MHz, or a number with Hz, kHz or MHz after it
+    pub fn parse(input: &str) -> Result<Self, ValueError> {
+        let invalid = || ValueError::Frequency(input.trim().to_string());
+        let (number, unit) = number_and_unit(input).ok_or_else(invalid)?;
+        let hz = match unit.as_str() {
+            "" | "mhz" => number * 1e6,
+            "khz" => number * 1e3,
+            "hz" => number,
+            _ => return Err(invalid()),
+        };
+        if hz <= 0.0 {
+            return Err(invalid());
+        }
+        Ok(Frequency(hz.round() as i64))
+    }
+
+    /// ADIF band the frequency lies in
+    pub fn band(&self) -> Option<Band> {
+        super::band::band_for_frequency(self.mhz()).map(Band::from)
+    }
+}
+
+impl TryFrom<f64> for Frequency {
+    type Error = ValueError;
+
+    fn try_from(mhz: f64) -> Result<Self, ValueError> {
+        Frequency::from_mhz(mhz)
+    }
+}
+
+impl From<Frequency> for f64 {
+    fn from(frequency: Frequency) -> Self {
+        frequency.mhz()
+    }
+}
+
+/// MHz with at least three decimals and down to the hertz, e.g. 14.074 or
+/// 14.0755
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&trimmed(self.mhz(), 3, 6))
+    }
+}
+
+/// Transmit power, kept in whole milliwatts. Like `Frequency` it is
+/// signed, so a negative power read from a log is kept for `fsck`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "f64", into = "f64")]
+pub struct Power(i64);
+
+impl Power {
+    /// Rounded to the nearest milliwatt, sign and all; not-a-number and
+    /// infinity are not powers
+    pub fn from_watts(watts: f64) -> Result<Self, ValueError> {
+        if !watts.is_finite() {
+            return Err(ValueError::Power(watts.to_string()));
+        }
+        Ok(Power((watts * 1e3).round() as i64))
+    }
+
+    pub fn watts(&self) -> f64 {
+        self.0 as f64 / 1e3
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.0 < 0
+    }
+
+    /// Watts, or a number with mW, W or kW after it
+    pub fn parse(input: &str) -> Result<Self, ValueError> {
+        let invalid = || ValueError::Power(input.trim().to_string());
+        let (number, unit) = number_and_unit(input).ok_or_else(invalid)?;
+        let milliwatts = match unit.as_str() {
+            "" | "w" => number * 1e3,
+            "mw" => number,
+            "kw" => number * 1e6,
+            _ => return Err(invalid()),
+        };
+        if milliwatts <= 0.0 {
+            return Err(invalid());
+        }
+        Ok(Power(milliwatts.round() as i64))
+    }
+
+    /// Watts as written to ADIF TX_PWR, without a unit
+    pub fn to_adif(&self) -> String {
+        trimmed(self.watts(), 0, 3)
+    }
+}
+
+impl TryFrom<f64> for Power {
+    type Error = ValueError;
+
+    fn try_from(watts: f64) -> Result<Self, ValueError> {
+        Power::from_watts(watts)
+    }
+}
+
+impl From<Power> for f64 {
+    fn from(power: Power) -> Self {
+        power.watts()
+    }
+}
+
+/// In the unit that reads best: 500 mW, 5 W, 1.5 kW
+impl fmt::Display for Power {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+            mw if mw.abs() < 1_000 => write!(f, "{} mW", mw),
+            mw if mw.abs() >= 1_000_000 => write!(f, "{} kW", trimmed(mw as f64 / 1e6, 0, 3)),
+            _ => write!(f, "{} W", trimmed(self.watts(), 0, 3)),
+        }
+    }
+}
+
+/// A signal report
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub enum Rst {
+    /// Readability and strength, for phone
+    Rs(u8, u8),
+    /// Readability, strength and tone, for CW and RTTY
+    Rst(u8, u8, u8),
+    /// Signal to noise in dB, for digital modes such as FT8
+    Db(i8),
+    /// Anything else found in a log, e.g. `5NN` or `59+20`, kept as it is
+    Other(String),
+}
+
+impl Rst {
+    pub fn parse(input: &str) -> Result<Self, ValueError> {
+        match Rst::from(input.trim()) {
+            Rst::Other(text) => Err(ValueError::Rst(text)),
+            rst => Ok(rst),
+        }
+    }
+
+    /// What the report is for, as shown next to it
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Rst::Rs(..) => "RS",
+            Rst::Rst(..) => "RST",
+            Rst::Db(_) => "dB",
+            Rst::Other(_) => "",
+        }
+    }
+}
+
+impl From<&str> for Rst {
+    fn from(text: &str) -> Self {
+        let text = text.trim();
+        let digits: Vec<u8> = text.bytes().map(|b| b.wrapping_sub(b'0')).collect();
+        let in_range = |value: u8, high: u8| (1..=high).contains(&value);
+        match digits.as_slice() {
+            [r, s] if in_range(*r, 5) && in_range(*s, 9) => Rst::Rs(*r, *s),
+            [r, s, t] if in_range(*r, 5) && in_range(*s, 9) && in_range(*t, 9) => Rst::Rst(*r, *s, *t),
+            _ => match text.strip_prefix(['+', '-']).map(|_| text.parse::<i8>()) {
+                Some(Ok(db)) if (-60..=60).contains(&db) => Rst::Db(db),
+                _ => Rst::Other(text.to_string()),
+            },
+        }
+    }
+}
+
+impl From<String> for Rst {
+    fn from(text: String) -> Self {
+        Rst::from(text.as_str())
+    }
+}
+
+impl From<Rst> for String {
+    fn from(rst: Rst) -> Self {
+        rst.to_string()
+    }
+}
+
+/// As logged: 59, 599, or dB with a sign and two digits, e.g. -10 or +05
+impl fmt::Display for Rst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rst::Rs(r, s) => write!(f, "{}{}", r, s),
+            Rst::Rst(r, s, t) => write!(f, "{}{}{}", r, s, t),
+            Rst::Db(db) => write!(f, "{:+03}", db),
+            Rst::Other(text) => f.write_str(text),
+        }
+    }
+}
+
+/// Text values that read and compare as `str`
+macro_rules! text_value {
+    ($name:ident) => {
+        impl $name {
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl Deref for $name {
+            type Target = str;
+
+            fn deref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl From<String> for $name {
+            fn from(text: String) -> Self {
+                $name::from(text.as_str())
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+/// An upper-case callsign, e.g. W1AW, VE3/W1AW or W1AW/P
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Callsign(String);
+
+impl Callsign {
+    /// Letters, digits and `/` between the parts, with a digit and a letter
+    pub fn parse(input: &str) -> Result<Self, ValueError> {
+        let call = Callsign::from(input);
+        let valid = call.0.split('/').all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric()))
+            && call.0.chars().any(|c| c.is_ascii_digit())
+            && call.0.chars().any(|c| c.is_ascii_alphabetic());
+        if !valid {
+            return Err(ValueError::Callsign(input.trim().to_string()));
+        }
+        Ok(call)
+    }
+}
+
+impl From<&str> for Callsign {
+    fn from(text: &str) -> Self {
+        Callsign(text.trim().to_uppercase())
+    }
+}
+
+text_value!(Callsign);
+
+/// An upper-case ADIF mode, e.g. FT8 or SSB
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Mode(String);
+
+impl Mode {
+    /// Letters, digits and dashes, as ADIF mode names are written
+    pub fn parse(input: &str) -> Result<Self, ValueError> {
+        let mode = Mode::from(input);
+        if mode.0.is_empty() || !mode.0.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(ValueError::Mode(input.trim().to_string()));
+        }
+        Ok(mode)
+    }
+}
+
+impl From<&str> for Mode {
+    fn from(text: &str) -> Self {
+        Mode(text.trim().to_uppercase())
+    }
+}
+
+text_value!(Mode);
+
+/// A lower-case ADIF band name, e.g. 20m or 70cm
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct Band(String);
+
+impl From<&str> for Band {
+    fn from(text: &str) -> Self {
+        Band(text.trim().to_lowercase())
+    }
+}
+
+text_value!(Band);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frequency() {
+        let ft8 = Frequency::parse("14.0755").unwrap();
+        assert_eq!(ft8.hz(), 14_075_500);
+        assert_eq!(ft8.to_string(), "14.0755");
+        assert_eq!(Frequency::parse("7074 kHz").unwrap().to_string(), "7.074");
+        assert_eq!(Frequency::parse("7000000Hz").unwrap().to_string(), "7.000");
+        assert_eq!(Frequency::from_mhz(14.074).unwrap(), Frequency::from_hz(14_074_000));
+        assert_eq!(ft8.band().unwrap(), "20m");
+        assert!(Frequency::parse("fourteen").is_err());
+        assert!(Frequency::parse("-7").is_err());
+
+        // Stored as MHz, as before
+        assert_eq!(serde_json::to_string(&ft8).unwrap(), "14.0755");
+        assert_eq!(serde_json::from_str::<Frequency>("14.074").unwrap().hz(), 14_074_000);
+        // A bad value in a log is kept as it is for fsck to find
+        assert_eq!(serde_json::from_str::<Frequency>("-7.074").unwrap().hz(), -7_074_000);
+        assert!(Frequency::from_mhz(f64::INFINITY).is_err());
+    }
+
+    #[test]
+    fn test_power() {
+        assert_eq!(Power::parse("5").unwrap().to_string(), "5 W");
+        assert_eq!(Power::parse("1.5 kW").unwrap().to_string(), "1.5 kW");
+        assert_eq!(Power::parse("500mW").unwrap().to_string(), "500 mW");
+        assert_eq!(Power::parse("500mW").unwrap().to_adif(), "0.5");
+        assert!(Power::parse("5 dBm").is_err());
+        assert_eq!(serde_json::from_str::<Power>("100.0").unwrap(), Power::from_watts(100.0).unwrap());
+        assert!(Power::parse("-5").is_err());
+        // A bad value in a log is kept as it is for fsck to find
+        assert!(serde_json::from_str::<Power>("-5.0").unwrap().is_negative());
+        assert!(Power::from_watts(f64::NAN).is_err());
+    }
+
+    #[test]
+    fn test_reports() {
+        assert_eq!(Rst::parse("59").unwrap(), Rst::Rs(5, 9));
+        assert_eq!(Rst::parse("599").unwrap().kind(), "RST");
+        assert_eq!(Rst::parse("-10").unwrap(), Rst::Db(-10));
+        assert_eq!(Rst::parse("+5").unwrap().to_string(), "+05");
+        assert!(Rst::parse("5NN").is_err());
+        assert!(Rst::parse("69").is_err());
+        // Whatever a log has is kept
+        assert_eq!(serde_json::from_str::<Rst>("\"5NN\"").unwrap().to_string(), "5NN");
+    }
+
+    #[test]
+    fn test_text_values() {
+        assert_eq!(Callsign::parse(" ve3/w1aw ").unwrap(), "VE3/W1AW");
+        assert!(Callsign::parse("W1AW//P").is_err());
+        assert!(Callsign::parse("HELLO").is_err());
+        assert_eq!(Mode::parse("ft8").unwrap(), "FT8");
+        assert!(Mode::parse("").is_err());
+        assert_eq!(Band::from("70CM"), "70cm");
+        assert_eq!(serde_json::to_string(&Callsign::from("w1aw")).unwrap(), "\"W1AW\"");
+    }
+}
@@ -0,0 +1,349 @@
+//! Award progress tracking (DXCC, WAS, WAZ, VUCC, WPX, ...)
+//! Awards are described by data, so new ones only need a definition
+
+mod wpx;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use thiserror::Error;
+
+pub use wpx::wpx_prefix;
+
+use crate::app::band::BANDS;
+use crate::app::mode::ModeCategory;
+use crate::LogEntry;
+
+/// Definitions shipped with amlog
+const BUILTIN_DEFINITIONS: &str = include_str!("definitions.json");
+
+/// Entry fields an award can count
+const KEYS: &[&str] = &[
+    "dxcc", "state", "cq_zone", "itu_zone", "grid4", "wpx_prefix",
+    "cont", "country", "county", "iota", "sota_ref", "pota_ref",
+];
+
+#[derive(Error, Debug)]
+pub enum AwardError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid award definitions: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Award {0} has unknown key {1}")]
+    UnknownKey(String, String),
+}
+
+/// Which confirmations an award accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConfirmationKind {
+    Qsl,
+    Lotw,
+    Eqsl,
+}
+
+fn default_confirmations() -> Vec<ConfirmationKind> {
+    vec![ConfirmationKind::Qsl, ConfirmationKind::Lotw]
+}
+
+/// Data-driven award description
+///
+/// `key` names the entry field whose distinct values are counted (one of
+/// `KEYS`, or `custom:<name>` for a custom field). The optional filters
+/// restrict which QSOs can earn credit.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AwardDefinition {
+    pub id: String,
+    pub name: String,
+    pub key: String,
+    pub target: usize,
+    /// Only QSOs with these DXCC entities count (when the entity is known)
+    #[serde(default)]
+    pub dxcc: Option<Vec<u32>>,
+    /// Only these values count, e.g. the 50 state abbreviations
+    #[serde(default)]
+    pub valid_values: Option<Vec<String>>,
+    #[serde(default)]
+    pub min_frequency: Option<f64>,
+    #[serde(default)]
+    pub max_frequency: Option<f64>,
+    /// Only QSOs with this propagation mode count, e.g. `SAT`
+    #[serde(default)]
+    pub prop_mode: Option<String>,
+    /// QSOs with these propagation modes never count, e.g. `SAT` for the
+    /// terrestrial VUCC
+    #[serde(default)]
+    pub exclude_prop_modes: Vec<String>,
+    #[serde(default = "default_confirmations")]
+    pub confirmations: Vec<ConfirmationKind>,
+}
+
+impl AwardDefinition {
+    fn validate(&self) -> Result<(), AwardError> {
+        if KEYS.contains(&self.key.as_str()) || self.key.starts_with("custom:") {
+            Ok(())
+        } else {
+            Err(AwardError::UnknownKey(self.id.clone(), self.key.clone()))
+        }
+    }
+
+    /// The value this QSO credits towards the award, if any
+    pub fn credit_value(&self, entry: &LogEntry) -> Option<String> {
+        if let (Some(allowed), Some(dxcc)) = (&self.dxcc, entry.dxcc) {
+            if !allowed.contains(&dxcc) {
+                return None;
+            }
+        }
+        if self.min_frequency.is_some_and(|min| entry.frequency.mhz() < min)
+            || self.max_frequency.is_some_and(|max| entry.frequency.mhz() > max)
+        {
+            return None;
+        }
+        if let Some(prop_mode) = &self.prop_mode {
+            if !entry.prop_mode.as_deref().is_some_and(|p| p.eq_ignore_ascii_case(prop_mode)) {
+                return None;
+            }
+        }
+        if let Some(prop_mode) = entry.prop_mode.as_deref() {
+            if self.exclude_prop_modes.iter().any(|p| p.eq_ignore_ascii_case(prop_mode.trim())) {
+                return None;
+            }
+        }
+
+        let value = key_value(&self.key, entry)?;
+        match &self.valid_values {
+            Some(valid) if !valid.iter().any(|v| v.eq_ignore_ascii_case(&value)) => None,
+            _ => Some(value),
+        }
+    }
+
+    pub fn is_confirmed(&self, entry: &LogEntry) -> bool {
+        self.confirmations.iter().any(|kind| match kind {
+            ConfirmationKind::Qsl => entry.qsl.is_confirmed(),
+            ConfirmationKind::Lotw => entry.lotw.is_confirmed(),
+            ConfirmationKind::Eqsl => entry.eqsl.is_confirmed(),
+        })
+    }
+}
+
+fn text(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty()).map(str::to_uppercase)
+}
+
+pub(crate) fn key_value(key: &str, entry: &LogEntry) -> Option<String> {
+    match key {
+        "dxcc" => entry.dxcc.map(|d| d.to_string()),
+        "state" => text(&entry.state),
+        "cq_zone" => entry.cq_zone.map(|z| z.to_string()),
+        "itu_zone" => entry.itu_zone.map(|z| z.to_string()),
+        "grid4" => text(&entry.grid).filter(|g| g.len() >= 4).map(|g| g[..4].to_string()),
+        "wpx_prefix" => wpx_prefix(&entry.callsign),
+        "cont" => text(&entry.cont),
+        "country" => text(&entry.country),
+        "county" => text(&entry.county),
+        "iota" => text(&entry.iota),
+        "sota_ref" => text(&entry.sota_ref),
+        "pota_ref" => text(&entry.pota_ref),
+        _ => key
+            .strip_prefix("custom:")
+            .and_then(|name| entry.custom_fields.get(name))
+            .map(|v| v.trim().to_uppercase())
+            .filter(|v| !v.is_empty()),
+    }
+}
+
+pub fn builtin_definitions() -> Vec<AwardDefinition> {
+    serde_json::from_str(BUILTIN_DEFINITIONS).expect("built-in award definitions are valid")
+}
+
+/// Parse a JSON array of award definitions
+pub fn parse_definitions(json: &str) -> Result<Vec<AwardDefinition>, AwardError> {
+    let definitions: Vec<AwardDefinition> = serde_json::from_str(json)?;
+    for definition in &definitions {
+        definition.validate()?;
+    }
+    Ok(definitions)
+}
+
+/// Built-in definitions plus any in `path`; user definitions replace
+/// built-ins with the same id
+pub fn load_definitions(path: &Path) -> Result<Vec<AwardDefinition>, AwardError> {
+    let mut definitions = builtin_definitions();
+    if path.exists() {
+        for definition in parse_definitions(&std::fs::read_to_string(path)?)? {
+            match definitions.iter_mut().find(|d| d.id == definition.id) {
+                Some(existing) => *existing = definition,
+                None => definitions.push(definition),
+            }
+        }
+    }
+    Ok(definitions)
+}
+
+/// One QSO's contribution to an award
+#[derive(Debug, Clone)]
+pub struct Credit {
+    pub value: String,
+    pub band: String,
+    pub mode: ModeCategory,
+    pub entry_id: String,
+    pub confirmed: bool,
+}
+
+/// A distinct award value with the QSOs that earned it
+#[derive(Debug, Clone)]
+pub struct CreditedValue {
+    pub value: String,
+    pub confirmed: bool,
+    pub entry_ids: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AwardProgress {
+    pub definition: AwardDefinition,
+    pub credits: Vec<Credit>,
+}
+
+impl AwardProgress {
+    pub fn compute(definition: &AwardDefinition, entries: &[LogEntry]) -> Self {
+        let credits = entries
+            .iter()
+            .filter_map(|entry| {
+                Some(Credit {
+                    value: definition.credit_value(entry)?,
+                    band: entry.band_name().map_or_else(|| "other".to_string(), String::from),
+                    mode: entry.mode_category(),
+                    entry_id: entry.id.clone(),
+                    confirmed: definition.is_confirmed(entry),
+                })
+            })
+            .collect();
+        Self {
+            definition: definition.clone(),
+            credits,
+        }
+    }
+
+    /// Bands with at least one credit, in frequency order
+    pub fn bands(&self) -> Vec<String> {
+        let bands: BTreeSet<&str> = self.credits.iter().map(|c| c.band.as_str()).collect();
+        let mut bands: Vec<String> = bands.into_iter().map(str::to_string).collect();
+        bands.sort_by_key(|band| BANDS.iter().position(|(name, _, _)| name == band).unwrap_or(usize::MAX));
+        bands
+    }
+
+    /// Distinct values worked in a slot; `None` means any band or mode
+    pub fn values(&self, band: Option<&str>, mode: Option<ModeCategory>) -> Vec<CreditedValue> {
+        let mut values: BTreeMap<&str, CreditedValue> = BTreeMap::new();
+        for credit in &self.credits {
+            if band.is_some_and(|b| b != credit.band) || mode.is_some_and(|m| m != credit.mode) {
+                continue;
+            }
+            let value = values.entry(&credit.value).or_insert_with(|| CreditedValue {
+                value: credit.value.clone(),
+                confirmed: false,
+                entry_ids: Vec::new(),
+            });
+            value.confirmed |= credit.confirmed;
+            value.entry_ids.push(credit.entry_id.clone());
+        }
+        values.into_values().collect()
+    }
+
+    pub fn worked(&self, band: Option<&str>, mode: Option<ModeCategory>) -> usize {
+        self.values(band, mode).len()
+    }
+
+    pub fn confirmed(&self, band: Option<&str>, mode: Option<ModeCategory>) -> usize {
+        self.values(band, mode).iter().filter(|v| v.confirmed).count()
+    }
+}
+
+pub fn compute_all(definitions: &[AwardDefinition], entries: &[LogEntry]) -> Vec<AwardProgress> {
+    definitions.iter().map(|d| AwardProgress::compute(d, entries)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::{QslConfirmation, QslStatus};
+    use crate::storage::formats::fixtures::qso_on;
+
+    fn confirmed() -> QslConfirmation {
+        QslConfirmation {
+            received: Some(QslStatus::Yes),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_builtin_definitions_are_valid() {
+        let definitions = builtin_definitions();
+        assert!(definitions.iter().all(|d| d.validate().is_ok()));
+        assert!(definitions.iter().any(|d| d.id == "was"));
+    }
+
+    #[test]
+    fn test_was_progress_by_band_and_mode() {
+        let was = builtin_definitions().into_iter().find(|d| d.id == "was").unwrap();
+        let entries = vec![
+            LogEntry { state: Some("ct".to_string()), dxcc: Some(291), lotw: confirmed(), ..qso_on("1", "W1AW", 14.025, "CW") },
+            LogEntry { state: Some("CT".to_string()), dxcc: Some(291), ..qso_on("2", "K1ABC", 7.074, "FT8") },
+            LogEntry { state: Some("TX".to_string()), ..qso_on("3", "W5XX", 14.074, "FT8") },
+            // Canadian province abbreviations are not states
+            LogEntry { state: Some("ON".to_string()), dxcc: Some(1), ..qso_on("4", "VE3XX", 14.074, "FT8") },
+        ];
+
+        let progress = AwardProgress::compute(&was, &entries);
+        assert_eq!(progress.worked(None, None), 2);
+        assert_eq!(progress.confirmed(None, None), 1);
+        assert_eq!(progress.worked(Some("20m"), None), 2);
+        assert_eq!(progress.worked(Some("20m"), Some(ModeCategory::Digital)), 1);
+        assert_eq!(progress.confirmed(Some("40m"), None), 0);
+        assert_eq!(progress.bands(), vec!["40m".to_string(), "20m".to_string()]);
+
+        let ct = &progress.values(None, None)[0];
+        assert_eq!(ct.value, "CT");
+        assert_eq!(ct.entry_ids, vec!["1".to_string(), "2".to_string()]);
+    }
+
+    #[test]
+    fn test_custom_award_definition() {
+        let definitions = parse_definitions(r#"[{
+            "id": "iota", "name": "Islands on the Air", "key": "iota", "target": 100
+        }]"#).unwrap();
+        let entry = LogEntry { iota: Some("na-001".to_string()), ..qso_on("1", "W1AW", 14.0, "SSB") };
+        assert_eq!(definitions[0].credit_value(&entry).as_deref(), Some("NA-001"));
+
+        let bad = parse_definitions(r#"[{"id": "x", "name": "X", "key": "nope", "target": 1}]"#);
+        assert!(matches!(bad, Err(AwardError::UnknownKey(_, _))));
+    }
+
+    #[test]
+    fn test_vucc_ignores_hf() {
+        let vucc = builtin_definitions().into_iter().find(|d| d.id == "vucc").unwrap();
+        let hf = LogEntry { grid: Some("FN31pr".to_string()), ..qso_on("1", "W1AW", 14.0, "SSB") };
+        let vhf = LogEntry { grid: Some("fn31pr".to_string()), ..qso_on("2", "W1AW", 144.2, "SSB") };
+        assert_eq!(vucc.credit_value(&hf), None);
+        assert_eq!(vucc.credit_value(&vhf).as_deref(), Some("FN31"));
+    }
+
+    #[test]
+    fn test_satellite_grids_count_for_satellite_vucc_only() {
+        let definitions = builtin_definitions();
+        let vucc = definitions.iter().find(|d| d.id == "vucc").unwrap();
+        let vucc_sat = definitions.iter().find(|d| d.id == "vucc-sat").unwrap();
+        let sat = LogEntry {
+            grid: Some("EM12".to_string()),
+            prop_mode: Some("SAT".to_string()),
+            sat_name: Some("SO-50".to_string()),
+            ..qso_on("1", "K5XX", 145.85, "FM")
+        };
+        let terrestrial = LogEntry { grid: Some("FN31".to_string()), ..qso_on("2", "W1AW", 144.2, "SSB") };
+        assert_eq!(vucc.credit_value(&sat), None);
+        assert_eq!(vucc_sat.credit_value(&sat).as_deref(), Some("EM12"));
+        assert_eq!(vucc_sat.credit_value(&terrestrial), None);
+    }
+}
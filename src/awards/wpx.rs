@@ -0,0 +1,71 @@
+/// Portable and operating suffixes that do not change the WPX prefix
+const IGNORED_SUFFIXES: &[&str] = &["P", "M", "MM", "AM", "QRP", "A", "LH"];
+
+/// Compute the CQ WPX prefix of a callsign
+///
+/// The prefix is the letter/number combination up to and including the last
+/// digit before the suffix (`N8BJQ` -> `N8`, `2E0ABC` -> `2E0`). Portable
+/// designators replace it (`W1AW/4` -> `W4`, `VE3/W1AW` -> `VE3`) and a
+/// prefix without a digit gets a zero (`F/W1AW` -> `F0`).
+pub fn wpx_prefix(callsign: &str) -> Option<String> {
+    let callsign = callsign.trim().to_uppercase();
+    let parts: Vec<&str> = callsign
+        .split('/')
+        .filter(|part| !part.is_empty() && !IGNORED_SUFFIXES.contains(part))
+        .collect();
+
+    match parts.as_slice() {
+        [] => None,
+        [call] => base_prefix(call),
+        [first, second, ..] => {
+            // The longer part is the home call, the shorter one a designator
+            let (home, designator) = if first.len() >= second.len() { (first, second) } else { (second, first) };
+            if designator.len() == 1 && designator.chars().all(|c| c.is_ascii_digit()) {
+                // Call area change: replace the digit of the home prefix
+                let prefix = base_prefix(home)?;
+                let letters = prefix.trim_end_matches(|c: char| c.is_ascii_digit());
+                Some(format!("{}{}", letters, designator))
+            } else if designator.chars().any(|c| c.is_ascii_digit()) {
+                Some(designator.to_string())
+            } else {
+                Some(format!("{}0", designator))
+            }
+        }
+    }
+}
+
+fn base_prefix(call: &str) -> Option<String> {
+    let chars: Vec<char> = call.chars().collect();
+    // Last digit that is followed only by letters
+    let last_digit = chars
+        .iter()
+        .enumerate()
+        .filter(|(i, c)| c.is_ascii_digit() && chars[i + 1..].iter().all(|c| c.is_ascii_alphabetic()))
+        .map(|(i, _)| i)
+        .next_back();
+
+    match last_digit {
+        Some(i) if i + 1 < chars.len() => Some(chars[..=i].iter().collect()),
+        Some(_) => None,
+        None if chars.len() >= 2 => Some(format!("{}0", chars[..2].iter().collect::<String>())),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wpx_prefix() {
+        assert_eq!(wpx_prefix("N8BJQ").as_deref(), Some("N8"));
+        assert_eq!(wpx_prefix("wb2xyz").as_deref(), Some("WB2"));
+        assert_eq!(wpx_prefix("2E0ABC").as_deref(), Some("2E0"));
+        assert_eq!(wpx_prefix("W1AW/4").as_deref(), Some("W4"));
+        assert_eq!(wpx_prefix("W1AW/P").as_deref(), Some("W1"));
+        assert_eq!(wpx_prefix("VE3/W1AW").as_deref(), Some("VE3"));
+        assert_eq!(wpx_prefix("F/G4ABC").as_deref(), Some("F0"));
+        assert_eq!(wpx_prefix("RAEM").as_deref(), Some("RA0"));
+        assert_eq!(wpx_prefix(""), None);
+    }
+}
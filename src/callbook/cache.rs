@@ -0,0 +1,174 @@
+use chrono::{DateTime, Duration, Utc};
+use sqlx::sqlite::{SqliteConnectOptions, SqlitePool, SqlitePoolOptions};
+use sqlx::Row;
+use std::path::Path;
+
+use super::CallbookRecord;
+use crate::storage::StorageError;
+
+/// On-disk cache of callbook lookups so that previously seen stations can be
+/// resolved without a network round trip (or without a network at all)
+pub struct CallbookCache {
+    pool: SqlitePool,
+    ttl: Duration,
+}
+
+/// A cached lookup together with the time it was fetched
+#[derive(Debug, Clone)]
+pub struct CachedRecord {
+    pub record: CallbookRecord,
+    pub fetched_at: DateTime<Utc>,
+}
+
+impl CachedRecord {
+    pub fn is_fresh(&self, ttl: Duration) -> bool {
+        Utc::now() - self.fetched_at < ttl
+    }
+}
+
+impl CallbookCache {
+    pub async fn new(path: &Path, ttl: Duration) -> Result<Self, StorageError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true);
+
+        let pool = SqlitePoolOptions::new()
+            .max_connections(2)
+            .connect_with(options)
+            .await
+            .map_err(StorageError::Sqlx)?;
+
+        sqlx::query(
+            "
+            CREATE TABLE IF NOT EXISTS callbook_cache (
+                callsign TEXT PRIMARY KEY,
+                source TEXT NOT NULL,
+                record TEXT NOT NULL,
+                fetched_at TEXT NOT NULL
+            )
+            ",
+        )
+        .execute(&pool)
+        .await
+        .map_err(StorageError::Sqlx)?;
+
+        Ok(Self { pool, ttl })
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Fetch a cached record regardless of its age
+    pub async fn get(&self, callsign: &str) -> Result<Option<CachedRecord>, StorageError> {
+        let row = sqlx::query("SELECT record, fetched_at FROM callbook_cache WHERE callsign = ?1")
+            .bind(normalize(callsign))
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+
+        let record: String = row.try_get("record")?;
+        let fetched_at: String = row.try_get("fetched_at")?;
+        let fetched_at = DateTime::parse_from_rfc3339(&fetched_at)
+            .map_err(|e| StorageError::ParseError(e.to_string()))?
+            .with_timezone(&Utc);
+
+        Ok(Some(CachedRecord {
+            record: serde_json::from_str(&record)?,
+            fetched_at,
+        }))
+    }
+
+    /// Fetch a cached record only if it has not expired
+    pub async fn get_fresh(&self, callsign: &str) -> Result<Option<CallbookRecord>, StorageError> {
+        Ok(self
+            .get(callsign)
+            .await?
+            .filter(|cached| cached.is_fresh(self.ttl))
+            .map(|cached| cached.record))
+    }
+
+    /// Store a record under the callsign it was looked up by, which may
+    /// differ from the record's own callsign (e.g. portable prefixes)
+    pub async fn put(&self, callsign: &str, record: &CallbookRecord) -> Result<(), StorageError> {
+        self.put_at(callsign, record, Utc::now()).await
+    }
+
+    async fn put_at(&self, callsign: &str, record: &CallbookRecord, fetched_at: DateTime<Utc>) -> Result<(), StorageError> {
+        sqlx::query(
+            "
+            INSERT INTO callbook_cache (callsign, source, record, fetched_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT(callsign) DO UPDATE SET
+                source = excluded.source,
+                record = excluded.record,
+                fetched_at = excluded.fetched_at
+            ",
+        )
+        .bind(normalize(callsign))
+        .bind(&record.source)
+        .bind(serde_json::to_string(record)?)
+        .bind(fetched_at.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(StorageError::Sqlx)?;
+        Ok(())
+    }
+
+    /// Remove expired records, returning how many were dropped
+    pub async fn purge_expired(&self) -> Result<u64, StorageError> {
+        let cutoff = (Utc::now() - self.ttl).to_rfc3339();
+        let result = sqlx::query("DELETE FROM callbook_cache WHERE fetched_at < ?1")
+            .bind(cutoff)
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        Ok(result.rows_affected())
+    }
+}
+
+fn normalize(callsign: &str) -> String {
+    callsign.trim().to_uppercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(callsign: &str) -> CallbookRecord {
+        CallbookRecord {
+            callsign: callsign.to_string(),
+            name: Some("Hiram Percy Maxim".to_string()),
+            grid: Some("FN31pr".to_string()),
+            source: "test".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_expiry() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let cache = CallbookCache::new(&temp_dir.path().join("callbook.db"), Duration::days(30)).await?;
+
+        cache.put("W1AW", &record("W1AW")).await?;
+        let fresh = cache.get_fresh("w1aw").await?;
+        assert_eq!(fresh.unwrap().grid.as_deref(), Some("FN31pr"));
+
+        cache.put_at("K1ABC", &record("K1ABC"), Utc::now() - Duration::days(31)).await?;
+        assert!(cache.get_fresh("K1ABC").await?.is_none());
+        // Stale records are still available for offline use until purged
+        assert!(cache.get("K1ABC").await?.is_some());
+
+        assert_eq!(cache.purge_expired().await?, 1);
+        assert!(cache.get("K1ABC").await?.is_none());
+        Ok(())
+    }
+}
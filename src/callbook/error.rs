@@ -0,0 +1,38 @@
+use thiserror::Error;
+
+use crate::storage::StorageError;
+
+#[derive(Error, Debug)]
+pub enum CallbookError {
+    #[error("HTTP error: {0}")]
+    Http(String),
+
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+
+    #[error("Session expired")]
+    SessionExpired,
+
+    #[error("Callbook service error: {0}")]
+    Service(String),
+
+    #[error("Invalid callbook response: {0}")]
+    Parse(String),
+
+    #[error("Callbook cache error: {0}")]
+    Cache(#[from] StorageError),
+}
+
+impl From<reqwest::Error> for CallbookError {
+    fn from(error: reqwest::Error) -> Self {
+        CallbookError::Http(error.to_string())
+    }
+}
+
+impl CallbookError {
+    /// Whether the error means the service could not be reached, in which
+    /// case a stale cache entry is better than nothing
+    pub fn is_offline(&self) -> bool {
+        matches!(self, CallbookError::Http(_))
+    }
+}
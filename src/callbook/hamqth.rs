@@ -0,0 +1,169 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::{xml_block, xml_value, Callbook, CallbookError, CallbookRecord, RateLimiter};
+
+const HAMQTH_XML_URL: &str = "https://www.hamqth.com/xml.php";
+
+/// HamQTH.com XML interface client
+///
+/// Session ids are valid for one hour; an expired id is detected from the
+/// error message and replaced by logging in again.
+pub struct HamQthClient {
+    http: reqwest::Client,
+    base_url: String,
+    username: String,
+    password: String,
+    session_id: Mutex<Option<String>>,
+    limiter: RateLimiter,
+}
+
+impl HamQthClient {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            http: super::http_client(),
+            base_url: HAMQTH_XML_URL.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            session_id: Mutex::new(None),
+            limiter: RateLimiter::new(Duration::from_millis(500)),
+        }
+    }
+
+    /// Point the client at a different server (used by tests)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.limiter = RateLimiter::new(interval);
+        self
+    }
+
+    async fn get(&self, params: &[(&str, &str)]) -> Result<String, CallbookError> {
+        self.limiter.acquire().await;
+        let response = self.http
+            .get(&self.base_url)
+            .query(params)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.text().await?)
+    }
+
+    async fn login(&self) -> Result<String, CallbookError> {
+        let body = self.get(&[
+            ("u", self.username.as_str()),
+            ("p", self.password.as_str()),
+        ]).await?;
+
+        let session = xml_block(&body, "session")
+            .ok_or_else(|| CallbookError::Parse("missing session element".to_string()))?;
+        match xml_value(session, "session_id") {
+            Some(id) => Ok(id),
+            None => Err(CallbookError::Auth(
+                xml_value(session, "error").unwrap_or_else(|| "no session id returned".to_string()),
+            )),
+        }
+    }
+
+    async fn session_id(&self) -> Result<String, CallbookError> {
+        let mut session_id = self.session_id.lock().await;
+        if let Some(id) = session_id.as_ref() {
+            return Ok(id.clone());
+        }
+        let id = self.login().await?;
+        *session_id = Some(id.clone());
+        Ok(id)
+    }
+
+    async fn lookup_with_id(&self, id: &str, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError> {
+        let body = self.get(&[("id", id), ("callsign", callsign), ("prg", super::AGENT)]).await?;
+
+        if let Some(error) = xml_block(&body, "session").and_then(|s| xml_value(s, "error")) {
+            if error.contains("not found") {
+                return Ok(None);
+            }
+            if error.contains("Session does not exist or expired") {
+                return Err(CallbookError::SessionExpired);
+            }
+            return Err(CallbookError::Service(error));
+        }
+
+        Ok(xml_block(&body, "search").map(parse_search))
+    }
+}
+
+fn parse_search(block: &str) -> CallbookRecord {
+    CallbookRecord {
+        callsign: xml_value(block, "callsign").unwrap_or_default().to_uppercase(),
+        name: xml_value(block, "adr_name").or_else(|| xml_value(block, "nick")),
+        qth: xml_value(block, "qth").or_else(|| xml_value(block, "adr_city")),
+        state: xml_value(block, "us_state"),
+        county: xml_value(block, "us_county"),
+        country: xml_value(block, "country"),
+        grid: xml_value(block, "grid"),
+        dxcc: xml_value(block, "adif").and_then(|v| v.parse().ok()),
+        cq_zone: xml_value(block, "cq").and_then(|v| v.parse().ok()),
+        itu_zone: xml_value(block, "itu").and_then(|v| v.parse().ok()),
+        source: "HamQTH".to_string(),
+    }
+}
+
+#[async_trait]
+impl Callbook for HamQthClient {
+    async fn lookup(&self, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError> {
+        let id = self.session_id().await?;
+        match self.lookup_with_id(&id, callsign).await {
+            Err(CallbookError::SessionExpired) => {
+                *self.session_id.lock().await = None;
+                let id = self.session_id().await?;
+                self.lookup_with_id(&id, callsign).await
+            }
+            result => result,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "HamQTH"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callbook::mock::MockServer;
+
+    #[tokio::test]
+    async fn test_hamqth_lookup_and_session_renewal() {
+        let server = MockServer::start(|target| {
+            if target.contains("p=secret") {
+                "<HamQTH><session><session_id>fresh</session_id></session></HamQTH>".to_string()
+            } else if target.contains("id=fresh") && target.contains("callsign=OK1RR") {
+                "<HamQTH><search><callsign>ok1rr</callsign><nick>Petr</nick><qth>Neratovice</qth>\
+                 <country>Czech Republic</country><adif>503</adif><itu>28</itu><cq>15</cq>\
+                 <grid>JO70gg</grid></search></HamQTH>".to_string()
+            } else if target.contains("id=fresh") {
+                "<HamQTH><session><error>Callsign not found</error></session></HamQTH>".to_string()
+            } else {
+                "<HamQTH><session><error>Session does not exist or expired</error></session></HamQTH>".to_string()
+            }
+        }).await;
+
+        let client = HamQthClient::new("user", "secret")
+            .with_base_url(&server.url())
+            .with_min_interval(Duration::from_millis(1));
+        *client.session_id.lock().await = Some("expired".to_string());
+
+        let record = client.lookup("OK1RR").await.unwrap().unwrap();
+        assert_eq!(record.callsign, "OK1RR");
+        assert_eq!(record.name.as_deref(), Some("Petr"));
+        assert_eq!(record.qth.as_deref(), Some("Neratovice"));
+        assert_eq!(record.dxcc, Some(503));
+        assert_eq!(record.itu_zone, Some(28));
+
+        assert!(client.lookup("N0CALL").await.unwrap().is_none());
+    }
+}
@@ -0,0 +1,324 @@
+//! Callbook lookups for filling in station details
+//! Supports QRZ.com XML and HamQTH, backed by a local SQLite cache
+
+mod cache;
+mod error;
+mod hamqth;
+mod qrz;
+mod rate_limit;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+
+pub use cache::{CachedRecord, CallbookCache};
+pub use error::CallbookError;
+pub use hamqth::HamQthClient;
+pub use qrz::QrzClient;
+pub use rate_limit::RateLimiter;
+
+use crate::LogEntry;
+
+/// Program name reported to callbook services
+pub(crate) const AGENT: &str = concat!("amlog-", env!("CARGO_PKG_VERSION"));
+
+/// Station details returned by a callbook service
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CallbookRecord {
+    pub callsign: String,
+    pub name: Option<String>,
+    pub qth: Option<String>,
+    pub state: Option<String>,
+    pub county: Option<String>,
+    pub country: Option<String>,
+    pub grid: Option<String>,
+    pub dxcc: Option<u32>,
+    pub cq_zone: Option<u32>,
+    pub itu_zone: Option<u32>,
+    /// Name of the service the record came from
+    pub source: String,
+}
+
+impl CallbookRecord {
+    /// Fill in any station fields the entry does not already have
+    pub fn apply_to(&self, entry: &mut LogEntry) {
+        fill(&mut entry.name, &self.name);
+        fill(&mut entry.qth, &self.qth);
+        fill(&mut entry.state, &self.state);
+        fill(&mut entry.county, &self.county);
+        fill(&mut entry.country, &self.country);
+        fill(&mut entry.grid, &self.grid);
+        entry.dxcc = entry.dxcc.or(self.dxcc);
+        entry.cq_zone = entry.cq_zone.or(self.cq_zone);
+        entry.itu_zone = entry.itu_zone.or(self.itu_zone);
+    }
+}
+
+fn fill(target: &mut Option<String>, value: &Option<String>) {
+    if target.as_deref().is_none_or(str::is_empty) {
+        target.clone_from(value);
+    }
+}
+
+#[async_trait]
+pub trait Callbook: Send + Sync {
+    /// Look up a callsign, returning `None` if the service does not know it
+    async fn lookup(&self, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError>;
+
+    /// Human readable service name
+    fn name(&self) -> &'static str;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CallbookProvider {
+    Qrz,
+    HamQth,
+}
+
+/// Callbook settings, read from `AMLOG_CALLBOOK`, `AMLOG_CALLBOOK_USER`,
+/// `AMLOG_CALLBOOK_PASSWORD` and `AMLOG_CALLBOOK_CACHE_DAYS`
+#[derive(Debug, Clone)]
+pub struct CallbookConfig {
+    pub provider: CallbookProvider,
+    pub username: String,
+    pub password: String,
+    pub cache_days: i64,
+}
+
+impl CallbookConfig {
+    /// Returns `None` when no callbook is configured
+    pub fn from_env() -> Option<Self> {
+        let provider = match env::var("AMLOG_CALLBOOK").ok()?.to_lowercase().as_str() {
+            "qrz" => CallbookProvider::Qrz,
+            "hamqth" => CallbookProvider::HamQth,
+            _ => return None,
+        };
+
+        Some(Self {
+            provider,
+            username: env::var("AMLOG_CALLBOOK_USER").ok()?,
+            password: env::var("AMLOG_CALLBOOK_PASSWORD").ok()?,
+            cache_days: env::var("AMLOG_CALLBOOK_CACHE_DAYS")
+                .ok()
+                .and_then(|days| days.parse().ok())
+                .unwrap_or(30),
+        })
+    }
+}
+
+/// A callbook service fronted by the on-disk cache
+///
+/// Fresh cache hits never touch the network. When the service cannot be
+/// reached, an expired cache entry is returned instead of an error, so
+/// expired entries are kept rather than purged when the cache is opened.
+pub struct CachedCallbook {
+    service: Box<dyn Callbook>,
+    cache: CallbookCache,
+}
+
+impl CachedCallbook {
+    pub fn new(service: Box<dyn Callbook>, cache: CallbookCache) -> Self {
+        Self { service, cache }
+    }
+
+    pub async fn from_config(config: &CallbookConfig, cache_path: &Path) -> Result<Self, CallbookError> {
+        let service: Box<dyn Callbook> = match config.provider {
+            CallbookProvider::Qrz => Box::new(QrzClient::new(&config.username, &config.password)),
+            CallbookProvider::HamQth => Box::new(HamQthClient::new(&config.username, &config.password)),
+        };
+        let cache = CallbookCache::new(cache_path, chrono::Duration::days(config.cache_days)).await?;
+        Ok(Self::new(service, cache))
+    }
+
+    pub fn service_name(&self) -> &'static str {
+        self.service.name()
+    }
+
+    pub async fn lookup(&self, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError> {
+        let callsign = callsign.trim().to_uppercase();
+        if let Some(record) = self.cache.get_fresh(&callsign).await? {
+            return Ok(Some(record));
+        }
+
+        match self.service.lookup(&callsign).await {
+            Ok(Some(record)) => {
+                self.cache.put(&callsign, &record).await?;
+                Ok(Some(record))
+            }
+            Ok(None) => Ok(None),
+            Err(e) if e.is_offline() => match self.cache.get(&callsign).await? {
+                Some(cached) => Ok(Some(cached.record)),
+                None => Err(e),
+            },
+            Err(e) => Err(e),
+        }
+    }
+}
+
+pub(crate) fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .user_agent(AGENT)
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .unwrap_or_default()
+}
+
+/// Return the inner text of the first `<tag>...</tag>` element
+pub(crate) fn xml_block<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{}>", tag);
+    let close = format!("</{}>", tag);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&close)?;
+    Some(&xml[start..end])
+}
+
+/// Return the trimmed, unescaped text of a simple element, if non-empty
+pub(crate) fn xml_value(xml: &str, tag: &str) -> Option<String> {
+    let value = xml_block(xml, tag)?.trim();
+    if value.is_empty() {
+        return None;
+    }
+    Some(
+        value
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+#[cfg(test)]
+pub(crate) mod mock {
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    /// Minimal HTTP server answering every GET with the handler's body
+    pub struct MockServer {
+        addr: std::net::SocketAddr,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        pub async fn start<F>(handler: F) -> Self
+        where
+            F: Fn(&str) -> String + Send + Sync + 'static,
+        {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let handler = Arc::new(handler);
+
+            let log = requests.clone();
+            tokio::spawn(async move {
+                while let Ok((mut socket, _)) = listener.accept().await {
+                    let mut buf = Vec::new();
+                    let mut chunk = [0u8; 1024];
+                    while !buf.windows(4).any(|w| w == b"\r\n\r\n") {
+                        match socket.read(&mut chunk).await {
+                            Ok(0) | Err(_) => break,
+                            Ok(n) => buf.extend_from_slice(&chunk[..n]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&buf);
+                    let target = request.split_whitespace().nth(1).unwrap_or("").to_string();
+                    log.lock().unwrap().push(target.clone());
+
+                    let body = handler(&target);
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: text/xml\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                        body.len(),
+                        body
+                    );
+                    let _ = socket.write_all(response.as_bytes()).await;
+                }
+            });
+
+            Self { addr, requests }
+        }
+
+        pub fn url(&self) -> String {
+            format!("http://{}/xml", self.addr)
+        }
+
+        pub fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    struct FlakyCallbook {
+        online: Arc<AtomicBool>,
+    }
+
+    #[async_trait]
+    impl Callbook for FlakyCallbook {
+        async fn lookup(&self, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError> {
+            if !self.online.load(Ordering::SeqCst) {
+                return Err(CallbookError::Http("connection refused".to_string()));
+            }
+            Ok(Some(CallbookRecord {
+                callsign: callsign.to_string(),
+                grid: Some("FN31pr".to_string()),
+                source: "test".to_string(),
+                ..Default::default()
+            }))
+        }
+
+        fn name(&self) -> &'static str {
+            "test"
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cached_callbook_works_offline() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        // Zero TTL: every cached record is immediately stale
+        let cache = CallbookCache::new(&temp_dir.path().join("callbook.db"), chrono::Duration::zero())
+            .await
+            .unwrap();
+        let online = Arc::new(AtomicBool::new(true));
+        let callbook = CachedCallbook::new(Box::new(FlakyCallbook { online: online.clone() }), cache);
+
+        assert!(callbook.lookup("w1aw").await.unwrap().is_some());
+
+        online.store(false, Ordering::SeqCst);
+        let record = callbook.lookup("W1AW").await.unwrap().unwrap();
+        assert_eq!(record.grid.as_deref(), Some("FN31pr"));
+        assert!(callbook.lookup("K1ABC").await.unwrap_err().is_offline());
+    }
+
+    #[test]
+    fn test_apply_to_keeps_existing_values() {
+        let record = CallbookRecord {
+            callsign: "W1AW".to_string(),
+            name: Some("Hiram".to_string()),
+            qth: Some("Newington".to_string()),
+            county: Some("Hartford".to_string()),
+            dxcc: Some(291),
+            cq_zone: Some(5),
+            itu_zone: Some(8),
+            ..Default::default()
+        };
+        let mut entry = LogEntry {
+            qth: Some("Hartford".to_string()),
+            itu_zone: Some(7),
+            ..Default::default()
+        };
+        record.apply_to(&mut entry);
+        assert_eq!(entry.name.as_deref(), Some("Hiram"));
+        assert_eq!(entry.qth.as_deref(), Some("Hartford"));
+        assert_eq!(entry.county.as_deref(), Some("Hartford"));
+        assert_eq!(entry.dxcc, Some(291));
+        assert_eq!(entry.cq_zone, Some(5));
+        assert_eq!(entry.itu_zone, Some(7));
+    }
+}
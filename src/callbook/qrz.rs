@@ -0,0 +1,212 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::{xml_block, xml_value, Callbook, CallbookError, CallbookRecord, RateLimiter};
+
+const QRZ_XML_URL: &str = "https://xmldata.qrz.com/xml/current/";
+
+/// QRZ.com XML data service client
+///
+/// Logs in lazily, keeps the session key for subsequent lookups and logs in
+/// again once when the service reports the key as expired or invalid.
+pub struct QrzClient {
+    http: reqwest::Client,
+    base_url: String,
+    username: String,
+    password: String,
+    session_key: Mutex<Option<String>>,
+    limiter: RateLimiter,
+}
+
+impl QrzClient {
+    pub fn new(username: &str, password: &str) -> Self {
+        Self {
+            http: super::http_client(),
+            base_url: QRZ_XML_URL.to_string(),
+            username: username.to_string(),
+            password: password.to_string(),
+            session_key: Mutex::new(None),
+            limiter: RateLimiter::new(Duration::from_millis(500)),
+        }
+    }
+
+    /// Point the client at a different server (used by tests)
+    pub fn with_base_url(mut self, base_url: &str) -> Self {
+        self.base_url = base_url.to_string();
+        self
+    }
+
+    pub fn with_min_interval(mut self, interval: Duration) -> Self {
+        self.limiter = RateLimiter::new(interval);
+        self
+    }
+
+    async fn get(&self, params: &[(&str, &str)]) -> Result<String, CallbookError> {
+        self.limiter.acquire().await;
+        let response = self.http
+            .get(&self.base_url)
+            .query(params)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(response.text().await?)
+    }
+
+    async fn login(&self) -> Result<String, CallbookError> {
+        let body = self.get(&[
+            ("username", self.username.as_str()),
+            ("password", self.password.as_str()),
+            ("agent", super::AGENT),
+        ]).await?;
+
+        let session = xml_block(&body, "Session")
+            .ok_or_else(|| CallbookError::Parse("missing Session element".to_string()))?;
+        match xml_value(session, "Key") {
+            Some(key) => Ok(key),
+            None => Err(CallbookError::Auth(
+                xml_value(session, "Error").unwrap_or_else(|| "no session key returned".to_string()),
+            )),
+        }
+    }
+
+    async fn session_key(&self) -> Result<String, CallbookError> {
+        let mut session_key = self.session_key.lock().await;
+        if let Some(key) = session_key.as_ref() {
+            return Ok(key.clone());
+        }
+        let key = self.login().await?;
+        *session_key = Some(key.clone());
+        Ok(key)
+    }
+
+    async fn lookup_with_key(&self, key: &str, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError> {
+        let body = self.get(&[("s", key), ("callsign", callsign)]).await?;
+
+        if let Some(error) = xml_block(&body, "Session").and_then(|s| xml_value(s, "Error")) {
+            if error.starts_with("Not found") {
+                return Ok(None);
+            }
+            if error.contains("Session Timeout") || error.contains("Invalid session key") {
+                return Err(CallbookError::SessionExpired);
+            }
+            return Err(CallbookError::Service(error));
+        }
+
+        Ok(xml_block(&body, "Callsign").map(parse_callsign))
+    }
+}
+
+fn parse_callsign(block: &str) -> CallbookRecord {
+    let name = match (xml_value(block, "fname"), xml_value(block, "name")) {
+        (Some(first), Some(last)) => Some(format!("{} {}", first, last)),
+        (first, last) => first.or(last),
+    };
+
+    CallbookRecord {
+        callsign: xml_value(block, "call").unwrap_or_default().to_uppercase(),
+        name,
+        qth: xml_value(block, "addr2"),
+        state: xml_value(block, "state"),
+        county: xml_value(block, "county"),
+        country: xml_value(block, "country"),
+        grid: xml_value(block, "grid"),
+        dxcc: xml_value(block, "dxcc").and_then(|v| v.parse().ok()),
+        cq_zone: xml_value(block, "cqzone").and_then(|v| v.parse().ok()),
+        itu_zone: xml_value(block, "ituzone").and_then(|v| v.parse().ok()),
+        source: "QRZ".to_string(),
+    }
+}
+
+#[async_trait]
+impl Callbook for QrzClient {
+    async fn lookup(&self, callsign: &str) -> Result<Option<CallbookRecord>, CallbookError> {
+        let key = self.session_key().await?;
+        match self.lookup_with_key(&key, callsign).await {
+            Err(CallbookError::SessionExpired) => {
+                *self.session_key.lock().await = None;
+                let key = self.session_key().await?;
+                self.lookup_with_key(&key, callsign).await
+            }
+            result => result,
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        "QRZ"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::callbook::mock::MockServer;
+
+    const LOGIN: &str = "<QRZDatabase><Session><Key>abc123</Key><Count>1</Count></Session></QRZDatabase>";
+    const W1AW: &str = "<QRZDatabase><Callsign><call>W1AW</call><fname>Hiram</fname><name>Maxim</name>\
+        <addr2>Newington</addr2><state>CT</state><country>United States</country><grid>FN31pr</grid>\
+        <dxcc>291</dxcc><cqzone>5</cqzone><ituzone>8</ituzone></Callsign>\
+        <Session><Key>abc123</Key></Session></QRZDatabase>";
+
+    fn client(server: &MockServer) -> QrzClient {
+        QrzClient::new("user", "secret")
+            .with_base_url(&server.url())
+            .with_min_interval(Duration::from_millis(1))
+    }
+
+    #[tokio::test]
+    async fn test_qrz_lookup() {
+        let server = MockServer::start(|target| {
+            if target.contains("password=secret") {
+                LOGIN.to_string()
+            } else if target.contains("s=abc123") && target.contains("callsign=W1AW") {
+                W1AW.to_string()
+            } else {
+                "<QRZDatabase><Session><Error>Not found: K0XXX</Error></Session></QRZDatabase>".to_string()
+            }
+        }).await;
+        let client = client(&server);
+
+        let record = client.lookup("W1AW").await.unwrap().unwrap();
+        assert_eq!(record.name.as_deref(), Some("Hiram Maxim"));
+        assert_eq!(record.qth.as_deref(), Some("Newington"));
+        assert_eq!(record.grid.as_deref(), Some("FN31pr"));
+        assert_eq!(record.dxcc, Some(291));
+        assert_eq!(record.cq_zone, Some(5));
+
+        assert!(client.lookup("K0XXX").await.unwrap().is_none());
+        // One login, two lookups: the session key is reused
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_qrz_relogin_on_expired_session() {
+        let server = MockServer::start(|target| {
+            if target.contains("password=secret") {
+                LOGIN.to_string()
+            } else if target.contains("s=stale") {
+                "<QRZDatabase><Session><Error>Session Timeout</Error></Session></QRZDatabase>".to_string()
+            } else {
+                W1AW.to_string()
+            }
+        }).await;
+        let client = client(&server);
+        *client.session_key.lock().await = Some("stale".to_string());
+
+        let record = client.lookup("W1AW").await.unwrap().unwrap();
+        assert_eq!(record.callsign, "W1AW");
+        assert_eq!(client.session_key.lock().await.as_deref(), Some("abc123"));
+    }
+
+    #[tokio::test]
+    async fn test_qrz_bad_credentials() {
+        let server = MockServer::start(|_| {
+            "<QRZDatabase><Session><Error>Username/password incorrect</Error></Session></QRZDatabase>".to_string()
+        }).await;
+
+        match client(&server).lookup("W1AW").await {
+            Err(CallbookError::Auth(message)) => assert!(message.contains("incorrect")),
+            other => panic!("expected auth error, got {:?}", other),
+        }
+    }
+}
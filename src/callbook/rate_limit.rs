@@ -0,0 +1,45 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Enforces a minimum interval between requests to a callbook service
+pub struct RateLimiter {
+    min_interval: Duration,
+    last_request: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            last_request: Mutex::new(None),
+        }
+    }
+
+    /// Wait until another request is allowed and claim the slot
+    pub async fn acquire(&self) {
+        let mut last = self.last_request.lock().await;
+        if let Some(previous) = *last {
+            let ready_at = previous + self.min_interval;
+            if ready_at > Instant::now() {
+                tokio::time::sleep_until(ready_at).await;
+            }
+        }
+        *last = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_rate_limiter_spaces_requests() {
+        let limiter = RateLimiter::new(Duration::from_millis(50));
+        let start = std::time::Instant::now();
+        limiter.acquire().await;
+        limiter.acquire().await;
+        limiter.acquire().await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
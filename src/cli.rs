@@ -0,0 +1,83 @@
+//! Backup, integrity and custom field commands run from the shell instead
+//! of the TUI, e.g. to bring back a log that will not open
+
+use std::io;
+use std::process;
+use tokio::runtime::Runtime;
+
+use amlog::app;
+use amlog::storage::{backup, BackupConfig, BackupReason, FieldDefinition, FieldType, StorageError};
+
+const USAGE: &str = "usage: amlog [backup | backups | restore <backup> | fsck [--fix] | fields \
+    | field add <name> <text|number|date|enum|boolean> [value,...] | field remove <name>]";
+
+/// Run the command in `args`, exiting with an error status on failure
+pub fn run(args: &[String]) -> Result<(), io::Error> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    if !matches!(
+        args.as_slice(),
+        ["backup"] | ["backups"] | ["restore", _] | ["fsck"] | ["fsck", "--fix"] | ["fields"]
+            | ["field", "add", _, _] | ["field", "add", _, _, _] | ["field", "remove", _]
+    ) {
+        eprintln!("{}", USAGE);
+        process::exit(2);
+    }
+
+    let runtime = Runtime::new()?;
+    let result = runtime.block_on(async {
+        let data_dir = app::data_dir()?;
+        if let ["restore", name] = args.as_slice() {
+            // Restored without opening the log, which may be what is broken
+            let path = app::log_path(&data_dir);
+            let config = BackupConfig::from_env(&path);
+            let restored = backup::find(&config.dir, &path, name)?;
+            match backup::restore(&config, &path, &restored).await? {
+                Some(previous) => println!("Restored {}; the log as it was is in {}", restored.name(), previous.name()),
+                None => println!("Restored {}", restored.name()),
+            }
+            return Ok(());
+        }
+
+        let log = app::open_log(&data_dir).await?;
+        match args.as_slice() {
+            ["backup"] => {
+                let backup = log.backup(BackupReason::Manual).await?;
+                println!("{}", backup.path.display());
+            }
+            ["fsck", options @ ..] => {
+                let report = log.fsck(&app::known_custom_fields(&data_dir), options == ["--fix"]).await?;
+                for problem in &report.problems {
+                    println!("{}", problem);
+                }
+                println!("{}", report.summary());
+                if !report.repaired && report.fixable() > 0 {
+                    println!("Run amlog fsck --fix to fix them; the log is backed up first");
+                }
+            }
+            ["fields"] => {
+                for definition in log.field_definitions().await? {
+                    println!("{:<20} {:<8} {}", definition.name, definition.field_type.as_str(), definition.options.join(","));
+                }
+            }
+            ["field", "add", name, field_type, options @ ..] => {
+                let field_type = FieldType::parse(field_type)
+                    .ok_or_else(|| StorageError::Validation(format!("unknown field type '{}'", field_type)))?;
+                let options = options.first().map(|o| o.split(',').map(str::to_string).collect()).unwrap_or_default();
+                log.define_field(FieldDefinition::new(name, field_type, options)?).await?;
+            }
+            ["field", "remove", name] => log.remove_field(&name.to_uppercase()).await?,
+            _ => {
+                for backup in log.backups()? {
+                    println!("{}  {:<12} {}", backup.taken_at.format("%Y-%m-%d %H:%M:%S"), backup.reason.as_str(), backup.name());
+                }
+            }
+        }
+        Ok::<(), StorageError>(())
+    });
+
+    if let Err(e) = result {
+        eprintln!("amlog: {}", e);
+        process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,288 @@
+// src/contest/cabrillo.rs
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{exchange_value, ContestSession};
+use crate::app::band::BANDS;
+use crate::app::mode::ModeCategory;
+use crate::app::Frequency;
+use crate::LogEntry;
+
+/// Calls per line on the dupe sheet
+const CALLS_PER_LINE: usize = 6;
+
+/// Cabrillo frequency: kHz on HF, the band designator from 6m up
+fn cabrillo_frequency(frequency: Frequency) -> String {
+    let band = match frequency.mhz() {
+        f if f < 30.0 => None,
+        f if (50.0..=54.0).contains(&f) => Some("50"),
+        f if (144.0..=148.0).contains(&f) => Some("144"),
+        f if (222.0..=225.0).contains(&f) => Some("222"),
+        f if (420.0..=450.0).contains(&f) => Some("432"),
+        f if (902.0..=928.0).contains(&f) => Some("902"),
+        f if (1240.0..=1300.0).contains(&f) => Some("1.2G"),
+        f if (2300.0..=2450.0).contains(&f) => Some("2.3G"),
+        f if (3300.0..=3500.0).contains(&f) => Some("3.4G"),
+        f if (5650.0..=5925.0).contains(&f) => Some("5.7G"),
+        f if (10000.0..=10500.0).contains(&f) => Some("10G"),
+        _ => None,
+    };
+    band.map(str::to_string)
+        .unwrap_or_else(|| format!("{}", (frequency.mhz() * 1000.0).round() as i64))
+}
+
+fn cabrillo_mode(entry: &LogEntry) -> &'static str {
+    match entry.mode_category() {
+        ModeCategory::Cw => "CW",
+        ModeCategory::Phone if entry.mode.eq_ignore_ascii_case("FM") => "FM",
+        ModeCategory::Phone => "PH",
+        ModeCategory::Digital if entry.mode.eq_ignore_ascii_case("RTTY") => "RY",
+        ModeCategory::Digital => "DG",
+    }
+}
+
+/// Our exchange as sent on a QSO, falling back to the session's values
+fn sent_exchange(session: &ContestSession, entry: &LogEntry) -> Vec<String> {
+    session.definition.sent.iter()
+        .map(|field| match field.field.as_str() {
+            "stx" => entry.stx.map(|n| format!("{:03}", n)).unwrap_or_default(),
+            name => exchange_value(entry, name)
+                .or_else(|| session.sent_value(name).map(str::to_string))
+                .unwrap_or_default(),
+        })
+        .collect()
+}
+
+/// Cabrillo 3.0 log of the contest QSOs, including dupes (the log checker
+/// removes them) and the claimed score
+pub fn cabrillo_log(session: &ContestSession, entries: &[LogEntry], callsign: &str) -> String {
+    let definition = &session.definition;
+    let qsos = session.checked_qsos(entries);
+    let score = session.score(entries);
+    let callsign = callsign.trim().to_uppercase();
+
+    let operators: BTreeSet<String> = qsos.iter()
+        .filter_map(|(entry, _)| entry.operator.as_deref())
+        .map(|op| op.trim().to_uppercase())
+        .filter(|op| !op.is_empty())
+        .collect();
+
+    let mut log = String::new();
+    log.push_str("START-OF-LOG: 3.0\n");
+    log.push_str(&format!("CREATED-BY: amlog v{}\n", env!("CARGO_PKG_VERSION")));
+    log.push_str(&format!("CONTEST: {}\n", definition.cabrillo_name()));
+    log.push_str(&format!("CALLSIGN: {}\n", callsign));
+    if let Some(section) = session.sent_value("MY_ARRL_SECT") {
+        log.push_str(&format!("LOCATION: {}\n", section));
+    }
+    log.push_str(&format!(
+        "CATEGORY-OPERATOR: {}\n",
+        if operators.len() > 1 { "MULTI-OP" } else { "SINGLE-OP" }
+    ));
+    log.push_str(&format!("CLAIMED-SCORE: {}\n", score.total()));
+    if !operators.is_empty() {
+        log.push_str(&format!("OPERATORS: {}\n", operators.into_iter().collect::<Vec<_>>().join(" ")));
+    }
+    if let Some(level) = session.power_level() {
+        log.push_str(&format!("SOAPBOX: Power: {} (x{})\n", level.label, level.factor));
+    }
+    for (bonus, count) in session.claimed_bonuses() {
+        log.push_str(&format!("SOAPBOX: Bonus: {} x{} = {} points\n", bonus.name, count, bonus.points * count));
+    }
+
+    for (entry, _) in &qsos {
+        let sent = sent_exchange(session, entry);
+        let received: Vec<String> = definition.exchange.iter()
+            .map(|field| exchange_value(entry, &field.field).unwrap_or_default())
+            .collect();
+        let line = format!(
+            "QSO: {:>5} {} {} {:<13} {} {:<13} {}",
+            cabrillo_frequency(entry.frequency),
+            cabrillo_mode(entry),
+            entry.timestamp.format("%Y-%m-%d %H%M"),
+            callsign,
+            sent.iter().map(|v| format!("{:<4}", v)).collect::<Vec<_>>().join(" "),
+            entry.callsign.to_uppercase(),
+            received.iter().map(|v| format!("{:<4}", v)).collect::<Vec<_>>().join(" "),
+        );
+        log.push_str(line.trim_end());
+        log.push('\n');
+    }
+
+    log.push_str("END-OF-LOG:\n");
+    log
+}
+
+/// Plain-text dupe sheet: calls worked per band and mode group, followed
+/// by the multipliers worked and still needed
+pub fn dupe_sheet(session: &ContestSession, entries: &[LogEntry]) -> String {
+    let definition = &session.definition;
+    let score = session.score(entries);
+    let rule = definition.dupe;
+
+    let mut slots: BTreeMap<(usize, Option<ModeCategory>), (String, BTreeSet<String>)> = BTreeMap::new();
+    for (entry, dupe) in session.checked_qsos(entries) {
+        if dupe {
+            continue;
+        }
+        let band = entry.band_name().map_or_else(|| "other".to_string(), String::from);
+        let order = if rule.per_band {
+            BANDS.iter().position(|(name, _, _)| *name == band).unwrap_or(usize::MAX)
+        } else {
+            0
+        };
+        let mode = rule.per_mode.then(|| entry.mode_category());
+        let label = match (rule.per_band, mode) {
+            (true, Some(mode)) => format!("{} {}", band, mode.label()),
+            (true, None) => band,
+            (false, Some(mode)) => mode.label().to_string(),
+            (false, None) => "All bands".to_string(),
+        };
+        slots.entry((order, mode))
+            .or_insert_with(|| (label, BTreeSet::new()))
+            .1
+            .insert(entry.callsign.to_uppercase());
+    }
+
+    let mut sheet = format!("{} dupe sheet\n", definition.name);
+    sheet.push_str(&format!(
+        "QSOs: {} ({} dupes)  Points: {}  Score: {}\n",
+        score.qsos, score.dupes, score.points, score.total()
+    ));
+
+    for (label, calls) in slots.values() {
+        sheet.push_str(&format!("\n{} ({})\n", label, calls.len()));
+        let calls: Vec<&String> = calls.iter().collect();
+        for line in calls.chunks(CALLS_PER_LINE) {
+            let line: Vec<String> = line.iter().map(|call| format!("{:<12}", call)).collect();
+            sheet.push_str(&format!("  {}\n", line.join("").trim_end()));
+        }
+    }
+
+    for multiplier in &definition.multipliers {
+        let worked = session.multiplier_values(multiplier, entries);
+        match &multiplier.valid_values {
+            Some(valid) => {
+                sheet.push_str(&format!("\n{} worked ({}/{})\n", multiplier.name, worked.len(), valid.len()));
+                sheet.push_str(&format!("  {}\n", worked.iter().cloned().collect::<Vec<_>>().join(" ")));
+                let missing: Vec<&str> = valid.iter()
+                    .filter(|v| !worked.contains(&v.to_uppercase()))
+                    .map(String::as_str)
+                    .collect();
+                sheet.push_str(&format!("{} needed ({})\n", multiplier.name, missing.len()));
+                sheet.push_str(&format!("  {}\n", missing.join(" ")));
+            }
+            None => {
+                sheet.push_str(&format!("\n{} worked ({})\n", multiplier.name, worked.len()));
+                sheet.push_str(&format!("  {}\n", worked.iter().cloned().collect::<Vec<_>>().join(" ")));
+            }
+        }
+    }
+
+    let bonuses = session.claimed_bonuses();
+    if !bonuses.is_empty() {
+        sheet.push_str(&format!("\nBonus points ({})\n", score.bonus));
+        for (bonus, count) in bonuses {
+            sheet.push_str(&format!("  {:<40} x{:<3} {:>5}\n", bonus.name, count, bonus.points * count));
+        }
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Frequency;
+    use crate::contest::builtin_definitions;
+    use chrono::{Duration, Utc};
+
+    fn field_day_qso(call: &str, frequency: f64, mode: &str, class: &str, section: &str) -> LogEntry {
+        LogEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Utc::now() - Duration::minutes(5),
+            callsign: call.into(),
+            frequency: Frequency::from_mhz(frequency).unwrap(),
+            mode: mode.into(),
+            contest_id: Some("ARRL-FIELD-DAY".to_string()),
+            custom_fields: [
+                ("CLASS".to_string(), class.to_string()),
+                ("ARRL_SECT".to_string(), section.to_string()),
+            ].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    fn field_day(entries: &[LogEntry]) -> ContestSession {
+        let definition = builtin_definitions().into_iter().find(|d| d.id == "ARRL-FIELD-DAY").unwrap();
+        ContestSession::new(definition, &["3A", "CT"], entries).unwrap()
+    }
+
+    #[test]
+    fn test_field_day_scoring() {
+        let entries = vec![
+            field_day_qso("K1ABC", 14.030, "CW", "2A", "EMA"),
+            field_day_qso("K1ABC", 14.250, "SSB", "2A", "EMA"),  // new mode group, not a dupe
+            field_day_qso("K1ABC", 14.035, "CW", "2A", "EMA"),   // dupe
+            field_day_qso("VE3XX", 7.074, "FT8", "1D", "ONE"),
+            field_day_qso("W9XX", 7.200, "SSB", "1E", "XYZ"),    // not a section
+        ];
+        let mut session = field_day(&entries);
+        session.set_power_level(1).unwrap();
+        session.claim_bonus("emergency_power", 3).unwrap();
+        session.claim_bonus("w1aw_bulletin", 1).unwrap();
+        assert!(session.claim_bonus("w1aw_bulletin", 2).is_err());
+        assert!(session.claim_bonus("no_such_bonus", 1).is_err());
+
+        let score = session.score(&entries);
+        assert_eq!(score.qsos, 5);
+        assert_eq!(score.dupes, 1);
+        // CW 2 + phone 1 + digital 2 + phone 1
+        assert_eq!(score.points, 6);
+        assert_eq!(score.power_factor, 2);
+        assert_eq!(score.bonus, 400);
+        // Sections are tracked, not multiplied
+        assert_eq!(score.multipliers[0].count, 2);
+        assert_eq!(score.total(), 6 * 2 + 400);
+    }
+
+    #[test]
+    fn test_cabrillo_log() {
+        let entries = vec![
+            field_day_qso("k1abc", 14.030, "CW", "2A", "EMA"),
+            field_day_qso("VE3XX", 146.52, "FM", "1D", "ONE"),
+        ];
+        let session = field_day(&entries);
+        let log = cabrillo_log(&session, &entries, "w1aw");
+
+        assert!(log.starts_with("START-OF-LOG: 3.0\n"));
+        assert!(log.contains("CONTEST: ARRL-FD\n"));
+        assert!(log.contains("CALLSIGN: W1AW\n"));
+        assert!(log.contains("LOCATION: CT\n"));
+        let qsos: Vec<&str> = log.lines().filter(|l| l.starts_with("QSO:")).collect();
+        assert_eq!(qsos.len(), 2);
+        let fields: Vec<&str> = qsos[0].split_whitespace().collect();
+        assert_eq!(fields[1..3], ["14030", "CW"]);
+        assert_eq!(fields[5..], ["W1AW", "3A", "CT", "K1ABC", "2A", "EMA"]);
+        assert!(qsos[1].starts_with("QSO:   144 FM"));
+        assert!(log.ends_with("END-OF-LOG:\n"));
+    }
+
+    #[test]
+    fn test_dupe_sheet_lists_calls_and_missing_sections() {
+        let entries = vec![
+            field_day_qso("K1ABC", 14.030, "CW", "2A", "EMA"),
+            field_day_qso("K1ABC", 14.035, "CW", "2A", "EMA"),
+            field_day_qso("N1XYZ", 7.030, "CW", "1A", "CT"),
+        ];
+        let sheet = dupe_sheet(&field_day(&entries), &entries);
+        assert!(sheet.contains("QSOs: 3 (1 dupes)"));
+        assert!(sheet.contains("40m CW (1)\n  N1XYZ\n"));
+        assert!(sheet.contains("20m CW (1)\n  K1ABC\n"));
+        // 40m comes before 20m
+        assert!(sheet.find("40m CW").unwrap() < sheet.find("20m CW").unwrap());
+        assert!(sheet.contains("Sections worked (2/"));
+        assert!(sheet.contains("  CT EMA\n"));
+        let needed = sheet.split("Sections needed").nth(1).unwrap();
+        assert!(needed.contains(" WMA"));
+        assert!(!needed.contains(" EMA"));
+    }
+}
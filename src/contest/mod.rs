@@ -0,0 +1,347 @@
+//! Contest operating: data-driven contest definitions, exchange mapping,
+//! dupe checking and live scoring
+
+mod cabrillo;
+mod session;
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+pub use cabrillo::{cabrillo_log, dupe_sheet};
+pub use session::{ContestScore, ContestSession, ContestState, MultiplierCount};
+
+use crate::app::mode::ModeCategory;
+use crate::app::Rst;
+use crate::LogEntry;
+
+/// Definitions shipped with amlog
+const BUILTIN_DEFINITIONS: &str = include_str!("definitions.json");
+
+/// Entry fields an exchange value can be stored in. Any other field name
+/// must be an upper-case ADIF field name and is kept in `custom_fields`.
+const ENTRY_FIELDS: &[&str] = &[
+    "rst_sent", "rst_rcvd", "srx", "stx", "name", "qth", "state", "county",
+    "grid", "cq_zone", "itu_zone",
+];
+
+#[derive(Error, Debug)]
+pub enum ContestError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid contest definition: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Contest {0} uses unknown field {1}")]
+    UnknownField(String, String),
+
+    #[error("Unknown contest: {0}")]
+    UnknownContest(String),
+
+    #[error("Invalid {0}: {1}")]
+    InvalidExchange(String, String),
+}
+
+/// One value of the exchange and where it is stored on the QSO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExchangeField {
+    pub label: String,
+    pub field: String,
+    /// Pre-filled value, e.g. `59` for a signal report
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// What makes a second QSO with the same station a dupe
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DupeRule {
+    #[serde(default)]
+    pub per_band: bool,
+    #[serde(default)]
+    pub per_mode: bool,
+}
+
+/// A multiplier counted over distinct values of an entry field
+///
+/// `key` is any award key (`dxcc`, `cq_zone`, `wpx_prefix`, ...), an
+/// exchange field, or `qso` to count each band/mode slot worked once.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MultiplierRule {
+    pub name: String,
+    pub key: String,
+    #[serde(default)]
+    pub per_band: bool,
+    #[serde(default)]
+    pub per_mode: bool,
+    /// Only these values count, e.g. the ARRL/RAC sections
+    #[serde(default)]
+    pub valid_values: Option<Vec<String>>,
+    /// Tracked and shown, but not part of the score
+    #[serde(default)]
+    pub track_only: bool,
+}
+
+impl MultiplierRule {
+    pub fn is_valid(&self, value: &str) -> bool {
+        self.valid_values
+            .as_ref()
+            .is_none_or(|valid| valid.iter().any(|v| v.eq_ignore_ascii_case(value)))
+    }
+}
+
+/// Bonus points claimed by the operator rather than earned per QSO
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BonusDefinition {
+    pub id: String,
+    pub name: String,
+    pub points: u32,
+    /// How many times the bonus can be claimed, e.g. per message relayed
+    #[serde(default = "default_bonus_count")]
+    pub max_count: u32,
+}
+
+fn default_bonus_count() -> u32 {
+    1
+}
+
+/// A power category and the multiplier applied to QSO points
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerLevel {
+    pub label: String,
+    pub factor: u32,
+}
+
+/// Points for a valid (non-dupe) QSO by mode group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QsoPoints {
+    #[serde(default = "default_points")]
+    pub default: u32,
+    #[serde(default)]
+    pub cw: Option<u32>,
+    #[serde(default)]
+    pub phone: Option<u32>,
+    #[serde(default)]
+    pub digital: Option<u32>,
+}
+
+fn default_points() -> u32 {
+    1
+}
+
+impl Default for QsoPoints {
+    fn default() -> Self {
+        Self {
+            default: default_points(),
+            cw: None,
+            phone: None,
+            digital: None,
+        }
+    }
+}
+
+impl QsoPoints {
+    pub fn for_mode(&self, mode: ModeCategory) -> u32 {
+        match mode {
+            ModeCategory::Cw => self.cw,
+            ModeCategory::Phone => self.phone,
+            ModeCategory::Digital => self.digital,
+        }
+        .unwrap_or(self.default)
+    }
+}
+
+fn default_duration() -> i64 {
+    48
+}
+
+/// Data-driven contest description
+///
+/// `id` is the ADIF `CONTEST_ID` written on every QSO of the contest.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContestDefinition {
+    pub id: String,
+    pub name: String,
+    /// Cabrillo `CONTEST:` name when it differs from the ADIF id
+    #[serde(default)]
+    pub cabrillo: Option<String>,
+    /// Length of the event; older QSOs with the same id belong to an
+    /// earlier running of the contest
+    #[serde(default = "default_duration")]
+    pub duration_hours: i64,
+    /// Exchange received from the other station
+    pub exchange: Vec<ExchangeField>,
+    /// Exchange we send; an `stx` field is the auto-incrementing serial
+    #[serde(default)]
+    pub sent: Vec<ExchangeField>,
+    #[serde(default)]
+    pub dupe: DupeRule,
+    #[serde(default)]
+    pub multipliers: Vec<MultiplierRule>,
+    #[serde(default)]
+    pub points: QsoPoints,
+    #[serde(default)]
+    pub bonuses: Vec<BonusDefinition>,
+    /// Choices for the power multiplier; empty when the contest has none
+    #[serde(default)]
+    pub power_levels: Vec<PowerLevel>,
+}
+
+impl ContestDefinition {
+    fn validate(&self) -> Result<(), ContestError> {
+        for field in self.exchange.iter().chain(&self.sent) {
+            if !is_exchange_field(&field.field) {
+                return Err(ContestError::UnknownField(self.id.clone(), field.field.clone()));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn cabrillo_name(&self) -> &str {
+        self.cabrillo.as_deref().unwrap_or(&self.id)
+    }
+
+    /// Whether we send an auto-incrementing serial number
+    pub fn sends_serial(&self) -> bool {
+        self.sent.iter().any(|field| field.field == "stx")
+    }
+
+    /// Sent exchange fields the operator has to fill in before starting
+    pub fn sent_inputs(&self) -> Vec<&ExchangeField> {
+        self.sent
+            .iter()
+            .filter(|field| field.field != "stx" && field.default.is_none())
+            .collect()
+    }
+}
+
+fn is_exchange_field(field: &str) -> bool {
+    ENTRY_FIELDS.contains(&field)
+        || (!field.is_empty() && field.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'))
+}
+
+/// Read an exchange value back from a QSO
+pub fn exchange_value(entry: &LogEntry, field: &str) -> Option<String> {
+    let value = match field {
+        "rst_sent" => entry.rst_sent.as_ref().map(Rst::to_string),
+        "rst_rcvd" => entry.rst_received.as_ref().map(Rst::to_string),
+        "srx" => entry.srx.map(|n| n.to_string()),
+        "stx" => entry.stx.map(|n| n.to_string()),
+        "name" => entry.name.clone(),
+        "qth" => entry.qth.clone(),
+        "state" => entry.state.clone(),
+        "county" => entry.county.clone(),
+        "grid" => entry.grid.clone(),
+        "cq_zone" => entry.cq_zone.map(|z| z.to_string()),
+        "itu_zone" => entry.itu_zone.map(|z| z.to_string()),
+        _ => entry.custom_fields.get(field).cloned(),
+    };
+    value.filter(|v| !v.trim().is_empty())
+}
+
+/// Store an exchange value on a QSO, parsing numeric fields
+pub fn set_exchange_value(entry: &mut LogEntry, field: &ExchangeField, value: &str) -> Result<(), ContestError> {
+    let value = value.trim().to_uppercase();
+    let number = || {
+        value
+            .parse::<u32>()
+            .map_err(|_| ContestError::InvalidExchange(field.label.clone(), value.clone()))
+    };
+    match field.field.as_str() {
+        "rst_sent" => entry.rst_sent = Some(Rst::from(value)),
+        "rst_rcvd" => entry.rst_received = Some(Rst::from(value)),
+        "srx" => entry.srx = Some(number()?),
+        "stx" => entry.stx = Some(number()?),
+        "name" => entry.name = Some(value),
+        "qth" => entry.qth = Some(value),
+        "state" => entry.state = Some(value),
+        "county" => entry.county = Some(value),
+        "grid" => entry.grid = Some(value),
+        "cq_zone" => entry.cq_zone = Some(number()?),
+        "itu_zone" => entry.itu_zone = Some(number()?),
+        name => {
+            entry.custom_fields.insert(name.to_string(), value);
+        }
+    }
+    Ok(())
+}
+
+pub fn builtin_definitions() -> Vec<ContestDefinition> {
+    serde_json::from_str(BUILTIN_DEFINITIONS).expect("built-in contest definitions are valid")
+}
+
+/// Parse a single contest definition
+pub fn parse_definition(json: &str) -> Result<ContestDefinition, ContestError> {
+    let definition: ContestDefinition = serde_json::from_str(json)?;
+    definition.validate()?;
+    Ok(definition)
+}
+
+/// Built-in definitions plus one per `*.json` file in `dir`; files replace
+/// built-ins with the same id
+pub fn load_definitions(dir: &Path) -> Result<Vec<ContestDefinition>, ContestError> {
+    let mut definitions = builtin_definitions();
+    if dir.is_dir() {
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .collect();
+        paths.sort();
+        for path in paths {
+            let definition = parse_definition(&std::fs::read_to_string(&path)?)?;
+            match definitions.iter_mut().find(|d| d.id.eq_ignore_ascii_case(&definition.id)) {
+                Some(existing) => *existing = definition,
+                None => definitions.push(definition),
+            }
+        }
+    }
+    Ok(definitions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_definitions_are_valid() {
+        let definitions = builtin_definitions();
+        assert!(definitions.iter().all(|d| d.validate().is_ok()));
+        assert!(definitions.iter().any(|d| d.id == "CQ-WPX-CW"));
+    }
+
+    #[test]
+    fn test_exchange_values_map_to_entry_fields() {
+        let zone = ExchangeField { label: "Zone".to_string(), field: "cq_zone".to_string(), default: None };
+        let precedence = ExchangeField { label: "Prec".to_string(), field: "PRECEDENCE".to_string(), default: None };
+        let mut entry = LogEntry::default();
+
+        set_exchange_value(&mut entry, &zone, " 5 ").unwrap();
+        set_exchange_value(&mut entry, &precedence, "a").unwrap();
+        assert_eq!(entry.cq_zone, Some(5));
+        assert_eq!(exchange_value(&entry, "PRECEDENCE").as_deref(), Some("A"));
+        assert!(matches!(
+            set_exchange_value(&mut entry, &zone, "five"),
+            Err(ContestError::InvalidExchange(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_load_definitions_from_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("naqp.json"), r#"{
+            "id": "NAQP-CW", "name": "My NAQP",
+            "exchange": [{"label": "Name", "field": "name"}, {"label": "Loc", "field": "state"}]
+        }"#).unwrap();
+        std::fs::write(dir.path().join("bad.txt"), "ignored").unwrap();
+
+        let definitions = load_definitions(dir.path()).unwrap();
+        let naqp: Vec<_> = definitions.iter().filter(|d| d.id == "NAQP-CW").collect();
+        assert_eq!(naqp.len(), 1);
+        assert_eq!(naqp[0].name, "My NAQP");
+
+        std::fs::write(dir.path().join("bad.json"), r#"{
+            "id": "X", "name": "X", "exchange": [{"label": "X", "field": "lowercase"}]
+        }"#).unwrap();
+        assert!(matches!(load_definitions(dir.path()), Err(ContestError::UnknownField(_, _))));
+    }
+}
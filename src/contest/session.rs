@@ -0,0 +1,418 @@
+// src/contest/session.rs
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+
+use super::{exchange_value, set_exchange_value, BonusDefinition, ContestDefinition, ContestError, MultiplierRule, PowerLevel};
+use crate::app::mode::ModeCategory;
+use crate::awards;
+use crate::LogEntry;
+
+/// Band and mode group a value counts in; `None` when the rule ignores it
+type Slot = (Option<String>, Option<ModeCategory>);
+
+/// Distinct values worked for one multiplier rule
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultiplierCount {
+    pub name: String,
+    pub count: usize,
+    /// Size of the value list, when the rule has one
+    pub possible: Option<usize>,
+    pub track_only: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ContestScore {
+    pub qsos: usize,
+    pub dupes: usize,
+    pub points: u32,
+    pub multipliers: Vec<MultiplierCount>,
+    pub power_factor: u32,
+    pub bonus: u32,
+}
+
+impl ContestScore {
+    /// Multipliers that count towards the score
+    pub fn multiplier_total(&self) -> usize {
+        self.multipliers.iter().filter(|m| !m.track_only).map(|m| m.count).sum()
+    }
+
+    /// QSO points times the power factor and multipliers, plus bonus points
+    pub fn total(&self) -> u64 {
+        let mut total = self.points as u64 * self.power_factor.max(1) as u64;
+        if self.multipliers.iter().any(|m| !m.track_only) {
+            total *= self.multiplier_total() as u64;
+        }
+        total + self.bonus as u64
+    }
+}
+
+/// The operator's choices for a contest, saved so a restart resumes it
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContestState {
+    pub contest_id: String,
+    /// QSOs before this belong to an earlier running of the contest
+    pub since: DateTime<Utc>,
+    pub sent_values: HashMap<String, String>,
+    #[serde(default)]
+    pub power_level: Option<usize>,
+    #[serde(default)]
+    pub bonuses: BTreeMap<String, u32>,
+}
+
+impl ContestState {
+    /// Whether the state belongs to the running of the contest under way now
+    pub fn is_current(&self, definition: &ContestDefinition) -> bool {
+        self.contest_id.eq_ignore_ascii_case(&definition.id)
+            && Utc::now() - self.since < Duration::hours(definition.duration_hours * 2)
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>, ContestError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), ContestError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// A running contest: the definition, our sent exchange and the serial counter
+#[derive(Debug, Clone)]
+pub struct ContestSession {
+    pub definition: ContestDefinition,
+    state: ContestState,
+    next_serial: u32,
+}
+
+impl ContestSession {
+    /// Start a contest. `sent_input` holds the values for `sent_inputs()`
+    /// in order; the serial continues from QSOs already logged.
+    pub fn new(definition: ContestDefinition, sent_input: &[&str], entries: &[LogEntry]) -> Result<Self, ContestError> {
+        let inputs = definition.sent_inputs();
+        if sent_input.len() < inputs.len() {
+            let missing = inputs[sent_input.len()];
+            return Err(ContestError::InvalidExchange(missing.label.clone(), String::new()));
+        }
+
+        let mut sent_values = HashMap::new();
+        let mut input = sent_input.iter();
+        for field in &definition.sent {
+            if field.field == "stx" {
+                continue;
+            }
+            let value = match &field.default {
+                Some(default) => default.clone(),
+                None => input.next().map(|v| v.to_string()).unwrap_or_default(),
+            };
+            sent_values.insert(field.field.clone(), value);
+        }
+
+        let state = ContestState {
+            contest_id: definition.id.clone(),
+            since: Utc::now() - Duration::hours(definition.duration_hours),
+            sent_values,
+            power_level: None,
+            bonuses: BTreeMap::new(),
+        };
+        Ok(Self::resume(definition, state, entries))
+    }
+
+    /// Continue a contest from saved state
+    pub fn resume(definition: ContestDefinition, state: ContestState, entries: &[LogEntry]) -> Self {
+        let mut session = Self {
+            definition,
+            state,
+            next_serial: 1,
+        };
+        session.next_serial = session
+            .qsos(entries)
+            .filter_map(|entry| entry.stx)
+            .max()
+            .map_or(1, |serial| serial + 1);
+        session
+    }
+
+    pub fn state(&self) -> &ContestState {
+        &self.state
+    }
+
+    /// The value we send for an exchange field
+    pub fn sent_value(&self, field: &str) -> Option<&str> {
+        self.state.sent_values.get(field).map(String::as_str)
+    }
+
+    pub fn power_level(&self) -> Option<&PowerLevel> {
+        self.state.power_level.and_then(|i| self.definition.power_levels.get(i))
+    }
+
+    /// Choose a power level by its position in the definition
+    pub fn set_power_level(&mut self, index: usize) -> Result<(), ContestError> {
+        if index >= self.definition.power_levels.len() {
+            return Err(ContestError::InvalidExchange("power level".to_string(), (index + 1).to_string()));
+        }
+        self.state.power_level = Some(index);
+        Ok(())
+    }
+
+    /// Claim a bonus `count` times; zero withdraws the claim
+    pub fn claim_bonus(&mut self, id: &str, count: u32) -> Result<(), ContestError> {
+        let bonus = self
+            .definition
+            .bonuses
+            .iter()
+            .find(|b| b.id.eq_ignore_ascii_case(id))
+            .ok_or_else(|| ContestError::InvalidExchange("bonus".to_string(), id.to_string()))?;
+        if count > bonus.max_count {
+            return Err(ContestError::InvalidExchange(bonus.name.clone(), count.to_string()));
+        }
+        if count == 0 {
+            self.state.bonuses.remove(&bonus.id);
+        } else {
+            self.state.bonuses.insert(bonus.id.clone(), count);
+        }
+        Ok(())
+    }
+
+    /// Claimed bonuses with the points each earns
+    pub fn claimed_bonuses(&self) -> Vec<(&BonusDefinition, u32)> {
+        self.definition
+            .bonuses
+            .iter()
+            .filter_map(|bonus| {
+                let count = *self.state.bonuses.get(&bonus.id)?;
+                Some((bonus, count))
+            })
+            .collect()
+    }
+
+    pub fn next_serial(&self) -> u32 {
+        self.next_serial
+    }
+
+    /// QSOs logged in this running of the contest
+    pub fn qsos<'a>(&'a self, entries: &'a [LogEntry]) -> impl Iterator<Item = &'a LogEntry> + 'a {
+        entries.iter().filter(|entry| {
+            entry.timestamp >= self.state.since
+                && entry
+                    .contest_id
+                    .as_deref()
+                    .is_some_and(|id| id.eq_ignore_ascii_case(&self.definition.id))
+        })
+    }
+
+    /// Tag a QSO with the contest id, our sent exchange and the next serial
+    pub fn stamp(&self, entry: &mut LogEntry) -> Result<(), ContestError> {
+        entry.contest_id = Some(self.definition.id.clone());
+        for field in &self.definition.sent {
+            if field.field == "stx" {
+                entry.stx = Some(self.next_serial);
+            } else if let Some(value) = self.state.sent_values.get(&field.field) {
+                set_exchange_value(entry, field, value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Call once a stamped QSO has been saved
+    pub fn advance_serial(&mut self) {
+        if self.definition.sends_serial() {
+            self.next_serial += 1;
+        }
+    }
+
+    fn dupe_key(&self, callsign: &str, band: Option<String>, mode: ModeCategory) -> (String, Option<String>, Option<ModeCategory>) {
+        let rule = self.definition.dupe;
+        (
+            callsign.trim().to_uppercase(),
+            if rule.per_band { band.map(|b| b.to_lowercase()) } else { None },
+            if rule.per_mode { Some(mode) } else { None },
+        )
+    }
+
+    /// The earlier contest QSO that a new QSO with `callsign` would duplicate
+    pub fn find_dupe<'a>(&'a self, entries: &'a [LogEntry], callsign: &str, band: Option<String>, mode: ModeCategory) -> Option<&'a LogEntry> {
+        if callsign.trim().is_empty() {
+            return None;
+        }
+        let key = self.dupe_key(callsign, band, mode);
+        self.qsos(entries)
+            .find(|entry| self.dupe_key(&entry.callsign, entry.band_name().map(String::from), entry.mode_category()) == key)
+    }
+
+    fn multiplier_value(rule: &MultiplierRule, entry: &LogEntry) -> Option<String> {
+        let value = match rule.key.as_str() {
+            "qso" => Some("QSO".to_string()),
+            key => awards::key_value(key, entry)
+                .or_else(|| exchange_value(entry, key).map(|v| v.trim().to_uppercase())),
+        }?;
+        rule.is_valid(&value).then_some(value)
+    }
+
+    /// Contest QSOs in time order, each flagged when it is a dupe
+    pub fn checked_qsos<'a>(&'a self, entries: &'a [LogEntry]) -> Vec<(&'a LogEntry, bool)> {
+        let mut qsos: Vec<&LogEntry> = self.qsos(entries).collect();
+        qsos.sort_by_key(|entry| entry.timestamp);
+
+        let mut worked = HashSet::new();
+        qsos.into_iter()
+            .map(|entry| {
+                let key = self.dupe_key(&entry.callsign, entry.band_name().map(String::from), entry.mode_category());
+                (entry, !worked.insert(key))
+            })
+            .collect()
+    }
+
+    /// Distinct values of a multiplier worked on any band or mode
+    pub fn multiplier_values(&self, rule: &MultiplierRule, entries: &[LogEntry]) -> BTreeSet<String> {
+        self.checked_qsos(entries)
+            .into_iter()
+            .filter(|(_, dupe)| !dupe)
+            .filter_map(|(entry, _)| Self::multiplier_value(rule, entry))
+            .collect()
+    }
+
+    /// Score the contest from the log; dupes earn no points or multipliers
+    pub fn score(&self, entries: &[LogEntry]) -> ContestScore {
+        let mut score = ContestScore {
+            power_factor: self.power_level().map_or(1, |level| level.factor),
+            bonus: self.claimed_bonuses().iter().map(|(bonus, count)| bonus.points * count).sum(),
+            ..Default::default()
+        };
+        let mut multipliers: Vec<BTreeSet<(Slot, String)>> =
+            vec![BTreeSet::new(); self.definition.multipliers.len()];
+
+        for (entry, dupe) in self.checked_qsos(entries) {
+            score.qsos += 1;
+            if dupe {
+                score.dupes += 1;
+                continue;
+            }
+            let band = entry.band_name().map(String::from);
+            let mode = entry.mode_category();
+            score.points += self.definition.points.for_mode(mode);
+            for (rule, values) in self.definition.multipliers.iter().zip(multipliers.iter_mut()) {
+                if let Some(value) = Self::multiplier_value(rule, entry) {
+                    let band = if rule.per_band { band.clone() } else { None };
+                    let mode = if rule.per_mode { Some(mode) } else { None };
+                    values.insert(((band, mode), value));
+                }
+            }
+        }
+
+        score.multipliers = self
+            .definition
+            .multipliers
+            .iter()
+            .zip(multipliers)
+            .map(|(rule, values)| MultiplierCount {
+                name: rule.name.clone(),
+                count: values.len(),
+                possible: rule.valid_values.as_ref().map(Vec::len),
+                track_only: rule.track_only,
+            })
+            .collect();
+        score
+    }
+
+    /// Contest QSOs logged in the `minutes` before `now`
+    pub fn rate(&self, entries: &[LogEntry], now: DateTime<Utc>, minutes: i64) -> usize {
+        let since = now - Duration::minutes(minutes);
+        self.qsos(entries)
+            .filter(|entry| entry.timestamp > since && entry.timestamp <= now)
+            .count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contest::builtin_definitions;
+    use crate::storage::formats::fixtures::qso_on;
+
+    fn definition(id: &str) -> ContestDefinition {
+        builtin_definitions().into_iter().find(|d| d.id == id).unwrap()
+    }
+
+    fn contest_qso(call: &str, frequency: f64, mode: &str, minutes_ago: i64, contest: &str) -> LogEntry {
+        LogEntry {
+            timestamp: Utc::now() - Duration::minutes(minutes_ago),
+            contest_id: Some(contest.to_string()),
+            ..qso_on(&uuid::Uuid::new_v4().to_string(), call, frequency, mode)
+        }
+    }
+
+    #[test]
+    fn test_serials_continue_from_log() {
+        let mut entries = vec![contest_qso("W1AW", 14.025, "CW", 5, "CQ-WPX-CW")];
+        entries[0].stx = Some(41);
+        let mut session = ContestSession::new(definition("CQ-WPX-CW"), &[], &entries).unwrap();
+        assert_eq!(session.next_serial(), 42);
+
+        let mut entry = contest_qso("K1ABC", 14.030, "CW", 0, "");
+        session.stamp(&mut entry).unwrap();
+        assert_eq!(entry.stx, Some(42));
+        assert_eq!(entry.rst_sent, Some("599".into()));
+        assert_eq!(entry.contest_id.as_deref(), Some("CQ-WPX-CW"));
+        session.advance_serial();
+        assert_eq!(session.next_serial(), 43);
+    }
+
+    #[test]
+    fn test_sent_exchange_input_is_required() {
+        let cqww = definition("CQ-WW-CW");
+        assert!(ContestSession::new(cqww.clone(), &[], &[]).is_err());
+
+        let session = ContestSession::new(cqww, &["5"], &[]).unwrap();
+        let mut entry = LogEntry::default();
+        session.stamp(&mut entry).unwrap();
+        assert_eq!(entry.custom_fields.get("STX_STRING").map(String::as_str), Some("5"));
+        assert_eq!(entry.stx, None);
+    }
+
+    #[test]
+    fn test_dupes_per_band() {
+        let entries = vec![
+            contest_qso("W1AW", 14.025, "CW", 10, "CQ-WPX-CW"),
+            contest_qso("K1ABC", 7.025, "CW", 10, "OTHER-CONTEST"),
+        ];
+        let session = ContestSession::new(definition("CQ-WPX-CW"), &[], &entries).unwrap();
+        assert!(session.find_dupe(&entries, "w1aw", Some("20m".to_string()), ModeCategory::Cw).is_some());
+        assert!(session.find_dupe(&entries, "W1AW", Some("40m".to_string()), ModeCategory::Cw).is_none());
+        // QSOs from other contests never count
+        assert!(session.find_dupe(&entries, "K1ABC", Some("40m".to_string()), ModeCategory::Cw).is_none());
+    }
+
+    #[test]
+    fn test_score_and_rate() {
+        let mut entries = vec![
+            contest_qso("W1AW", 14.025, "CW", 90, "CQ-WW-CW"),
+            contest_qso("W1AW", 14.026, "CW", 30, "CQ-WW-CW"), // dupe
+            contest_qso("K1ABC", 14.027, "CW", 5, "CQ-WW-CW"),
+            contest_qso("DL1XX", 7.010, "CW", 2, "CQ-WW-CW"),
+        ];
+        for (entry, (zone, dxcc)) in entries.iter_mut().zip([(5, 291), (5, 291), (5, 291), (14, 230)]) {
+            entry.cq_zone = Some(zone);
+            entry.dxcc = Some(dxcc);
+        }
+
+        let session = ContestSession::new(definition("CQ-WW-CW"), &["5"], &entries).unwrap();
+        let score = session.score(&entries);
+        assert_eq!(score.qsos, 4);
+        assert_eq!(score.dupes, 1);
+        assert_eq!(score.points, 3);
+        // zone 5 and USA on 20m, zone 14 and Germany on 40m
+        assert_eq!(score.multiplier_total(), 4);
+        assert_eq!(score.total(), 12);
+
+        assert_eq!(session.rate(&entries, Utc::now(), 10), 2);
+        assert_eq!(session.rate(&entries, Utc::now(), 60), 3);
+    }
+}
@@ -1,6 +1,12 @@
 // Main library exports
 pub mod app;
+pub mod awards;
+pub mod callbook;
+pub mod contest;
+pub mod portable;
+pub mod satellite;
 pub mod storage;
+pub mod sync;
 pub mod ui;
 // pub mod db;
 
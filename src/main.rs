@@ -9,7 +9,14 @@ use std::{io, time::Duration};
 // Import the required types from our lib
 use amlog::{App, AppMode, ui};
 
+mod cli;
+
 fn main() -> Result<(), io::Error> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        return cli::run(&args);
+    }
+
     // Setup terminal
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -21,6 +28,8 @@ fn main() -> Result<(), io::Error> {
     let mut app = App::new().expect("Failed to create app");
 
     loop {
+        app.poll_sync();
+        app.poll_external_changes();
         terminal.draw(|f| ui::draw(f, &app))?;
 
         if event::poll(Duration::from_millis(100))? {
@@ -42,11 +51,147 @@ fn main() -> Result<(), io::Error> {
                                 app.set_status("Export feature coming soon");
                             },
                             KeyCode::Char('d') => {
-                                app.delete_current_entry();
-                            },
-                            KeyCode::Char('u') => {
-                                app.undo_delete();
+                                if let Err(e) = app.delete_current_entry() {
+                                    app.set_error(&format!("Failed to delete entry: {}", e));
+                                }
                             },
+                            KeyCode::Char('u') => app.undo(),
+                            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo(),
+                            KeyCode::Char('H') => app.enter_history_mode(),
+                            KeyCode::Char('B') => app.enter_backups_mode(),
+                            KeyCode::Char('F') => app.enter_fsck_mode(),
+                            KeyCode::Char('/') => app.enter_search_mode(),
+                            KeyCode::Char('v') | KeyCode::Enter => app.view_selected_entry(),
+                            KeyCode::Char('?') => app.toggle_help(),
+                            KeyCode::Char('s') => app.toggle_stats(),
+                            KeyCode::Char('Q') => app.enter_qsl_mode(),
+                            KeyCode::Char('R') => app.start_reconcile(),
+                            KeyCode::Char('A') => app.enter_awards_mode(),
+                            KeyCode::Char('C') => app.start_contest(),
+                            KeyCode::Char('P') => app.toggle_activation(),
+                            KeyCode::Char('U') => app.prompt_uploads(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Prompt => {
+                        match key.code {
+                            KeyCode::Esc => app.cancel_prompt(),
+                            KeyCode::Enter => app.submit_prompt(),
+                            KeyCode::Backspace => app.prompt_backspace(),
+                            KeyCode::Char(c) => app.prompt_input(c),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Reconcile => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.enter_normal_mode(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Awards => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.enter_normal_mode(),
+                            KeyCode::Tab => app.next_award(),
+                            KeyCode::BackTab => app.previous_award(),
+                            KeyCode::Up | KeyCode::Char('k') => app.move_award_cursor(-1, 0),
+                            KeyCode::Down | KeyCode::Char('j') => app.move_award_cursor(1, 0),
+                            KeyCode::Left | KeyCode::Char('h') => app.move_award_cursor(0, -1),
+                            KeyCode::Right | KeyCode::Char('l') => app.move_award_cursor(0, 1),
+                            KeyCode::Enter => app.toggle_award_drill_down(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Contest => {
+                        match key.code {
+                            KeyCode::Esc => app.enter_normal_mode(),
+                            KeyCode::Char('x') if key.modifiers.contains(KeyModifiers::CONTROL) => app.end_contest(),
+                            KeyCode::Char('b') if key.modifiers.contains(KeyModifiers::CONTROL) => app.prompt_contest_bonus(),
+                            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => app.prompt_contest_power(),
+                            KeyCode::Char('w') if key.modifiers.contains(KeyModifiers::CONTROL) => app.prompt_cabrillo(),
+                            KeyCode::Tab => app.contest_form.next_field(),
+                            KeyCode::BackTab => app.contest_form.previous_field(),
+                            KeyCode::Enter => app.log_contest_qso(),
+                            KeyCode::Char(c) => app.contest_input(c),
+                            KeyCode::Backspace => app.contest_form.backspace(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::History => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.enter_normal_mode(),
+                            KeyCode::Up | KeyCode::Char('k') => app.history_select_previous(),
+                            KeyCode::Down | KeyCode::Char('j') => app.history_select_next(),
+                            KeyCode::Char('u') => app.undo(),
+                            KeyCode::Char('r') if key.modifiers.contains(KeyModifiers::CONTROL) => app.redo(),
+                            KeyCode::Enter => app.view_history_entry(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Backups => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.enter_normal_mode(),
+                            KeyCode::Up | KeyCode::Char('k') => app.backup_select_previous(),
+                            KeyCode::Down | KeyCode::Char('j') => app.backup_select_next(),
+                            KeyCode::Char('b') => app.backup_now(),
+                            KeyCode::Enter => app.prompt_restore_backup(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Fsck => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.enter_normal_mode(),
+                            KeyCode::Up | KeyCode::Char('k') => app.fsck_select_previous(),
+                            KeyCode::Down | KeyCode::Char('j') => app.fsck_select_next(),
+                            KeyCode::Char('f') => app.prompt_repair(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Search => {
+                        match key.code {
+                            KeyCode::Esc => app.enter_normal_mode(),
+                            KeyCode::Up => app.search_select_previous(),
+                            KeyCode::Down => app.search_select_next(),
+                            KeyCode::Enter => app.view_search_result(),
+                            KeyCode::Backspace => app.search_backspace(),
+                            KeyCode::Char(c) => app.search_input(c),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Qsl => {
+                        match key.code {
+                            KeyCode::Esc => app.enter_normal_mode(),
+                            KeyCode::Up | KeyCode::Char('k') => app.qsl_select_previous(),
+                            KeyCode::Down | KeyCode::Char('j') => app.qsl_select_next(),
+                            KeyCode::Char(' ') => app.toggle_qsl_selection(),
+                            KeyCode::Char('a') => app.toggle_qsl_select_all(),
+                            KeyCode::Char('f') => app.cycle_qsl_filter(),
+                            KeyCode::Char('v') => app.cycle_qsl_via(),
+                            KeyCode::Char('+') => app.shift_qsl_date(1),
+                            KeyCode::Char('-') => app.shift_qsl_date(-1),
+                            KeyCode::Char('m') => app.mark_selected_qsl_sent(),
+                            KeyCode::Char('Q') => app.mark_selected_qsl_queued(),
+                            KeyCode::Char('x') => app.export_qsl_queue(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Detail => {
+                        match key.code {
+                            KeyCode::Esc | KeyCode::Char('q') => app.enter_normal_mode(),
+                            KeyCode::Char('l') => app.lookup_selected_entry(),
+                            KeyCode::Char('a') => app.apply_callbook_result(),
+                            KeyCode::Char('e') => app.edit_selected_entry(),
+                            KeyCode::Char('[') => app.select_older_revision(),
+                            KeyCode::Char(']') => app.select_newer_revision(),
+                            KeyCode::Char('r') => app.restore_selected_revision(),
+                            _ => {}
+                        }
+                    },
+                    AppMode::Help | AppMode::Stats => {
+                        match key.code {
+                            KeyCode::Char('q') => break,
+                            KeyCode::Esc => app.enter_normal_mode(),
+                            KeyCode::Char('?') => app.toggle_help(),
+                            KeyCode::Char('s') => app.toggle_stats(),
                             _ => {}
                         }
                     },
@@ -68,6 +213,12 @@ fn main() -> Result<(), io::Error> {
                                     app.set_error("Please fill in all required fields");
                                 }
                             },
+                            KeyCode::Char('l') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                app.lookup_form_callsign();
+                            },
+                            KeyCode::Char('t') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                                app.next_satellite_preset();
+                            },
                             KeyCode::Char(c) => app.handle_input(c),
                             KeyCode::Backspace => app.handle_backspace(),
                             _ => {}
@@ -87,5 +238,9 @@ fn main() -> Result<(), io::Error> {
     )?;
     terminal.show_cursor()?;
 
+    if let Err(e) = app.shutdown() {
+        eprintln!("Shutdown backup failed: {}", e);
+    }
+
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,67 @@
+//! Just enough CSV for reference lists and upload files
+
+/// Split CSV content into records, honouring quoted fields with embedded
+/// commas, doubled quotes and line breaks. Blank lines are skipped.
+pub fn parse_records(content: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                field.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => record.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                record.push(std::mem::take(&mut field));
+                if record.iter().any(|f| !f.is_empty()) {
+                    records.push(std::mem::take(&mut record));
+                }
+                record.clear();
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records
+}
+
+/// Quote a field if it contains a separator, quote or line break
+pub fn field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Index of each wanted column in a header row, matched case-insensitively
+pub fn columns(header: &[String], names: &[&str]) -> Vec<Option<usize>> {
+    names.iter()
+        .map(|name| header.iter().position(|h| h.trim().eq_ignore_ascii_case(name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quoted_fields() {
+        let records = parse_records("reference,name\r\n\"K-0001\",\"Acadia, \"\"Mount Desert\"\"\"\r\n\nK-0002,Other\n");
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1], vec!["K-0001", "Acadia, \"Mount Desert\""]);
+        assert_eq!(records[2], vec!["K-0002", "Other"]);
+        assert_eq!(field("Acadia, \"MDI\""), "\"Acadia, \"\"MDI\"\"\"");
+        assert_eq!(field("W1AW"), "W1AW");
+    }
+}
@@ -0,0 +1,141 @@
+//! Portable operating programs: reference lists, activation tracking and
+//! the upload formats each program expects
+
+mod csv;
+pub mod pota;
+pub mod sota;
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::LogEntry;
+
+#[derive(Error, Debug)]
+pub enum PortableError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid activation state: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Invalid CSV: {0}")]
+    Csv(String),
+
+    #[error("Invalid reference: {0}")]
+    InvalidReference(String),
+}
+
+/// An activation in progress, from one or more parks, a summit or both,
+/// saved so a restart resumes it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Activation {
+    #[serde(default)]
+    pub parks: Vec<String>,
+    #[serde(default)]
+    pub summit: Option<String>,
+    pub started: DateTime<Utc>,
+}
+
+impl Activation {
+    /// Park and summit references separated by commas or spaces
+    pub fn parse(input: &str) -> Result<Self, PortableError> {
+        let mut parks = Vec::new();
+        let mut summit = None;
+        for reference in input.split([',', ' ']).filter(|r| !r.trim().is_empty()) {
+            if sota::is_summit_reference(reference) {
+                if summit.is_some() {
+                    return Err(PortableError::InvalidReference(format!("{} (one summit at a time)", reference)));
+                }
+                summit = sota::parse_reference(reference)?;
+            } else {
+                for park in pota::parse_references(reference)? {
+                    if !parks.contains(&park) {
+                        parks.push(park);
+                    }
+                }
+            }
+        }
+        Ok(Self { parks, summit, started: Utc::now() })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parks.is_empty() && self.summit.is_none()
+    }
+
+    /// Activations are abandoned once they are a day old
+    pub fn is_current(&self) -> bool {
+        Utc::now() - self.started < Duration::hours(24)
+    }
+
+    pub fn load(path: &Path) -> Result<Option<Self>, PortableError> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        Ok(Some(serde_json::from_str(&std::fs::read_to_string(path)?)?))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), PortableError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Mark a new QSO as made from the activated park(s) and summit
+    pub fn stamp(&self, entry: &mut LogEntry) {
+        if !self.parks.is_empty() {
+            entry.my_sig = Some(pota::SIG.to_string());
+            entry.my_sig_info = Some(self.parks.join(","));
+        }
+        if let Some(summit) = &self.summit {
+            entry.my_sota_ref = Some(summit.clone());
+        }
+    }
+
+    /// QSOs so far today for each activated park
+    pub fn park_progress(&self, entries: &[LogEntry], now: DateTime<Utc>) -> Vec<(String, usize)> {
+        self.parks.iter()
+            .map(|park| (park.clone(), pota::activation_qsos(entries, park, now.date_naive())))
+            .collect()
+    }
+
+    /// Stations worked so far today from the summit
+    pub fn summit_progress(&self, entries: &[LogEntry], now: DateTime<Utc>) -> Option<(String, usize)> {
+        let summit = self.summit.as_ref()?;
+        Some((summit.clone(), sota::activation_qsos(entries, summit, now.date_naive())))
+    }
+
+    /// Progress toward each program's threshold, e.g. `K-0001 7/10, G/LD-001 4/4`
+    pub fn progress_text(&self, entries: &[LogEntry], now: DateTime<Utc>) -> String {
+        let parks = self.park_progress(entries, now).into_iter()
+            .map(|(park, count)| format!("{} {}/{}", park, count, pota::ACTIVATION_THRESHOLD));
+        let summit = self.summit_progress(entries, now).into_iter()
+            .map(|(summit, count)| format!("{} {}/{}", summit, count, sota::ACTIVATION_THRESHOLD));
+        parks.chain(summit).collect::<Vec<_>>().join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_stamp_activation() {
+        let activation = Activation::parse("k-0001, G/LD-001 K-0001").unwrap();
+        assert_eq!(activation.parks, vec!["K-0001"]);
+        assert_eq!(activation.summit.as_deref(), Some("G/LD-001"));
+        assert!(Activation::parse("G/LD-001 G/LD-003").is_err());
+
+        let mut entry = LogEntry { callsign: "M0ABC".into(), timestamp: activation.started, ..Default::default() };
+        activation.stamp(&mut entry);
+        assert_eq!(entry.my_sig.as_deref(), Some("POTA"));
+        assert_eq!(entry.my_sota_ref.as_deref(), Some("G/LD-001"));
+        assert_eq!(
+            activation.progress_text(std::slice::from_ref(&entry), activation.started),
+            "K-0001 1/10, G/LD-001 1/4"
+        );
+    }
+}
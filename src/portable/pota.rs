@@ -0,0 +1,275 @@
+//! Parks on the Air: park references, activations and upload files
+
+use chrono::NaiveDate;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use super::{csv, PortableError};
+use crate::storage::AdifStorage;
+use crate::LogEntry;
+
+/// ADIF SIG value for Parks on the Air
+pub const SIG: &str = "POTA";
+
+/// QSOs a park needs in one UTC day for the activation to count
+pub const ACTIVATION_THRESHOLD: usize = 10;
+
+lazy_static! {
+    static ref PARK_REFERENCE: Regex = Regex::new(r"^[A-Z0-9]{1,4}-\d{4,5}$").unwrap();
+}
+
+/// A park from the POTA reference list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Park {
+    pub reference: String,
+    pub name: String,
+    pub location: Option<String>,
+    pub grid: Option<String>,
+}
+
+/// Parks loaded from the CSV published by POTA (`reference,name,active,
+/// entityId,locationDesc,latitude,longitude,grid`)
+#[derive(Debug, Clone, Default)]
+pub struct ParkList {
+    parks: HashMap<String, Park>,
+}
+
+impl ParkList {
+    /// Load the list, or an empty one if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, PortableError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, PortableError> {
+        let mut records = csv::parse_records(content).into_iter();
+        let header = records.next().unwrap_or_default();
+        let columns = csv::columns(&header, &["reference", "name", "locationDesc", "grid"]);
+        let (Some(reference), Some(name)) = (columns[0], columns[1]) else {
+            return Err(PortableError::Csv("park list needs reference and name columns".to_string()));
+        };
+
+        let text = |record: &[String], column: Option<usize>| {
+            column.and_then(|i| record.get(i))
+                .map(|value| value.trim().to_string())
+                .filter(|value| !value.is_empty())
+        };
+        let parks = records
+            .filter_map(|record| {
+                let park = Park {
+                    reference: text(&record, Some(reference))?.to_uppercase(),
+                    name: text(&record, Some(name)).unwrap_or_default(),
+                    location: text(&record, columns[2]),
+                    grid: text(&record, columns[3]),
+                };
+                Some((park.reference.clone(), park))
+            })
+            .collect();
+        Ok(Self { parks })
+    }
+
+    pub fn get(&self, reference: &str) -> Option<&Park> {
+        self.parks.get(&reference.trim().to_uppercase())
+    }
+
+    pub fn len(&self) -> usize {
+        self.parks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parks.is_empty()
+    }
+}
+
+/// Parse one or more park references separated by commas or spaces
+pub fn parse_references(input: &str) -> Result<Vec<String>, PortableError> {
+    let mut references = Vec::new();
+    for reference in input.split([',', ' ']).map(str::trim).filter(|r| !r.is_empty()) {
+        let reference = reference.to_uppercase();
+        if !PARK_REFERENCE.is_match(&reference) {
+            return Err(PortableError::InvalidReference(reference));
+        }
+        if !references.contains(&reference) {
+            references.push(reference);
+        }
+    }
+    Ok(references)
+}
+
+fn split_references(value: &Option<String>) -> Vec<String> {
+    value.as_deref()
+        .map(|refs| refs.split(',').map(|r| r.trim().to_uppercase()).filter(|r| !r.is_empty()).collect())
+        .unwrap_or_default()
+}
+
+fn is_pota(sig: &Option<String>) -> bool {
+    sig.as_deref().is_some_and(|sig| sig.trim().eq_ignore_ascii_case(SIG))
+}
+
+/// Parks we were activating when the QSO was made
+pub fn activated_parks(entry: &LogEntry) -> Vec<String> {
+    if is_pota(&entry.my_sig) {
+        split_references(&entry.my_sig_info)
+    } else {
+        Vec::new()
+    }
+}
+
+/// Parks the worked station was in
+pub fn hunted_parks(entry: &LogEntry) -> Vec<String> {
+    if is_pota(&entry.sig) {
+        split_references(&entry.sig_info)
+    } else {
+        split_references(&entry.pota_ref)
+    }
+}
+
+/// Record the worked station's park(s); an empty list clears them
+pub fn set_hunted_parks(entry: &mut LogEntry, parks: &[String]) {
+    if parks.is_empty() {
+        if is_pota(&entry.sig) {
+            entry.sig = None;
+            entry.sig_info = None;
+        }
+        entry.pota_ref = None;
+        return;
+    }
+    let parks = parks.join(",");
+    entry.sig = Some(SIG.to_string());
+    entry.sig_info = Some(parks.clone());
+    entry.pota_ref = Some(parks);
+}
+
+/// Both ends of the QSO were in a park
+pub fn is_park_to_park(entry: &LogEntry) -> bool {
+    !activated_parks(entry).is_empty() && !hunted_parks(entry).is_empty()
+}
+
+/// QSOs counting toward activating `park` on `date`. A station counts once
+/// per band and mode.
+pub fn activation_qsos(entries: &[LogEntry], park: &str, date: NaiveDate) -> usize {
+    entries.iter()
+        .filter(|entry| entry.timestamp.date_naive() == date)
+        .filter(|entry| activated_parks(entry).iter().any(|p| p == park))
+        .map(|entry| (entry.callsign.trim().to_uppercase(), entry.band_name(), entry.mode.to_uppercase()))
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// The QSOs of one park on one UTC day, as uploaded to POTA
+#[derive(Debug, Clone)]
+pub struct UploadFile {
+    pub park: String,
+    pub date: NaiveDate,
+    pub entries: Vec<LogEntry>,
+}
+
+impl UploadFile {
+    /// POTA's naming convention, e.g. `N0CALL@K-0001-20240704.adi`
+    pub fn file_name(&self, callsign: &str) -> String {
+        format!(
+            "{}@{}-{}.adi",
+            callsign.trim().to_uppercase().replace('/', "-"),
+            self.park,
+            self.date.format("%Y%m%d")
+        )
+    }
+
+    pub fn adif(&self) -> String {
+        AdifStorage::entries_to_adif(&self.entries)
+    }
+}
+
+/// Split activation QSOs into one file per park per UTC day. QSOs from a
+/// multi-park activation go into every park's file with that park alone in
+/// MY_SIG_INFO; `callsign` fills in OPERATOR where it is missing.
+pub fn upload_files(entries: &[LogEntry], callsign: &str) -> Vec<UploadFile> {
+    let mut files: BTreeMap<(String, NaiveDate), Vec<LogEntry>> = BTreeMap::new();
+    for entry in entries {
+        for park in activated_parks(entry) {
+            let mut entry = entry.clone();
+            entry.my_sig_info = Some(park.clone());
+            if entry.operator.as_deref().is_none_or(|op| op.trim().is_empty()) {
+                entry.operator = Some(callsign.trim().to_uppercase());
+            }
+            files.entry((park, entry.timestamp.date_naive())).or_default().push(entry);
+        }
+    }
+    files.into_iter()
+        .map(|((park, date), mut entries)| {
+            entries.sort_by_key(|entry| entry.timestamp);
+            UploadFile { park, date, entries }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::portable::Activation;
+    use crate::storage::formats::fixtures::qso_on;
+    use chrono::{Duration, TimeZone, Utc};
+
+    fn activation_qso(call: &str, hour: u32, parks: &str) -> LogEntry {
+        let mut entry = LogEntry {
+            timestamp: Utc.with_ymd_and_hms(2024, 7, 4, hour, 0, 0).unwrap(),
+            ..qso_on(&format!("{}-{}", call, hour), call, 14.062, "CW")
+        };
+        Activation::parse(parks).unwrap().stamp(&mut entry);
+        entry
+    }
+
+    #[test]
+    fn test_parse_park_list_and_references() {
+        let list = ParkList::parse(
+            "\"reference\",\"name\",\"active\",\"entityId\",\"locationDesc\",\"latitude\",\"longitude\",\"grid\"\n\
+             \"K-0001\",\"Acadia National Park\",\"1\",\"291\",\"US-ME\",\"44.31\",\"-68.2034\",\"FN54vh\"\n",
+        ).unwrap();
+        assert_eq!(list.len(), 1);
+        let park = list.get("k-0001").unwrap();
+        assert_eq!(park.name, "Acadia National Park");
+        assert_eq!(park.location.as_deref(), Some("US-ME"));
+
+        assert_eq!(parse_references("k-0001, K-4567").unwrap(), vec!["K-0001", "K-4567"]);
+        assert!(matches!(parse_references("K0001"), Err(PortableError::InvalidReference(_))));
+    }
+
+    #[test]
+    fn test_activation_counts_unique_contacts_per_utc_day() {
+        let mut entries: Vec<LogEntry> = (0..9).map(|i| activation_qso(&format!("W{}AW", i), 10, "K-0001,K-0002")).collect();
+        entries.push(activation_qso("W0AW", 11, "K-0001,K-0002"));
+        assert_eq!(activation_qsos(&entries, "K-0001", entries[0].timestamp.date_naive()), 9);
+
+        let mut p2p = activation_qso("K1ABC", 12, "K-0001,K-0002");
+        set_hunted_parks(&mut p2p, &["K-4567".to_string()]);
+        assert!(is_park_to_park(&p2p));
+        entries.push(p2p);
+        let activation = Activation::parse("K-0001 K-0002").unwrap();
+        assert_eq!(activation.park_progress(&entries, entries[0].timestamp), vec![
+            ("K-0001".to_string(), ACTIVATION_THRESHOLD),
+            ("K-0002".to_string(), ACTIVATION_THRESHOLD),
+        ]);
+    }
+
+    #[test]
+    fn test_upload_files_per_park_and_day() {
+        let mut entries = vec![activation_qso("W1AW", 23, "K-0001,K-0002"), activation_qso("K1ABC", 1, "K-0001")];
+        entries[1].timestamp += Duration::days(1);
+        entries.push(LogEntry { callsign: "DL1ABC".into(), ..Default::default() });
+
+        let files = upload_files(&entries, "n0call/p");
+        let names: Vec<String> = files.iter().map(|f| f.file_name("n0call/p")).collect();
+        assert_eq!(names, vec![
+            "N0CALL-P@K-0001-20240704.adi",
+            "N0CALL-P@K-0001-20240705.adi",
+            "N0CALL-P@K-0002-20240704.adi",
+        ]);
+        let adif = files[2].adif();
+        assert!(adif.contains("<MY_SIG_INFO:6>K-0002"));
+        assert!(adif.contains("<OPERATOR:8>N0CALL/P"));
+    }
+}
@@ -0,0 +1,300 @@
+//! Summits on the Air: summit references, points and CSV v2 uploads
+
+use chrono::{Datelike, NaiveDate};
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::Path;
+
+use super::{csv, PortableError};
+use crate::app::mode::ModeCategory;
+use crate::LogEntry;
+
+/// Different stations an activator needs in one UTC day to qualify
+pub const ACTIVATION_THRESHOLD: usize = 4;
+
+lazy_static! {
+    static ref SUMMIT_REFERENCE: Regex = Regex::new(r"^[A-Z0-9]{1,8}/[A-Z0-9]{2}-\d{3}$").unwrap();
+}
+
+/// A summit from the SOTA summits list
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summit {
+    pub reference: String,
+    pub name: String,
+    pub altitude_m: Option<u32>,
+    pub points: u32,
+}
+
+/// Summits loaded from `summitslist.csv` as published by SOTA. The file
+/// starts with a title line before the `SummitCode,...` header.
+#[derive(Debug, Clone, Default)]
+pub struct SummitList {
+    summits: HashMap<String, Summit>,
+}
+
+impl SummitList {
+    /// Load the list, or an empty one if the file does not exist
+    pub fn load(path: &Path) -> Result<Self, PortableError> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, PortableError> {
+        let mut records = csv::parse_records(content).into_iter()
+            .skip_while(|record| !record.first().is_some_and(|f| f.trim().eq_ignore_ascii_case("SummitCode")));
+        let header = records.next()
+            .ok_or_else(|| PortableError::Csv("summits list has no SummitCode header".to_string()))?;
+        let columns = csv::columns(&header, &["SummitCode", "SummitName", "AltM", "Points"]);
+        let (Some(code), Some(points)) = (columns[0], columns[3]) else {
+            return Err(PortableError::Csv("summits list needs SummitCode and Points columns".to_string()));
+        };
+
+        let text = |record: &[String], column: Option<usize>| {
+            column.and_then(|i| record.get(i)).map(|value| value.trim().to_string()).unwrap_or_default()
+        };
+        let summits = records
+            .filter_map(|record| {
+                let summit = Summit {
+                    reference: text(&record, Some(code)).to_uppercase(),
+                    name: text(&record, columns[1]),
+                    altitude_m: text(&record, columns[2]).parse().ok(),
+                    points: text(&record, Some(points)).parse().ok()?,
+                };
+                Some((summit.reference.clone(), summit))
+            })
+            .collect();
+        Ok(Self { summits })
+    }
+
+    pub fn get(&self, reference: &str) -> Option<&Summit> {
+        self.summits.get(&reference.trim().to_uppercase())
+    }
+
+    /// Points for a summit, zero when it is not in the list
+    pub fn points(&self, reference: &str) -> u32 {
+        self.get(reference).map_or(0, |summit| summit.points)
+    }
+
+    pub fn len(&self) -> usize {
+        self.summits.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.summits.is_empty()
+    }
+}
+
+pub fn is_summit_reference(reference: &str) -> bool {
+    SUMMIT_REFERENCE.is_match(&reference.trim().to_uppercase())
+}
+
+/// Parse a summit reference; blank input means no summit
+pub fn parse_reference(input: &str) -> Result<Option<String>, PortableError> {
+    let reference = input.trim().to_uppercase();
+    if reference.is_empty() {
+        Ok(None)
+    } else if is_summit_reference(&reference) {
+        Ok(Some(reference))
+    } else {
+        Err(PortableError::InvalidReference(reference))
+    }
+}
+
+fn reference(value: &Option<String>) -> Option<String> {
+    value.as_deref().map(|r| r.trim().to_uppercase()).filter(|r| !r.is_empty())
+}
+
+/// The summit we were activating when the QSO was made
+pub fn activated_summit(entry: &LogEntry) -> Option<String> {
+    reference(&entry.my_sota_ref)
+}
+
+/// The summit the worked station was on
+pub fn chased_summit(entry: &LogEntry) -> Option<String> {
+    reference(&entry.sota_ref)
+}
+
+/// Both ends of the QSO were on a summit
+pub fn is_summit_to_summit(entry: &LogEntry) -> bool {
+    activated_summit(entry).is_some() && chased_summit(entry).is_some()
+}
+
+/// Different stations worked from `summit` on `date`
+pub fn activation_qsos(entries: &[LogEntry], summit: &str, date: NaiveDate) -> usize {
+    entries.iter()
+        .filter(|entry| entry.timestamp.date_naive() == date)
+        .filter(|entry| activated_summit(entry).as_deref() == Some(summit))
+        .map(|entry| entry.callsign.trim().to_uppercase())
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+/// Points claimed under the SOTA rules, without seasonal bonuses
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SotaPoints {
+    /// Each summit scores once per calendar year, on a qualifying day
+    pub activator: u32,
+    /// Each summit scores once per UTC day
+    pub chaser: u32,
+    /// Chased summit points for QSOs made from a summit
+    pub summit_to_summit: u32,
+}
+
+pub fn points(entries: &[LogEntry], summits: &SummitList) -> SotaPoints {
+    let mut activations: BTreeMap<(String, NaiveDate), HashSet<String>> = BTreeMap::new();
+    let mut chased = HashSet::new();
+    let mut summit_to_summit = HashSet::new();
+    for entry in entries {
+        let date = entry.timestamp.date_naive();
+        let mine = activated_summit(entry);
+        let theirs = chased_summit(entry);
+        if let Some(summit) = &mine {
+            activations.entry((summit.clone(), date)).or_default().insert(entry.callsign.trim().to_uppercase());
+        }
+        match (mine, theirs) {
+            (Some(mine), Some(theirs)) => {
+                summit_to_summit.insert((mine, theirs, date));
+            }
+            (None, Some(theirs)) => {
+                chased.insert((theirs, date));
+            }
+            _ => {}
+        }
+    }
+
+    let activated: HashSet<(String, i32)> = activations.into_iter()
+        .filter(|(_, stations)| stations.len() >= ACTIVATION_THRESHOLD)
+        .map(|((summit, date), _)| (summit, date.year()))
+        .collect();
+    SotaPoints {
+        activator: activated.iter().map(|(summit, _)| summits.points(summit)).sum(),
+        chaser: chased.iter().map(|(summit, _)| summits.points(summit)).sum(),
+        summit_to_summit: summit_to_summit.iter().map(|(_, summit, _)| summits.points(summit)).sum(),
+    }
+}
+
+/// Frequency in MHz without trailing zeros, as V2 files accept it
+fn frequency(mhz: f64) -> String {
+    let text = format!("{:.4}", mhz);
+    format!("{}MHz", text.trim_end_matches('0').trim_end_matches('.'))
+}
+
+fn mode(entry: &LogEntry) -> &'static str {
+    match entry.mode_category() {
+        ModeCategory::Cw => "CW",
+        ModeCategory::Digital => "DATA",
+        ModeCategory::Phone => match entry.mode.trim().to_uppercase().as_str() {
+            "FM" => "FM",
+            "AM" => "AM",
+            _ => "SSB",
+        },
+    }
+}
+
+/// `V2,MyCallsign,MySummit,Date,Time,Band,Mode,HisCallsign,HisSummit,Notes`
+fn csv_line(entry: &LogEntry, callsign: &str) -> String {
+    let fields = [
+        "V2".to_string(),
+        callsign.trim().to_uppercase(),
+        activated_summit(entry).unwrap_or_default(),
+        entry.timestamp.format("%d/%m/%y").to_string(),
+        entry.timestamp.format("%H%M").to_string(),
+        frequency(entry.frequency.mhz()),
+        mode(entry).to_string(),
+        entry.callsign.trim().to_uppercase(),
+        chased_summit(entry).unwrap_or_default(),
+        entry.notes.clone().unwrap_or_default(),
+    ];
+    let fields: Vec<String> = fields.iter().map(|f| csv::field(f)).collect();
+    fields.join(",")
+}
+
+fn csv_lines<'a>(entries: impl Iterator<Item = &'a LogEntry>, callsign: &str) -> String {
+    let mut entries: Vec<&LogEntry> = entries.collect();
+    entries.sort_by_key(|entry| entry.timestamp);
+    entries.iter().map(|entry| csv_line(entry, callsign) + "\n").collect()
+}
+
+/// Activator upload: every QSO made from a summit, summit-to-summit included
+pub fn activator_csv(entries: &[LogEntry], callsign: &str) -> String {
+    csv_lines(entries.iter().filter(|entry| activated_summit(entry).is_some()), callsign)
+}
+
+/// Chaser upload: summits worked from home. Summit-to-summit QSOs are
+/// claimed through the activator upload.
+pub fn chaser_csv(entries: &[LogEntry], callsign: &str) -> String {
+    csv_lines(
+        entries.iter().filter(|entry| activated_summit(entry).is_none() && chased_summit(entry).is_some()),
+        callsign,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::formats::fixtures::qso_on;
+    use chrono::{TimeZone, Utc};
+
+    const SUMMITS: &str = "SOTA Summits List (Date=01/07/2024)\n\
+        SummitCode,AssociationName,RegionName,SummitName,AltM,AltFt,GridRef1,GridRef2,Longitude,Latitude,Points,BonusPoints\n\
+        W7W/LC-001,USA (Washington),WA-Lewis,\"Mount Adams\",3743,12280,-121.49,46.2,-121.49,46.2,10,3\n\
+        G/LD-001,England,Lake District,Scafell Pike,978,3209,NY2154,07,-3.2,54.4,10,3\n\
+        G/LD-003,England,Lake District,Skiddaw,931,3054,NY2607,29,-3.1,54.6,8,3\n";
+
+    fn sota_qso(call: &str, day: u32, mine: Option<&str>, theirs: Option<&str>) -> LogEntry {
+        LogEntry {
+            timestamp: Utc.with_ymd_and_hms(2024, 7, day, 10, 5, 0).unwrap(),
+            my_sota_ref: mine.map(str::to_string),
+            sota_ref: theirs.map(str::to_string),
+            ..qso_on("", call, 14.062, "CW")
+        }
+    }
+
+    #[test]
+    fn test_parse_summits_list() {
+        let summits = SummitList::parse(SUMMITS).unwrap();
+        assert_eq!(summits.len(), 3);
+        assert_eq!(summits.get("w7w/lc-001").unwrap().name, "Mount Adams");
+        assert_eq!(summits.get("G/LD-001").unwrap().altitude_m, Some(978));
+        assert_eq!(summits.points("G/LD-003"), 8);
+        assert_eq!(summits.points("G/LD-999"), 0);
+        assert_eq!(parse_reference(" g/ld-001 ").unwrap().as_deref(), Some("G/LD-001"));
+        assert!(parse_reference("K-0001").is_err());
+    }
+
+    #[test]
+    fn test_points() {
+        let summits = SummitList::parse(SUMMITS).unwrap();
+        let mut entries: Vec<LogEntry> = ["M0ABC", "G4XYZ", "M0ABC", "2E0AAA"].iter()
+            .map(|call| sota_qso(call, 1, Some("G/LD-001"), None))
+            .collect();
+        // Three different stations: not yet qualified
+        assert_eq!(points(&entries, &summits).activator, 0);
+
+        entries.push(sota_qso("G3ZZZ", 1, Some("G/LD-001"), Some("G/LD-003")));
+        entries.push(sota_qso("DL1ABC", 2, None, Some("W7W/LC-001")));
+        entries.push(sota_qso("DL1ABC", 2, None, Some("W7W/LC-001")));
+        entries.push(sota_qso("DL1ABC", 3, None, Some("W7W/LC-001")));
+        assert!(is_summit_to_summit(&entries[4]));
+        assert_eq!(points(&entries, &summits), SotaPoints { activator: 10, chaser: 20, summit_to_summit: 8 });
+    }
+
+    #[test]
+    fn test_csv_v2() {
+        let mut s2s = sota_qso("G3ZZZ", 1, Some("G/LD-001"), Some("G/LD-003"));
+        s2s.notes = Some("S2S, 559".to_string());
+        let entries = vec![s2s, sota_qso("DL1ABC", 2, None, Some("W7W/LC-001"))];
+
+        assert_eq!(
+            activator_csv(&entries, "m0call/p"),
+            "V2,M0CALL/P,G/LD-001,01/07/24,1005,14.062MHz,CW,G3ZZZ,G/LD-003,\"S2S, 559\"\n"
+        );
+        assert_eq!(
+            chaser_csv(&entries, "M0CALL"),
+            "V2,M0CALL,,02/07/24,1005,14.062MHz,CW,DL1ABC,W7W/LC-001,\n"
+        );
+    }
+}
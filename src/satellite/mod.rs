@@ -0,0 +1,179 @@
+//! Amateur satellites: a catalogue of transponder passbands used to fill in
+//! and check satellite QSOs the way LoTW expects them
+//!
+//! LoTW credits a satellite QSO when PROP_MODE is `SAT`, SAT_NAME is one of
+//! its satellite names and BAND/BAND_RX are the uplink/downlink bands.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use thiserror::Error;
+
+use crate::LogEntry;
+
+/// Satellites shipped with amlog
+const BUILTIN_SATELLITES: &str = include_str!("satellites.json");
+
+/// ADIF propagation mode for satellite QSOs
+pub const PROP_MODE: &str = "SAT";
+
+#[derive(Error, Debug)]
+pub enum SatelliteError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid satellite catalogue: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("{0} {1} {2:.3} MHz is outside {3:.3}-{4:.3} MHz")]
+    OutOfPassband(String, &'static str, f64, f64, f64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transponder {
+    /// Single-channel FM repeater
+    Fm,
+    /// Linear transponder for SSB and CW
+    Linear,
+}
+
+/// Frequency range in MHz
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Passband {
+    pub low: f64,
+    pub high: f64,
+}
+
+impl Passband {
+    pub fn contains(&self, mhz: f64) -> bool {
+        (self.low..=self.high).contains(&mhz)
+    }
+
+    pub fn center(&self) -> f64 {
+        (self.low + self.high) / 2.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Satellite {
+    /// The name LoTW knows the satellite by, e.g. `SO-50`
+    pub name: String,
+    /// Other names operators use for it
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    pub transponder: Transponder,
+    /// ADIF SAT_MODE, uplink/downlink band letters
+    pub sat_mode: String,
+    pub uplink: Passband,
+    pub downlink: Passband,
+}
+
+impl Satellite {
+    pub fn matches(&self, name: &str) -> bool {
+        let name = name.trim();
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+
+    /// Mode to log when the operator has not chosen one
+    pub fn default_mode(&self) -> &'static str {
+        match self.transponder {
+            Transponder::Fm => "FM",
+            Transponder::Linear => "SSB",
+        }
+    }
+
+    /// Mark the QSO as made through this satellite: propagation mode,
+    /// LoTW name, and the uplink and downlink bands
+    pub fn apply(&self, entry: &mut LogEntry) {
+        entry.prop_mode = Some(PROP_MODE.to_string());
+        entry.sat_name = Some(self.name.clone());
+        entry.sat_mode = Some(self.sat_mode.clone());
+        entry.band = entry.frequency.band();
+        entry.band_rx = entry.freq_rx.and_then(|rx| rx.band());
+    }
+
+    /// Check the transmit and receive frequencies against the passbands
+    pub fn check(&self, entry: &LogEntry) -> Result<(), SatelliteError> {
+        if !self.uplink.contains(entry.frequency.mhz()) {
+            return Err(SatelliteError::OutOfPassband(
+                self.name.clone(), "uplink", entry.frequency.mhz(), self.uplink.low, self.uplink.high,
+            ));
+        }
+        match entry.freq_rx {
+            Some(rx) if !self.downlink.contains(rx.mhz()) => Err(SatelliteError::OutOfPassband(
+                self.name.clone(), "downlink", rx.mhz(), self.downlink.low, self.downlink.high,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Whether the QSO was made through a satellite
+pub fn is_satellite_qso(entry: &LogEntry) -> bool {
+    entry.prop_mode.as_deref().is_some_and(|mode| mode.trim().eq_ignore_ascii_case(PROP_MODE))
+}
+
+/// Look a satellite up by its LoTW name or an alias
+pub fn find<'a>(satellites: &'a [Satellite], name: &str) -> Option<&'a Satellite> {
+    satellites.iter().find(|satellite| satellite.matches(name))
+}
+
+pub fn builtin_satellites() -> Vec<Satellite> {
+    serde_json::from_str(BUILTIN_SATELLITES).expect("built-in satellite catalogue is valid")
+}
+
+/// Built-in satellites plus any in `path`; entries with the same name
+/// replace built-ins, e.g. after a frequency change
+pub fn load_satellites(path: &Path) -> Result<Vec<Satellite>, SatelliteError> {
+    let mut satellites = builtin_satellites();
+    if path.exists() {
+        let extra: Vec<Satellite> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        for satellite in extra {
+            match satellites.iter_mut().find(|s| s.name.eq_ignore_ascii_case(&satellite.name)) {
+                Some(existing) => *existing = satellite,
+                None => satellites.push(satellite),
+            }
+        }
+    }
+    Ok(satellites)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Frequency;
+
+    #[test]
+    fn test_builtin_catalogue() {
+        let satellites = builtin_satellites();
+        let so50 = find(&satellites, "saudisat-1c").unwrap();
+        assert_eq!(so50.name, "SO-50");
+        assert_eq!(so50.default_mode(), "FM");
+        assert!(satellites.iter().all(|s| s.uplink.low <= s.uplink.high && s.downlink.low <= s.downlink.high));
+        assert!(satellites.iter().all(|s| Frequency::from_mhz(s.uplink.center()).unwrap().band().is_some()));
+        assert!(find(&satellites, "XX-99").is_none());
+    }
+
+    #[test]
+    fn test_apply_and_check() {
+        let satellites = builtin_satellites();
+        let rs44 = find(&satellites, "RS-44").unwrap();
+        let mut entry = LogEntry {
+            callsign: "W1AW".into(),
+            frequency: Frequency::from_hz(145_960_000),
+            freq_rx: Some(Frequency::from_hz(435_645_000)),
+            mode: "SSB".into(),
+            ..Default::default()
+        };
+        rs44.apply(&mut entry);
+        assert!(is_satellite_qso(&entry));
+        assert_eq!(entry.sat_name.as_deref(), Some("RS-44"));
+        assert_eq!(entry.band.as_deref(), Some("2m"));
+        assert_eq!(entry.band_rx.as_deref(), Some("70cm"));
+        assert!(rs44.check(&entry).is_ok());
+
+        // Uplink and downlink swapped
+        entry.frequency = Frequency::from_hz(435_645_000);
+        assert!(matches!(rs44.check(&entry), Err(SatelliteError::OutOfPassband(_, "uplink", _, _, _))));
+    }
+}
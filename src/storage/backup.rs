@@ -0,0 +1,325 @@
+//! Timestamped copies of the log, taken on startup, on shutdown, every few
+//! QSOs and on demand, with the oldest removed beyond the retention limit
+//!
+//! A backup is a complete log in the same format, named after the log with
+//! the time and reason added, e.g.
+//! `logbook-20241018T143000.123456789Z-startup.adi`, so it can be opened
+//! directly as well as restored. The journal, revisions and custom fields
+//! kept beside a file-based log are backed up and restored with it.
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::fields::FieldFile;
+use super::journal::JournalFile;
+use super::lock::LogLock;
+use super::revision::RevisionFile;
+use super::StorageError;
+
+const DEFAULT_EVERY: u32 = 50;
+const DEFAULT_KEEP: usize = 20;
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%S%.9fZ";
+/// Also reads the millisecond stamps of older backups
+const PARSE_FORMAT: &str = "%Y%m%dT%H%M%S%.fZ";
+
+/// Why a backup was taken
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BackupReason {
+    Startup,
+    Shutdown,
+    Periodic,
+    Manual,
+    /// The log as it was before a backup was restored over it
+    PreRestore,
+    /// The log as it was before `fsck` repaired it
+    PreRepair,
+    /// A log that could not be read
+    Corrupt,
+}
+
+impl BackupReason {
+    const ALL: [BackupReason; 7] = [
+        BackupReason::Startup,
+        BackupReason::Shutdown,
+        BackupReason::Periodic,
+        BackupReason::Manual,
+        BackupReason::PreRestore,
+        BackupReason::PreRepair,
+        BackupReason::Corrupt,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupReason::Startup => "startup",
+            BackupReason::Shutdown => "shutdown",
+            BackupReason::Periodic => "periodic",
+            BackupReason::Manual => "manual",
+            BackupReason::PreRestore => "pre-restore",
+            BackupReason::PreRepair => "pre-repair",
+            BackupReason::Corrupt => "corrupt",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|reason| reason.as_str() == s)
+    }
+}
+
+/// Where backups go, how often they are taken and how many are kept
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    pub dir: PathBuf,
+    /// Back up after this many entries are added, changed or removed; 0
+    /// turns periodic backups off
+    pub every: u32,
+    /// Number of backups to keep; 0 keeps them all
+    pub keep: usize,
+}
+
+impl BackupConfig {
+    /// Defaults, with backups in `backups/` next to the log
+    pub fn beside(log_path: &Path) -> Self {
+        Self {
+            dir: log_path.parent().unwrap_or(Path::new(".")).join("backups"),
+            every: DEFAULT_EVERY,
+            keep: DEFAULT_KEEP,
+        }
+    }
+
+    /// Defaults overridden by `AMLOG_BACKUP_DIR`, `AMLOG_BACKUP_EVERY` and
+    /// `AMLOG_BACKUP_KEEP`
+    pub fn from_env(log_path: &Path) -> Self {
+        let defaults = Self::beside(log_path);
+        Self {
+            dir: env::var("AMLOG_BACKUP_DIR").map(PathBuf::from).unwrap_or(defaults.dir),
+            every: env::var("AMLOG_BACKUP_EVERY").ok().and_then(|v| v.parse().ok()).unwrap_or(defaults.every),
+            keep: env::var("AMLOG_BACKUP_KEEP").ok().and_then(|v| v.parse().ok()).unwrap_or(defaults.keep),
+        }
+    }
+}
+
+/// A backup file of a log
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backup {
+    pub path: PathBuf,
+    pub taken_at: DateTime<Utc>,
+    pub reason: BackupReason,
+}
+
+impl Backup {
+    /// A backup of `log_path` taken now, not yet written. The name is never
+    /// one already in `dir`.
+    pub fn new(dir: &Path, log_path: &Path, reason: BackupReason) -> Self {
+        let (stem, extension) = name_parts(log_path);
+        let mut taken_at = Utc::now();
+        loop {
+            let name = format!("{}-{}-{}.{}", stem, taken_at.format(STAMP_FORMAT), reason.as_str(), extension);
+            let path = dir.join(name);
+            if !path.exists() {
+                return Self { path, taken_at, reason };
+            }
+            taken_at += Duration::nanoseconds(1);
+        }
+    }
+
+    /// Recognise a backup of `log_path` by its file name
+    fn parse(log_path: &Path, path: &Path) -> Option<Self> {
+        let (stem, extension) = name_parts(log_path);
+        let name = path.file_name()?.to_str()?;
+        let rest = name.strip_prefix(&format!("{}-", stem))?.strip_suffix(&format!(".{}", extension))?;
+        let (stamp, reason) = rest.split_once('-')?;
+        let taken_at = NaiveDateTime::parse_from_str(stamp, PARSE_FORMAT).ok()?.and_utc();
+        Some(Self { path: path.to_path_buf(), taken_at, reason: BackupReason::parse(reason)? })
+    }
+
+    pub fn name(&self) -> String {
+        self.path.file_name().unwrap_or_default().to_string_lossy().into_owned()
+    }
+}
+
+fn name_parts(log_path: &Path) -> (String, String) {
+    let stem = log_path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+    let extension = log_path.extension().map_or("bak".into(), |e| e.to_string_lossy().into_owned());
+    (stem, extension)
+}
+
+/// `path` with `suffix` added to its file name
+pub fn suffixed(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_os_string();
+    name.push(OsStr::new(suffix));
+    PathBuf::from(name)
+}
+
+/// Files that go wherever the log goes: its journal, revisions and custom
+/// fields, and SQLite's write-ahead log
+fn sidecars(log_path: &Path) -> [PathBuf; 5] {
+    [
+        JournalFile::path_for(log_path),
+        RevisionFile::path_for(log_path),
+        FieldFile::path_for(log_path),
+        suffixed(log_path, "-wal"),
+        suffixed(log_path, "-shm"),
+    ]
+}
+
+/// Copy the sidecars of the log at `from` to sit beside `to`, removing
+/// those `to` has that `from` does not
+pub fn copy_sidecars(from: &Path, to: &Path) -> Result<(), StorageError> {
+    for (from, to) in sidecars(from).into_iter().zip(sidecars(to)) {
+        if from.exists() {
+            fs::copy(&from, &to)?;
+        } else if to.exists() {
+            fs::remove_file(&to)?;
+        }
+    }
+    Ok(())
+}
+
+/// Move a log and its sidecars to a new name. The log is moved last so it
+/// is never found without them.
+pub fn rename_with_sidecars(from: &Path, to: &Path) -> Result<(), StorageError> {
+    for (from, to) in sidecars(from).into_iter().zip(sidecars(to)) {
+        if from.exists() {
+            fs::rename(&from, &to)?;
+        } else if to.exists() {
+            fs::remove_file(&to)?;
+        }
+    }
+    fs::rename(from, to)?;
+    Ok(())
+}
+
+/// Backups of a log in `dir`, newest first
+pub fn list(dir: &Path, log_path: &Path) -> Result<Vec<Backup>, StorageError> {
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut backups = Vec::new();
+    for item in fs::read_dir(dir)? {
+        if let Some(backup) = Backup::parse(log_path, &item?.path()) {
+            backups.push(backup);
+        }
+    }
+    backups.sort_by(|a, b| b.taken_at.cmp(&a.taken_at).then_with(|| b.path.cmp(&a.path)));
+    Ok(backups)
+}
+
+/// Find a backup by file name or path
+pub fn find(dir: &Path, log_path: &Path, name: &str) -> Result<Backup, StorageError> {
+    let path = Path::new(name);
+    let path = if path.components().count() > 1 { path.to_path_buf() } else { dir.join(name) };
+    Backup::parse(log_path, &path)
+        .filter(|backup| backup.path.exists())
+        .ok_or_else(|| StorageError::NotFound(format!("backup {}", name)))
+}
+
+/// Delete the oldest backups beyond `keep`, returning those removed
+pub fn prune(dir: &Path, log_path: &Path, keep: usize) -> Result<Vec<Backup>, StorageError> {
+    if keep == 0 {
+        return Ok(Vec::new());
+    }
+    let stale: Vec<Backup> = list(dir, log_path)?.into_iter().skip(keep).collect();
+    for backup in &stale {
+        fs::remove_file(&backup.path)?;
+        for sidecar in sidecars(&backup.path).iter().filter(|path| path.exists()) {
+            fs::remove_file(sidecar)?;
+        }
+    }
+    Ok(stale)
+}
+
+/// Replace a log with a backup using its files alone, so a log that will
+/// not open can be restored. A copy of the log as it was is kept and
+/// returned, unless there was no log.
+pub async fn restore(config: &BackupConfig, log_path: &Path, backup: &Backup) -> Result<Option<Backup>, StorageError> {
+    let lock = LogLock::open(log_path)?;
+    let _guard = lock.exclusive().await?;
+
+    // Copy the backup before pruning can remove it
+    let incoming = suffixed(log_path, ".restore");
+    fs::copy(&backup.path, &incoming)?;
+    copy_sidecars(&backup.path, &incoming)?;
+
+    let previous = if log_path.exists() {
+        fs::create_dir_all(&config.dir)?;
+        let previous = Backup::new(&config.dir, log_path, BackupReason::PreRestore);
+        let partial = suffixed(&previous.path, ".partial");
+        fs::copy(log_path, &partial)?;
+        copy_sidecars(log_path, &partial)?;
+        rename_with_sidecars(&partial, &previous.path)?;
+        prune(&config.dir, log_path, config.keep)?;
+        Some(previous)
+    } else {
+        None
+    };
+    rename_with_sidecars(&incoming, log_path)?;
+    Ok(previous)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_names_and_pruning() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("logbook.adi");
+        let backups = dir.path().join("backups");
+        fs::create_dir_all(&backups).unwrap();
+
+        let mut taken = Vec::new();
+        for reason in [BackupReason::Startup, BackupReason::PreRestore, BackupReason::Shutdown] {
+            let backup = Backup::new(&backups, &log, reason);
+            fs::write(&backup.path, "").unwrap();
+            taken.push(backup);
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        // Other files in the directory are left alone
+        fs::write(backups.join("logbook-notes.adi"), "").unwrap();
+        fs::write(backups.join("other-20241018T143000.000Z-startup.adi"), "").unwrap();
+
+        let found = list(&backups, &log).unwrap();
+        assert_eq!(found.iter().map(|b| b.reason).collect::<Vec<_>>(),
+            vec![BackupReason::Shutdown, BackupReason::PreRestore, BackupReason::Startup]);
+        assert_eq!(found[2], taken[0]);
+        assert_eq!(find(&backups, &log, &found[1].name()).unwrap().path, taken[1].path);
+
+        let removed = prune(&backups, &log, 2).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert_eq!(removed[0].reason, BackupReason::Startup);
+        assert_eq!(list(&backups, &log).unwrap().len(), 2);
+        assert_eq!(fs::read_dir(&backups).unwrap().count(), 4);
+
+        // Backups taken together get names of their own
+        let first = Backup::new(&backups, &log, BackupReason::Manual);
+        fs::write(&first.path, "").unwrap();
+        let second = Backup::new(&backups, &log, BackupReason::Manual);
+        assert_ne!(first.path, second.path);
+    }
+
+    #[tokio::test]
+    async fn test_restore_log_with_sidecars() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("logbook.jsonl");
+        let config = BackupConfig::beside(&log);
+        fs::create_dir_all(&config.dir).unwrap();
+        let backup = Backup::new(&config.dir, &log, BackupReason::Manual);
+        fs::write(&backup.path, "backed up").unwrap();
+        fs::write(JournalFile::path_for(&backup.path), "backed up journal").unwrap();
+        fs::write(&log, "not a log").unwrap();
+        fs::write(JournalFile::path_for(&log), "journal").unwrap();
+        fs::write(RevisionFile::path_for(&log), "revisions").unwrap();
+
+        let previous = restore(&config, &log, &backup).await.unwrap().unwrap();
+        assert_eq!(fs::read_to_string(&log).unwrap(), "backed up");
+        assert_eq!(fs::read_to_string(JournalFile::path_for(&log)).unwrap(), "backed up journal");
+        // The backup had no revisions, so the log has none either
+        assert!(!RevisionFile::path_for(&log).exists());
+        assert_eq!(fs::read_to_string(&previous.path).unwrap(), "not a log");
+        assert_eq!(fs::read_to_string(RevisionFile::path_for(&previous.path)).unwrap(), "revisions");
+        assert_eq!(list(&config.dir, &log).unwrap().len(), 2);
+    }
+}
@@ -37,6 +37,12 @@ pub enum StorageError {
     #[error("Parse error: {0}")]
     ParseError(String),
 
+    #[error("Entry {0} was changed by another program at the same time; the log has been reloaded")]
+    Conflict(String),
+
+    #[error("Log is locked by another program ({0})")]
+    Locked(String),
+
 }
 
 impl From<sqlx::Error> for StorageError {
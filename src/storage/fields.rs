@@ -0,0 +1,398 @@
+//! Typed custom fields registered per log, and queries over their values
+//!
+//! Custom field values live in each entry's `custom_fields` under the
+//! field name. A definition gives a field a type, so values are checked
+//! and kept in one form when saved, compare as that type in queries, and
+//! carry their type through ADIF as a USERDEF field. Values of fields
+//! without a definition are kept too and treated as text.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::NaiveDate;
+
+use super::StorageError;
+use crate::LogEntry;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FieldType {
+    Text,
+    Number,
+    /// Stored as YYYY-MM-DD
+    Date,
+    /// One of the definition's options
+    Enum,
+    /// Stored as Y or N, as in ADIF
+    Boolean,
+}
+
+impl FieldType {
+    const ALL: [FieldType; 5] = [
+        FieldType::Text,
+        FieldType::Number,
+        FieldType::Date,
+        FieldType::Enum,
+        FieldType::Boolean,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FieldType::Text => "text",
+            FieldType::Number => "number",
+            FieldType::Date => "date",
+            FieldType::Enum => "enum",
+            FieldType::Boolean => "boolean",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|field_type| field_type.as_str().eq_ignore_ascii_case(s.trim()))
+    }
+
+    /// ADIF data type indicator
+    pub fn adif_indicator(&self) -> char {
+        match self {
+            FieldType::Text => 'S',
+            FieldType::Number => 'N',
+            FieldType::Date => 'D',
+            FieldType::Enum => 'E',
+            FieldType::Boolean => 'B',
+        }
+    }
+
+    /// Type for an ADIF data type indicator; strings, times and anything
+    /// else without a type of its own are text
+    pub fn from_adif_indicator(indicator: char) -> Self {
+        match indicator.to_ascii_uppercase() {
+            'N' => FieldType::Number,
+            'D' => FieldType::Date,
+            'E' => FieldType::Enum,
+            'B' => FieldType::Boolean,
+            _ => FieldType::Text,
+        }
+    }
+
+    /// Order two stored values. Text compares without regard to ASCII case
+    /// and numbers by value, with anything that is not a number taken as 0,
+    /// the way SQLite casts it.
+    pub fn compare(&self, a: &str, b: &str) -> Ordering {
+        match self {
+            FieldType::Number => number(a).partial_cmp(&number(b)).unwrap_or(Ordering::Equal),
+            FieldType::Text | FieldType::Enum => a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()),
+            FieldType::Date | FieldType::Boolean => a.cmp(b),
+        }
+    }
+}
+
+fn number(value: &str) -> f64 {
+    value.trim().parse().unwrap_or(0.0)
+}
+
+/// ADIF fields that amlog reads into an entry's own fields, so a custom
+/// field cannot take their names
+const RESERVED_NAMES: &[&str] = &[
+    "BAND", "BAND_RX", "CALL", "CNTY", "COMMENT", "CONT", "CONTEST_ID", "COUNTRY", "CQZ", "DXCC",
+    "EOH", "EOR", "EQSL_QSLRDATE", "EQSL_QSLSDATE", "EQSL_QSL_RCVD", "EQSL_QSL_SENT", "FREQ", "FREQ_RX",
+    "GRIDSQUARE", "IOTA", "ITUZ", "LOTW_QSLRDATE", "LOTW_QSLSDATE", "LOTW_QSL_RCVD", "LOTW_QSL_SENT",
+    "MODE", "MY_SIG", "MY_SIG_INFO", "MY_SOTA_REF", "NAME", "OPERATOR", "POTA_REF", "PROP_MODE",
+    "QSLRDATE", "QSLSDATE", "QSL_RCVD", "QSL_RCVD_VIA", "QSL_SENT", "QSL_SENT_VIA", "QSO_DATE",
+    "QSO_DATE_OFF", "QTH", "RST_RCVD", "RST_SENT", "SAT_MODE", "SAT_NAME", "SIG", "SIG_INFO", "SOTA_REF",
+    "SRX", "STATE", "STX", "SUBMODE", "TIME_OFF", "TIME_ON", "TX_PWR",
+];
+
+/// A custom field registered for a log
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FieldDefinition {
+    /// Upper-case name, as the field is called in ADIF
+    pub name: String,
+    pub field_type: FieldType,
+    /// Allowed values of an enum field
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<String>,
+}
+
+impl FieldDefinition {
+    /// A definition with its name upper-cased. Names are ADIF field names
+    /// of letters, digits and underscores; `APP_` names belong to programs,
+    /// and the ADIF fields a QSO already has are taken.
+    pub fn new(name: &str, field_type: FieldType, options: Vec<String>) -> Result<Self, StorageError> {
+        let name = name.trim().to_uppercase();
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') || name.starts_with("APP_") {
+            return Err(StorageError::Validation(format!("'{}' cannot be used as a field name", name)));
+        }
+        if RESERVED_NAMES.contains(&name.as_str()) || name.starts_with("USERDEF") {
+            return Err(StorageError::Validation(format!("{} is already an ADIF field of every QSO", name)));
+        }
+        let options: Vec<String> = options.iter().map(|o| o.trim().to_string()).filter(|o| !o.is_empty()).collect();
+        if field_type == FieldType::Enum && options.is_empty() {
+            return Err(StorageError::Validation(format!("{} needs a list of allowed values", name)));
+        }
+        if options.iter().any(|o| o.contains([',', '{', '}'])) {
+            return Err(StorageError::Validation(format!("values of {} cannot contain commas or braces", name)));
+        }
+        let options = if field_type == FieldType::Enum { options } else { Vec::new() };
+        Ok(Self { name, field_type, options })
+    }
+
+    /// Check a value and put it in the stored form of the field
+    pub fn normalize(&self, value: &str) -> Result<String, StorageError> {
+        let value = value.trim();
+        let invalid = |expected: &str| {
+            StorageError::Validation(format!("{} must be {}, not '{}'", self.name, expected, value))
+        };
+        match self.field_type {
+            FieldType::Text => Ok(value.to_string()),
+            FieldType::Number => match value.parse::<f64>() {
+                Ok(n) if n.is_finite() => Ok(value.to_string()),
+                _ => Err(invalid("a number")),
+            },
+            FieldType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(value, "%Y%m%d"))
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .map_err(|_| invalid("a date (YYYY-MM-DD)")),
+            FieldType::Enum => self.options.iter()
+                .find(|option| option.eq_ignore_ascii_case(value))
+                .cloned()
+                .ok_or_else(|| invalid(&format!("one of {}", self.options.join(", ")))),
+            FieldType::Boolean => match value.to_uppercase().as_str() {
+                "Y" | "YES" | "TRUE" | "1" => Ok("Y".to_string()),
+                "N" | "NO" | "FALSE" | "0" => Ok("N".to_string()),
+                _ => Err(invalid("Y or N")),
+            },
+        }
+    }
+
+    /// A stored value as written to ADIF
+    pub fn to_adif(&self, value: &str) -> String {
+        match self.field_type {
+            FieldType::Date => value.replace('-', ""),
+            _ => value.to_string(),
+        }
+    }
+
+    /// An ADIF value in the stored form, or as it is if it does not fit
+    pub fn from_adif(&self, value: &str) -> String {
+        self.normalize(value).unwrap_or_else(|_| value.to_string())
+    }
+}
+
+/// Normalize the values of defined fields in an entry
+pub fn normalize_entry(definitions: &[FieldDefinition], entry: &mut LogEntry) -> Result<(), StorageError> {
+    for definition in definitions {
+        if let Some(value) = entry.custom_fields.get_mut(&definition.name) {
+            *value = definition.normalize(value)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    /// The value contains the text, ignoring ASCII case
+    Contains,
+}
+
+impl Comparison {
+    fn holds(&self, field_type: FieldType, value: &str, operand: &str) -> bool {
+        let ordering = field_type.compare(value, operand);
+        match self {
+            Comparison::Equal => ordering == Ordering::Equal,
+            Comparison::NotEqual => ordering != Ordering::Equal,
+            Comparison::Less => ordering == Ordering::Less,
+            Comparison::LessOrEqual => ordering != Ordering::Greater,
+            Comparison::Greater => ordering == Ordering::Greater,
+            Comparison::GreaterOrEqual => ordering != Ordering::Less,
+            Comparison::Contains => value.to_ascii_lowercase().contains(&operand.to_ascii_lowercase()),
+        }
+    }
+}
+
+/// A condition on one custom field; only entries that have the field match
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldFilter {
+    pub field: String,
+    pub comparison: Comparison,
+    pub value: String,
+}
+
+/// Entries selected and ordered by custom field values. Without a sort
+/// field, entries come in QSO time order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FieldQuery {
+    pub filters: Vec<FieldFilter>,
+    pub sort: Option<String>,
+    pub descending: bool,
+}
+
+impl FieldQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only entries whose `field` compares with `value` as asked
+    pub fn filter(mut self, field: &str, comparison: Comparison, value: &str) -> Self {
+        self.filters.push(FieldFilter {
+            field: field.trim().to_uppercase(),
+            comparison,
+            value: value.to_string(),
+        });
+        self
+    }
+
+    /// Order by `field`; entries without it come last either way
+    pub fn sort_by(mut self, field: &str, descending: bool) -> Self {
+        self.sort = Some(field.trim().to_uppercase());
+        self.descending = descending;
+        self
+    }
+
+    /// The filters with their values in the stored form of their fields,
+    /// and the type each field compares as
+    pub fn resolve(&self, definitions: &[FieldDefinition]) -> Result<Vec<(FieldType, FieldFilter)>, StorageError> {
+        self.filters.iter()
+            .map(|filter| {
+                let Some(definition) = find(definitions, &filter.field) else {
+                    return Ok((FieldType::Text, filter.clone()));
+                };
+                let value = match filter.comparison {
+                    Comparison::Contains => filter.value.clone(),
+                    _ => definition.normalize(&filter.value)?,
+                };
+                Ok((definition.field_type, FieldFilter { value, ..filter.clone() }))
+            })
+            .collect()
+    }
+
+    /// Run the query over entries in memory
+    pub fn apply(&self, definitions: &[FieldDefinition], entries: Vec<LogEntry>) -> Result<Vec<LogEntry>, StorageError> {
+        let filters = self.resolve(definitions)?;
+        let mut entries: Vec<LogEntry> = entries.into_iter()
+            .filter(|entry| {
+                filters.iter().all(|(field_type, filter)| {
+                    entry.custom_fields.get(&filter.field)
+                        .is_some_and(|value| filter.comparison.holds(*field_type, value, &filter.value))
+                })
+            })
+            .collect();
+
+        let sort_type = self.sort.as_deref().map_or(FieldType::Text, |name| type_of(definitions, name));
+        entries.sort_by(|a, b| {
+            let by_field = match &self.sort {
+                Some(name) => match (a.custom_fields.get(name), b.custom_fields.get(name)) {
+                    (Some(a), Some(b)) if self.descending => sort_type.compare(b, a),
+                    (Some(a), Some(b)) => sort_type.compare(a, b),
+                    (Some(_), None) => Ordering::Less,
+                    (None, Some(_)) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                },
+                None => Ordering::Equal,
+            };
+            by_field.then_with(|| a.timestamp.cmp(&b.timestamp)).then_with(|| a.id.cmp(&b.id))
+        });
+        Ok(entries)
+    }
+}
+
+pub fn find<'a>(definitions: &'a [FieldDefinition], name: &str) -> Option<&'a FieldDefinition> {
+    definitions.iter().find(|definition| definition.name == name)
+}
+
+/// How values of a field compare; fields without a definition are text
+pub fn type_of(definitions: &[FieldDefinition], name: &str) -> FieldType {
+    find(definitions, name).map_or(FieldType::Text, |definition| definition.field_type)
+}
+
+/// Field definitions kept as JSON next to a file-based log
+pub struct FieldFile {
+    path: PathBuf,
+    definitions: Vec<FieldDefinition>,
+}
+
+impl FieldFile {
+    /// Definitions path for a log file, e.g. `logbook.adi.fields.json`
+    pub fn path_for(log_path: &Path) -> PathBuf {
+        let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".fields.json");
+        log_path.with_file_name(name)
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self, StorageError> {
+        let definitions = if path.exists() {
+            serde_json::from_str(&fs::read_to_string(&path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path, definitions })
+    }
+
+    /// Definitions in the order they were first registered
+    pub fn definitions(&self) -> &[FieldDefinition] {
+        &self.definitions
+    }
+
+    /// Register a field, replacing a definition of the same name in place
+    pub fn define(&mut self, definition: FieldDefinition) -> Result<(), StorageError> {
+        match self.definitions.iter_mut().find(|d| d.name == definition.name) {
+            Some(existing) => *existing = definition,
+            None => self.definitions.push(definition),
+        }
+        self.write()
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), StorageError> {
+        let pos = self.definitions.iter()
+            .position(|d| d.name == name)
+            .ok_or_else(|| StorageError::NotFound(format!("field {}", name)))?;
+        self.definitions.remove(pos);
+        self.write()
+    }
+
+    fn write(&self) -> Result<(), StorageError> {
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, serde_json::to_string_pretty(&self.definitions)?)?;
+        fs::rename(&temp_path, &self.path)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_field_values() {
+        let size = FieldDefinition::new("size", FieldType::Enum, vec!["S".into(), " M ".into(), "XL".into()]).unwrap();
+        assert_eq!(size.name, "SIZE");
+        assert_eq!(size.normalize("m").unwrap(), "M");
+        assert!(matches!(size.normalize("XXL"), Err(StorageError::Validation(_))));
+
+        let date = FieldDefinition::new("SENT", FieldType::Date, vec!["ignored".into()]).unwrap();
+        assert!(date.options.is_empty());
+        assert_eq!(date.normalize("20241018").unwrap(), "2024-10-18");
+        assert_eq!(date.to_adif("2024-10-18"), "20241018");
+        assert_eq!(date.from_adif("2024-02-30"), "2024-02-30");
+
+        let flag = FieldDefinition::new("PAPER", FieldType::Boolean, Vec::new()).unwrap();
+        assert_eq!(flag.normalize("yes").unwrap(), "Y");
+        let watts = FieldDefinition::new("WATTS", FieldType::Number, Vec::new()).unwrap();
+        assert!(watts.normalize("5W").is_err());
+        assert_eq!(FieldType::Number.compare("100", "20.5"), Ordering::Greater);
+        assert_eq!(FieldType::Text.compare("abc", "ABC"), Ordering::Equal);
+
+        assert!(FieldDefinition::new("APP_X", FieldType::Text, Vec::new()).is_err());
+        assert!(FieldDefinition::new("name", FieldType::Text, Vec::new()).is_err());
+        assert!(FieldDefinition::new("FREQ", FieldType::Number, Vec::new()).is_err());
+        assert!(FieldDefinition::new("USERDEF1", FieldType::Text, Vec::new()).is_err());
+        assert!(FieldDefinition::new("MY FIELD", FieldType::Text, Vec::new()).is_err());
+        assert!(FieldDefinition::new("LEVEL", FieldType::Enum, Vec::new()).is_err());
+        assert!(FieldDefinition::new("LEVEL", FieldType::Enum, vec!["{A,B}".into()]).is_err());
+    }
+}
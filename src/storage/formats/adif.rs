@@ -1,13 +1,22 @@
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::PathBuf;
-use std::fs;
 use async_trait::async_trait;
-use chrono::{DateTime, Utc, TimeZone};
+use chrono::{DateTime, NaiveDate, Utc, TimeZone};
 use regex::Regex;
 use lazy_static::lazy_static;
 use thiserror::Error;
 
 use crate::LogEntry;
-use crate::storage::{Storage, StorageError, StorageFormat};
+use crate::app::{Band, Frequency, Power, QslConfirmation, QslStatus, QslVia, Rst};
+use crate::storage::fields::{self, FieldDefinition, FieldFile, FieldType};
+use crate::storage::fsck::{Scan, Unreadable};
+use crate::storage::journal::{self, JournalFile};
+use crate::storage::lock::{FileStamp, LogLock};
+use crate::storage::revision::RevisionFile;
+use crate::storage::{ExternalChanges, JournalRecord, Revision, Storage, StorageError, StorageFormat};
 
 #[derive(Error, Debug)]
 pub enum AdifError {
@@ -26,43 +35,315 @@ pub enum AdifError {
 
 lazy_static! {
     static ref ADIF_FIELD_PATTERN: Regex = Regex::new(
-        r"<(\w+):(\d+)(?::[A-Za-z])?>(.*?)"
+        r"<(\w+)(?::(\d+)(?::([A-Za-z]))?)?>"
     ).unwrap();
 }
 
+/// Header written by `entries_to_adif`
+const EXPORT_HEADER: &str = "Generated by Amateur Radio Logbook\n<ADIF_VER:5>3.1.4\n<EOH>\n\n";
+
+/// Written after each `<EOR>`
+const RECORD_SEPARATOR: &str = "\n\n";
+
+/// Blanked bytes tolerated before the file is rewritten; past this the
+/// file is compacted once half of it is blank
+const COMPACT_MIN_BYTES: u64 = 64 * 1024;
+
+/// Contest exchange fields without a `LogEntry` field; they live in
+/// `custom_fields` under their ADIF names
+pub const CONTEST_EXCHANGE_FIELDS: &[&str] = &[
+    "SRX_STRING", "STX_STRING", "CLASS", "ARRL_SECT", "MY_ARRL_SECT", "CHECK", "PRECEDENCE",
+];
+
+/// Other custom fields without a USERDEF are written as this prefix and
+/// their name
+const CUSTOM_FIELD_PREFIX: &str = "APP_AMLOG_FIELD_";
+
+/// Where a record sits in the file, from its first field to the end of `<EOR>`
+#[derive(Debug, Clone, Copy)]
+struct RecordSpan {
+    start: u64,
+    len: u64,
+}
+
+/// ADIF log written incrementally: new records are appended, edits that fit
+/// overwrite the record in place, and records that are deleted or outgrown
+/// are blanked with spaces. ADIF readers skip text between records, so the
+/// file stays valid throughout; it is rewritten once enough of it is blank.
+///
+/// Other loggers such as WSJT-X may write the same file. Before every write
+/// the file is checked for changes, which are merged into the cache; a write
+/// to an entry someone else changed in the meantime fails with `Conflict`.
 pub struct AdifStorage {
     path: PathBuf,
     cached_entries: Vec<LogEntry>,
+    /// Byte ranges of the records in the file, by entry id
+    index: HashMap<String, RecordSpan>,
+    file_len: u64,
+    /// Bytes of blanked records and padding
+    dead_bytes: u64,
+    stamp: FileStamp,
+    /// External changes merged but not yet reported by `refresh`
+    pending: ExternalChanges,
+    /// The file's header up to and including `<EOH>`, kept as written
+    header: Option<String>,
+    /// Custom fields the header declares as USERDEFs
+    userdefs: Vec<FieldDefinition>,
+    /// Text of the records `load` could not read, kept as written
+    unreadable: Vec<String>,
+    lock: LogLock,
+    journal: JournalFile,
+    revisions: RevisionFile,
+    fields: FieldFile,
 }
 
 impl AdifStorage {
     pub fn new(path: &PathBuf) -> Result<Self, StorageError> {
-        let cached_entries = if path.exists() {
-            let content = fs::read_to_string(path).map_err(|e| {
-                StorageError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to read ADIF file: {}", e)))
-            })?;
-            Self::adif_to_entries(&content)?
-        } else {
+        if !path.exists() {
             // Create directory if it doesn't exist
             if let Some(parent) = path.parent() {
                 fs::create_dir_all(parent).map_err(|e| {
-                    StorageError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to create directory: {}", e)))
+                    StorageError::Io(std::io::Error::other(format!("Failed to create directory: {}", e)))
                 })?;
             }
             
             // Create empty ADIF file with header
             let empty_adif = Self::create_empty_adif();
             fs::write(path, &empty_adif).map_err(|e| {
-                StorageError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to create empty ADIF file: {}", e)))
+                StorageError::Io(std::io::Error::other(format!("Failed to create empty ADIF file: {}", e)))
             })?;
-            
-            Vec::new()
-        };
+        }
 
-        Ok(Self {
+        let lock = LogLock::open(path)?;
+        let guard = lock.blocking_shared()?;
+        let content = fs::read_to_string(path).map_err(|e| {
+            StorageError::Io(std::io::Error::other(format!("Failed to read ADIF file: {}", e)))
+        })?;
+        let stamp = FileStamp::of(path)?;
+        drop(guard);
+
+        let mut storage = Self {
             path: path.clone(),
-            cached_entries,
-        })
+            cached_entries: Vec::new(),
+            index: HashMap::new(),
+            file_len: 0,
+            dead_bytes: 0,
+            stamp,
+            pending: ExternalChanges::default(),
+            header: None,
+            userdefs: Vec::new(),
+            unreadable: Vec::new(),
+            lock,
+            journal: JournalFile::open(JournalFile::path_for(path))?,
+            revisions: RevisionFile::open(RevisionFile::path_for(path))?,
+            fields: FieldFile::open(FieldFile::path_for(path))?,
+        };
+        storage.load(&content);
+        Ok(storage)
+    }
+
+    /// Merge changes other programs made to the file since amlog last read
+    /// or wrote it. Call with the lock held.
+    fn merge_external(&mut self) -> Result<ExternalChanges, StorageError> {
+        let stamp = FileStamp::of(&self.path)?;
+        if stamp == self.stamp {
+            return Ok(ExternalChanges::default());
+        }
+        let content = fs::read_to_string(&self.path)?;
+        let previous: HashMap<String, LogEntry> = std::mem::take(&mut self.cached_entries)
+            .into_iter()
+            .map(|entry| (entry.id.clone(), entry))
+            .collect();
+        self.load(&content);
+        self.stamp = stamp;
+
+        let mut changes = ExternalChanges::default();
+        for entry in &self.cached_entries {
+            match previous.get(&entry.id) {
+                None => changes.added.push(entry.id.clone()),
+                // Compare as written, since fields such as BAND are filled in on write
+                Some(before) if Self::entry_to_adif(before) != Self::entry_to_adif(entry) => {
+                    changes.modified.push(entry.id.clone());
+                }
+                Some(_) => {}
+            }
+        }
+        changes.removed = previous.into_keys().filter(|id| !self.index.contains_key(id)).collect();
+        changes.removed.sort();
+
+        self.pending.added.extend(changes.added.iter().cloned());
+        self.pending.modified.extend(changes.modified.iter().cloned());
+        self.pending.removed.extend(changes.removed.iter().cloned());
+        Ok(changes)
+    }
+
+    /// Merge external changes before writing `id`, failing if someone else
+    /// changed that entry. Call with the lock held.
+    fn check_external(&mut self, id: &str) -> Result<(), StorageError> {
+        if self.merge_external()?.touches(id) {
+            return Err(StorageError::Conflict(id.to_string()));
+        }
+        Ok(())
+    }
+
+    /// Compact if due and remember the file as amlog left it
+    fn finish_write(&mut self) -> Result<(), StorageError> {
+        if self.dead_bytes > COMPACT_MIN_BYTES && self.dead_bytes * 2 > self.file_len {
+            self.save_to_file()?;
+        }
+        self.stamp = FileStamp::of(&self.path)?;
+        Ok(())
+    }
+
+    /// Rebuild the entries and record index from the file content. When an
+    /// id appears twice, e.g. after a crash while moving a record, the later
+    /// record wins.
+    fn load(&mut self, content: &str) {
+        self.cached_entries.clear();
+        self.index.clear();
+        self.unreadable.clear();
+        self.file_len = content.len() as u64;
+        self.dead_bytes = 0;
+        self.header = Self::header_end(content).map(|end| content[..end].to_string());
+        self.userdefs = Self::userdefs(content);
+
+        for (range, fields) in Self::spanned_records(content) {
+            // Records without a call, date or mode are left alone in the file
+            let Some(mut entry) = Self::build_entry_from_fields(&fields, &self.userdefs) else {
+                self.unreadable.push(content[range].to_string());
+                continue;
+            };
+            if !fields.contains_key("APP_AMLOG_ID") {
+                // Two identical QSOs from another program need distinct ids
+                let base = entry.id.clone();
+                let mut n = 1;
+                while self.index.contains_key(&entry.id) {
+                    n += 1;
+                    entry.id = format!("{}#{}", base, n);
+                }
+            }
+            let span = RecordSpan { start: range.start as u64, len: range.len() as u64 };
+            match self.index.insert(entry.id.clone(), span) {
+                Some(earlier) => {
+                    self.dead_bytes += earlier.len;
+                    if let Some(pos) = self.cached_entries.iter().position(|e| e.id == entry.id) {
+                        self.cached_entries[pos] = entry;
+                    }
+                }
+                None => self.cached_entries.push(entry),
+            }
+        }
+    }
+
+    /// Whether a field holds a number that is not a usable frequency
+    fn bad_frequency(fields: &HashMap<String, String>, name: &str) -> bool {
+        fields.get(name)
+            .and_then(|f| f.trim().parse::<f64>().ok())
+            .is_some_and(|mhz| Frequency::from_mhz(mhz).is_err())
+    }
+
+    /// Whether TX_PWR holds a number that is not a usable power
+    fn bad_power(fields: &HashMap<String, String>) -> bool {
+        fields.get("TX_PWR")
+            .and_then(|p| p.trim().parse::<f64>().ok())
+            .is_some_and(|watts| Power::from_watts(watts).is_err())
+    }
+
+    /// Every record in the file as written, keeping records that `load`
+    /// passes over as unreadable
+    fn scan_content(content: &str) -> Scan {
+        let mut scan = Scan::default();
+        let mut natural_ids = HashSet::new();
+        let userdefs = Self::userdefs(content);
+        for (range, fields) in Self::spanned_records(content) {
+            let Some(mut entry) = Self::build_entry_from_fields(&fields, &userdefs) else {
+                let missing: Vec<&str> = ["CALL", "QSO_DATE", "MODE"].into_iter()
+                    .filter(|name| !fields.contains_key(*name))
+                    .collect();
+                scan.unreadable.push(Unreadable {
+                    location: format!("line {}", content[..range.start].lines().count().max(1)),
+                    error: if !missing.is_empty() {
+                        format!("missing {}", missing.join(", "))
+                    } else if ["FREQ", "FREQ_RX"].iter().any(|name| Self::bad_frequency(&fields, name)) {
+                        "invalid FREQ or FREQ_RX".to_string()
+                    } else if Self::bad_power(&fields) {
+                        "invalid TX_PWR".to_string()
+                    } else {
+                        "invalid QSO_DATE or TIME_ON".to_string()
+                    },
+                    raw: content[range].to_string(),
+                });
+                continue;
+            };
+            if !fields.contains_key("APP_AMLOG_ID") {
+                let base = entry.id.clone();
+                let mut n = 1;
+                while !natural_ids.insert(entry.id.clone()) {
+                    n += 1;
+                    entry.id = format!("{}#{}", base, n);
+                }
+            }
+            scan.entries.push(entry);
+        }
+        scan
+    }
+
+    /// Write an entry's record: over the old one when it fits, otherwise at
+    /// the end of the file with the old one blanked afterwards, so the
+    /// record is never missing from the file
+    fn write_record(&mut self, entry: &LogEntry) -> Result<(), StorageError> {
+        let record = Self::record(entry, &self.userdefs);
+        match self.index.get(&entry.id).copied() {
+            Some(span) if record.len() as u64 <= span.len => {
+                let padding = span.len - record.len() as u64;
+                let mut bytes = record.into_bytes();
+                bytes.resize(span.len as usize, b' ');
+                self.write_at(span.start, &bytes)?;
+                self.index.insert(entry.id.clone(), RecordSpan { start: span.start, len: span.len - padding });
+                self.dead_bytes += padding;
+            }
+            old_span => {
+                let start = self.append(&record)?;
+                self.index.insert(entry.id.clone(), RecordSpan { start, len: record.len() as u64 });
+                if let Some(span) = old_span {
+                    self.blank(span)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Append a record and its separator, returning where the record starts
+    fn append(&mut self, record: &str) -> Result<u64, StorageError> {
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        file.write_all(record.as_bytes())?;
+        file.write_all(RECORD_SEPARATOR.as_bytes())?;
+        file.sync_data()?;
+        let start = self.file_len;
+        self.file_len += (record.len() + RECORD_SEPARATOR.len()) as u64;
+        Ok(start)
+    }
+
+    /// Overwrite a record with spaces, keeping its line breaks
+    fn blank(&mut self, span: RecordSpan) -> Result<(), StorageError> {
+        let mut file = OpenOptions::new().read(true).open(&self.path)?;
+        file.seek(SeekFrom::Start(span.start))?;
+        let mut bytes = vec![0; span.len as usize];
+        file.read_exact(&mut bytes)?;
+        for byte in bytes.iter_mut().filter(|b| **b != b'\n') {
+            *byte = b' ';
+        }
+        self.write_at(span.start, &bytes)?;
+        self.dead_bytes += span.len;
+        Ok(())
+    }
+
+    fn write_at(&self, offset: u64, bytes: &[u8]) -> Result<(), StorageError> {
+        let mut file = OpenOptions::new().write(true).open(&self.path)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(bytes)?;
+        file.sync_data()?;
+        Ok(())
     }
 
     fn create_empty_adif() -> String {
@@ -82,91 +363,349 @@ impl AdifStorage {
     /// Convert ADIF string to LogEntries with improved error handling
     pub fn adif_to_entries(content: &str) -> Result<Vec<LogEntry>, StorageError> {
         let mut entries = Vec::new();
-        let mut current_fields = std::collections::HashMap::new();
+        let userdefs = Self::userdefs(content);
+        for fields in Self::adif_to_records(content) {
+            match Self::build_entry_from_fields(&fields, &userdefs) {
+                Some(entry) => entries.push(entry),
+                None => {
+                    // Log warning but continue processing other entries
+                    eprintln!("Warning: Skipped invalid ADIF record due to missing required fields");
+                }
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Split ADIF content into records of upper-cased field names to values
+    pub fn adif_to_records(content: &str) -> Vec<HashMap<String, String>> {
+        Self::spanned_records(content).into_iter().map(|(_, fields)| fields).collect()
+    }
+
+    /// Records with the bytes each occupies in `content`, from its first
+    /// field to the end of `<EOR>`
+    fn spanned_records(content: &str) -> Vec<(Range<usize>, HashMap<String, String>)> {
+        let mut records = Vec::new();
+        let mut current_fields = HashMap::new();
+        let mut record_start = None;
 
         // Skip header if present
-        let content = if let Some(pos) = content.to_uppercase().find("<EOH>") {
-            &content[pos + 5..]
-        } else {
-            content
-        };
+        let body_start = Self::header_end(content).unwrap_or(0);
+        let body = &content[body_start..];
 
-        for cap in ADIF_FIELD_PATTERN.captures_iter(content) {
+        for cap in ADIF_FIELD_PATTERN.captures_iter(body) {
+            let Some(tag) = cap.get(0) else { continue };
+            let start = *record_start.get_or_insert(tag.start());
             let field_name = cap[1].to_uppercase();
-            let field_value = cap[3].to_string();
+            let field_value = match cap.get(2) {
+                Some(len) => {
+                    let len: usize = len.as_str().parse().unwrap_or(0);
+                    Self::field_value(body, tag.end(), len)
+                }
+                None => String::new(),
+            };
 
             if field_name == "EOR" {
-                match Self::build_entry_from_fields(&current_fields) {
-                    Some(entry) => entries.push(entry),
-                    None => {
-                        // Log warning but continue processing other entries
-                        eprintln!("Warning: Skipped invalid ADIF record due to missing required fields");
-                    }
-                }
-                current_fields.clear();
+                records.push((body_start + start..body_start + tag.end(), std::mem::take(&mut current_fields)));
+                record_start = None;
             } else {
                 current_fields.insert(field_name, field_value);
             }
         }
 
-        Ok(entries)
+        records
+    }
+
+    /// Byte offset just past `<EOH>`, if the content has a header
+    fn header_end(content: &str) -> Option<usize> {
+        content.as_bytes()
+            .windows(5)
+            .position(|window| window.eq_ignore_ascii_case(b"<EOH>"))
+            .map(|pos| pos + 5)
+    }
+
+    /// Read the header field `name` (e.g. PROGRAMID), if the content has a header
+    pub fn header_field(content: &str, name: &str) -> Option<String> {
+        let header = &content[..Self::header_end(content)? - 5];
+        ADIF_FIELD_PATTERN.captures_iter(header)
+            .find(|cap| cap[1].eq_ignore_ascii_case(name))
+            .and_then(|cap| {
+                let start = cap.get(0)?.end();
+                let len: usize = cap.get(2)?.as_str().parse().ok()?;
+                Some(Self::field_value(header, start, len))
+            })
+    }
+
+    /// Custom fields declared in the header as `USERDEFn` fields. Enum
+    /// values come as `NAME,{A,B,C}`; ranges of numbers are not kept.
+    pub fn userdefs(content: &str) -> Vec<FieldDefinition> {
+        let Some(end) = Self::header_end(content) else {
+            return Vec::new();
+        };
+        let header = &content[..end - 5];
+        ADIF_FIELD_PATTERN.captures_iter(header)
+            .filter(|cap| cap[1].to_uppercase().starts_with("USERDEF"))
+            .filter_map(|cap| {
+                let start = cap.get(0)?.end();
+                let len: usize = cap.get(2)?.as_str().parse().ok()?;
+                let field_type = cap.get(3)
+                    .and_then(|t| t.as_str().chars().next())
+                    .map_or(FieldType::Text, FieldType::from_adif_indicator);
+                let value = Self::field_value(header, start, len);
+                let (name, options) = value.split_once(',').unwrap_or((&value, ""));
+                let options = match options.trim().strip_prefix('{').and_then(|o| o.strip_suffix('}')) {
+                    Some(options) if field_type == FieldType::Enum => options.split(',').map(str::to_string).collect(),
+                    _ => Vec::new(),
+                };
+                FieldDefinition::new(name, field_type, options).ok()
+            })
+            .collect()
+    }
+
+    /// Parse an ADIF QSO_DATE/TIME_ON pair
+    pub fn parse_qso_datetime(date: &str, time: &str) -> Option<DateTime<Utc>> {
+        Self::parse_datetime(date, time).ok()
     }
 
     /// Convert LogEntries to ADIF string
     pub fn entries_to_adif(entries: &[LogEntry]) -> String {
-        let mut adif = String::from("Generated by Amateur Radio Logbook\n<ADIF_VER:5>3.1.4\n<EOH>\n\n");
-        
-        for entry in entries {
-            // Required fields
-            adif.push_str(&format!(
-                "<CALL:{}>{}\n", 
-                entry.callsign.len(), 
-                entry.callsign
-            ));
-            
-            adif.push_str(&format!(
-                "<QSO_DATE:10>{}\n",
-                entry.timestamp.format("%Y-%m-%d")
-            ));
-            
-            adif.push_str(&format!(
-                "<TIME_ON:8>{}\n",
-                entry.timestamp.format("%H%M%S")
-            ));
-            
-            adif.push_str(&format!(
-                "<FREQ:{}>{:.3}\n",
-                format!("{:.3}", entry.frequency).len(),
-                entry.frequency
-            ));
-            
+        Self::export(entries, &[])
+    }
+
+    /// Convert LogEntries to ADIF, declaring the defined custom fields in
+    /// the header so other programs know their types
+    pub fn export(entries: &[LogEntry], definitions: &[FieldDefinition]) -> String {
+        let definitions: Vec<&FieldDefinition> = definitions.iter()
+            .filter(|definition| !CONTEST_EXCHANGE_FIELDS.contains(&definition.name.as_str()))
+            .collect();
+        let mut adif = EXPORT_HEADER.strip_suffix("<EOH>\n\n").unwrap_or(EXPORT_HEADER).to_string();
+        for (n, definition) in definitions.iter().enumerate() {
+            let value = match definition.field_type {
+                FieldType::Enum => format!("{},{{{}}}", definition.name, definition.options.join(",")),
+                _ => definition.name.clone(),
+            };
             adif.push_str(&format!(
-                "<MODE:{}>{}\n",
-                entry.mode.len(),
-                entry.mode
+                "<USERDEF{}:{}:{}>{}\n",
+                n + 1,
+                value.len(),
+                definition.field_type.adif_indicator(),
+                value,
             ));
+        }
+        adif.push_str("<EOH>\n\n");
 
-            // Optional fields
-            if let Some(ref rst) = entry.rst_sent {
-                adif.push_str(&format!("<RST_SENT:{}>{}\n", rst.len(), rst));
+        let definitions: Vec<FieldDefinition> = definitions.into_iter().cloned().collect();
+        for entry in entries {
+            adif.push_str(&Self::record(entry, &definitions));
+            adif.push_str(RECORD_SEPARATOR);
+        }
+        adif
+    }
+
+    /// One ADIF record, from its first field to `<EOR>`
+    pub fn entry_to_adif(entry: &LogEntry) -> String {
+        Self::record(entry, &[])
+    }
+
+    /// One ADIF record, writing custom fields with a definition as the
+    /// USERDEF fields of that name
+    fn record(entry: &LogEntry, userdefs: &[FieldDefinition]) -> String {
+        let mut adif = String::new();
+        // Required fields
+        Self::push_field(&mut adif, "CALL", &entry.callsign);
+        Self::push_field(&mut adif, "QSO_DATE", &entry.timestamp.format("%Y%m%d").to_string());
+        Self::push_field(&mut adif, "TIME_ON", &entry.timestamp.format("%H%M%S").to_string());
+        Self::push_field(&mut adif, "FREQ", &entry.frequency.to_string());
+        Self::push_field(&mut adif, "MODE", &entry.mode);
+
+        // Optional fields
+        if let Some(ref rst) = entry.rst_sent {
+            Self::push_field(&mut adif, "RST_SENT", &rst.to_string());
+        }
+
+        if let Some(ref rst) = entry.rst_received {
+            Self::push_field(&mut adif, "RST_RCVD", &rst.to_string());
+        }
+
+        if let Some(ref notes) = entry.notes {
+            Self::push_field(&mut adif, "COMMENT", notes);
+        }
+
+        if let Some(time_off) = entry.time_off {
+            Self::push_field(&mut adif, "QSO_DATE_OFF", &time_off.format("%Y%m%d").to_string());
+            Self::push_field(&mut adif, "TIME_OFF", &time_off.format("%H%M%S").to_string());
+        }
+
+        // LoTW needs both bands to credit satellite and split QSOs
+        let band = entry.band_name().map(String::from);
+        let band_rx = entry.band_rx_name().map(String::from);
+        let text_fields = [
+            ("SUBMODE", &entry.submode),
+            ("BAND", &band),
+            ("BAND_RX", &band_rx),
+            ("NAME", &entry.name),
+            ("QTH", &entry.qth),
+            ("STATE", &entry.state),
+            ("CNTY", &entry.county),
+            ("COUNTRY", &entry.country),
+            ("CONT", &entry.cont),
+            ("GRIDSQUARE", &entry.grid),
+            ("OPERATOR", &entry.operator),
+            ("PROP_MODE", &entry.prop_mode),
+            ("SAT_NAME", &entry.sat_name),
+            ("SAT_MODE", &entry.sat_mode),
+            ("IOTA", &entry.iota),
+            ("SOTA_REF", &entry.sota_ref),
+            ("POTA_REF", &entry.pota_ref),
+            ("SIG", &entry.sig),
+            ("SIG_INFO", &entry.sig_info),
+            ("MY_SIG", &entry.my_sig),
+            ("MY_SIG_INFO", &entry.my_sig_info),
+            ("MY_SOTA_REF", &entry.my_sota_ref),
+            ("CONTEST_ID", &entry.contest_id),
+        ];
+        for (name, value) in text_fields {
+            if let Some(value) = value {
+                Self::push_field(&mut adif, name, value);
             }
+        }
+
+        if let Some(freq_rx) = entry.freq_rx {
+            Self::push_field(&mut adif, "FREQ_RX", &freq_rx.to_string());
+        }
+
+        if let Some(power) = entry.power {
+            Self::push_field(&mut adif, "TX_PWR", &power.to_adif());
+        }
 
-            if let Some(ref rst) = entry.rst_received {
-                adif.push_str(&format!("<RST_RCVD:{}>{}\n", rst.len(), rst));
+        let number_fields = [
+            ("DXCC", entry.dxcc),
+            ("CQZ", entry.cq_zone),
+            ("ITUZ", entry.itu_zone),
+            ("SRX", entry.srx),
+            ("STX", entry.stx),
+        ];
+        for (name, value) in number_fields {
+            if let Some(value) = value {
+                Self::push_field(&mut adif, name, &value.to_string());
             }
+        }
+
+        Self::push_confirmation(&mut adif, "QSL_SENT", "QSLSDATE", "QSL_RCVD", "QSLRDATE", &entry.qsl);
+        if let Some(via) = entry.qsl_sent_via {
+            Self::push_field(&mut adif, "QSL_SENT_VIA", via.as_adif());
+        }
+        if let Some(via) = entry.qsl_rcvd_via {
+            Self::push_field(&mut adif, "QSL_RCVD_VIA", via.as_adif());
+        }
+        Self::push_confirmation(&mut adif, "LOTW_QSL_SENT", "LOTW_QSLSDATE", "LOTW_QSL_RCVD", "LOTW_QSLRDATE", &entry.lotw);
+        Self::push_confirmation(&mut adif, "EQSL_QSL_SENT", "EQSL_QSLSDATE", "EQSL_QSL_RCVD", "EQSL_QSLRDATE", &entry.eqsl);
+
+        let mut exchange: Vec<(&String, &String)> = entry.custom_fields.iter()
+            .filter(|(name, _)| CONTEST_EXCHANGE_FIELDS.contains(&name.as_str()))
+            .collect();
+        exchange.sort();
+        for (name, value) in exchange {
+            Self::push_field(&mut adif, name, value);
+        }
 
-            if let Some(ref notes) = entry.notes {
-                adif.push_str(&format!("<COMMENT:{}>{}\n", notes.len(), notes));
+        // Only names that can stand as ADIF field names are written
+        let mut custom: Vec<(&String, &String)> = entry.custom_fields.iter()
+            .filter(|(name, _)| !CONTEST_EXCHANGE_FIELDS.contains(&name.as_str()))
+            .filter(|(name, _)| name.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_'))
+            .collect();
+        custom.sort();
+        for (name, value) in custom {
+            match fields::find(userdefs, name) {
+                Some(definition) => {
+                    let value = definition.to_adif(value);
+                    adif.push_str(&format!(
+                        "<{}:{}:{}>{}\n",
+                        name,
+                        value.len(),
+                        definition.field_type.adif_indicator(),
+                        value,
+                    ));
+                }
+                None => Self::push_field(&mut adif, &format!("{}{}", CUSTOM_FIELD_PREFIX, name), value),
             }
+        }
 
-            // End of record
-            adif.push_str("<EOR>\n\n");
+        // Keep our own id so entries keep their identity across reloads
+        Self::push_field(&mut adif, "APP_AMLOG_ID", &entry.id);
+        if let Some(station) = &entry.station {
+            Self::push_field(&mut adif, "APP_AMLOG_STATION", station);
+        }
+        if let Some(created_at) = entry.created_at {
+            Self::push_field(&mut adif, "APP_AMLOG_CREATED", &created_at.to_rfc3339());
+        }
+        if let Some(updated_at) = entry.updated_at {
+            Self::push_field(&mut adif, "APP_AMLOG_UPDATED", &updated_at.to_rfc3339());
         }
 
+        // End of record
+        adif.push_str("<EOR>");
         adif
     }
 
-    fn build_entry_from_fields(fields: &std::collections::HashMap<String, String>) -> Option<LogEntry> {
+    /// Append a single `<NAME:len>value` field on its own line
+    fn push_field(adif: &mut String, name: &str, value: &str) {
+        adif.push_str(&format!("<{}:{}>{}\n", name, value.len(), value));
+    }
+
+    fn push_confirmation(
+        adif: &mut String,
+        sent: &str,
+        sent_date: &str,
+        rcvd: &str,
+        rcvd_date: &str,
+        confirmation: &QslConfirmation,
+    ) {
+        if let Some(status) = confirmation.sent {
+            Self::push_field(adif, sent, status.as_adif());
+        }
+        if let Some(date) = confirmation.sent_date {
+            Self::push_field(adif, sent_date, &date.format("%Y%m%d").to_string());
+        }
+        if let Some(status) = confirmation.received {
+            Self::push_field(adif, rcvd, status.as_adif());
+        }
+        if let Some(date) = confirmation.received_date {
+            Self::push_field(adif, rcvd_date, &date.format("%Y%m%d").to_string());
+        }
+    }
+
+    fn confirmation_from_fields(
+        fields: &HashMap<String, String>,
+        sent: &str,
+        sent_date: &str,
+        rcvd: &str,
+        rcvd_date: &str,
+    ) -> QslConfirmation {
+        let date = |name: &str| {
+            fields.get(name).and_then(|d| NaiveDate::parse_from_str(d, "%Y%m%d").ok())
+        };
+        QslConfirmation {
+            sent: fields.get(sent).and_then(|s| QslStatus::from_adif(s)),
+            sent_date: date(sent_date),
+            received: fields.get(rcvd).and_then(|s| QslStatus::from_adif(s)),
+            received_date: date(rcvd_date),
+        }
+    }
+
+    /// Read `len` bytes of field data starting at `start`, clamped to the
+    /// content and to a valid character boundary
+    fn field_value(content: &str, start: usize, len: usize) -> String {
+        let mut end = (start + len).min(content.len());
+        while !content.is_char_boundary(end) {
+            end -= 1;
+        }
+        content[start..end].to_string()
+    }
+
+    /// An entry from a record's fields. Fields declared in `userdefs` and
+    /// amlog's own custom fields become custom fields.
+    fn build_entry_from_fields(fields: &HashMap<String, String>, userdefs: &[FieldDefinition]) -> Option<LogEntry> {
         // Required fields
         let callsign = fields.get("CALL")?.clone();
         
@@ -176,68 +715,176 @@ impl AdifStorage {
         let time = fields.get("TIME_ON").unwrap_or(&binding);
         let timestamp = Self::parse_datetime(date, time).ok()?;
 
-        // Parse frequency
-        let frequency = fields.get("FREQ")
-            .and_then(|f| f.parse::<f64>().ok())
-            .unwrap_or(0.0);
+        // Parse frequency; a number that is not a frequency makes the
+        // record unreadable rather than being quietly replaced
+        let frequency = match fields.get("FREQ").and_then(|f| f.trim().parse::<f64>().ok()) {
+            Some(mhz) => Frequency::from_mhz(mhz).ok()?,
+            None => Frequency::default(),
+        };
 
         let mode = fields.get("MODE")?.clone();
 
+        let time_off = match fields.get("TIME_OFF") {
+            Some(time_off) => {
+                let date_off = fields.get("QSO_DATE_OFF").unwrap_or(date);
+                Self::parse_datetime(date_off, time_off).ok().map(|t| {
+                    // A QSO spanning midnight without QSO_DATE_OFF ends the next day
+                    if t < timestamp && !fields.contains_key("QSO_DATE_OFF") {
+                        t + chrono::Duration::days(1)
+                    } else {
+                        t
+                    }
+                })
+            }
+            None => None,
+        };
+
+        let text = |name: &str| fields.get(name).filter(|v| !v.trim().is_empty()).cloned();
+        let number = |name: &str| fields.get(name).and_then(|v| v.trim().parse::<u32>().ok());
+        let instant = |name: &str| {
+            text(name)
+                .and_then(|v| DateTime::parse_from_rfc3339(v.trim()).ok())
+                .map(|t| t.with_timezone(&Utc))
+        };
+        // Records written by other programs get an id derived from the QSO,
+        // so it stays the same each time the file is read
+        let id = text("APP_AMLOG_ID").unwrap_or_else(|| {
+            format!("{}-{}-{}-{}", callsign, timestamp.format("%Y%m%dT%H%M%S"), frequency.hz(), mode)
+        });
+
+        let mut custom_fields: HashMap<String, String> = CONTEST_EXCHANGE_FIELDS.iter()
+            .filter_map(|name| text(name).map(|value| (name.to_string(), value)))
+            .collect();
+        for definition in userdefs {
+            if let Some(value) = text(&definition.name) {
+                custom_fields.insert(definition.name.clone(), definition.from_adif(&value));
+            }
+        }
+        for (name, value) in fields {
+            if let Some(name) = name.strip_prefix(CUSTOM_FIELD_PREFIX).filter(|name| !name.is_empty()) {
+                custom_fields.insert(name.to_string(), value.clone());
+            }
+        }
+
         Some(LogEntry {
-            id: uuid::Uuid::new_v4().to_string(),
+            id,
             timestamp,
-            callsign,
+            callsign: callsign.into(),
             frequency,
-            mode,
-            rst_sent: fields.get("RST_SENT").cloned(),
-            rst_received: fields.get("RST_RCVD").cloned(),
+            mode: mode.into(),
+            rst_sent: fields.get("RST_SENT").map(|rst| Rst::from(rst.as_str())),
+            rst_received: fields.get("RST_RCVD").map(|rst| Rst::from(rst.as_str())),
             notes: fields.get("COMMENT").cloned(),
-            ..Default::default()
+            name: text("NAME"),
+            qth: text("QTH"),
+            state: text("STATE"),
+            country: text("COUNTRY"),
+            dxcc: number("DXCC"),
+            band: text("BAND").map(Band::from),
+            operator: text("OPERATOR"),
+            grid: text("GRIDSQUARE"),
+            power: match fields.get("TX_PWR").and_then(|p| p.trim().parse::<f64>().ok()) {
+                Some(watts) => Some(Power::from_watts(watts).ok()?),
+                None => None,
+            },
+            time_off,
+            submode: text("SUBMODE"),
+            freq_rx: match fields.get("FREQ_RX").and_then(|f| f.trim().parse::<f64>().ok()) {
+                Some(mhz) => Some(Frequency::from_mhz(mhz).ok()?),
+                None => None,
+            },
+            band_rx: text("BAND_RX").map(Band::from),
+            prop_mode: text("PROP_MODE"),
+            sat_name: text("SAT_NAME"),
+            sat_mode: text("SAT_MODE"),
+            cq_zone: number("CQZ"),
+            itu_zone: number("ITUZ"),
+            cont: text("CONT"),
+            county: text("CNTY"),
+            iota: text("IOTA"),
+            sota_ref: text("SOTA_REF"),
+            pota_ref: text("POTA_REF"),
+            sig: text("SIG"),
+            sig_info: text("SIG_INFO"),
+            my_sig: text("MY_SIG"),
+            my_sig_info: text("MY_SIG_INFO"),
+            my_sota_ref: text("MY_SOTA_REF"),
+            srx: number("SRX"),
+            stx: number("STX"),
+            contest_id: text("CONTEST_ID"),
+            station: text("APP_AMLOG_STATION"),
+            created_at: instant("APP_AMLOG_CREATED"),
+            updated_at: instant("APP_AMLOG_UPDATED"),
+            qsl: Self::confirmation_from_fields(fields, "QSL_SENT", "QSLSDATE", "QSL_RCVD", "QSLRDATE"),
+            qsl_sent_via: fields.get("QSL_SENT_VIA").and_then(|v| QslVia::from_adif(v)),
+            qsl_rcvd_via: fields.get("QSL_RCVD_VIA").and_then(|v| QslVia::from_adif(v)),
+            lotw: Self::confirmation_from_fields(fields, "LOTW_QSL_SENT", "LOTW_QSLSDATE", "LOTW_QSL_RCVD", "LOTW_QSLRDATE"),
+            eqsl: Self::confirmation_from_fields(fields, "EQSL_QSL_SENT", "EQSL_QSLSDATE", "EQSL_QSL_RCVD", "EQSL_QSLRDATE"),
+            custom_fields,
         })
     }
 
     fn parse_datetime(date: &str, time: &str) -> Result<DateTime<Utc>, StorageError> {
+        if date.len() < 8 || !date.is_ascii() {
+            return Err(StorageError::Adif("Invalid date format".to_string()));
+        }
+        let time = if time.len() >= 4 && time.is_ascii() { time } else { "000000" };
+
         let year: i32 = date[0..4].parse().map_err(|_| StorageError::Adif("Invalid date format".to_string()))?;
         let month: u32 = date[4..6].parse().map_err(|_| StorageError::Adif("Invalid date format".to_string()))?;
         let day: u32 = date[6..8].parse().map_err(|_| StorageError::Adif("Invalid date format".to_string()))?;
         
         let hour: u32 = time[0..2].parse().unwrap_or(0);
         let min: u32 = time[2..4].parse().unwrap_or(0);
-        let sec: u32 = time[4..6].parse().unwrap_or(0);
+        let sec: u32 = time.get(4..6).and_then(|s| s.parse().ok()).unwrap_or(0);
 
         Utc.with_ymd_and_hms(year, month, day, hour, min, sec)
             .single()
             .ok_or_else(|| StorageError::Adif("Invalid datetime".to_string()))
     }
 
-    fn save_to_file(&self) -> Result<(), StorageError> {
+    /// Rewrite the whole file without blank space. The header and the
+    /// records amlog cannot read are written back as they were.
+    fn save_to_file(&mut self) -> Result<(), StorageError> {
         // Write to temporary file first
         let temp_path = self.path.with_extension("adi.tmp");
-        let adif = Self::entries_to_adif(&self.cached_entries);
+        let mut adif = match &self.header {
+            Some(header) => format!("{}\n\n", header),
+            None => String::from(EXPORT_HEADER),
+        };
+        for record in &self.unreadable {
+            adif.push_str(record);
+            adif.push_str(RECORD_SEPARATOR);
+        }
+        let mut index = HashMap::new();
+        for entry in &self.cached_entries {
+            let record = Self::record(entry, &self.userdefs);
+            index.insert(entry.id.clone(), RecordSpan { start: adif.len() as u64, len: record.len() as u64 });
+            adif.push_str(&record);
+            adif.push_str(RECORD_SEPARATOR);
+        }
         
         fs::write(&temp_path, &adif).map_err(|e| {
-            StorageError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to write temporary file: {}", e)))
+            StorageError::Io(std::io::Error::other(format!("Failed to write temporary file: {}", e)))
         })?;
         
         // Rename temporary file to actual file
         fs::rename(&temp_path, &self.path).map_err(|e| {
-            StorageError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to save ADIF file: {}", e)))
+            StorageError::Io(std::io::Error::other(format!("Failed to save ADIF file: {}", e)))
         })?;
-        
+
+        self.index = index;
+        self.file_len = adif.len() as u64;
+        self.dead_bytes = 0;
+        self.stamp = FileStamp::of(&self.path)?;
         Ok(())
     }
 
-    pub fn backup(&self) -> Result<PathBuf, StorageError> {
-        let backup_path = self.path.with_extension(format!(
-            "adi.bak.{}", 
-            Utc::now().format("%Y%m%d_%H%M%S")
-        ));
-        
-        fs::copy(&self.path, &backup_path).map_err(|e| {
-            StorageError::Io(std::io::Error::new(std::io::ErrorKind::Other, format!("Failed to create backup: {}", e)))
-        })?;
-        
-        Ok(backup_path)
+    /// Rewrite the file now rather than waiting for blank space to pile up
+    pub async fn compact(&mut self) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
+        self.merge_external()?;
+        self.save_to_file()
     }
 }
 
@@ -245,17 +892,92 @@ impl AdifStorage {
 impl Storage for AdifStorage {
     
     async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
+        self.check_external(&entry.id)?;
+        self.write_record(&entry)?;
         if let Some(pos) = self.cached_entries.iter().position(|e| e.id == entry.id) {
             self.cached_entries[pos] = entry;
         } else {
             self.cached_entries.push(entry);
         }
-        self.save_to_file()?;
-        Ok(())
+        self.finish_write()
     }
 
     async fn add_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        Ok(())
+        let _guard = self.lock.exclusive().await?;
+        self.check_external(&entry.id)?;
+        if self.cached_entries.iter().any(|e| e.id == entry.id) {
+            return Err(StorageError::EntryExists);
+        }
+        self.write_record(&entry)?;
+        self.cached_entries.push(entry);
+        self.finish_write()
+    }
+
+    async fn refresh(&mut self) -> Result<ExternalChanges, StorageError> {
+        let _guard = self.lock.shared().await?;
+        self.merge_external()?;
+        Ok(std::mem::take(&mut self.pending))
+    }
+
+    async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError> {
+        Ok(self.journal.records().to_vec())
+    }
+
+    async fn save_batch(
+        &mut self,
+        entries: Vec<LogEntry>,
+        revisions: Vec<Revision>,
+        records: Vec<JournalRecord>,
+    ) -> Result<(), StorageError> {
+        self.revisions.append_all(revisions)?;
+        self.save_entries(entries).await?;
+        self.journal.append_all(records)
+    }
+
+    async fn append_journal(&mut self, record: JournalRecord) -> Result<(), StorageError> {
+        self.journal.append(record)
+    }
+
+    async fn mark_journal(&mut self, seq: u64, undone: bool) -> Result<(), StorageError> {
+        self.journal.mark(seq, undone)
+    }
+
+    async fn last_seq(&self) -> Result<u64, StorageError> {
+        Ok(self.journal.last_seq())
+    }
+
+    async fn journal_record(&self, seq: u64) -> Result<Option<JournalRecord>, StorageError> {
+        Ok(self.journal.record(seq).cloned())
+    }
+
+    fn damaged_history(&self) -> Vec<Unreadable> {
+        journal::damaged_history(&self.journal, &self.revisions)
+    }
+
+    async fn drop_damaged_history(&mut self) -> Result<(), StorageError> {
+        self.journal.drop_damaged()?;
+        self.revisions.drop_damaged()
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError> {
+        Ok(self.revisions.for_entry(id))
+    }
+
+    async fn add_revision(&mut self, revision: Revision) -> Result<(), StorageError> {
+        self.revisions.append(revision)
+    }
+
+    async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError> {
+        Ok(self.fields.definitions().to_vec())
+    }
+
+    async fn define_field(&mut self, definition: FieldDefinition) -> Result<(), StorageError> {
+        self.fields.define(definition)
+    }
+
+    async fn remove_field(&mut self, name: &str) -> Result<(), StorageError> {
+        self.fields.remove(name)
     }
 
     async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError> {
@@ -267,31 +989,57 @@ impl Storage for AdifStorage {
     }
 
     async fn update_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
+        self.check_external(&entry.id)?;
         if let Some(pos) = self.cached_entries.iter().position(|e| e.id == entry.id) {
+            self.write_record(&entry)?;
             self.cached_entries[pos] = entry;
-            self.save_to_file()?;
-            Ok(())
+            self.finish_write()
         } else {
-            Err(StorageError::Backend(format!("Entry with id {} not found", entry.id)))
+            Err(StorageError::NotFound(entry.id))
         }
     }
 
     async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
+        self.check_external(id)?;
         if let Some(pos) = self.cached_entries.iter().position(|e| e.id == id) {
+            if let Some(span) = self.index.remove(id) {
+                self.blank(span)?;
+            }
             self.cached_entries.remove(pos);
-            self.save_to_file()?;
-            Ok(())
+            self.finish_write()
         } else {
-            Err(StorageError::Backend(format!("Entry with id {} not found", id)))
+            Err(StorageError::NotFound(id.to_string()))
         }
     }
 
+    async fn scan(&self) -> Result<Scan, StorageError> {
+        let content = {
+            let _guard = self.lock.shared().await?;
+            fs::read_to_string(&self.path)?
+        };
+        Ok(Self::scan_content(&content))
+    }
+
     async fn clear(&mut self) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
         self.cached_entries.clear();
+        self.unreadable.clear();
         self.save_to_file()?;
         Ok(())
     }
 
+    async fn replace_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
+        let previous = std::mem::replace(&mut self.cached_entries, entries);
+        let unreadable = std::mem::take(&mut self.unreadable);
+        self.save_to_file().inspect_err(|_| {
+            self.cached_entries = previous;
+            self.unreadable = unreadable;
+        })
+    }
+
 
     fn format(&self) -> StorageFormat {
         StorageFormat::Adif
@@ -319,22 +1067,300 @@ mod tests {
         
         let entry = &entries[0];
         assert_eq!(entry.callsign, "W1AW");
-        assert_eq!(entry.frequency, 14.074);
+        assert_eq!(entry.frequency.mhz(), 14.074);
         assert_eq!(entry.mode, "FT8");
-        assert_eq!(entry.rst_sent.as_deref(), Some("599"));
+        assert_eq!(entry.rst_sent, Some("599".into()));
+    }
+
+    #[test]
+    fn test_adif_scan_reports_bad_frequency() {
+        let adif = "<EOH>\n<CALL:4>W1AW<QSO_DATE:8>20240327<TIME_ON:6>143000<FREQ:3>inf<MODE:3>FT8<EOR>\n";
+        let scan = AdifStorage::scan_content(adif);
+        assert!(scan.entries.is_empty());
+        assert_eq!(scan.unreadable.len(), 1);
+        assert_eq!(scan.unreadable[0].error, "invalid FREQ or FREQ_RX");
+
+        let adif = adif.replace("<FREQ:3>inf", "<TX_PWR:3>NaN");
+        let scan = AdifStorage::scan_content(&adif);
+        assert_eq!(scan.unreadable[0].error, "invalid TX_PWR");
     }
 
     #[tokio::test]
     async fn test_adif_export() {
-        let mut entry = LogEntry::default();
-        entry.callsign = "W1AW".to_string();
-        entry.frequency = 14.074;
-        entry.mode = "FT8".to_string();
-        entry.timestamp = Utc::now();
+        let entry = LogEntry {
+            callsign: "W1AW".into(),
+            frequency: Frequency::from_hz(14_074_000),
+            mode: "FT8".into(),
+            timestamp: Utc::now(),
+            ..Default::default()
+        };
         
         let adif = AdifStorage::entries_to_adif(&[entry]);
         assert!(adif.contains("<CALL:4>W1AW"));
         assert!(adif.contains("<MODE:3>FT8"));
         assert!(adif.contains("<EOR>"));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_adif_round_trip() {
+        let entry = LogEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            callsign: "W1AW".into(),
+            frequency: Frequency::from_hz(14_074_000),
+            mode: "FT8".into(),
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 27, 14, 30, 0).unwrap(),
+            notes: Some("Test entry".to_string()),
+            ..Default::default()
+        };
+
+        let entries = AdifStorage::adif_to_entries(&AdifStorage::entries_to_adif(std::slice::from_ref(&entry))).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].timestamp, entry.timestamp);
+        assert_eq!(entries[0].notes.as_deref(), Some("Test entry"));
+        assert_eq!(entries[0].id, entry.id);
+    }
+
+    #[test]
+    fn test_adif_custom_fields_round_trip() {
+        let definitions = vec![
+            FieldDefinition::new("RIG", FieldType::Enum, vec!["IC-705".into(), "KX2".into()]).unwrap(),
+            FieldDefinition::new("SENT", FieldType::Date, Vec::new()).unwrap(),
+        ];
+        let entry = LogEntry {
+            callsign: "W1AW".into(),
+            mode: "CW".into(),
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 27, 14, 30, 0).unwrap(),
+            custom_fields: [("RIG", "KX2"), ("SENT", "2024-04-01"), ("MY_NOTE", "ok"), ("CLASS", "1D"), ("bad name", "x")]
+                .into_iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+
+        let adif = AdifStorage::export(std::slice::from_ref(&entry), &definitions);
+        assert!(adif.contains("<USERDEF1:16:E>RIG,{IC-705,KX2}"));
+        assert!(adif.contains("<USERDEF2:4:D>SENT"));
+        assert!(adif.contains("<SENT:8:D>20240401"));
+        assert!(adif.contains("<APP_AMLOG_FIELD_MY_NOTE:2>ok"));
+        assert_eq!(AdifStorage::userdefs(&adif), definitions);
+
+        let parsed = &AdifStorage::adif_to_entries(&adif).unwrap()[0];
+        let mut expected = entry.custom_fields.clone();
+        expected.remove("bad name");
+        assert_eq!(parsed.custom_fields, expected);
+
+        // Another program's USERDEF values come in the field's stored form
+        let other = "<USERDEF1:6:B>PAPER\n<EOH>\n<CALL:4>K1AB<QSO_DATE:8>20240327<MODE:2>CW<PAPER:1>y<EOR>\n";
+        assert_eq!(AdifStorage::adif_to_entries(other).unwrap()[0].custom_fields.get("PAPER").map(String::as_str), Some("Y"));
+    }
+
+    #[test]
+    fn test_adif_satellite_qso_has_both_bands() {
+        let entry = LogEntry {
+            callsign: "K5XX".into(),
+            frequency: Frequency::from_hz(145_850_000),
+            freq_rx: Some(Frequency::from_hz(436_795_000)),
+            mode: "FM".into(),
+            prop_mode: Some("SAT".to_string()),
+            sat_name: Some("SO-50".to_string()),
+            ..Default::default()
+        };
+        let adif = AdifStorage::entries_to_adif(&[entry]);
+        assert!(adif.contains("<BAND:2>2m"));
+        assert!(adif.contains("<BAND_RX:4>70cm"));
+        assert!(adif.contains("<FREQ_RX:7>436.795"));
+        assert!(adif.contains("<PROP_MODE:3>SAT"));
+        assert!(adif.contains("<SAT_NAME:5>SO-50"));
+    }
+
+    #[tokio::test]
+    async fn test_adif_extended_fields() {
+        let entry = LogEntry {
+            callsign: "W1AW".into(),
+            frequency: Frequency::from_hz(14_074_000),
+            mode: "MFSK".into(),
+            submode: Some("FT4".to_string()),
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 27, 23, 58, 0).unwrap(),
+            time_off: Some(Utc.with_ymd_and_hms(2024, 3, 28, 0, 2, 0).unwrap()),
+            cq_zone: Some(5),
+            pota_ref: Some("K-0001".to_string()),
+            my_sig: Some("POTA".to_string()),
+            my_sig_info: Some("K-4567".to_string()),
+            my_sota_ref: Some("W7W/LC-001".to_string()),
+            stx: Some(12),
+            contest_id: Some("ARRL-FD".to_string()),
+            custom_fields: [("ARRL_SECT".to_string(), "CT".to_string())].into_iter().collect(),
+            qsl_sent_via: Some(QslVia::Bureau),
+            lotw: QslConfirmation {
+                sent: Some(QslStatus::Yes),
+                sent_date: NaiveDate::from_ymd_opt(2024, 3, 29),
+                received: Some(QslStatus::Yes),
+                received_date: NaiveDate::from_ymd_opt(2024, 4, 2),
+            },
+            ..Default::default()
+        };
+
+        let adif = AdifStorage::entries_to_adif(std::slice::from_ref(&entry));
+        assert!(adif.contains("<LOTW_QSLRDATE:8>20240402"));
+        assert!(adif.contains("<CQZ:1>5"));
+
+        let parsed = &AdifStorage::adif_to_entries(&adif).unwrap()[0];
+        assert_eq!(parsed.submode.as_deref(), Some("FT4"));
+        assert_eq!(parsed.time_off, entry.time_off);
+        assert_eq!(parsed.cq_zone, Some(5));
+        assert_eq!(parsed.pota_ref.as_deref(), Some("K-0001"));
+        assert_eq!(parsed.my_sig.as_deref(), Some("POTA"));
+        assert_eq!(parsed.my_sig_info.as_deref(), Some("K-4567"));
+        assert_eq!(parsed.my_sota_ref.as_deref(), Some("W7W/LC-001"));
+        assert_eq!(parsed.stx, Some(12));
+        assert_eq!(parsed.contest_id.as_deref(), Some("ARRL-FD"));
+        assert_eq!(parsed.custom_fields.get("ARRL_SECT").map(String::as_str), Some("CT"));
+        assert_eq!(parsed.qsl_sent_via, Some(QslVia::Bureau));
+        assert_eq!(parsed.lotw, entry.lotw);
+    }
+
+    #[tokio::test]
+    async fn test_adif_storage_persists_qsl_status() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.adi");
+
+        let mut storage = AdifStorage::new(&path)?;
+        let mut entry = LogEntry {
+            id: "qso-1".to_string(),
+            callsign: "DL1ABC".into(),
+            frequency: Frequency::from_hz(7_025_000),
+            mode: "CW".into(),
+            timestamp: Utc::now(),
+            ..Default::default()
+        };
+        storage.save_entry(entry.clone()).await?;
+
+        crate::app::qsl::mark_sent(&mut entry, NaiveDate::from_ymd_opt(2024, 5, 1).unwrap(), QslVia::Direct);
+        storage.update_entry(entry).await?;
+
+        let reopened = AdifStorage::new(&path)?;
+        let loaded = reopened.get_entry("qso-1").await?.unwrap();
+        assert!(loaded.qsl.is_sent());
+        assert_eq!(loaded.qsl.sent_date, NaiveDate::from_ymd_opt(2024, 5, 1));
+        assert_eq!(loaded.qsl_sent_via, Some(QslVia::Direct));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_adif_storage_writes_incrementally() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.adi");
+        let qso = |id: &str, call: &str| LogEntry {
+            id: id.to_string(),
+            callsign: call.into(),
+            frequency: Frequency::from_hz(14_074_000),
+            mode: "FT8".into(),
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 27, 14, 30, 0).unwrap(),
+            ..Default::default()
+        };
+
+        let mut storage = AdifStorage::new(&path)?;
+        storage.add_entry(qso("1", "W1AW")).await?;
+        storage.add_entry(qso("2", "K1ABC")).await?;
+        let header = fs::read_to_string(&path)?;
+        assert!(header.starts_with("Amateur Radio Logbook"), "appends keep the original header");
+
+        // A shorter record is written in place
+        let len = fs::metadata(&path)?.len();
+        storage.save_entry(qso("1", "N1A")).await?;
+        assert_eq!(fs::metadata(&path)?.len(), len);
+
+        // A longer one moves to the end and the old copy is blanked
+        let mut longer = qso("2", "K1ABC");
+        longer.notes = Some("Worked on a long path".to_string());
+        storage.save_entry(longer).await?;
+        storage.delete_entry("1").await?;
+        assert!(fs::metadata(&path)?.len() > len);
+
+        // Other programs see exactly the live records
+        let content = fs::read_to_string(&path)?;
+        let entries = AdifStorage::adif_to_entries(&content)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].notes.as_deref(), Some("Worked on a long path"));
+
+        // The index rebuilt on open supports further in-place edits
+        let mut storage = AdifStorage::new(&path)?;
+        storage.save_entry(qso("2", "K1AB")).await?;
+        storage.compact().await?;
+        let content = fs::read_to_string(&path)?;
+        assert!(!content.contains("   "));
+        let entries = AdifStorage::adif_to_entries(&content)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].callsign, "K1AB");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_adif_compaction_keeps_header_and_unreadable_records() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.adi");
+        let header = "Exported by another logger\n<ADIF_VER:5>3.1.4\n<USERDEF1:5:B>PAPER\n<EOH>";
+        let unreadable = "<CALL:4>K1AB<QSO_DATE:8>20240327<TIME_ON:4>1431<EOR>";
+        fs::write(&path, format!(
+            "{}\n{}\n<CALL:4>W1AW<QSO_DATE:8>20240327<TIME_ON:4>1430<MODE:2>CW<PAPER:1>Y<EOR>\n",
+            header, unreadable,
+        ))?;
+
+        let mut storage = AdifStorage::new(&path)?;
+        let mut entry = storage.list_entries().await?.remove(0);
+        entry.notes = Some("Worked on a long path".to_string());
+        storage.save_entry(entry).await?;
+        storage.compact().await?;
+
+        let content = fs::read_to_string(&path)?;
+        assert!(content.starts_with(header));
+        assert!(content.contains(unreadable));
+        assert!(content.contains("<PAPER:1:B>Y"));
+        assert!(!content.contains(CUSTOM_FIELD_PREFIX));
+        let entries = AdifStorage::adif_to_entries(&content)?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].custom_fields.get("PAPER").map(String::as_str), Some("Y"));
+        assert_eq!(AdifStorage::scan_content(&content).unreadable.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_adif_storage_merges_external_writes() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.adi");
+        let entry = LogEntry {
+            id: "1".to_string(),
+            callsign: "W1AW".into(),
+            frequency: Frequency::from_hz(14_074_000),
+            mode: "FT8".into(),
+            timestamp: Utc.with_ymd_and_hms(2024, 3, 27, 14, 30, 0).unwrap(),
+            ..Default::default()
+        };
+        let mut storage = AdifStorage::new(&path)?;
+        storage.add_entry(entry.clone()).await?;
+
+        // Another logger appends a QSO
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        file.write_all(b"<CALL:4>K1AB<QSO_DATE:8>20240327<TIME_ON:6>143100<FREQ:6>14.074<MODE:3>FT8<EOR>\n")?;
+        drop(file);
+        let changes = storage.refresh().await?;
+        assert_eq!(changes.added, vec!["K1AB-20240327T143100-14074000-FT8".to_string()]);
+        assert_eq!(storage.list_entries().await?.len(), 2);
+        assert!(storage.refresh().await?.is_empty());
+
+        // Writing our own QSO keeps theirs
+        storage.add_entry(LogEntry { id: "2".to_string(), ..entry.clone() }).await?;
+        let reopened = AdifStorage::new(&path)?;
+        assert_eq!(reopened.list_entries().await?.len(), 3);
+
+        // Both programs change the same QSO
+        let content = fs::read_to_string(&path)?.replacen("<CALL:4>W1AW", "<CALL:6>W1AW/P", 1);
+        fs::write(&path, content)?;
+        let mut ours = entry.clone();
+        ours.notes = Some("edited in amlog".to_string());
+        assert!(matches!(storage.save_entry(ours).await, Err(StorageError::Conflict(id)) if id == "1"));
+        assert_eq!(storage.get_entry("1").await?.unwrap().callsign, "W1AW/P");
+        assert_eq!(storage.refresh().await?.modified, vec!["1".to_string()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! Behaviour every `Storage` backend has to share, checked against each
+//! of them in turn
+
+use chrono::{TimeZone, Utc};
+use std::path::Path;
+
+use crate::LogEntry;
+use crate::storage::journal;
+use crate::storage::{
+    Comparison, FieldDefinition, FieldQuery, FieldType, JournalRecord, Revision, SearchHit, Storage, StorageError,
+    StorageFormat, StorageManager,
+};
+
+use super::fixtures::qso;
+
+/// The same QSO as far as the log is concerned
+fn assert_same(actual: Option<&LogEntry>, expected: &LogEntry) {
+    let actual = actual.unwrap_or_else(|| panic!("{} is missing", expected.id));
+    assert_eq!(journal::diff_entries(Some(actual), Some(expected)), vec![], "{}", expected.id);
+}
+
+async fn sorted(storage: &dyn Storage) -> Vec<LogEntry> {
+    let mut entries = storage.list_entries().await.unwrap();
+    entries.sort_by(|a, b| a.id.cmp(&b.id));
+    entries
+}
+
+async fn check_entries(storage: &mut dyn Storage) {
+    assert!(storage.list_entries().await.unwrap().is_empty());
+    assert!(storage.get_entry("1").await.unwrap().is_none());
+
+    storage.add_entry(qso("1", "W1AW", "FT8")).await.unwrap();
+    assert!(matches!(storage.add_entry(qso("1", "K1ABC", "FT8")).await, Err(StorageError::EntryExists)));
+    assert_same(storage.get_entry("1").await.unwrap().as_ref(), &qso("1", "W1AW", "FT8"));
+
+    storage.update_entry(qso("1", "W1AW", "FT4")).await.unwrap();
+    assert_same(storage.get_entry("1").await.unwrap().as_ref(), &qso("1", "W1AW", "FT4"));
+    assert!(matches!(storage.update_entry(qso("2", "K1ABC", "FT8")).await, Err(StorageError::NotFound(_))));
+    assert!(storage.get_entry("2").await.unwrap().is_none());
+
+    // Saving creates or replaces, one entry at a time or many
+    storage.save_entry(qso("2", "K1ABC", "FT8")).await.unwrap();
+    storage.save_entries(vec![qso("2", "K1ABC", "CW"), qso("3", "N0CALL", "FT8")]).await.unwrap();
+    let entries = sorted(storage).await;
+    assert_eq!(entries.len(), 3);
+    assert_same(entries.get(1), &qso("2", "K1ABC", "CW"));
+
+    storage.delete_entry("3").await.unwrap();
+    assert!(matches!(storage.delete_entry("3").await, Err(StorageError::NotFound(_))));
+    assert!(storage.get_entry("3").await.unwrap().is_none());
+    assert_eq!(sorted(storage).await.len(), 2);
+}
+
+async fn check_history(storage: &mut dyn Storage) {
+    let record = |seq, mode| JournalRecord::new(seq, None, Some(qso("1", "W1AW", mode)));
+    storage.append_journal(record(1, "FT8")).await.unwrap();
+    storage.append_journal(record(2, "FT4")).await.unwrap();
+    storage.append_journal(record(3, "CW")).await.unwrap();
+    storage.mark_journal(3, true).await.unwrap();
+    assert!(storage.journal().await.unwrap()[2].undone);
+    assert!(matches!(storage.mark_journal(9, true).await, Err(StorageError::NotFound(_))));
+
+    // A new record replaces those from its sequence number on
+    storage.append_journal(record(3, "SSB")).await.unwrap();
+    let journal = storage.journal().await.unwrap();
+    assert_eq!(journal.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+    assert!(!journal[2].undone);
+    assert_same(journal[2].after.as_ref(), &qso("1", "W1AW", "SSB"));
+
+    storage.add_revision(Revision::new(1, qso("1", "W1AW", "FT8"))).await.unwrap();
+    storage.add_revision(Revision::new(2, qso("1", "W1AW", "FT4"))).await.unwrap();
+    storage.add_revision(Revision::new(1, qso("2", "K1ABC", "FT8"))).await.unwrap();
+    let revisions = storage.revisions("1").await.unwrap();
+    assert_eq!(revisions.iter().map(|r| r.number).collect::<Vec<_>>(), vec![1, 2]);
+    assert_same(Some(&revisions[1].entry), &qso("1", "W1AW", "FT4"));
+    assert!(storage.revisions("3").await.unwrap().is_empty());
+}
+
+fn definition(name: &str, field_type: FieldType, options: &[&str]) -> FieldDefinition {
+    FieldDefinition::new(name, field_type, options.iter().map(|o| o.to_string()).collect()).unwrap()
+}
+
+async fn check_definitions(storage: &mut dyn Storage) {
+    assert!(storage.field_definitions().await.unwrap().is_empty());
+    storage.define_field(definition("WATTS", FieldType::Text, &[])).await.unwrap();
+    storage.define_field(definition("RIG", FieldType::Enum, &["IC-705", "KX2"])).await.unwrap();
+    storage.define_field(definition("SENT", FieldType::Date, &[])).await.unwrap();
+    storage.define_field(definition("TEMP", FieldType::Boolean, &[])).await.unwrap();
+
+    // A new definition of a field replaces the old one where it stands
+    storage.define_field(definition("WATTS", FieldType::Number, &[])).await.unwrap();
+    storage.remove_field("TEMP").await.unwrap();
+    assert!(matches!(storage.remove_field("TEMP").await, Err(StorageError::NotFound(_))));
+    assert_eq!(storage.field_definitions().await.unwrap(), definitions());
+}
+
+/// What `check_definitions` leaves behind
+fn definitions() -> Vec<FieldDefinition> {
+    vec![
+        definition("WATTS", FieldType::Number, &[]),
+        definition("RIG", FieldType::Enum, &["IC-705", "KX2"]),
+        definition("SENT", FieldType::Date, &[]),
+    ]
+}
+
+async fn queried(storage: &dyn Storage, query: FieldQuery) -> Vec<String> {
+    storage.query_fields(&query).await.unwrap().into_iter().map(|entry| entry.id).collect()
+}
+
+async fn check_field_queries(storage: &mut dyn Storage) {
+    let with = |id: &str, hours: u32, fields: &[(&str, &str)]| {
+        let mut entry = qso(id, "K2XYZ", "CW");
+        entry.timestamp = Utc.with_ymd_and_hms(2024, 10, 19, hours, 0, 0).unwrap();
+        entry.custom_fields = fields.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        entry
+    };
+    storage.save_entries(vec![
+        with("q1", 1, &[("WATTS", "100"), ("RIG", "IC-705"), ("SENT", "2024-11-02"), ("OPERATOR_NOTE", "Big gun")]),
+        with("q2", 2, &[("WATTS", "5"), ("RIG", "KX2"), ("SENT", "2024-10-20")]),
+        with("q3", 3, &[("WATTS", "20.5"), ("RIG", "KX2")]),
+        with("q4", 4, &[("OPERATOR_NOTE", "big signal")]),
+    ]).await.unwrap();
+
+    // Numbers compare by value, not as text
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("watts", Comparison::Greater, "10")).await, vec!["q1", "q3"]);
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("WATTS", Comparison::LessOrEqual, "20.5")).await, vec!["q2", "q3"]);
+    // Values are taken in the field's form
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("RIG", Comparison::Equal, "kx2")).await, vec!["q2", "q3"]);
+    assert_eq!(
+        queried(&*storage, FieldQuery::new()
+            .filter("SENT", Comparison::GreaterOrEqual, "20241020")
+            .filter("SENT", Comparison::Less, "2024-11-01")).await,
+        vec!["q2"],
+    );
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("RIG", Comparison::NotEqual, "KX2")).await, vec!["q1"]);
+    // Fields without a definition are text
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("OPERATOR_NOTE", Comparison::Contains, "BIG")).await, vec!["q1", "q4"]);
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("OPERATOR_NOTE", Comparison::Equal, "big gun")).await, vec!["q1"]);
+    assert!(matches!(
+        storage.query_fields(&FieldQuery::new().filter("RIG", Comparison::Equal, "FT-817")).await,
+        Err(StorageError::Validation(_))
+    ));
+
+    // Entries without the sort field come last either way
+    let by_watts = |descending| FieldQuery::new().filter("RIG", Comparison::Contains, "").sort_by("WATTS", descending);
+    assert_eq!(queried(&*storage, FieldQuery::new().sort_by("WATTS", true)).await[..3], ["q1", "q3", "q2"]);
+    assert_eq!(queried(&*storage, by_watts(false)).await, vec!["q2", "q3", "q1"]);
+    assert_eq!(queried(&*storage, FieldQuery::new().sort_by("SENT", false)).await, vec!["q2", "q1", "1", "2", "q3", "q4"]);
+
+    // Values follow changes to the entries
+    let mut changed = storage.get_entry("q2").await.unwrap().unwrap();
+    changed.custom_fields.insert("WATTS".to_string(), "500".to_string());
+    storage.update_entry(changed).await.unwrap();
+    storage.delete_entry("q1").await.unwrap();
+    assert_eq!(queried(&*storage, FieldQuery::new().filter("WATTS", Comparison::Greater, "10")).await, vec!["q2", "q3"]);
+    for id in ["q2", "q3", "q4"] {
+        storage.delete_entry(id).await.unwrap();
+    }
+}
+
+async fn check_search(storage: &mut dyn Storage) {
+    let mut summit = qso("5", "W1AW", "CW");
+    summit.notes = Some("Portable on the summit, portable all day".to_string());
+    summit.name = Some("Ann".to_string());
+    summit.qth = Some("Hilltop".to_string());
+    let mut field_day = qso("6", "K1ABC", "SSB");
+    field_day.notes = Some("Portable station".to_string());
+    field_day.qth = Some("Springfield".to_string());
+    field_day.custom_fields.insert("ARRL_SECT".to_string(), "EMA".to_string());
+    let mut home = qso("7", "N0CALL", "FT8");
+    home.notes = Some("Home station".to_string());
+    home.name = Some("Annette".to_string());
+    storage.save_entries(vec![summit, field_day, home]).await.unwrap();
+
+    let ids = |hits: Vec<SearchHit>| hits.into_iter().map(|hit| hit.entry.id).collect::<Vec<_>>();
+    // The entry that mentions it twice comes first
+    let hits = storage.search_text("portable").await.unwrap();
+    assert_eq!(hits[0].matches[0].field, "notes");
+    assert_eq!(hits[0].matches[0].ranges.len(), 2);
+    assert_eq!(ids(hits), vec!["5", "6"]);
+    assert_eq!(ids(storage.search_text("station PORTABLE").await.unwrap()), vec!["6"]);
+    assert_eq!(ids(storage.search_text("\"portable station\"").await.unwrap()), vec!["6"]);
+    assert!(storage.search_text("\"station portable\"").await.unwrap().is_empty());
+    let mut prefixed = ids(storage.search_text("ann*").await.unwrap());
+    prefixed.sort();
+    assert_eq!(prefixed, vec!["5", "7"]);
+    assert_eq!(ids(storage.search_text("ema springfield").await.unwrap()), vec!["6"]);
+    assert!(storage.search_text("").await.unwrap().is_empty());
+    assert!(storage.search_text("station OR (").await.is_ok());
+
+    // Changes are searchable straight away
+    storage.delete_entry("6").await.unwrap();
+    let mut home = storage.get_entry("7").await.unwrap().unwrap();
+    home.notes = Some("Portable from the car".to_string());
+    storage.update_entry(home).await.unwrap();
+    assert_eq!(ids(storage.search_text("portable").await.unwrap()), vec!["5", "7"]);
+    assert!(storage.search_text("ema").await.unwrap().is_empty());
+}
+
+/// What the checks above leave behind
+async fn check_contents(storage: &dyn Storage) {
+    let entries = sorted(storage).await;
+    assert_eq!(entries.len(), 2);
+    assert_same(entries.first(), &qso("1", "W1AW", "FT4"));
+    assert_same(entries.get(1), &qso("2", "K1ABC", "CW"));
+    assert_eq!(storage.journal().await.unwrap().len(), 3);
+    assert_eq!(storage.revisions("1").await.unwrap().len(), 2);
+    assert_eq!(storage.field_definitions().await.unwrap(), definitions());
+}
+
+async fn conformance(format: StorageFormat, dir: &Path) {
+    let path = dir.join(format.file_name());
+    let mut storage = StorageManager::open(format, &path).await.unwrap();
+    assert_eq!(storage.format(), format);
+    check_entries(&mut *storage).await;
+    check_history(&mut *storage).await;
+    check_definitions(&mut *storage).await;
+    check_contents(&*storage).await;
+
+    // A complete copy can be opened as a log of its own
+    let copy = dir.join(format!("copy-{}", format.file_name()));
+    storage.backup(&copy).await.unwrap();
+    let entries = sorted(&*StorageManager::open(format, &copy).await.unwrap()).await;
+    assert_eq!(entries.len(), 2);
+
+    let mut storage = if format == StorageFormat::Memory {
+        storage
+    } else {
+        // Everything survives reopening
+        storage.close().await.unwrap();
+        drop(storage);
+        let storage = StorageManager::open(format, &path).await.unwrap();
+        check_contents(&*storage).await;
+        storage
+    };
+    check_field_queries(&mut *storage).await;
+    check_search(&mut *storage).await;
+    storage.clear().await.unwrap();
+    assert!(storage.list_entries().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_memory_conformance() {
+    conformance(StorageFormat::Memory, tempfile::tempdir().unwrap().path()).await;
+}
+
+#[tokio::test]
+async fn test_json_conformance() {
+    conformance(StorageFormat::Json, tempfile::tempdir().unwrap().path()).await;
+}
+
+#[tokio::test]
+async fn test_jsonl_conformance() {
+    conformance(StorageFormat::JsonLines, tempfile::tempdir().unwrap().path()).await;
+}
+
+#[tokio::test]
+async fn test_adif_conformance() {
+    conformance(StorageFormat::Adif, tempfile::tempdir().unwrap().path()).await;
+}
+
+#[tokio::test]
+async fn test_sqlite_conformance() {
+    conformance(StorageFormat::Sqlite, tempfile::tempdir().unwrap().path()).await;
+}
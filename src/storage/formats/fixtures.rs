@@ -0,0 +1,28 @@
+//! QSOs for tests to log, so each test only spells out the fields it is
+//! about, e.g. `LogEntry { iota: Some(..), ..qso("1", "W1AW", "SSB") }`
+
+use chrono::{TimeZone, Utc};
+
+use crate::app::Frequency;
+use crate::LogEntry;
+
+/// A QSO on 20m at a fixed time, with signal reports
+pub fn qso(id: &str, call: &str, mode: &str) -> LogEntry {
+    LogEntry {
+        id: id.to_string(),
+        timestamp: Utc.with_ymd_and_hms(2024, 10, 18, 14, 30, 0).unwrap(),
+        callsign: call.into(),
+        frequency: Frequency::from_hz(14_074_000),
+        band: Some("20m".into()),
+        mode: mode.into(),
+        rst_sent: Some("-10".into()),
+        rst_received: Some("-12".into()),
+        ..Default::default()
+    }
+}
+
+/// A `qso` on another frequency, with the band to match
+pub fn qso_on(id: &str, call: &str, mhz: f64, mode: &str) -> LogEntry {
+    let frequency = Frequency::from_mhz(mhz).unwrap();
+    LogEntry { frequency, band: frequency.band(), ..qso(id, call, mode) }
+}
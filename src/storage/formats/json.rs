@@ -1,52 +1,160 @@
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::fs;
 use async_trait::async_trait;
 
 use crate::LogEntry;
-use crate::storage::{Storage, StorageError, StorageFormat};
+use crate::storage::fields::{FieldDefinition, FieldFile};
+use crate::storage::fsck::{Scan, Unreadable};
+use crate::storage::journal::{self, JournalFile};
+use crate::storage::lock::{FileStamp, LogLock};
+use crate::storage::revision::RevisionFile;
+use crate::storage::{Backup, BackupConfig, BackupReason, JournalRecord, Revision, Storage, StorageError, StorageFormat};
 
+/// Log kept as one JSON array, rewritten whole on every change. Another
+/// amlog instance may have the same log open, so every write first takes in
+/// what it wrote since this one last read or wrote the file.
 pub struct JsonStorage {
     path: PathBuf,
     cached_entries: Vec<LogEntry>,
+    /// The file as this instance last read or wrote it, if it existed
+    stamp: Option<FileStamp>,
+    journal: JournalFile,
+    revisions: RevisionFile,
+    fields: FieldFile,
+    lock: LogLock,
+    warnings: Vec<String>,
 }
 
 impl JsonStorage {
     pub fn new(path: &PathBuf) -> Result<Self, StorageError> {
-        let cached_entries = if path.exists() {
-            let content = fs::read_to_string(path)?;
-            // Handle empty file case
-            if content.trim().is_empty() {
-                Vec::new()
-            } else {
-                serde_json::from_str(&content).map_err(|e| {
-                    // If JSON is invalid, backup the file and start fresh
-                    if let Err(backup_err) = fs::rename(path, path.with_extension("json.bak")) {
-                        eprintln!("Failed to create backup: {}", backup_err);
-                    }
-                    StorageError::Json(e)
-                })?
-            }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let lock = LogLock::open(path)?;
+        let mut warnings = Vec::new();
+        let (cached_entries, stamp) = if path.exists() {
+            let (content, stamp) = {
+                let _guard = lock.blocking_shared()?;
+                (fs::read_to_string(path)?, FileStamp::of(path)?)
+            };
+            let entries = match serde_json::from_str(&content) {
+                Ok(entries) => entries,
+                Err(_) if content.trim().is_empty() => Vec::new(),
+                Err(e) => Self::recover(path, &content, e, &mut warnings),
+            };
+            (entries, Some(stamp))
         } else {
-            // Create directory if it doesn't exist
-            if let Some(parent) = path.parent() {
-                fs::create_dir_all(parent)?;
-            }
-            Vec::new()
+            (Vec::new(), None)
         };
 
         Ok(Self {
             path: path.clone(),
             cached_entries,
+            stamp,
+            journal: JournalFile::open(JournalFile::path_for(path))?,
+            revisions: RevisionFile::open(RevisionFile::path_for(path))?,
+            fields: FieldFile::open(FieldFile::path_for(path))?,
+            lock,
+            warnings,
         })
     }
 
-    fn save_to_file(&self) -> Result<(), StorageError> {
-        let json = serde_json::to_string_pretty(&self.cached_entries)?;
+    /// Keep what can be read from a damaged log, with a copy of the file
+    /// as it was in the backups
+    fn recover(path: &PathBuf, content: &str, error: serde_json::Error, warnings: &mut Vec<String>) -> Vec<LogEntry> {
+        let scan = Self::salvage(content);
+        let dir = BackupConfig::from_env(path).dir;
+        let backup = Backup::new(&dir, path, BackupReason::Corrupt);
+        warnings.push(match fs::create_dir_all(&dir).and_then(|_| fs::copy(path, &backup.path)) {
+            Ok(_) => format!(
+                "Log {} is damaged ({}); recovered {} entries, the original is in {}",
+                path.display(), error, scan.entries.len(), backup.path.display(),
+            ),
+            Err(backup_err) => format!(
+                "Log {} is damaged ({}); recovered {} entries, but failed to back up the original: {}",
+                path.display(), error, scan.entries.len(), backup_err,
+            ),
+        });
+        scan.entries
+    }
+
+    /// Read a log that may not be valid JSON, e.g. one cut short by a
+    /// crash. Each object in the top-level array that reads as an entry is
+    /// kept, and the text between them is set aside as unreadable.
+    fn salvage(content: &str) -> Scan {
+        let mut scan = Scan::default();
+        let mut pos = content.find('[').map_or(0, |i| i + 1);
+        let mut skipped_from = None;
+        let set_aside = |scan: &mut Scan, from: Option<usize>, to: usize| {
+            let Some(from) = from else { return };
+            let raw = content[from..to.max(from)].trim().trim_end_matches(',').trim();
+            if !raw.is_empty() {
+                scan.unreadable.push(Unreadable {
+                    location: format!("line {}", content[..from].lines().count().max(1)),
+                    error: "not a readable entry".to_string(),
+                    raw: raw.to_string(),
+                });
+            }
+        };
+
+        while pos < content.len() {
+            let rest = &content[pos..];
+            let next = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+            pos += rest.len() - next.len();
+            if next.is_empty() || next.starts_with(']') {
+                break;
+            }
+            let mut stream = serde_json::Deserializer::from_str(next).into_iter::<LogEntry>();
+            match stream.next() {
+                Some(Ok(entry)) => {
+                    set_aside(&mut scan, skipped_from.take(), pos);
+                    scan.entries.push(entry);
+                    pos += stream.byte_offset();
+                }
+                _ => {
+                    // Look for the next entry at the next opening brace
+                    skipped_from.get_or_insert(pos);
+                    let from = pos + next.chars().next().map_or(1, char::len_utf8);
+                    pos = content[from..].find('{').map_or(content.len(), |i| from + i);
+                }
+            }
+        }
+        // Anything left over runs to the closing bracket, if the file has one
+        let tail = content.trim_end();
+        let end = tail.strip_suffix(']').map_or(content.len(), str::len);
+        set_aside(&mut scan, skipped_from, end);
+        scan
+    }
+
+    /// Apply `change` to the log as it is on disk and write it out. Under
+    /// the lock, the file is read again first if another instance wrote it
+    /// since this one last did, so that instance's changes are kept. The
+    /// cache is left as it was if the change or the write fails.
+    async fn write(
+        &mut self,
+        change: impl FnOnce(&mut Vec<LogEntry>) -> Result<(), StorageError>,
+    ) -> Result<(), StorageError> {
+        let _guard = self.lock.exclusive().await?;
+        let stamp = FileStamp::of(&self.path).ok();
+        let mut entries = if stamp != self.stamp && stamp.is_some() {
+            let content = fs::read_to_string(&self.path)?;
+            serde_json::from_str(&content).map_err(|e| {
+                StorageError::Backend(format!("{} changed on disk and cannot be read: {}", self.path.display(), e))
+            })?
+        } else {
+            self.cached_entries.clone()
+        };
+        change(&mut entries)?;
+
+        let json = serde_json::to_string_pretty(&entries)?;
         // Write to temporary file first
         let temp_path = self.path.with_extension("json.tmp");
         fs::write(&temp_path, &json)?;
         // Then rename it to the actual file
         fs::rename(&temp_path, &self.path)?;
+        self.cached_entries = entries;
+        self.stamp = Some(FileStamp::of(&self.path)?);
         Ok(())
     }
 }
@@ -54,15 +162,34 @@ impl JsonStorage {
 #[async_trait]
 impl Storage for JsonStorage {
     async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        // First check if an entry with this ID already exists
-        if let Some(pos) = self.cached_entries.iter().position(|e| e.id == entry.id) {
-            self.cached_entries[pos] = entry;
-        } else {
-            self.cached_entries.push(entry);
-        }
+        self.write(|entries| {
+            // First check if an entry with this ID already exists
+            if let Some(pos) = entries.iter().position(|e| e.id == entry.id) {
+                entries[pos] = entry;
+            } else {
+                entries.push(entry);
+            }
+            Ok(())
+        }).await
+    }
 
-        self.save_to_file()?;
-        Ok(())
+    async fn save_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        self.write(|cached| {
+            let mut positions: HashMap<String, usize> = cached.iter()
+                .enumerate()
+                .map(|(pos, e)| (e.id.clone(), pos))
+                .collect();
+            for entry in entries {
+                match positions.get(&entry.id) {
+                    Some(&pos) => cached[pos] = entry,
+                    None => {
+                        positions.insert(entry.id.clone(), cached.len());
+                        cached.push(entry);
+                    }
+                }
+            }
+            Ok(())
+        }).await
     }
 
     async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError> {
@@ -73,7 +200,73 @@ impl Storage for JsonStorage {
     }
 
     async fn add_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        Ok(())
+        self.write(|entries| {
+            if entries.iter().any(|e| e.id == entry.id) {
+                return Err(StorageError::EntryExists);
+            }
+            entries.push(entry);
+            Ok(())
+        }).await
+    }
+
+    async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError> {
+        Ok(self.journal.records().to_vec())
+    }
+
+    async fn save_batch(
+        &mut self,
+        entries: Vec<LogEntry>,
+        revisions: Vec<Revision>,
+        records: Vec<JournalRecord>,
+    ) -> Result<(), StorageError> {
+        self.revisions.append_all(revisions)?;
+        self.save_entries(entries).await?;
+        self.journal.append_all(records)
+    }
+
+    async fn append_journal(&mut self, record: JournalRecord) -> Result<(), StorageError> {
+        self.journal.append(record)
+    }
+
+    async fn mark_journal(&mut self, seq: u64, undone: bool) -> Result<(), StorageError> {
+        self.journal.mark(seq, undone)
+    }
+
+    async fn last_seq(&self) -> Result<u64, StorageError> {
+        Ok(self.journal.last_seq())
+    }
+
+    async fn journal_record(&self, seq: u64) -> Result<Option<JournalRecord>, StorageError> {
+        Ok(self.journal.record(seq).cloned())
+    }
+
+    fn damaged_history(&self) -> Vec<Unreadable> {
+        journal::damaged_history(&self.journal, &self.revisions)
+    }
+
+    async fn drop_damaged_history(&mut self) -> Result<(), StorageError> {
+        self.journal.drop_damaged()?;
+        self.revisions.drop_damaged()
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError> {
+        Ok(self.revisions.for_entry(id))
+    }
+
+    async fn add_revision(&mut self, revision: Revision) -> Result<(), StorageError> {
+        self.revisions.append(revision)
+    }
+
+    async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError> {
+        Ok(self.fields.definitions().to_vec())
+    }
+
+    async fn define_field(&mut self, definition: FieldDefinition) -> Result<(), StorageError> {
+        self.fields.define(definition)
+    }
+
+    async fn remove_field(&mut self, name: &str) -> Result<(), StorageError> {
+        self.fields.remove(name)
     }
 
     async fn list_entries(&self) -> Result<Vec<LogEntry>, StorageError> {
@@ -81,29 +274,54 @@ impl Storage for JsonStorage {
     }
 
     async fn update_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        if let Some(pos) = self.cached_entries.iter().position(|e| e.id == entry.id) {
-            self.cached_entries[pos] = entry;
-            self.save_to_file()?;
-            Ok(())
-        } else {
-            Err(StorageError::Backend(format!("Entry with id {} not found", entry.id)))
-        }
+        self.write(|entries| {
+            if let Some(pos) = entries.iter().position(|e| e.id == entry.id) {
+                entries[pos] = entry;
+                Ok(())
+            } else {
+                Err(StorageError::NotFound(entry.id))
+            }
+        }).await
     }
 
     async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError> {
-        if let Some(pos) = self.cached_entries.iter().position(|e| e.id == id) {
-            self.cached_entries.remove(pos);
-            self.save_to_file()?;
-            Ok(())
-        } else {
-            Err(StorageError::Backend(format!("Entry with id {} not found", id)))
+        self.write(|entries| {
+            if let Some(pos) = entries.iter().position(|e| e.id == id) {
+                entries.remove(pos);
+                Ok(())
+            } else {
+                Err(StorageError::NotFound(id.to_string()))
+            }
+        }).await
+    }
+
+    async fn scan(&self) -> Result<Scan, StorageError> {
+        if !self.path.exists() {
+            return Ok(Scan::default());
         }
+        let content = {
+            let _guard = self.lock.shared().await?;
+            fs::read_to_string(&self.path)?
+        };
+        Ok(Self::salvage(&content))
     }
 
     async fn clear(&mut self) -> Result<(), StorageError> {
-        self.cached_entries.clear();
-        self.save_to_file()?;
-        Ok(())
+        self.write(|entries| {
+            entries.clear();
+            Ok(())
+        }).await
+    }
+
+    async fn replace_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        self.write(|cached| {
+            *cached = entries;
+            Ok(())
+        }).await
+    }
+
+    fn warnings(&self) -> Vec<String> {
+        self.warnings.clone()
     }
 
     fn format(&self) -> StorageFormat {
@@ -118,6 +336,7 @@ impl Storage for JsonStorage {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::app::Frequency;
     use chrono::Utc;
     use uuid::Uuid;
 
@@ -125,11 +344,11 @@ mod tests {
         LogEntry {
             id: Uuid::new_v4().to_string(),
             timestamp: Utc::now(),
-            callsign: "W1AW".to_string(),
-            frequency: 14.074,
-            mode: "FT8".to_string(),
-            rst_sent: Some("599".to_string()),
-            rst_received: Some("599".to_string()),
+            callsign: "W1AW".into(),
+            frequency: Frequency::from_hz(14_074_000),
+            mode: "FT8".into(),
+            rst_sent: Some("599".into()),
+            rst_received: Some("599".into()),
             notes: Some("Test QSO".to_string()),
             ..Default::default()
         }
@@ -155,7 +374,7 @@ mod tests {
         // Test Update
         let mut updated_entry = create_test_entry().await;
         updated_entry.id = entry_id.clone();
-        updated_entry.callsign = "K1ABC".to_string();
+        updated_entry.callsign = "K1ABC".into();
         storage.update_entry(updated_entry).await?;
 
         let retrieved = storage.get_entry(&entry_id).await?;
@@ -168,4 +387,102 @@ mod tests {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_json_loads_logs_without_extended_fields() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("old_log.json");
+        fs::write(&path, r#"[{
+            "id": "1", "timestamp": "2024-03-27T14:30:00Z", "callsign": "W1AW",
+            "frequency": 14.074, "mode": "FT8", "rst_sent": null, "rst_received": null,
+            "notes": null, "name": null, "qth": null, "state": null, "country": null,
+            "dxcc": null, "band": null
+        }]"#)?;
+
+        let mut storage = JsonStorage::new(&path)?;
+        let mut entry = storage.get_entry("1").await?.unwrap();
+        assert!(entry.time_off.is_none());
+        assert!(!entry.lotw.is_confirmed());
+
+        entry.lotw.received = Some(crate::app::QslStatus::Yes);
+        entry.iota = Some("NA-001".to_string());
+        storage.save_entry(entry).await?;
+
+        let reloaded = JsonStorage::new(&path)?;
+        let entry = reloaded.get_entry("1").await?.unwrap();
+        assert!(entry.lotw.is_confirmed());
+        assert_eq!(entry.iota.as_deref(), Some("NA-001"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_keeps_what_another_instance_wrote() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.json");
+        let mut ours = JsonStorage::new(&path)?;
+        let mut theirs = JsonStorage::new(&path)?;
+
+        let first = create_test_entry().await;
+        ours.add_entry(first.clone()).await?;
+        let second = create_test_entry().await;
+        theirs.add_entry(second.clone()).await?;
+        // Ours has not seen the second entry, but must not write it away
+        let mut edited = first.clone();
+        edited.callsign = "K1ABC".into();
+        ours.update_entry(edited).await?;
+        assert!(matches!(ours.add_entry(second.clone()).await, Err(StorageError::EntryExists)));
+
+        let reopened = JsonStorage::new(&path)?;
+        assert_eq!(reopened.list_entries().await?.len(), 2);
+        assert_eq!(reopened.get_entry(&first.id).await?.unwrap().callsign, "K1ABC");
+        assert!(reopened.get_entry(&second.id).await?.is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_salvages_damaged_log() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.json");
+        let mut storage = JsonStorage::new(&path)?;
+        for n in 0..3 {
+            let mut entry = create_test_entry().await;
+            entry.id = n.to_string();
+            storage.add_entry(entry).await?;
+        }
+
+        // Damage the middle entry and cut the last one short
+        let content = fs::read_to_string(&path)?;
+        let middle = content.find("\"id\": \"1\"").unwrap();
+        let mut damaged = content.clone();
+        damaged.replace_range(middle..middle + 4, "\"i\"");
+        damaged.truncate(content.rfind("\"notes\"").unwrap());
+        fs::write(&path, &damaged)?;
+
+        let storage = JsonStorage::new(&path)?;
+        let entries = storage.list_entries().await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].id, "0");
+        let scan = storage.scan().await?;
+        assert_eq!(scan.entries.len(), 1);
+        assert_eq!(scan.unreadable.len(), 1);
+        assert!(scan.unreadable[0].raw.contains("\"i\""));
+        assert!(storage.warnings()[0].contains("recovered 1 entries"));
+
+        // The damaged file is kept as it was
+        let backups = fs::read_dir(temp_dir.path().join("backups"))?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(backups.len(), 1);
+        assert_eq!(fs::read_to_string(backups[0].path())?, damaged);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_json_salvage_skips_non_ascii_garbage() {
+        let entry = create_test_entry().await;
+        let content = format!("[ü€, {}, ßüñ {{ ]", serde_json::to_string(&entry).unwrap());
+        let scan = JsonStorage::salvage(&content);
+        assert_eq!(scan.entries.len(), 1);
+        assert_eq!(scan.entries[0].id, entry.id);
+        assert_eq!(scan.unreadable.iter().map(|u| u.raw.as_str()).collect::<Vec<_>>(), vec!["ü€", "ßüñ {"]);
+    }
+}
+
@@ -0,0 +1,348 @@
+//! Append-only JSON lines log: one upsert or delete per line, replayed on
+//! open and compacted to one line per entry once enough lines are dead
+//!
+//! A compacted file holds one entry per line in QSO time order, so it
+//! diffs well and can be kept in git.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::LogEntry;
+use crate::storage::fields::{FieldDefinition, FieldFile};
+use crate::storage::fsck::{Scan, Unreadable};
+use crate::storage::journal::{self, JournalFile};
+use crate::storage::revision::RevisionFile;
+use crate::storage::{JournalRecord, Revision, Storage, StorageError, StorageFormat};
+
+/// Dead lines tolerated before compacting, on top of one per live entry
+const COMPACT_SLACK: usize = 256;
+
+/// One line of the log
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Operation {
+    Upsert { entry: Box<LogEntry> },
+    Delete { id: String },
+}
+
+#[derive(Default)]
+struct ReplayedLog {
+    entries: Vec<LogEntry>,
+    lines: usize,
+    unreadable: Vec<Unreadable>,
+    /// Bytes up to the end of the last whole line
+    valid_len: usize,
+}
+
+pub struct JsonLinesStorage {
+    path: PathBuf,
+    cached_entries: Vec<LogEntry>,
+    /// Lines in the file, live or superseded
+    lines: usize,
+    /// Lines that could not be read; the file is not compacted while there
+    /// are any, so they stay for `fsck` to deal with
+    unreadable: usize,
+    journal: JournalFile,
+    revisions: RevisionFile,
+    fields: FieldFile,
+}
+
+impl JsonLinesStorage {
+    pub fn new(path: &PathBuf) -> Result<Self, StorageError> {
+        let scan = if path.exists() {
+            Self::replay(path)?
+        } else {
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            ReplayedLog::default()
+        };
+
+        Ok(Self {
+            path: path.clone(),
+            cached_entries: scan.entries,
+            lines: scan.lines,
+            unreadable: scan.unreadable.len(),
+            journal: JournalFile::open(JournalFile::path_for(path))?,
+            revisions: RevisionFile::open(RevisionFile::path_for(path))?,
+            fields: FieldFile::open(FieldFile::path_for(path))?,
+        })
+    }
+
+    /// Rebuild the entries from the file, trimming a last line cut short
+    /// by a crash
+    fn replay(path: &PathBuf) -> Result<ReplayedLog, StorageError> {
+        let content = fs::read_to_string(path)?;
+        let replayed = Self::read(&content);
+
+        if replayed.valid_len < content.len() {
+            OpenOptions::new().write(true).open(path)?.set_len(replayed.valid_len as u64)?;
+        } else if !content.is_empty() && !content.ends_with('\n') {
+            // A complete last line that only lacks its newline
+            writeln!(OpenOptions::new().append(true).open(path)?)?;
+        }
+        Ok(replayed)
+    }
+
+    /// Rebuild the entries without touching the file. A last line cut short
+    /// is left out; bad lines elsewhere are skipped and returned as
+    /// unreadable.
+    fn read(content: &str) -> ReplayedLog {
+        let mut replayed = ReplayedLog::default();
+
+        for (number, line) in content.split_inclusive('\n').enumerate() {
+            if !line.trim().is_empty() {
+                match serde_json::from_str::<Operation>(line) {
+                    Ok(operation) => Self::apply(&mut replayed.entries, operation),
+                    Err(_) if !line.ends_with('\n') => break,
+                    Err(e) => replayed.unreadable.push(Unreadable {
+                        location: format!("line {}", number + 1),
+                        error: e.to_string(),
+                        raw: line.trim_end().to_string(),
+                    }),
+                }
+                replayed.lines += 1;
+            }
+            replayed.valid_len += line.len();
+        }
+        replayed
+    }
+
+    fn apply(entries: &mut Vec<LogEntry>, operation: Operation) {
+        match operation {
+            Operation::Upsert { entry } => match entries.iter().position(|e| e.id == entry.id) {
+                Some(pos) => entries[pos] = *entry,
+                None => entries.push(*entry),
+            },
+            Operation::Delete { id } => entries.retain(|e| e.id != id),
+        }
+    }
+
+    /// Append an operation and apply it, compacting once dead lines pile up
+    fn write(&mut self, operation: Operation) -> Result<(), StorageError> {
+        let line = serde_json::to_string(&operation)?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", line)?;
+        file.sync_data()?;
+        self.lines += 1;
+        Self::apply(&mut self.cached_entries, operation);
+
+        if self.unreadable == 0 && self.lines > self.cached_entries.len() + COMPACT_SLACK {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrite the file with one upsert per live entry
+    pub fn compact(&mut self) -> Result<(), StorageError> {
+        let mut entries: Vec<&LogEntry> = self.cached_entries.iter().collect();
+        entries.sort_by(|a, b| a.timestamp.cmp(&b.timestamp).then_with(|| a.id.cmp(&b.id)));
+
+        let temp_path = self.path.with_extension("jsonl.tmp");
+        let mut file = File::create(&temp_path)?;
+        for entry in &entries {
+            let operation = Operation::Upsert { entry: Box::new((*entry).clone()) };
+            writeln!(file, "{}", serde_json::to_string(&operation)?)?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp_path, &self.path)?;
+        self.lines = entries.len();
+        self.unreadable = 0;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Storage for JsonLinesStorage {
+    async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        self.write(Operation::Upsert { entry: Box::new(entry) })
+    }
+
+    async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError> {
+        Ok(self.cached_entries.iter().find(|e| e.id == id).cloned())
+    }
+
+    async fn list_entries(&self) -> Result<Vec<LogEntry>, StorageError> {
+        Ok(self.cached_entries.clone())
+    }
+
+    async fn update_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        if !self.cached_entries.iter().any(|e| e.id == entry.id) {
+            return Err(StorageError::NotFound(entry.id));
+        }
+        self.write(Operation::Upsert { entry: Box::new(entry) })
+    }
+
+    async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError> {
+        if !self.cached_entries.iter().any(|e| e.id == id) {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
+        self.write(Operation::Delete { id: id.to_string() })
+    }
+
+    async fn add_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        if self.cached_entries.iter().any(|e| e.id == entry.id) {
+            return Err(StorageError::EntryExists);
+        }
+        self.write(Operation::Upsert { entry: Box::new(entry) })
+    }
+
+    async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError> {
+        Ok(self.journal.records().to_vec())
+    }
+
+    async fn save_batch(
+        &mut self,
+        entries: Vec<LogEntry>,
+        revisions: Vec<Revision>,
+        records: Vec<JournalRecord>,
+    ) -> Result<(), StorageError> {
+        self.revisions.append_all(revisions)?;
+        self.save_entries(entries).await?;
+        self.journal.append_all(records)
+    }
+
+    async fn append_journal(&mut self, record: JournalRecord) -> Result<(), StorageError> {
+        self.journal.append(record)
+    }
+
+    async fn mark_journal(&mut self, seq: u64, undone: bool) -> Result<(), StorageError> {
+        self.journal.mark(seq, undone)
+    }
+
+    async fn last_seq(&self) -> Result<u64, StorageError> {
+        Ok(self.journal.last_seq())
+    }
+
+    async fn journal_record(&self, seq: u64) -> Result<Option<JournalRecord>, StorageError> {
+        Ok(self.journal.record(seq).cloned())
+    }
+
+    fn damaged_history(&self) -> Vec<Unreadable> {
+        journal::damaged_history(&self.journal, &self.revisions)
+    }
+
+    async fn drop_damaged_history(&mut self) -> Result<(), StorageError> {
+        self.journal.drop_damaged()?;
+        self.revisions.drop_damaged()
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError> {
+        Ok(self.revisions.for_entry(id))
+    }
+
+    async fn add_revision(&mut self, revision: Revision) -> Result<(), StorageError> {
+        self.revisions.append(revision)
+    }
+
+    async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError> {
+        Ok(self.fields.definitions().to_vec())
+    }
+
+    async fn define_field(&mut self, definition: FieldDefinition) -> Result<(), StorageError> {
+        self.fields.define(definition)
+    }
+
+    async fn remove_field(&mut self, name: &str) -> Result<(), StorageError> {
+        self.fields.remove(name)
+    }
+
+    async fn scan(&self) -> Result<Scan, StorageError> {
+        if !self.path.exists() {
+            return Ok(Scan::default());
+        }
+        let replayed = Self::read(&fs::read_to_string(&self.path)?);
+        Ok(Scan { entries: replayed.entries, unreadable: replayed.unreadable })
+    }
+
+    async fn clear(&mut self) -> Result<(), StorageError> {
+        self.cached_entries.clear();
+        self.compact()
+    }
+
+    async fn replace_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        let previous = std::mem::replace(&mut self.cached_entries, entries);
+        self.compact().inspect_err(|_| self.cached_entries = previous)
+    }
+
+    fn format(&self) -> StorageFormat {
+        StorageFormat::JsonLines
+    }
+
+    fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::formats::fixtures::qso;
+
+    #[tokio::test]
+    async fn test_jsonl_replay_and_truncated_line() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.jsonl");
+
+        let mut storage = JsonLinesStorage::new(&path)?;
+        storage.add_entry(qso("1", "W1AW", "CW")).await?;
+        storage.add_entry(qso("2", "K1ABC", "CW")).await?;
+        storage.save_entry(qso("1", "W1AW/P", "CW")).await?;
+        storage.delete_entry("2").await?;
+        assert_eq!(fs::read_to_string(&path)?.lines().count(), 4);
+
+        // A crash in the middle of writing leaves half a line behind
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        write!(file, "{{\"op\":\"upsert\",\"entry\":{{\"id\":\"3\",\"call")?;
+        drop(file);
+
+        let mut storage = JsonLinesStorage::new(&path)?;
+        let entries = storage.list_entries().await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].callsign, "W1AW/P");
+
+        // The partial line is gone, so new lines append cleanly
+        storage.add_entry(qso("3", "N0CALL", "CW")).await?;
+        let storage = JsonLinesStorage::new(&path)?;
+        assert_eq!(storage.list_entries().await?.len(), 2);
+
+        // A damaged line in the middle is skipped and kept for fsck
+        let content = fs::read_to_string(&path)?.replacen("\"op\":\"delete\"", "\"op\":\"remove\"", 1);
+        fs::write(&path, content)?;
+        let storage = JsonLinesStorage::new(&path)?;
+        assert_eq!(storage.list_entries().await?.len(), 3);
+        let scan = storage.scan().await?;
+        assert_eq!(scan.unreadable.len(), 1);
+        assert_eq!(scan.unreadable[0].location, "line 4");
+
+        // Scanning leaves a torn line for the next open to deal with
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        write!(file, "{{\"op\":\"del")?;
+        drop(file);
+        let before = fs::read_to_string(&path)?;
+        assert_eq!(storage.scan().await?.entries.len(), 3);
+        assert_eq!(fs::read_to_string(&path)?, before);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_compaction() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.jsonl");
+
+        let mut storage = JsonLinesStorage::new(&path)?;
+        for n in 0..=COMPACT_SLACK + 1 {
+            storage.save_entry(qso("1", &format!("W{}AW", n), "CW")).await?;
+        }
+        // Compacted down to the one live entry
+        assert_eq!(fs::read_to_string(&path)?.lines().count(), 1);
+
+        let storage = JsonLinesStorage::new(&path)?;
+        let entries = storage.list_entries().await?;
+        assert_eq!(entries[0].callsign.as_str(), format!("W{}AW", COMPACT_SLACK + 1));
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+//! Log kept in memory only, for tests and for trying things out without
+//! touching a log on disk
+//!
+//! Nothing is written back to `path`. A backup is a JSON snapshot of the
+//! entries, and a snapshot found at `path` on open, such as a restored
+//! backup, is read in.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use async_trait::async_trait;
+
+use crate::LogEntry;
+use crate::storage::fields::FieldDefinition;
+use crate::storage::journal;
+use crate::storage::{JournalRecord, Revision, Storage, StorageError, StorageFormat};
+
+#[derive(Default)]
+pub struct InMemoryStorage {
+    path: PathBuf,
+    entries: Vec<LogEntry>,
+    journal: Vec<JournalRecord>,
+    revisions: Vec<Revision>,
+    fields: Vec<FieldDefinition>,
+}
+
+impl InMemoryStorage {
+    pub fn new(path: &PathBuf) -> Result<Self, StorageError> {
+        let entries = if path.is_file() {
+            serde_json::from_str(&fs::read_to_string(path)?)?
+        } else {
+            Vec::new()
+        };
+        Ok(Self { path: path.clone(), entries, ..Default::default() })
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.entries.iter().position(|e| e.id == id)
+    }
+}
+
+#[async_trait]
+impl Storage for InMemoryStorage {
+    async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        match self.position(&entry.id) {
+            Some(pos) => self.entries[pos] = entry,
+            None => self.entries.push(entry),
+        }
+        Ok(())
+    }
+
+    async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError> {
+        Ok(self.entries.iter().find(|e| e.id == id).cloned())
+    }
+
+    async fn list_entries(&self) -> Result<Vec<LogEntry>, StorageError> {
+        Ok(self.entries.clone())
+    }
+
+    async fn update_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        let pos = self.position(&entry.id).ok_or_else(|| StorageError::NotFound(entry.id.clone()))?;
+        self.entries[pos] = entry;
+        Ok(())
+    }
+
+    async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError> {
+        let pos = self.position(id).ok_or_else(|| StorageError::NotFound(id.to_string()))?;
+        self.entries.remove(pos);
+        Ok(())
+    }
+
+    async fn add_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        if self.position(&entry.id).is_some() {
+            return Err(StorageError::EntryExists);
+        }
+        self.entries.push(entry);
+        Ok(())
+    }
+
+    async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError> {
+        Ok(self.journal.clone())
+    }
+
+    async fn append_journal(&mut self, record: JournalRecord) -> Result<(), StorageError> {
+        self.journal.retain(|r| r.seq < record.seq);
+        self.journal.push(record);
+        if self.journal.len() > journal::MAX_RECORDS {
+            self.journal.drain(..self.journal.len() - journal::MAX_RECORDS);
+        }
+        Ok(())
+    }
+
+    async fn mark_journal(&mut self, seq: u64, undone: bool) -> Result<(), StorageError> {
+        let record = self.journal.iter_mut()
+            .find(|r| r.seq == seq)
+            .ok_or_else(|| StorageError::NotFound(format!("journal record {}", seq)))?;
+        record.undone = undone;
+        Ok(())
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError> {
+        Ok(self.revisions.iter().filter(|r| r.entry_id == id).cloned().collect())
+    }
+
+    async fn add_revision(&mut self, revision: Revision) -> Result<(), StorageError> {
+        self.revisions.push(revision);
+        Ok(())
+    }
+
+    async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError> {
+        Ok(self.fields.clone())
+    }
+
+    async fn define_field(&mut self, definition: FieldDefinition) -> Result<(), StorageError> {
+        match self.fields.iter_mut().find(|d| d.name == definition.name) {
+            Some(existing) => *existing = definition,
+            None => self.fields.push(definition),
+        }
+        Ok(())
+    }
+
+    async fn remove_field(&mut self, name: &str) -> Result<(), StorageError> {
+        let pos = self.fields.iter()
+            .position(|d| d.name == name)
+            .ok_or_else(|| StorageError::NotFound(format!("field {}", name)))?;
+        self.fields.remove(pos);
+        Ok(())
+    }
+
+    async fn backup(&self, dest: &Path) -> Result<(), StorageError> {
+        fs::write(dest, serde_json::to_string_pretty(&self.entries)?)?;
+        Ok(())
+    }
+
+    async fn clear(&mut self) -> Result<(), StorageError> {
+        self.entries.clear();
+        Ok(())
+    }
+
+    fn format(&self) -> StorageFormat {
+        StorageFormat::Memory
+    }
+
+    fn path(&self) -> &PathBuf {
+        &self.path
+    }
+}
@@ -1,3 +1,9 @@
 pub mod json;
+pub mod jsonl;
 pub mod adif;
-pub mod sqlite;
\ No newline at end of file
+pub mod sqlite;
+pub mod memory;
+#[cfg(test)]
+mod conformance;
+#[cfg(test)]
+pub mod fixtures;
@@ -1,14 +1,338 @@
 use async_trait::async_trait;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use lazy_static::lazy_static;
 use serde_json;
-use sqlx::sqlite::{SqliteConnectOptions, SqlitePoolOptions, SqlitePool};
-use sqlx::{ConnectOptions, Row};
+use sqlx::sqlite::{
+    SqliteArguments, SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqlitePoolOptions, SqlitePool,
+    SqliteRow, SqliteSynchronous,
+};
+use sqlx::{Column, Connection, Row, Sqlite};
+use libsqlite3_sys as ffi;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
+use std::ptr;
 
-use crate::storage::{Storage, StorageError, StorageFormat};
+use crate::app::{Band, Frequency, Power, QslConfirmation, QslStatus, QslVia, Rst};
+use crate::storage::fields::{self, Comparison, FieldDefinition, FieldQuery, FieldType};
+use crate::storage::fsck::{Scan, Unreadable};
+use crate::storage::journal;
+use crate::storage::search::{self, SearchHit};
+use crate::storage::{JournalRecord, Revision, Storage, StorageError, StorageFormat};
 use crate::LogEntry;
 
+type SqliteQuery<'q> = sqlx::query::Query<'q, Sqlite, SqliteArguments<'q>>;
+
+/// Columns of `log_entries`, in the order `bind_entry` binds them
+const COLUMNS: &[&str] = &[
+    "id", "timestamp", "callsign", "frequency", "mode", "rst_sent", "rst_received",
+    "notes", "name", "qth", "state", "country", "dxcc", "band", "operator", "grid",
+    "power", "custom_fields",
+    "time_off", "submode", "freq_rx", "band_rx", "prop_mode", "sat_name", "sat_mode",
+    "cq_zone", "itu_zone", "cont", "county", "iota", "sota_ref", "pota_ref",
+    "srx", "stx", "contest_id",
+    "qsl_sent", "qsl_sent_date", "qsl_rcvd", "qsl_rcvd_date", "qsl_sent_via", "qsl_rcvd_via",
+    "lotw_qsl_sent", "lotw_qsl_sent_date", "lotw_qsl_rcvd", "lotw_qsl_rcvd_date",
+    "eqsl_qsl_sent", "eqsl_qsl_sent_date", "eqsl_qsl_rcvd", "eqsl_qsl_rcvd_date",
+    "station",
+    "sig", "sig_info", "my_sig", "my_sig_info",
+    "my_sota_ref",
+    "created_at", "updated_at",
+];
+
+/// Schema migrations applied on top of the original table. The number of
+/// migrations already applied is tracked in `PRAGMA user_version`.
+const MIGRATIONS: &[&str] = &[
+    // 1: first-class QSO detail, exchange and QSL fields
+    "
+    ALTER TABLE log_entries ADD COLUMN time_off TEXT;
+    ALTER TABLE log_entries ADD COLUMN submode TEXT;
+    ALTER TABLE log_entries ADD COLUMN freq_rx REAL;
+    ALTER TABLE log_entries ADD COLUMN band_rx TEXT;
+    ALTER TABLE log_entries ADD COLUMN prop_mode TEXT;
+    ALTER TABLE log_entries ADD COLUMN sat_name TEXT;
+    ALTER TABLE log_entries ADD COLUMN sat_mode TEXT;
+    ALTER TABLE log_entries ADD COLUMN cq_zone INTEGER;
+    ALTER TABLE log_entries ADD COLUMN itu_zone INTEGER;
+    ALTER TABLE log_entries ADD COLUMN cont TEXT;
+    ALTER TABLE log_entries ADD COLUMN county TEXT;
+    ALTER TABLE log_entries ADD COLUMN iota TEXT;
+    ALTER TABLE log_entries ADD COLUMN sota_ref TEXT;
+    ALTER TABLE log_entries ADD COLUMN pota_ref TEXT;
+    ALTER TABLE log_entries ADD COLUMN srx INTEGER;
+    ALTER TABLE log_entries ADD COLUMN stx INTEGER;
+    ALTER TABLE log_entries ADD COLUMN contest_id TEXT;
+    ALTER TABLE log_entries ADD COLUMN qsl_sent TEXT;
+    ALTER TABLE log_entries ADD COLUMN qsl_sent_date TEXT;
+    ALTER TABLE log_entries ADD COLUMN qsl_rcvd TEXT;
+    ALTER TABLE log_entries ADD COLUMN qsl_rcvd_date TEXT;
+    ALTER TABLE log_entries ADD COLUMN qsl_sent_via TEXT;
+    ALTER TABLE log_entries ADD COLUMN qsl_rcvd_via TEXT;
+    ALTER TABLE log_entries ADD COLUMN lotw_qsl_sent TEXT;
+    ALTER TABLE log_entries ADD COLUMN lotw_qsl_sent_date TEXT;
+    ALTER TABLE log_entries ADD COLUMN lotw_qsl_rcvd TEXT;
+    ALTER TABLE log_entries ADD COLUMN lotw_qsl_rcvd_date TEXT;
+    ALTER TABLE log_entries ADD COLUMN eqsl_qsl_sent TEXT;
+    ALTER TABLE log_entries ADD COLUMN eqsl_qsl_sent_date TEXT;
+    ALTER TABLE log_entries ADD COLUMN eqsl_qsl_rcvd TEXT;
+    ALTER TABLE log_entries ADD COLUMN eqsl_qsl_rcvd_date TEXT;
+    ",
+    // 2: station attribution for multi-operator logging
+    "
+    ALTER TABLE log_entries ADD COLUMN station TEXT;
+    ",
+    // 3: special interest activity references (POTA and friends)
+    "
+    ALTER TABLE log_entries ADD COLUMN sig TEXT;
+    ALTER TABLE log_entries ADD COLUMN sig_info TEXT;
+    ALTER TABLE log_entries ADD COLUMN my_sig TEXT;
+    ALTER TABLE log_entries ADD COLUMN my_sig_info TEXT;
+    ",
+    // 4: own summit for SOTA activations
+    "
+    ALTER TABLE log_entries ADD COLUMN my_sota_ref TEXT;
+    ",
+    // 5: operation journal for undo and redo
+    "
+    CREATE TABLE journal (
+        seq INTEGER PRIMARY KEY,
+        timestamp TEXT NOT NULL,
+        op TEXT NOT NULL,
+        entry_id TEXT NOT NULL,
+        before TEXT,
+        after TEXT,
+        undone INTEGER NOT NULL DEFAULT 0
+    );
+    ",
+    // 6: entry timestamps and per-QSO revision history
+    "
+    ALTER TABLE log_entries ADD COLUMN created_at TEXT;
+    ALTER TABLE log_entries ADD COLUMN updated_at TEXT;
+    CREATE TRIGGER update_log_entries_timestamp
+    AFTER UPDATE ON log_entries
+    WHEN NEW.updated_at IS OLD.updated_at
+    BEGIN
+        UPDATE log_entries SET updated_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')
+        WHERE id = NEW.id;
+    END;
+    CREATE TABLE revisions (
+        entry_id TEXT NOT NULL,
+        number INTEGER NOT NULL,
+        saved_at TEXT NOT NULL,
+        entry TEXT NOT NULL,
+        PRIMARY KEY (entry_id, number)
+    );
+    ",
+    // 7: indexes for lookups, dupe checks and sorting
+    "
+    CREATE INDEX idx_log_entries_callsign ON log_entries (callsign);
+    CREATE INDEX idx_log_entries_timestamp ON log_entries (timestamp);
+    CREATE INDEX idx_log_entries_band ON log_entries (band);
+    CREATE INDEX idx_log_entries_mode ON log_entries (mode);
+    ",
+    // 8: full-text index over the free-text fields, kept in step by triggers
+    // and keyed by the entry's rowid; custom fields are indexed by value.
+    // Migration 10 makes that rowid an explicit key.
+    "
+    CREATE VIRTUAL TABLE log_search USING fts5(
+        notes, name, qth, custom_fields,
+        tokenize = 'unicode61 remove_diacritics 0'
+    );
+    INSERT INTO log_search (rowid, notes, name, qth, custom_fields)
+    SELECT rowid, notes, name, qth,
+        (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(custom_fields) THEN custom_fields END))
+    FROM log_entries;
+    CREATE TRIGGER log_search_insert AFTER INSERT ON log_entries
+    BEGIN
+        INSERT INTO log_search (rowid, notes, name, qth, custom_fields)
+        VALUES (NEW.rowid, NEW.notes, NEW.name, NEW.qth,
+            (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END)));
+    END;
+    CREATE TRIGGER log_search_delete AFTER DELETE ON log_entries
+    BEGIN
+        DELETE FROM log_search WHERE rowid = OLD.rowid;
+    END;
+    CREATE TRIGGER log_search_update AFTER UPDATE OF notes, name, qth, custom_fields ON log_entries
+    BEGIN
+        DELETE FROM log_search WHERE rowid = OLD.rowid;
+        INSERT INTO log_search (rowid, notes, name, qth, custom_fields)
+        VALUES (NEW.rowid, NEW.notes, NEW.name, NEW.qth,
+            (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END)));
+    END;
+    ",
+    // 9: typed custom field definitions, and custom field values one row
+    // each, kept in step with the entries by triggers, for queries
+    "
+    CREATE TABLE field_definitions (
+        name TEXT PRIMARY KEY,
+        field_type TEXT NOT NULL,
+        options TEXT
+    );
+    CREATE TABLE custom_field_values (
+        entry_id TEXT NOT NULL,
+        name TEXT NOT NULL,
+        value TEXT,
+        PRIMARY KEY (entry_id, name)
+    );
+    CREATE INDEX idx_custom_field_values_lookup ON custom_field_values (name, value);
+    INSERT INTO custom_field_values (entry_id, name, value)
+    SELECT e.id, f.key, f.value
+    FROM log_entries e, json_each(CASE WHEN json_valid(e.custom_fields) THEN e.custom_fields END) f;
+    CREATE TRIGGER custom_field_values_insert AFTER INSERT ON log_entries
+    BEGIN
+        INSERT INTO custom_field_values (entry_id, name, value)
+        SELECT NEW.id, key, value FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END);
+    END;
+    CREATE TRIGGER custom_field_values_delete AFTER DELETE ON log_entries
+    BEGIN
+        DELETE FROM custom_field_values WHERE entry_id = OLD.id;
+    END;
+    CREATE TRIGGER custom_field_values_update AFTER UPDATE OF id, custom_fields ON log_entries
+    BEGIN
+        DELETE FROM custom_field_values WHERE entry_id = OLD.id;
+        INSERT INTO custom_field_values (entry_id, name, value)
+        SELECT NEW.id, key, value FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END);
+    END;
+    ",
+    // 10: an explicit integer key for entries, so VACUUM cannot renumber
+    // the rows the full-text index points at. The table is rebuilt with
+    // each entry keeping its rowid, and its indexes and triggers recreated.
+    "
+    CREATE TABLE log_entries_new (
+        entry_number INTEGER PRIMARY KEY,
+        id TEXT UNIQUE,
+        timestamp TEXT NOT NULL,
+        callsign TEXT NOT NULL,
+        frequency REAL NOT NULL,
+        mode TEXT NOT NULL,
+        rst_sent TEXT,
+        rst_received TEXT,
+        notes TEXT,
+        name TEXT,
+        qth TEXT,
+        state TEXT,
+        country TEXT,
+        dxcc INTEGER,
+        band TEXT,
+        operator TEXT,
+        grid TEXT,
+        power REAL,
+        custom_fields TEXT,
+        time_off TEXT,
+        submode TEXT,
+        freq_rx REAL,
+        band_rx TEXT,
+        prop_mode TEXT,
+        sat_name TEXT,
+        sat_mode TEXT,
+        cq_zone INTEGER,
+        itu_zone INTEGER,
+        cont TEXT,
+        county TEXT,
+        iota TEXT,
+        sota_ref TEXT,
+        pota_ref TEXT,
+        srx INTEGER,
+        stx INTEGER,
+        contest_id TEXT,
+        qsl_sent TEXT,
+        qsl_sent_date TEXT,
+        qsl_rcvd TEXT,
+        qsl_rcvd_date TEXT,
+        qsl_sent_via TEXT,
+        qsl_rcvd_via TEXT,
+        lotw_qsl_sent TEXT,
+        lotw_qsl_sent_date TEXT,
+        lotw_qsl_rcvd TEXT,
+        lotw_qsl_rcvd_date TEXT,
+        eqsl_qsl_sent TEXT,
+        eqsl_qsl_sent_date TEXT,
+        eqsl_qsl_rcvd TEXT,
+        eqsl_qsl_rcvd_date TEXT,
+        station TEXT,
+        sig TEXT,
+        sig_info TEXT,
+        my_sig TEXT,
+        my_sig_info TEXT,
+        my_sota_ref TEXT,
+        created_at TEXT,
+        updated_at TEXT
+    );
+    INSERT INTO log_entries_new SELECT rowid, * FROM log_entries;
+    DROP TABLE log_entries;
+    ALTER TABLE log_entries_new RENAME TO log_entries;
+    CREATE INDEX idx_log_entries_callsign ON log_entries (callsign);
+    CREATE INDEX idx_log_entries_timestamp ON log_entries (timestamp);
+    CREATE INDEX idx_log_entries_band ON log_entries (band);
+    CREATE INDEX idx_log_entries_mode ON log_entries (mode);
+    CREATE TRIGGER update_log_entries_timestamp
+    AFTER UPDATE ON log_entries
+    WHEN NEW.updated_at IS OLD.updated_at
+    BEGIN
+        UPDATE log_entries SET updated_at = strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')
+        WHERE id = NEW.id;
+    END;
+    CREATE TRIGGER log_search_insert AFTER INSERT ON log_entries
+    BEGIN
+        INSERT INTO log_search (rowid, notes, name, qth, custom_fields)
+        VALUES (NEW.rowid, NEW.notes, NEW.name, NEW.qth,
+            (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END)));
+    END;
+    CREATE TRIGGER log_search_delete AFTER DELETE ON log_entries
+    BEGIN
+        DELETE FROM log_search WHERE rowid = OLD.rowid;
+    END;
+    CREATE TRIGGER log_search_update AFTER UPDATE OF notes, name, qth, custom_fields ON log_entries
+    BEGIN
+        DELETE FROM log_search WHERE rowid = OLD.rowid;
+        INSERT INTO log_search (rowid, notes, name, qth, custom_fields)
+        VALUES (NEW.rowid, NEW.notes, NEW.name, NEW.qth,
+            (SELECT group_concat(value, ' ') FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END)));
+    END;
+    CREATE TRIGGER custom_field_values_insert AFTER INSERT ON log_entries
+    BEGIN
+        INSERT INTO custom_field_values (entry_id, name, value)
+        SELECT NEW.id, key, value FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END);
+    END;
+    CREATE TRIGGER custom_field_values_delete AFTER DELETE ON log_entries
+    BEGIN
+        DELETE FROM custom_field_values WHERE entry_id = OLD.id;
+    END;
+    CREATE TRIGGER custom_field_values_update AFTER UPDATE OF id, custom_fields ON log_entries
+    BEGIN
+        DELETE FROM custom_field_values WHERE entry_id = OLD.id;
+        INSERT INTO custom_field_values (entry_id, name, value)
+        SELECT NEW.id, key, value FROM json_each(CASE WHEN json_valid(NEW.custom_fields) THEN NEW.custom_fields END);
+    END;
+    ",
+];
+
+lazy_static! {
+    static ref INSERT_SQL: String = format!(
+        "INSERT INTO log_entries ({}) VALUES ({})",
+        COLUMNS.join(", "),
+        (1..=COLUMNS.len()).map(|i| format!("?{}", i)).collect::<Vec<_>>().join(", ")
+    );
+    static ref UPDATE_SQL: String = format!(
+        "UPDATE log_entries SET {} WHERE id = ?1",
+        COLUMNS.iter()
+            .enumerate()
+            .skip(1)
+            .map(|(i, column)| format!("{} = ?{}", column, i + 1))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    static ref UPSERT_SQL: String = format!(
+        "{} ON CONFLICT (id) DO UPDATE SET {}",
+        *INSERT_SQL,
+        COLUMNS.iter()
+            .skip(1)
+            .map(|column| format!("{} = excluded.{}", column, column))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+}
+
 pub struct SqliteStorage {
     pool: SqlitePool,
     path: PathBuf,
@@ -17,14 +341,27 @@ pub struct SqliteStorage {
 impl SqliteStorage {
     pub async fn new(path: &PathBuf) -> Result<Self, StorageError> {
         // Create SqliteConnectOptions and set create_if_missing to true
+        // Write-ahead logging lets readers carry on while a QSO is saved,
+        // and with it a commit only needs to sync at checkpoints
         let options = SqliteConnectOptions::new()
             .filename(path)
-            .create_if_missing(true);
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .synchronous(SqliteSynchronous::Normal);
 
         // Optionally, set additional options
         // For example, set logging level if needed
         // .log_statements(tracing::log::LevelFilter::Debug);
 
+        // Bring the schema up to date on a single connection before the pool
+        // opens any. A pooled connection that cached an older schema would
+        // describe `SELECT *` with a stale column list after a migration.
+        let mut connection = SqliteConnection::connect_with(&options)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        initialize_db(&mut connection, MIGRATIONS).await?;
+        connection.close().await.map_err(StorageError::Sqlx)?;
+
         // Establish the connection pool with the options
         let pool = SqlitePoolOptions::new()
             .max_connections(5) // Adjust as needed
@@ -32,9 +369,6 @@ impl SqliteStorage {
             .await
             .map_err(StorageError::Sqlx)?;
 
-        // Initialize database schema if necessary
-        initialize_db(&pool).await?;
-
         Ok(Self {
             pool,
             path: path.clone(),
@@ -42,8 +376,89 @@ impl SqliteStorage {
     }
 }
 
-async fn initialize_db(pool: &SqlitePool) -> Result<(), StorageError> {
-    // Create the table with the updated schema
+/// Copy the database opened on `source` into a new database at `dest` with
+/// SQLite's online backup API
+///
+/// # Safety
+/// `source` must be an open connection that nothing else uses until this
+/// returns.
+unsafe fn online_backup(source: *mut ffi::sqlite3, dest: &CStr) -> Result<(), StorageError> {
+    let mut target = ptr::null_mut();
+    let mut code = ffi::sqlite3_open(dest.as_ptr(), &mut target);
+    if code == ffi::SQLITE_OK {
+        let backup = ffi::sqlite3_backup_init(target, c"main".as_ptr(), source, c"main".as_ptr());
+        if backup.is_null() {
+            code = ffi::sqlite3_errcode(target);
+        } else {
+            // All pages in one step, so the copy is a single snapshot
+            code = ffi::sqlite3_backup_step(backup, -1);
+            // Finishing reports errors the step left behind, e.g. out of memory
+            let finished = ffi::sqlite3_backup_finish(backup);
+            if code == ffi::SQLITE_DONE {
+                code = finished;
+            }
+        }
+    }
+    // Closing writes out what is left of the copy, so it can fail too
+    let closed = ffi::sqlite3_close(target);
+    if matches!(code, ffi::SQLITE_OK | ffi::SQLITE_DONE) {
+        code = closed;
+    }
+    match code {
+        ffi::SQLITE_OK | ffi::SQLITE_DONE => Ok(()),
+        _ => {
+            let message = CStr::from_ptr(ffi::sqlite3_errstr(code)).to_string_lossy();
+            Err(StorageError::Backend(format!("SQLite backup failed: {}", message)))
+        }
+    }
+}
+
+/// Write a journal record, replacing any with the same or a later sequence
+/// number and dropping the oldest beyond the journal's limit
+async fn insert_journal(connection: &mut SqliteConnection, record: &JournalRecord) -> Result<(), StorageError> {
+    let image = |entry: &Option<LogEntry>| entry.as_ref().map(serde_json::to_string).transpose();
+    sqlx::query("DELETE FROM journal WHERE seq >= ?1")
+        .bind(record.seq as i64)
+        .execute(&mut *connection)
+        .await
+        .map_err(StorageError::Sqlx)?;
+    sqlx::query(
+        "INSERT INTO journal (seq, timestamp, op, entry_id, before, after, undone) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )
+        .bind(record.seq as i64)
+        .bind(record.timestamp)
+        .bind(record.op.as_str())
+        .bind(&record.entry_id)
+        .bind(image(&record.before)?)
+        .bind(image(&record.after)?)
+        .bind(record.undone)
+        .execute(&mut *connection)
+        .await
+        .map_err(StorageError::Sqlx)?;
+    // Sequence numbers have no gaps, so this keeps the latest records
+    sqlx::query("DELETE FROM journal WHERE seq <= ?1")
+        .bind(record.seq as i64 - journal::MAX_RECORDS as i64)
+        .execute(&mut *connection)
+        .await
+        .map_err(StorageError::Sqlx)?;
+    Ok(())
+}
+
+async fn insert_revision(connection: &mut SqliteConnection, revision: &Revision) -> Result<(), StorageError> {
+    sqlx::query("INSERT INTO revisions (entry_id, number, saved_at, entry) VALUES (?1, ?2, ?3, ?4)")
+        .bind(&revision.entry_id)
+        .bind(revision.number as i64)
+        .bind(revision.saved_at)
+        .bind(serde_json::to_string(&revision.entry)?)
+        .execute(&mut *connection)
+        .await
+        .map_err(StorageError::Sqlx)?;
+    Ok(())
+}
+
+/// Create the table and apply `migrations` to it
+async fn initialize_db(connection: &mut SqliteConnection, migrations: &[&str]) -> Result<(), StorageError> {
+    // Create the table with the original schema; later columns come from MIGRATIONS
     sqlx::query(
         "
         CREATE TABLE IF NOT EXISTS log_entries (
@@ -68,118 +483,296 @@ async fn initialize_db(pool: &SqlitePool) -> Result<(), StorageError> {
         )
         ",
     )
-    .execute(pool)
+    .execute(&mut *connection)
     .await
     .map_err(StorageError::Sqlx)?;
-    Ok(())
+
+    migrate(connection, migrations).await
 }
 
+async fn migrate(connection: &mut SqliteConnection, migrations: &[&str]) -> Result<(), StorageError> {
+    let version: i64 = sqlx::query_scalar("PRAGMA user_version")
+        .fetch_one(&mut *connection)
+        .await
+        .map_err(StorageError::Sqlx)?;
 
-#[async_trait]
-impl Storage for SqliteStorage {
-    async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError> {
-        let row = sqlx::query("SELECT * FROM log_entries WHERE id = ?1")
-            .bind(id)
-            .fetch_optional(&self.pool)
+    for (index, migration) in migrations.iter().enumerate().skip(version.max(0) as usize) {
+        let mut tx = connection.begin().await.map_err(StorageError::Sqlx)?;
+        sqlx::raw_sql(migration)
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| StorageError::Migration(format!("migration {} failed: {}", index + 1, e)))?;
+        // PRAGMA does not accept bound parameters
+        sqlx::raw_sql(&format!("PRAGMA user_version = {}", index + 1))
+            .execute(&mut *tx)
             .await
             .map_err(StorageError::Sqlx)?;
+        tx.commit().await.map_err(StorageError::Sqlx)?;
+    }
+    Ok(())
+}
 
-        if let Some(row) = row {
-            let id: String = row.try_get("id")?;
-            let timestamp_str: String = row.try_get("timestamp")?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .map_err(|e| StorageError::ParseError(e.to_string()))?
-                .with_timezone(&Utc);
-            let callsign: String = row.try_get("callsign")?;
-            let frequency: f64 = row.try_get("frequency")?;
-            let mode: String = row.try_get("mode")?;
-            let rst_sent: Option<String> = row.try_get("rst_sent")?;
-            let rst_received: Option<String> = row.try_get("rst_received")?;
-            let notes: Option<String> = row.try_get("notes")?;
-            let name: Option<String> = row.try_get("name")?;
-            let qth: Option<String> = row.try_get("qth")?;
-            let state: Option<String> = row.try_get("state")?;
-            let country: Option<String> = row.try_get("country")?;
-            let dxcc: Option<i64> = row.try_get("dxcc")?;
-            let band: Option<String> = row.try_get("band")?;
-            let operator: Option<String> = row.try_get("operator")?;
-            let grid: Option<String> = row.try_get("grid")?;
-            let power: Option<f32> = row.try_get("power")?;
-            let custom_fields_json: Option<String> = row.try_get("custom_fields")?;
-            let custom_fields: HashMap<String, String> = custom_fields_json
-                .and_then(|s| serde_json::from_str(&s).ok())
-                .unwrap_or_default();
-
-            Ok(Some(LogEntry {
-                id,
-                timestamp,
-                callsign,
-                frequency,
-                mode,
-                rst_sent,
-                rst_received,
-                notes,
-                name,
-                qth,
-                state,
-                country,
-                dxcc: dxcc.map(|v| v as u32),
-                band,
-                operator,
-                grid,
-                power,
-                custom_fields,
-            }))
-        } else {
-            Ok(None)
-        }
+fn journal_from_row(row: &SqliteRow) -> Result<JournalRecord, StorageError> {
+    let image = |column: &str| -> Result<Option<LogEntry>, StorageError> {
+        let json: Option<String> = row.try_get(column)?;
+        Ok(json.map(|json| serde_json::from_str(&json)).transpose()?)
+    };
+    let op: String = row.try_get("op")?;
+    Ok(JournalRecord {
+        seq: row.try_get::<i64, _>("seq")? as u64,
+        timestamp: row.try_get("timestamp")?,
+        op: serde_json::from_value(serde_json::Value::String(op))?,
+        entry_id: row.try_get("entry_id")?,
+        before: image("before")?,
+        after: image("after")?,
+        undone: row.try_get("undone")?,
+    })
+}
+
+fn revision_from_row(row: &SqliteRow) -> Result<Revision, StorageError> {
+    let entry: String = row.try_get("entry")?;
+    Ok(Revision {
+        entry_id: row.try_get("entry_id")?,
+        number: row.try_get::<i64, _>("number")? as u32,
+        saved_at: row.try_get("saved_at")?,
+        entry: serde_json::from_str(&entry)?,
+    })
+}
+
+fn definition_from_row(row: &SqliteRow) -> Result<FieldDefinition, StorageError> {
+    let field_type: String = row.try_get("field_type")?;
+    let options: Option<String> = row.try_get("options")?;
+    Ok(FieldDefinition {
+        name: row.try_get("name")?,
+        field_type: FieldType::parse(&field_type)
+            .ok_or_else(|| StorageError::Backend(format!("Unknown field type '{}'", field_type)))?,
+        options: options.map(|o| serde_json::from_str(&o)).transpose()?.unwrap_or_default(),
+    })
+}
+
+/// A custom field value in SQL, made to compare the way `FieldType::compare`
+/// does
+fn typed(field_type: FieldType, value: &str) -> String {
+    match field_type {
+        FieldType::Number => format!("CAST({} AS REAL)", value),
+        FieldType::Text | FieldType::Enum => format!("{} COLLATE NOCASE", value),
+        FieldType::Date | FieldType::Boolean => value.to_string(),
     }
+}
 
-    async fn update_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        sqlx::query(
-            "
-            UPDATE log_entries SET
-                timestamp = ?2,
-                callsign = ?3,
-                frequency = ?4,
-                mode = ?5,
-                rst_sent = ?6,
-                rst_received = ?7,
-                notes = ?8,
-                name = ?9,
-                qth = ?10,
-                state = ?11,
-                country = ?12,
-                dxcc = ?13,
-                band = ?14,
-                operator = ?15,
-                grid = ?16,
-                power = ?17,
-                custom_fields = ?18
-            WHERE id = ?1
-            ",
-        )
+fn status_to_sql(status: Option<QslStatus>) -> Option<&'static str> {
+    status.map(|s| s.as_adif())
+}
+
+fn via_to_sql(via: Option<QslVia>) -> Option<&'static str> {
+    via.map(|v| v.as_adif())
+}
+
+fn date_to_sql(date: Option<NaiveDate>) -> Option<String> {
+    date.map(|d| d.format("%Y-%m-%d").to_string())
+}
+
+/// Bind every column of `entry` in `COLUMNS` order
+fn bind_entry<'q>(query: SqliteQuery<'q>, entry: &'q LogEntry) -> SqliteQuery<'q> {
+    let query = query
         .bind(&entry.id)
         .bind(entry.timestamp.to_rfc3339())
-        .bind(&entry.callsign)
-        .bind(entry.frequency)
-        .bind(&entry.mode)
-        .bind(&entry.rst_sent)
-        .bind(&entry.rst_received)
+        .bind(entry.callsign.as_str())
+        .bind(entry.frequency.mhz())
+        .bind(entry.mode.as_str())
+        .bind(entry.rst_sent.as_ref().map(Rst::to_string))
+        .bind(entry.rst_received.as_ref().map(Rst::to_string))
         .bind(&entry.notes)
         .bind(&entry.name)
         .bind(&entry.qth)
         .bind(&entry.state)
         .bind(&entry.country)
         .bind(entry.dxcc)
-        .bind(&entry.band)
+        .bind(entry.band.as_deref())
         .bind(&entry.operator)
         .bind(&entry.grid)
-        .bind(entry.power)
+        .bind(entry.power.map(|p| p.watts()))
         .bind(serde_json::to_string(&entry.custom_fields).unwrap_or_else(|_| "{}".to_string()))
-        .execute(&self.pool)
-        .await
-        .map_err(StorageError::Sqlx)?;
+        .bind(entry.time_off.map(|t| t.to_rfc3339()))
+        .bind(&entry.submode)
+        .bind(entry.freq_rx.map(|f| f.mhz()))
+        .bind(entry.band_rx.as_deref())
+        .bind(&entry.prop_mode)
+        .bind(&entry.sat_name)
+        .bind(&entry.sat_mode)
+        .bind(entry.cq_zone)
+        .bind(entry.itu_zone)
+        .bind(&entry.cont)
+        .bind(&entry.county)
+        .bind(&entry.iota)
+        .bind(&entry.sota_ref)
+        .bind(&entry.pota_ref)
+        .bind(entry.srx)
+        .bind(entry.stx)
+        .bind(&entry.contest_id);
+
+    let query = bind_confirmation(query, &entry.qsl)
+        .bind(via_to_sql(entry.qsl_sent_via))
+        .bind(via_to_sql(entry.qsl_rcvd_via));
+    let query = bind_confirmation(query, &entry.lotw);
+    bind_confirmation(query, &entry.eqsl)
+        .bind(&entry.station)
+        .bind(&entry.sig)
+        .bind(&entry.sig_info)
+        .bind(&entry.my_sig)
+        .bind(&entry.my_sig_info)
+        .bind(&entry.my_sota_ref)
+        .bind(entry.created_at.map(|t| t.to_rfc3339()))
+        .bind(entry.updated_at.map(|t| t.to_rfc3339()))
+}
+
+fn bind_confirmation<'q>(query: SqliteQuery<'q>, confirmation: &QslConfirmation) -> SqliteQuery<'q> {
+    query
+        .bind(status_to_sql(confirmation.sent))
+        .bind(date_to_sql(confirmation.sent_date))
+        .bind(status_to_sql(confirmation.received))
+        .bind(date_to_sql(confirmation.received_date))
+}
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, StorageError> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| StorageError::ParseError(e.to_string()))
+}
+
+fn parse_date(value: Option<String>) -> Result<Option<NaiveDate>, StorageError> {
+    value
+        .map(|d| NaiveDate::parse_from_str(&d, "%Y-%m-%d"))
+        .transpose()
+        .map_err(|e| StorageError::ParseError(e.to_string()))
+}
+
+fn confirmation_from_row(row: &SqliteRow, prefix: &str) -> Result<QslConfirmation, StorageError> {
+    let status = |column: &str| -> Result<Option<QslStatus>, StorageError> {
+        let value: Option<String> = row.try_get(format!("{}{}", prefix, column).as_str())?;
+        Ok(value.as_deref().and_then(QslStatus::from_adif))
+    };
+    let date = |column: &str| -> Result<Option<NaiveDate>, StorageError> {
+        parse_date(row.try_get(format!("{}{}", prefix, column).as_str())?)
+    };
+
+    Ok(QslConfirmation {
+        sent: status("qsl_sent")?,
+        sent_date: date("qsl_sent_date")?,
+        received: status("qsl_rcvd")?,
+        received_date: date("qsl_rcvd_date")?,
+    })
+}
+
+fn entry_from_row(row: &SqliteRow) -> Result<LogEntry, StorageError> {
+    let timestamp_str: String = row.try_get("timestamp")?;
+    let time_off: Option<String> = row.try_get("time_off")?;
+    let created_at: Option<String> = row.try_get("created_at")?;
+    let updated_at: Option<String> = row.try_get("updated_at")?;
+    let dxcc: Option<i64> = row.try_get("dxcc")?;
+    let cq_zone: Option<i64> = row.try_get("cq_zone")?;
+    let itu_zone: Option<i64> = row.try_get("itu_zone")?;
+    let srx: Option<i64> = row.try_get("srx")?;
+    let stx: Option<i64> = row.try_get("stx")?;
+    let qsl_sent_via: Option<String> = row.try_get("qsl_sent_via")?;
+    let qsl_rcvd_via: Option<String> = row.try_get("qsl_rcvd_via")?;
+    let custom_fields_json: Option<String> = row.try_get("custom_fields")?;
+    let custom_fields: HashMap<String, String> = custom_fields_json
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+
+    let frequency = |mhz: f64| Frequency::from_mhz(mhz).map_err(|e| StorageError::Validation(e.to_string()));
+    let power = |watts: f64| Power::from_watts(watts).map_err(|e| StorageError::Validation(e.to_string()));
+
+    Ok(LogEntry {
+        id: row.try_get("id")?,
+        timestamp: parse_timestamp(&timestamp_str)?,
+        callsign: row.try_get::<String, _>("callsign")?.into(),
+        frequency: frequency(row.try_get("frequency")?)?,
+        mode: row.try_get::<String, _>("mode")?.into(),
+        rst_sent: row.try_get::<Option<String>, _>("rst_sent")?.map(Rst::from),
+        rst_received: row.try_get::<Option<String>, _>("rst_received")?.map(Rst::from),
+        notes: row.try_get("notes")?,
+        name: row.try_get("name")?,
+        qth: row.try_get("qth")?,
+        state: row.try_get("state")?,
+        country: row.try_get("country")?,
+        dxcc: dxcc.map(|v| v as u32),
+        band: row.try_get::<Option<String>, _>("band")?.map(Band::from),
+        operator: row.try_get("operator")?,
+        grid: row.try_get("grid")?,
+        power: row.try_get::<Option<f64>, _>("power")?.map(power).transpose()?,
+        time_off: time_off.as_deref().map(parse_timestamp).transpose()?,
+        submode: row.try_get("submode")?,
+        freq_rx: row.try_get::<Option<f64>, _>("freq_rx")?.map(frequency).transpose()?,
+        band_rx: row.try_get::<Option<String>, _>("band_rx")?.map(Band::from),
+        prop_mode: row.try_get("prop_mode")?,
+        sat_name: row.try_get("sat_name")?,
+        sat_mode: row.try_get("sat_mode")?,
+        cq_zone: cq_zone.map(|v| v as u32),
+        itu_zone: itu_zone.map(|v| v as u32),
+        cont: row.try_get("cont")?,
+        county: row.try_get("county")?,
+        iota: row.try_get("iota")?,
+        sota_ref: row.try_get("sota_ref")?,
+        pota_ref: row.try_get("pota_ref")?,
+        srx: srx.map(|v| v as u32),
+        stx: stx.map(|v| v as u32),
+        contest_id: row.try_get("contest_id")?,
+        qsl: confirmation_from_row(row, "")?,
+        qsl_sent_via: qsl_sent_via.as_deref().and_then(QslVia::from_adif),
+        qsl_rcvd_via: qsl_rcvd_via.as_deref().and_then(QslVia::from_adif),
+        lotw: confirmation_from_row(row, "lotw_")?,
+        eqsl: confirmation_from_row(row, "eqsl_")?,
+        station: row.try_get("station")?,
+        sig: row.try_get("sig")?,
+        sig_info: row.try_get("sig_info")?,
+        my_sig: row.try_get("my_sig")?,
+        my_sig_info: row.try_get("my_sig_info")?,
+        my_sota_ref: row.try_get("my_sota_ref")?,
+        created_at: created_at.as_deref().map(parse_timestamp).transpose()?,
+        updated_at: updated_at.as_deref().map(parse_timestamp).transpose()?,
+        custom_fields,
+    })
+}
+
+/// A row's columns as JSON, for keeping a row that does not read as an entry
+fn row_to_json(row: &SqliteRow) -> String {
+    let columns: serde_json::Map<String, serde_json::Value> = row.columns()
+        .iter()
+        .filter(|column| column.name() != "row_number")
+        .map(|column| {
+            let i = column.ordinal();
+            let value = row.try_get::<Option<String>, _>(i).map(serde_json::Value::from)
+                .or_else(|_| row.try_get::<Option<i64>, _>(i).map(serde_json::Value::from))
+                .or_else(|_| row.try_get::<Option<f64>, _>(i).map(serde_json::Value::from))
+                .unwrap_or(serde_json::Value::Null);
+            (column.name().to_string(), value)
+        })
+        .collect();
+    serde_json::Value::Object(columns).to_string()
+}
+
+#[async_trait]
+impl Storage for SqliteStorage {
+    async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError> {
+        let row = sqlx::query("SELECT * FROM log_entries WHERE id = ?1")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+
+        row.as_ref().map(entry_from_row).transpose()
+    }
+
+    async fn update_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
+        let result = bind_entry(sqlx::query(UPDATE_SQL.as_str()), &entry)
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(entry.id));
+        }
         Ok(())
     }
 
@@ -195,158 +788,127 @@ impl Storage for SqliteStorage {
         StorageFormat::Sqlite
     }
     async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        // Check if the entry exists
-        let existing = sqlx::query_scalar::<_, i64>("SELECT COUNT(1) FROM log_entries WHERE id = ?1")
-            .bind(&entry.id)
-            .fetch_one(&self.pool)
-            .await
-            .map_err(StorageError::Sqlx)?;
-
-        if existing > 0 {
-            // Update existing entry
-            sqlx::query(
-                "
-                UPDATE log_entries SET
-                    timestamp = ?2,
-                    callsign = ?3,
-                    frequency = ?4,
-                    mode = ?5,
-                    rst_sent = ?6,
-                    rst_received = ?7,
-                    notes = ?8,
-                    name = ?9,
-                    qth = ?10,
-                    state = ?11,
-                    country = ?12,
-                    dxcc = ?13,
-                    band = ?14,
-                    operator = ?15,
-                    grid = ?16,
-                    power = ?17,
-                    custom_fields = ?18
-                WHERE id = ?1
-                ",
-            )
-            .bind(&entry.id)
-            .bind(entry.timestamp.to_rfc3339())
-            .bind(&entry.callsign)
-            .bind(entry.frequency)
-            .bind(&entry.mode)
-            .bind(&entry.rst_sent)
-            .bind(&entry.rst_received)
-            .bind(&entry.notes)
-            .bind(&entry.name)
-            .bind(&entry.qth)
-            .bind(&entry.state)
-            .bind(&entry.country)
-            .bind(entry.dxcc)
-            .bind(&entry.band)
-            .bind(&entry.operator)
-            .bind(&entry.grid)
-            .bind(entry.power)
-            .bind(serde_json::to_string(&entry.custom_fields).unwrap_or_else(|_| "{}".to_string()))
+        bind_entry(sqlx::query(UPSERT_SQL.as_str()), &entry)
             .execute(&self.pool)
             .await
             .map_err(StorageError::Sqlx)?;
-        } else {
-            // Insert new entry
-            sqlx::query(
-                "
-                INSERT INTO log_entries (
-                    id, timestamp, callsign, frequency, mode, rst_sent, rst_received,
-                    notes, name, qth, state, country, dxcc, band, operator, grid,
-                    power, custom_fields
-                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
-                ",
-            )
-            .bind(&entry.id)
-            .bind(entry.timestamp.to_rfc3339())
-            .bind(&entry.callsign)
-            .bind(entry.frequency)
-            .bind(&entry.mode)
-            .bind(&entry.rst_sent)
-            .bind(&entry.rst_received)
-            .bind(&entry.notes)
-            .bind(&entry.name)
-            .bind(&entry.qth)
-            .bind(&entry.state)
-            .bind(&entry.country)
-            .bind(entry.dxcc)
-            .bind(&entry.band)
-            .bind(&entry.operator)
-            .bind(&entry.grid)
-            .bind(entry.power)
-            .bind(serde_json::to_string(&entry.custom_fields).unwrap_or_else(|_| "{}".to_string()))
-            .execute(&self.pool)
+        Ok(())
+    }
+
+    async fn replace_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await.map_err(StorageError::Sqlx)?;
+        sqlx::query("DELETE FROM log_entries")
+            .execute(&mut *tx)
             .await
             .map_err(StorageError::Sqlx)?;
+        for entry in &entries {
+            bind_entry(sqlx::query(UPSERT_SQL.as_str()), entry)
+                .execute(&mut *tx)
+                .await
+                .map_err(StorageError::Sqlx)?;
         }
-        Ok(())
+        tx.commit().await.map_err(StorageError::Sqlx)
     }
-    
+
+    async fn save_batch(
+        &mut self,
+        entries: Vec<LogEntry>,
+        revisions: Vec<Revision>,
+        records: Vec<JournalRecord>,
+    ) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await.map_err(StorageError::Sqlx)?;
+        for revision in &revisions {
+            insert_revision(&mut tx, revision).await?;
+        }
+        for entry in &entries {
+            bind_entry(sqlx::query(UPSERT_SQL.as_str()), entry)
+                .execute(&mut *tx)
+                .await
+                .map_err(StorageError::Sqlx)?;
+        }
+        for record in &records {
+            insert_journal(&mut tx, record).await?;
+        }
+        tx.commit().await.map_err(StorageError::Sqlx)
+    }
+
+    async fn save_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await.map_err(StorageError::Sqlx)?;
+        for entry in &entries {
+            bind_entry(sqlx::query(UPSERT_SQL.as_str()), entry)
+                .execute(&mut *tx)
+                .await
+                .map_err(StorageError::Sqlx)?;
+        }
+        tx.commit().await.map_err(StorageError::Sqlx)
+    }
+
     async fn list_entries(&self) -> Result<Vec<LogEntry>, StorageError> {
         let rows = sqlx::query("SELECT * FROM log_entries")
             .fetch_all(&self.pool)
             .await
             .map_err(StorageError::Sqlx)?;
 
-        let mut entries = Vec::new();
-        for row in rows {
-            let id: String = row.try_get("id")?;
-            let timestamp_str: String = row.try_get("timestamp")?;
-            let timestamp = DateTime::parse_from_rfc3339(&timestamp_str)
-                .map_err(|e| StorageError::ParseError(e.to_string()))?
-                .with_timezone(&Utc);
-            let callsign: String = row.try_get("callsign")?;
-            let frequency: f64 = row.try_get("frequency")?;
-            let mode: String = row.try_get("mode")?;
-            let rst_sent: Option<String> = row.try_get("rst_sent")?;
-            let rst_received: Option<String> = row.try_get("rst_received")?;
-            let notes: Option<String> = row.try_get("notes")?;
-            let name: Option<String> = row.try_get("name")?;
-            let qth: Option<String> = row.try_get("qth")?;
-            let state: Option<String> = row.try_get("state")?;
-            let country: Option<String> = row.try_get("country")?;
-            let dxcc: Option<i64> = row.try_get("dxcc")?;
-            let band: Option<String> = row.try_get("band")?;
-            let operator: Option<String> = row.try_get("operator")?;
-            let grid: Option<String> = row.try_get("grid")?;
-            let power: Option<f32> = row.try_get("power")?;
-            let custom_fields_json: Option<String> = row.try_get("custom_fields")?;
-            let custom_fields: HashMap<String, String> = custom_fields_json
-                .and_then(|s| serde_json::from_str(&s).ok())
-                .unwrap_or_default();
-
-            entries.push(LogEntry {
-                id,
-                timestamp,
-                callsign,
-                frequency,
-                mode,
-                rst_sent,
-                rst_received,
-                notes,
-                name,
-                qth,
-                state,
-                country,
-                dxcc: dxcc.map(|v| v as u32),
-                band,
-                operator,
-                grid,
-                power,
-                custom_fields,
-            });
+        rows.iter().map(entry_from_row).collect()
+    }
+
+    async fn scan(&self) -> Result<Scan, StorageError> {
+        let rows = sqlx::query("SELECT rowid AS row_number, * FROM log_entries ORDER BY rowid")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+
+        let mut scan = Scan::default();
+        for row in &rows {
+            match entry_from_row(row) {
+                Ok(entry) => scan.entries.push(entry),
+                Err(e) => scan.unreadable.push(Unreadable {
+                    location: format!("row {}", row.try_get::<i64, _>("row_number")?),
+                    error: e.to_string(),
+                    raw: row_to_json(row),
+                }),
+            }
         }
-        Ok(entries)
+        Ok(scan)
+    }
+
+    async fn search_text(&self, query: &str) -> Result<Vec<SearchHit>, StorageError> {
+        let query = search::Query::parse(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        let rows = sqlx::query(
+            "SELECT e.*, bm25(log_search) AS score FROM log_search
+             JOIN log_entries e ON e.rowid = log_search.rowid
+             WHERE log_search MATCH ?1
+             ORDER BY score, e.timestamp DESC",
+        )
+        .bind(query.to_fts())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(StorageError::Sqlx)?;
+
+        // bm25 is lower for better matches; the index finds the entries and
+        // ranks them, and the matched words are picked out the usual way
+        let mut hits = Vec::new();
+        for row in &rows {
+            let entry = entry_from_row(row)?;
+            let score: f64 = row.try_get("score")?;
+            let matches = search::match_entry(&query, &entry).map(|hit| hit.matches).unwrap_or_default();
+            hits.push(SearchHit { entry, score: -score, matches });
+        }
+        Ok(hits)
     }
 
     async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError> {
-        sqlx::query("DELETE FROM log_entries WHERE id = ?1")
+        let result = sqlx::query("DELETE FROM log_entries WHERE id = ?1")
             .bind(id)
             .execute(&self.pool)
             .await
             .map_err(StorageError::Sqlx)?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(id.to_string()));
+        }
         Ok(())
     }
 
@@ -363,37 +925,181 @@ impl Storage for SqliteStorage {
         }
 
         // Insert new entry
+        bind_entry(sqlx::query(INSERT_SQL.as_str()), &entry)
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+
+        Ok(())
+    }
+
+    async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError> {
+        let rows = sqlx::query("SELECT * FROM journal ORDER BY seq")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        rows.iter().map(journal_from_row).collect()
+    }
+
+    async fn append_journal(&mut self, record: JournalRecord) -> Result<(), StorageError> {
+        let mut tx = self.pool.begin().await.map_err(StorageError::Sqlx)?;
+        insert_journal(&mut tx, &record).await?;
+        tx.commit().await.map_err(StorageError::Sqlx)
+    }
+
+    async fn mark_journal(&mut self, seq: u64, undone: bool) -> Result<(), StorageError> {
+        let result = sqlx::query("UPDATE journal SET undone = ?1 WHERE seq = ?2")
+            .bind(undone)
+            .bind(seq as i64)
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(format!("journal record {}", seq)));
+        }
+        Ok(())
+    }
+
+    async fn last_seq(&self) -> Result<u64, StorageError> {
+        let seq: Option<i64> = sqlx::query_scalar("SELECT MAX(seq) FROM journal WHERE undone = 0")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        Ok(seq.unwrap_or(0) as u64)
+    }
+
+    async fn journal_record(&self, seq: u64) -> Result<Option<JournalRecord>, StorageError> {
+        let row = sqlx::query("SELECT * FROM journal WHERE seq = ?1")
+            .bind(seq as i64)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        row.as_ref().map(journal_from_row).transpose()
+    }
+
+    async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError> {
+        let rows = sqlx::query("SELECT * FROM revisions WHERE entry_id = ?1 ORDER BY number")
+            .bind(id)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        rows.iter().map(revision_from_row).collect()
+    }
+
+    async fn add_revision(&mut self, revision: Revision) -> Result<(), StorageError> {
+        let mut connection = self.pool.acquire().await.map_err(StorageError::Sqlx)?;
+        insert_revision(&mut connection, &revision).await
+    }
+
+    async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError> {
+        let rows = sqlx::query("SELECT * FROM field_definitions ORDER BY rowid")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        rows.iter().map(definition_from_row).collect()
+    }
+
+    async fn define_field(&mut self, definition: FieldDefinition) -> Result<(), StorageError> {
         sqlx::query(
-            "
-            INSERT INTO log_entries (
-                id, timestamp, callsign, frequency, mode, rst_sent, rst_received,
-                notes, name, qth, state, country, dxcc, band, operator, grid,
-                power, custom_fields
-            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18)
-            ",
+            "INSERT INTO field_definitions (name, field_type, options) VALUES (?1, ?2, ?3)
+             ON CONFLICT (name) DO UPDATE SET field_type = excluded.field_type, options = excluded.options",
         )
-        .bind(&entry.id)
-        .bind(entry.timestamp.to_rfc3339())
-        .bind(&entry.callsign)
-        .bind(entry.frequency)
-        .bind(&entry.mode)
-        .bind(&entry.rst_sent)
-        .bind(&entry.rst_received)
-        .bind(&entry.notes)
-        .bind(&entry.name)
-        .bind(&entry.qth)
-        .bind(&entry.state)
-        .bind(&entry.country)
-        .bind(entry.dxcc)
-        .bind(&entry.band)
-        .bind(&entry.operator)
-        .bind(&entry.grid)
-        .bind(entry.power)
-        .bind(serde_json::to_string(&entry.custom_fields).unwrap_or_else(|_| "{}".to_string()))
-        .execute(&self.pool)
-        .await
-        .map_err(StorageError::Sqlx)?;
+            .bind(&definition.name)
+            .bind(definition.field_type.as_str())
+            .bind(serde_json::to_string(&definition.options)?)
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        Ok(())
+    }
+
+    async fn remove_field(&mut self, name: &str) -> Result<(), StorageError> {
+        let result = sqlx::query("DELETE FROM field_definitions WHERE name = ?1")
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        if result.rows_affected() == 0 {
+            return Err(StorageError::NotFound(format!("field {}", name)));
+        }
+        Ok(())
+    }
+
+    async fn query_fields(&self, query: &FieldQuery) -> Result<Vec<LogEntry>, StorageError> {
+        let definitions = self.field_definitions().await?;
+        let filters = query.resolve(&definitions)?;
+
+        // Each condition looks its field up in custom_field_values, so the
+        // (name, value) index does the selecting
+        let mut sql = String::from("SELECT e.* FROM log_entries e");
+        let mut binds: Vec<&str> = Vec::new();
+        for (i, (field_type, filter)) in filters.iter().enumerate() {
+            let name = binds.len() + 1;
+            let value = name + 1;
+            let condition = match filter.comparison {
+                Comparison::Contains => format!("instr(lower(v.value), lower(?{})) > 0", value),
+                comparison => {
+                    let op = match comparison {
+                        Comparison::Equal => "=",
+                        Comparison::NotEqual => "<>",
+                        Comparison::Less => "<",
+                        Comparison::LessOrEqual => "<=",
+                        Comparison::Greater => ">",
+                        _ => ">=",
+                    };
+                    format!("{} {} {}", typed(*field_type, "v.value"), op, typed(*field_type, &format!("?{}", value)))
+                }
+            };
+            sql.push_str(if i == 0 { " WHERE " } else { " AND " });
+            sql.push_str(&format!(
+                "EXISTS (SELECT 1 FROM custom_field_values v WHERE v.entry_id = e.id AND v.name = ?{} AND {})",
+                name, condition,
+            ));
+            binds.push(&filter.field);
+            binds.push(&filter.value);
+        }
+
+        sql.push_str(" ORDER BY ");
+        if let Some(field) = &query.sort {
+            binds.push(field);
+            let value = format!(
+                "(SELECT v.value FROM custom_field_values v WHERE v.entry_id = e.id AND v.name = ?{})",
+                binds.len(),
+            );
+            let field_type = fields::type_of(&definitions, field);
+            sql.push_str(&format!(
+                "{}{} NULLS LAST, ",
+                typed(field_type, &value),
+                if query.descending { " DESC" } else { "" },
+            ));
+        }
+        sql.push_str("e.timestamp, e.id");
+
+        let mut statement = sqlx::query(&sql);
+        for bind in binds {
+            statement = statement.bind(bind);
+        }
+        let rows = statement.fetch_all(&self.pool).await.map_err(StorageError::Sqlx)?;
+        rows.iter().map(entry_from_row).collect()
+    }
+
+    async fn backup(&self, dest: &Path) -> Result<(), StorageError> {
+        let dest = CString::new(dest.to_string_lossy().into_owned())
+            .map_err(|e| StorageError::Backend(format!("Invalid backup path: {}", e)))?;
+        let mut connection = self.pool.acquire().await.map_err(StorageError::Sqlx)?;
+        let mut handle = connection.lock_handle().await.map_err(StorageError::Sqlx)?;
+        // The locked handle keeps the connection to ourselves until dropped
+        unsafe { online_backup(handle.as_raw_handle().as_ptr(), &dest) }
+    }
 
+    async fn close(&mut self) -> Result<(), StorageError> {
+        // Move everything from the write-ahead log into the database file and
+        // empty it, so a file put in place of this one does not pick it up
+        sqlx::query("PRAGMA wal_checkpoint(TRUNCATE)")
+            .execute(&self.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        self.pool.close().await;
         Ok(())
     }
 
@@ -401,3 +1107,94 @@ impl Storage for SqliteStorage {
         &self.path
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[tokio::test]
+    async fn test_sqlite_round_trips_extended_fields() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.db");
+        let mut storage = SqliteStorage::new(&path).await?;
+
+        let entry = LogEntry {
+            id: "qso-1".to_string(),
+            timestamp: Utc.with_ymd_and_hms(2024, 6, 22, 18, 0, 0).unwrap(),
+            time_off: Some(Utc.with_ymd_and_hms(2024, 6, 22, 18, 5, 0).unwrap()),
+            callsign: "K1ABC".into(),
+            frequency: Frequency::from_hz(145_900_000),
+            freq_rx: Some(Frequency::from_hz(435_800_000)),
+            mode: "FM".into(),
+            prop_mode: Some("SAT".to_string()),
+            sat_name: Some("SO-50".to_string()),
+            cq_zone: Some(5),
+            srx: Some(42),
+            lotw: QslConfirmation {
+                received: Some(QslStatus::Yes),
+                received_date: NaiveDate::from_ymd_opt(2024, 7, 1),
+                ..Default::default()
+            },
+            qsl_sent_via: Some(QslVia::Bureau),
+            station: Some("GOTA".to_string()),
+            my_sig: Some("POTA".to_string()),
+            my_sig_info: Some("K-0001".to_string()),
+            ..Default::default()
+        };
+        storage.save_entry(entry.clone()).await?;
+
+        // Reopening must not re-run migrations
+        drop(storage);
+        let storage = SqliteStorage::new(&path).await?;
+        let loaded = storage.get_entry("qso-1").await?.unwrap();
+        assert_eq!(loaded.time_off, entry.time_off);
+        assert_eq!(loaded.freq_rx, Some(Frequency::from_hz(435_800_000)));
+        assert_eq!(loaded.sat_name.as_deref(), Some("SO-50"));
+        assert_eq!(loaded.cq_zone, Some(5));
+        assert_eq!(loaded.srx, Some(42));
+        assert_eq!(loaded.lotw, entry.lotw);
+        assert_eq!(loaded.qsl_sent_via, Some(QslVia::Bureau));
+        assert_eq!(loaded.station.as_deref(), Some("GOTA"));
+        assert_eq!(loaded.my_sig_info.as_deref(), Some("K-0001"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_search_keeps_its_entries_across_vacuum() -> Result<(), StorageError> {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("log.db");
+
+        // A log written before entries had an explicit key
+        let options = SqliteConnectOptions::new().filename(&path).create_if_missing(true);
+        let mut connection = SqliteConnection::connect_with(&options).await.map_err(StorageError::Sqlx)?;
+        initialize_db(&mut connection, &MIGRATIONS[..9]).await?;
+        sqlx::raw_sql(
+            "INSERT INTO log_entries (id, timestamp, callsign, frequency, mode, notes) VALUES
+                ('a', '2024-03-27T14:30:00+00:00', 'W1AW', 14.074, 'FT8', 'alpha'),
+                ('b', '2024-03-27T14:31:00+00:00', 'K1ABC', 14.074, 'FT8', 'bravo'),
+                ('c', '2024-03-27T14:32:00+00:00', 'N0CALL', 14.074, 'FT8', 'charlie');
+             DELETE FROM log_entries WHERE id = 'a';",
+        )
+        .execute(&mut connection)
+        .await
+        .map_err(StorageError::Sqlx)?;
+        connection.close().await.map_err(StorageError::Sqlx)?;
+
+        let storage = SqliteStorage::new(&path).await?;
+        let key: String = sqlx::query_scalar("SELECT name FROM pragma_table_info('log_entries') WHERE pk = 1")
+            .fetch_one(&storage.pool)
+            .await
+            .map_err(StorageError::Sqlx)?;
+        assert_eq!(key, "entry_number");
+
+        // VACUUM may renumber rows without an explicit key, but not these
+        sqlx::query("VACUUM").execute(&storage.pool).await.map_err(StorageError::Sqlx)?;
+        for (query, id) in [("bravo", "b"), ("charlie", "c")] {
+            let hits = storage.search_text(query).await?;
+            assert_eq!(hits.iter().map(|hit| hit.entry.id.as_str()).collect::<Vec<_>>(), vec![id]);
+        }
+        assert_eq!(storage.get_entry("c").await?.unwrap().notes.as_deref(), Some("charlie"));
+        Ok(())
+    }
+}
@@ -0,0 +1,388 @@
+//! Log integrity check: records that cannot be read, duplicate ids,
+//! impossible values and custom fields nothing uses, with automatic fixes
+//! where the right answer is clear. Custom fields are only reported: they
+//! may belong to another program, e.g. as USERDEF or APP_ fields.
+//!
+//! Checking only reads the log. Repairs are made by `StorageManager::fsck`,
+//! which backs the log up first and moves anything it removes to the
+//! `.lost+found` file next to the log.
+
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+use super::StorageError;
+use crate::app::{band, Power};
+use crate::LogEntry;
+
+/// How far ahead of now a QSO may be before it counts as a future date,
+/// allowing for clocks set to local time
+const FUTURE_SLACK_HOURS: i64 = 24;
+
+/// A record in the log that could not be read as an entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Unreadable {
+    /// Where the record is, e.g. `line 12` or `row 40`
+    pub location: String,
+    pub error: String,
+    /// The record as stored
+    pub raw: String,
+}
+
+/// Every record in the log as stored, duplicates included
+#[derive(Debug, Default)]
+pub struct Scan {
+    pub entries: Vec<LogEntry>,
+    pub unreadable: Vec<Unreadable>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProblemKind {
+    Unreadable,
+    DuplicateId,
+    NegativeFrequency,
+    NegativePower,
+    FutureDate,
+    BandMismatch,
+    OrphanCustomField,
+    DamagedHistory,
+}
+
+impl ProblemKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProblemKind::Unreadable => "unreadable",
+            ProblemKind::DuplicateId => "duplicate id",
+            ProblemKind::NegativeFrequency => "negative frequency",
+            ProblemKind::NegativePower => "negative power",
+            ProblemKind::FutureDate => "future date",
+            ProblemKind::BandMismatch => "band mismatch",
+            ProblemKind::OrphanCustomField => "orphan custom field",
+            ProblemKind::DamagedHistory => "damaged history",
+        }
+    }
+
+    /// Whether the problem is only reported and a repair leaves it alone
+    pub fn is_warning(&self) -> bool {
+        matches!(self, ProblemKind::OrphanCustomField)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub kind: ProblemKind,
+    /// Record location or QSO the problem is in
+    pub location: String,
+    pub detail: String,
+    /// What a repair does about it; `None` when it has to be fixed by hand
+    pub fix: Option<String>,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}: {}", self.location, self.kind.as_str(), self.detail)?;
+        match &self.fix {
+            Some(fix) => write!(f, " (fix: {})", fix),
+            None if self.kind.is_warning() => write!(f, " (left as is)"),
+            None => write!(f, " (fix by hand)"),
+        }
+    }
+}
+
+/// Problems found in a scan and the log as a repair would leave it
+#[derive(Debug, Default)]
+pub struct Check {
+    pub problems: Vec<Problem>,
+    /// Entries after fixing, one per id
+    pub entries: Vec<LogEntry>,
+    /// Ids of entries whose values a repair changes
+    pub changed: HashSet<String>,
+    /// Records a repair removes, as they were stored
+    pub removed: Vec<String>,
+}
+
+impl Check {
+    /// Whether repairing needs the log rewritten rather than entries updated
+    pub fn needs_rewrite(&self) -> bool {
+        !self.removed.is_empty()
+            || self.problems.iter().any(|p| matches!(p.kind, ProblemKind::Unreadable | ProblemKind::DuplicateId))
+    }
+
+    pub fn fixable(&self) -> usize {
+        self.problems.iter().filter(|p| p.fix.is_some()).count()
+    }
+}
+
+/// Result of `StorageManager::fsck`
+#[derive(Debug, Default)]
+pub struct FsckReport {
+    /// Records looked at, readable or not
+    pub records: usize,
+    pub problems: Vec<Problem>,
+    /// Whether fixes were applied
+    pub repaired: bool,
+}
+
+impl FsckReport {
+    pub fn fixable(&self) -> usize {
+        self.problems.iter().filter(|p| p.fix.is_some()).count()
+    }
+
+    pub fn summary(&self) -> String {
+        match (self.problems.len(), self.repaired) {
+            (0, _) => format!("{} records checked, no problems", self.records),
+            (n, true) => format!("{} records checked, {} problems, {} fixed", self.records, n, self.fixable()),
+            (n, false) => format!("{} records checked, {} problems, {} can be fixed", self.records, n, self.fixable()),
+        }
+    }
+}
+
+fn describe(entry: &LogEntry) -> String {
+    format!("{} {} ({})", entry.callsign, entry.timestamp.format("%Y-%m-%d %H:%M"), entry.id)
+}
+
+/// Check a scan. `custom_fields` names the custom fields something uses,
+/// e.g. contest exchanges and awards.
+pub fn check(scan: &Scan, custom_fields: &[String], now: DateTime<Utc>) -> Check {
+    let mut result = Check::default();
+
+    for unreadable in &scan.unreadable {
+        result.problems.push(Problem {
+            kind: ProblemKind::Unreadable,
+            location: unreadable.location.clone(),
+            detail: unreadable.error.clone(),
+            fix: Some("move to lost+found".to_string()),
+        });
+        result.removed.push(unreadable.raw.clone());
+    }
+
+    for mut entry in resolve_duplicates(&scan.entries, &mut result) {
+        if check_values(&mut entry, custom_fields, now, &mut result.problems) {
+            result.changed.insert(entry.id.clone());
+        }
+        result.entries.push(entry);
+    }
+    result
+}
+
+/// Problems for lines of the undo journal and revision history that could
+/// not be read. A repair moves them to lost+found.
+pub fn check_history(damaged: &[Unreadable]) -> Vec<Problem> {
+    damaged.iter()
+        .map(|line| Problem {
+            kind: ProblemKind::DamagedHistory,
+            location: line.location.clone(),
+            detail: line.error.clone(),
+            fix: Some("move to lost+found".to_string()),
+        })
+        .collect()
+}
+
+/// Keep one entry per id. Copies of the same QSO are older versions and
+/// are dropped in favour of the latest; different QSOs sharing an id get
+/// new ids.
+fn resolve_duplicates(entries: &[LogEntry], result: &mut Check) -> Vec<LogEntry> {
+    let mut groups: Vec<(String, Vec<&LogEntry>)> = Vec::new();
+    let mut positions: HashMap<&str, usize> = HashMap::new();
+    for entry in entries {
+        match positions.get(entry.id.as_str()) {
+            Some(&pos) => groups[pos].1.push(entry),
+            None => {
+                positions.insert(&entry.id, groups.len());
+                groups.push((entry.id.clone(), vec![entry]));
+            }
+        }
+    }
+
+    let mut kept = Vec::new();
+    for (id, copies) in groups {
+        // The most recently updated copy wins, the later one on a tie
+        let latest = copies.iter()
+            .enumerate()
+            .max_by_key(|(n, entry)| (entry.updated_at, *n))
+            .map_or(0, |(n, _)| n);
+        for (n, copy) in copies.iter().enumerate() {
+            if n == latest {
+                continue;
+            }
+            let keeper = copies[latest];
+            let same_qso = copy.callsign.eq_ignore_ascii_case(&keeper.callsign) && copy.timestamp == keeper.timestamp;
+            if same_qso {
+                result.problems.push(Problem {
+                    kind: ProblemKind::DuplicateId,
+                    location: describe(copy),
+                    detail: "older copy of the same QSO".to_string(),
+                    fix: Some("move to lost+found".to_string()),
+                });
+                result.removed.push(serde_json::to_string(copy).unwrap_or_default());
+            } else {
+                let mut renamed = (*copy).clone();
+                renamed.id = Uuid::new_v4().to_string();
+                result.problems.push(Problem {
+                    kind: ProblemKind::DuplicateId,
+                    location: describe(copy),
+                    detail: format!("id also used by {}", keeper.callsign),
+                    fix: Some(format!("give it the new id {}", renamed.id)),
+                });
+                kept.push(renamed);
+            }
+        }
+        let mut keeper = copies[latest].clone();
+        keeper.id = id;
+        kept.push(keeper);
+    }
+    kept
+}
+
+/// Check an entry's values, fixing what can be fixed. Returns whether the
+/// entry changed.
+fn check_values(entry: &mut LogEntry, custom_fields: &[String], now: DateTime<Utc>, problems: &mut Vec<Problem>) -> bool {
+    let location = describe(entry);
+    let mut changed = false;
+    let mut report = |kind, detail: String, fix: Option<String>| {
+        problems.push(Problem { kind, location: location.clone(), detail, fix });
+    };
+
+    // Frequencies that are not numbers at all never get this far: the
+    // record they are in is unreadable
+    if entry.frequency.is_negative() {
+        let positive = entry.frequency.abs();
+        match positive.band() {
+            Some(_) => {
+                report(ProblemKind::NegativeFrequency, format!("{} MHz", entry.frequency), Some(format!("use {} MHz", positive)));
+                entry.frequency = positive;
+                changed = true;
+            }
+            None => report(ProblemKind::NegativeFrequency, format!("{} MHz", entry.frequency), None),
+        }
+    }
+
+    if let Some(power) = entry.power.filter(Power::is_negative) {
+        report(ProblemKind::NegativePower, power.to_string(), None);
+    }
+
+    if entry.timestamp > now + Duration::hours(FUTURE_SLACK_HOURS) {
+        report(ProblemKind::FutureDate, format!("logged for {}", entry.timestamp.format("%Y-%m-%d %H:%M")), None);
+    }
+
+    for (band, frequency, label) in [
+        (&mut entry.band, Some(entry.frequency), "band"),
+        (&mut entry.band_rx, entry.freq_rx, "RX band"),
+    ] {
+        let (Some(logged), Some(expected)) = (band.as_deref(), frequency.and_then(|f| f.band())) else {
+            continue;
+        };
+        if !band::same_band(logged, &expected) {
+            report(
+                ProblemKind::BandMismatch,
+                format!("{} {} does not match {} MHz", label, logged, frequency.unwrap_or_default()),
+                Some(format!("set {} to {}", label, expected)),
+            );
+            *band = Some(expected);
+            changed = true;
+        }
+    }
+
+    let mut orphans: Vec<(&String, &String)> = entry.custom_fields.iter()
+        .filter(|(name, value)| value.trim().is_empty() || !custom_fields.contains(name))
+        .collect();
+    orphans.sort();
+    for (name, value) in orphans {
+        let detail = if value.trim().is_empty() {
+            format!("{} is empty", name)
+        } else {
+            format!("{}={} is not used by any contest or award", name, value)
+        };
+        report(ProblemKind::OrphanCustomField, detail, None);
+    }
+    changed
+}
+
+/// Lost+found file for a log, e.g. `logbook.adi.lost+found`
+pub fn lost_found_path(log_path: &Path) -> PathBuf {
+    let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+    name.push(".lost+found");
+    log_path.with_file_name(name)
+}
+
+/// Append removed records to the lost+found file
+pub fn keep_lost(log_path: &Path, records: &[String]) -> Result<(), StorageError> {
+    if records.is_empty() {
+        return Ok(());
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(lost_found_path(log_path))?;
+    for record in records {
+        writeln!(file, "# removed by fsck {}\n{}\n", Utc::now().to_rfc3339(), record.trim())?;
+    }
+    file.sync_data()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Frequency;
+    use crate::storage::formats::fixtures::qso;
+    use chrono::TimeZone;
+
+    fn qso_at(id: &str, call: &str, minute: u32) -> LogEntry {
+        LogEntry { timestamp: Utc.with_ymd_and_hms(2024, 3, 27, 14, minute, 0).unwrap(), ..qso(id, call, "FT8") }
+    }
+
+    #[test]
+    fn test_check_finds_and_fixes_problems() {
+        let now = Utc.with_ymd_and_hms(2024, 4, 1, 0, 0, 0).unwrap();
+        let mut older = qso_at("1", "W1AW", 0);
+        older.notes = Some("before edit".to_string());
+        let mut newer = qso_at("1", "W1AW", 0);
+        newer.updated_at = Some(now);
+        let mut negative = qso_at("3", "K1ABC", 5);
+        negative.frequency = Frequency::from_hz(-7_074_000);
+        negative.band = Some("40m".into());
+        negative.power = Some(Power::from_watts(-5.0).unwrap());
+        let mut mismatch = qso_at("4", "N0CALL", 6);
+        mismatch.band = Some("40m".into());
+        mismatch.timestamp = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        mismatch.custom_fields.insert("CLASS".to_string(), "2A".to_string());
+        mismatch.custom_fields.insert("TEMP".to_string(), "x".to_string());
+        let scan = Scan {
+            entries: vec![newer.clone(), older, qso_at("1", "VE3XYZ", 2), negative, mismatch],
+            unreadable: vec![Unreadable { location: "row 9".to_string(), error: "bad timestamp".to_string(), raw: "{}".to_string() }],
+        };
+
+        let check = check(&scan, &["CLASS".to_string()], now);
+        let kinds: Vec<ProblemKind> = check.problems.iter().map(|p| p.kind).collect();
+        assert_eq!(kinds, vec![
+            ProblemKind::Unreadable,
+            ProblemKind::DuplicateId,
+            ProblemKind::DuplicateId,
+            ProblemKind::NegativeFrequency,
+            ProblemKind::NegativePower,
+            ProblemKind::FutureDate,
+            ProblemKind::BandMismatch,
+            ProblemKind::OrphanCustomField,
+        ]);
+        assert!(check.needs_rewrite());
+        assert_eq!(check.fixable(), 5);
+        assert!(check.problems.last().unwrap().to_string().ends_with("(left as is)"));
+        // The unreadable row and the older copy of W1AW are removed
+        assert_eq!(check.removed.len(), 2);
+
+        let ids: HashSet<&str> = check.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids.len(), 4);
+        let kept = check.entries.iter().find(|e| e.id == "1").unwrap();
+        assert_eq!(kept.notes, None);
+        assert!(check.entries.iter().any(|e| e.callsign == "VE3XYZ" && e.id != "1"));
+        let fixed = check.entries.iter().find(|e| e.id == "3").unwrap();
+        assert_eq!(fixed.frequency, Frequency::from_hz(7_074_000));
+        let fixed = check.entries.iter().find(|e| e.id == "4").unwrap();
+        assert_eq!(fixed.band.as_deref(), Some("20m"));
+        // Custom fields nothing here uses are kept
+        assert_eq!(fixed.custom_fields.len(), 2);
+        assert_eq!(check.changed, ["3".to_string(), "4".to_string()].into_iter().collect());
+    }
+}
@@ -0,0 +1,411 @@
+//! Operation journal: every create, update and delete with the entry as it
+//! was before and after, so changes can be undone and redone across restarts
+//!
+//! The journal is linear. Undone records stay at its end until they are
+//! redone or a new change replaces them. Only the latest `MAX_RECORDS`
+//! changes are kept.
+
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::fsck::Unreadable;
+use super::StorageError;
+use crate::LogEntry;
+
+/// Changes kept for undo; the oldest are dropped as new ones arrive
+pub const MAX_RECORDS: usize = 10_000;
+
+/// Lines a journal file may hold beyond twice its records before it is
+/// rewritten without the replaced records and marks
+const COMPACT_SLACK: usize = 1_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JournalOp {
+    Create,
+    Update,
+    Delete,
+}
+
+impl JournalOp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JournalOp::Create => "create",
+            JournalOp::Update => "update",
+            JournalOp::Delete => "delete",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalRecord {
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    pub op: JournalOp,
+    pub entry_id: String,
+    /// The entry before the change; `None` for creates
+    pub before: Option<LogEntry>,
+    /// The entry after the change; `None` for deletes
+    pub after: Option<LogEntry>,
+    #[serde(default)]
+    pub undone: bool,
+}
+
+impl JournalRecord {
+    pub fn new(seq: u64, before: Option<LogEntry>, after: Option<LogEntry>) -> Self {
+        let op = match (&before, &after) {
+            (None, _) => JournalOp::Create,
+            (Some(_), Some(_)) => JournalOp::Update,
+            (Some(_), None) => JournalOp::Delete,
+        };
+        let entry_id = after.as_ref().or(before.as_ref()).map(|e| e.id.clone()).unwrap_or_default();
+        Self { seq, timestamp: Utc::now(), op, entry_id, before, after, undone: false }
+    }
+
+    /// Callsign of the QSO the record is about
+    pub fn callsign(&self) -> &str {
+        self.after.as_ref().or(self.before.as_ref()).map_or("", |e| e.callsign.as_str())
+    }
+
+    /// Names of the fields that differ between the before and after images
+    pub fn changed_fields(&self) -> Vec<String> {
+        diff_entries(self.before.as_ref(), self.after.as_ref())
+            .into_iter()
+            .map(|(field, _, _)| field)
+            .collect()
+    }
+}
+
+/// Bookkeeping fields that change on every save and are left out of diffs
+const AUDIT_FIELDS: &[&str] = &["created_at", "updated_at"];
+
+fn entry_fields(entry: Option<&LogEntry>) -> serde_json::Map<String, Value> {
+    let mut fields = match entry.map(serde_json::to_value) {
+        Some(Ok(Value::Object(map))) => map,
+        _ => serde_json::Map::new(),
+    };
+    fields.retain(|name, _| !AUDIT_FIELDS.contains(&name.as_str()));
+    fields
+}
+
+/// Display form of a field value; missing and null values are blank
+pub fn field_text(value: Option<&Value>) -> String {
+    match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Fields that differ between two versions of an entry, with their old and
+/// new values as text
+pub fn diff_entries(before: Option<&LogEntry>, after: Option<&LogEntry>) -> Vec<(String, String, String)> {
+    let before = entry_fields(before);
+    let after = entry_fields(after);
+    let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+    names.sort();
+    names.dedup();
+    names.into_iter()
+        .filter(|name| before.get(*name) != after.get(*name))
+        .map(|name| (name.clone(), field_text(before.get(name)), field_text(after.get(name))))
+        .filter(|(_, old, new)| old != new)
+        .collect()
+}
+
+/// A line of a journal file. Marks record an undo or redo, so the file
+/// is only ever appended to between compactions.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum JournalLine {
+    Record(Box<JournalRecord>),
+    Mark { mark: u64, undone: bool },
+}
+
+/// Journal kept as JSON lines next to a file-based log
+pub struct JournalFile {
+    path: PathBuf,
+    records: Vec<JournalRecord>,
+    /// Lines in the file, including replaced records and marks
+    lines: usize,
+    /// Lines that could not be read, left for fsck
+    damaged: Vec<Unreadable>,
+}
+
+impl JournalFile {
+    /// Journal path for a log file, e.g. `logbook.adi.journal.jsonl` for
+    /// `logbook.adi`; keeping the extension stops an ADIF and a JSON log of
+    /// the same name from sharing a journal
+    pub fn path_for(log_path: &Path) -> PathBuf {
+        let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".journal.jsonl");
+        log_path.with_file_name(name)
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self, StorageError> {
+        let lines = if path.exists() { read_lines(&path)? } else { Lines::default() };
+        let mut journal = Self { path, records: Vec::new(), lines: lines.items.len(), damaged: lines.damaged };
+        for line in lines.items {
+            journal.apply(line);
+        }
+        journal.trim();
+        Ok(journal)
+    }
+
+    pub fn records(&self) -> &[JournalRecord] {
+        &self.records
+    }
+
+    pub fn record(&self, seq: u64) -> Option<&JournalRecord> {
+        self.records.iter().rev().find(|r| r.seq == seq)
+    }
+
+    /// Sequence number of the latest change still in effect; 0 when none is
+    pub fn last_seq(&self) -> u64 {
+        self.records.iter().rev().find(|r| !r.undone).map_or(0, |r| r.seq)
+    }
+
+    /// Append a record, dropping any record with the same or a later sequence
+    pub fn append(&mut self, record: JournalRecord) -> Result<(), StorageError> {
+        self.write(vec![JournalLine::Record(Box::new(record))])
+    }
+
+    /// Append several records with one write
+    pub fn append_all(&mut self, records: Vec<JournalRecord>) -> Result<(), StorageError> {
+        self.write(records.into_iter().map(|record| JournalLine::Record(Box::new(record))).collect())
+    }
+
+    pub fn mark(&mut self, seq: u64, undone: bool) -> Result<(), StorageError> {
+        if self.record(seq).is_none() {
+            return Err(StorageError::NotFound(format!("journal record {}", seq)));
+        }
+        self.write(vec![JournalLine::Mark { mark: seq, undone }])
+    }
+
+    /// Lines that could not be read when the journal was opened
+    pub fn damaged(&self) -> &[Unreadable] {
+        &self.damaged
+    }
+
+    /// Rewrite the file without its damaged lines
+    pub fn drop_damaged(&mut self) -> Result<(), StorageError> {
+        if self.damaged.is_empty() {
+            return Ok(());
+        }
+        self.damaged.clear();
+        self.rewrite()
+    }
+
+    fn write(&mut self, lines: Vec<JournalLine>) -> Result<(), StorageError> {
+        if lines.is_empty() {
+            return Ok(());
+        }
+        let mut text = String::new();
+        for line in &lines {
+            text.push_str(&serde_json::to_string(line)?);
+            text.push('\n');
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(text.as_bytes())?;
+        self.lines += lines.len();
+        for line in lines {
+            self.apply(line);
+        }
+        self.trim();
+        if self.lines > self.records.len() * 2 + COMPACT_SLACK {
+            self.rewrite()?;
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, line: JournalLine) {
+        match line {
+            JournalLine::Record(record) => {
+                if self.records.last().is_some_and(|last| last.seq >= record.seq) {
+                    self.records.retain(|r| r.seq < record.seq);
+                }
+                self.records.push(*record);
+            }
+            JournalLine::Mark { mark, undone } => {
+                if let Some(record) = self.records.iter_mut().rev().find(|r| r.seq == mark) {
+                    record.undone = undone;
+                }
+            }
+        }
+    }
+
+    fn trim(&mut self) {
+        if self.records.len() > MAX_RECORDS {
+            self.records.drain(..self.records.len() - MAX_RECORDS);
+        }
+    }
+
+    /// Write the records as they stand. Damaged lines are carried over
+    /// until fsck drops them.
+    fn rewrite(&mut self) -> Result<(), StorageError> {
+        let mut content = String::new();
+        for damaged in &self.damaged {
+            content.push_str(damaged.raw.trim_end());
+            content.push('\n');
+        }
+        for record in &self.records {
+            content.push_str(&serde_json::to_string(record)?);
+            content.push('\n');
+        }
+        let temp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, &self.path)?;
+        self.lines = self.records.len();
+        Ok(())
+    }
+}
+
+/// What could be read from a JSON lines sidecar
+pub(crate) struct Lines<T> {
+    pub items: Vec<T>,
+    /// Lines that are not valid records
+    pub damaged: Vec<Unreadable>,
+}
+
+impl<T> Default for Lines<T> {
+    fn default() -> Self {
+        Self { items: Vec::new(), damaged: Vec::new() }
+    }
+}
+
+/// Read a JSON lines sidecar. A last line cut short by a crash is dropped
+/// and trimmed from the file so later appends start on a line of their own;
+/// other lines that cannot be read are skipped and returned as damaged.
+pub(crate) fn read_lines<T: DeserializeOwned>(path: &Path) -> Result<Lines<T>, StorageError> {
+    let content = fs::read_to_string(path)?;
+    let mut lines = Lines::default();
+    let mut valid_len = 0;
+
+    for (n, line) in content.split_inclusive('\n').enumerate() {
+        if !line.trim().is_empty() {
+            match serde_json::from_str(line) {
+                Ok(item) => lines.items.push(item),
+                Err(_) if !line.ends_with('\n') => break,
+                Err(e) => lines.damaged.push(Unreadable {
+                    location: format!("line {}", n + 1),
+                    error: e.to_string(),
+                    raw: line.trim_end().to_string(),
+                }),
+            }
+        }
+        valid_len += line.len();
+    }
+
+    if valid_len < content.len() {
+        OpenOptions::new().write(true).open(path)?.set_len(valid_len as u64)?;
+    } else if !content.is_empty() && !content.ends_with('\n') {
+        writeln!(OpenOptions::new().append(true).open(path)?)?;
+    }
+    Ok(lines)
+}
+
+/// Damaged lines of a log's journal and revision files, named by file
+pub(crate) fn damaged_history(journal: &JournalFile, revisions: &super::revision::RevisionFile) -> Vec<Unreadable> {
+    [(journal.path.as_path(), journal.damaged()), (revisions.path(), revisions.damaged())]
+        .into_iter()
+        .flat_map(|(path, damaged)| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            damaged.iter().map(move |line| Unreadable { location: format!("{} {}", name, line.location), ..line.clone() })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::formats::fixtures::qso;
+
+    #[test]
+    fn test_journal_file_round_trip_and_truncate() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = JournalFile::path_for(&dir.path().join("log.adi"));
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        journal.append(JournalRecord::new(1, None, Some(qso("1", "W1AW", "CW")))).unwrap();
+        journal.append(JournalRecord::new(2, Some(qso("1", "W1AW", "CW")), Some(qso("1", "W1AW", "SSB")))).unwrap();
+        journal.mark(2, true).unwrap();
+
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        assert_eq!(journal.records().len(), 2);
+        assert!(journal.records()[1].undone);
+        assert_eq!(journal.records()[1].changed_fields(), vec!["mode".to_string()]);
+
+        // A new change replaces the undone one
+        journal.append(JournalRecord::new(2, Some(qso("1", "W1AW", "CW")), None)).unwrap();
+        let journal = JournalFile::open(path).unwrap();
+        assert_eq!(journal.records().len(), 2);
+        assert_eq!(journal.records()[1].op, JournalOp::Delete);
+    }
+
+    #[test]
+    fn test_journal_file_drops_a_torn_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = JournalFile::path_for(&dir.path().join("log.adi"));
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        journal.append(JournalRecord::new(1, None, Some(qso("1", "W1AW", "CW")))).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"seq\":2,\"timestamp\":").unwrap();
+
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        assert_eq!(journal.records().len(), 1);
+        journal.append(JournalRecord::new(2, None, Some(qso("1", "K1ABC", "CW")))).unwrap();
+        assert_eq!(JournalFile::open(path).unwrap().records().len(), 2);
+    }
+
+    #[test]
+    fn test_journal_file_appends_marks_and_skips_damaged_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = JournalFile::path_for(&dir.path().join("log.adi"));
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        journal.append(JournalRecord::new(1, None, Some(qso("1", "W1AW", "CW")))).unwrap();
+        journal.append(JournalRecord::new(2, Some(qso("1", "W1AW", "CW")), Some(qso("1", "W1AW", "SSB")))).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        journal.mark(2, true).unwrap();
+        assert_eq!(journal.last_seq(), 1);
+
+        // Undo appends a mark instead of rewriting the file
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(&written));
+        assert_eq!(content.lines().count(), 3);
+
+        // A damaged line is skipped and reported, and the journal still opens
+        fs::write(&path, content.replacen("{", "{garbage", 1)).unwrap();
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        assert_eq!(journal.records().len(), 1);
+        assert!(journal.records()[0].undone);
+        assert_eq!(journal.damaged().len(), 1);
+        assert_eq!(journal.damaged()[0].location, "line 1");
+
+        journal.drop_damaged().unwrap();
+        let journal = JournalFile::open(path).unwrap();
+        assert!(journal.damaged().is_empty());
+        assert_eq!(journal.records().len(), 1);
+    }
+
+    #[test]
+    fn test_journal_file_keeps_latest_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = JournalFile::path_for(&dir.path().join("log.adi"));
+        let mut journal = JournalFile::open(path.clone()).unwrap();
+        let total = MAX_RECORDS as u64 + 5;
+        for seq in 1..=total {
+            journal.append(JournalRecord::new(seq, None, Some(qso("1", "W1AW", "CW")))).unwrap();
+        }
+        assert_eq!(journal.records().len(), MAX_RECORDS);
+        assert_eq!(journal.records()[0].seq, 6);
+
+        let journal = JournalFile::open(path.clone()).unwrap();
+        assert_eq!(journal.records().len(), MAX_RECORDS);
+        assert_eq!(journal.last_seq(), total);
+        assert!(fs::read_to_string(&path).unwrap().lines().count() <= MAX_RECORDS * 2 + COMPACT_SLACK);
+    }
+}
+
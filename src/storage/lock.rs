@@ -0,0 +1,150 @@
+//! Advisory locking for file-based logs
+//!
+//! The lock is taken on a `.lock` file next to the log rather than the log
+//! itself, because compaction replaces the log with a renamed copy.
+//!
+//! The lock only keeps amlog instances from getting in each other's way.
+//! It is advisory, so programs that know nothing of it, such as WSJT-X
+//! appending to an ADIF log, write regardless. Such changes are caught by
+//! comparing the log's `FileStamp` with the one amlog last saw before every
+//! write instead.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use super::StorageError;
+
+/// How long to wait for another program to release the log
+const LOCK_TIMEOUT: Duration = Duration::from_secs(2);
+const LOCK_RETRY: Duration = Duration::from_millis(20);
+
+/// Size and modification time of a log when amlog last read or wrote it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FileStamp {
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl FileStamp {
+    pub fn of(path: &Path) -> Result<Self, StorageError> {
+        let metadata = fs::metadata(path)?;
+        Ok(Self { len: metadata.len(), modified: metadata.modified().ok() })
+    }
+}
+
+pub struct LogLock {
+    path: PathBuf,
+    file: File,
+}
+
+/// Held lock, released when dropped
+pub struct LockGuard {
+    file: File,
+}
+
+impl Drop for LockGuard {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+impl LogLock {
+    /// Lock path for a log file, e.g. `logbook.adi.lock`
+    pub fn path_for(log_path: &Path) -> PathBuf {
+        let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        log_path.with_file_name(name)
+    }
+
+    pub fn open(log_path: &Path) -> Result<Self, StorageError> {
+        let path = Self::path_for(log_path);
+        let file = OpenOptions::new().create(true).truncate(false).write(true).open(&path)?;
+        Ok(Self { path, file })
+    }
+
+    /// Lock for writing; no other reader or writer may hold the lock
+    pub async fn exclusive(&self) -> Result<LockGuard, StorageError> {
+        self.acquire(|file| file.try_lock()).await
+    }
+
+    /// Lock for reading; other readers may hold the lock too
+    pub async fn shared(&self) -> Result<LockGuard, StorageError> {
+        self.acquire(|file| file.try_lock_shared()).await
+    }
+
+    /// `shared` for code that is not async, such as opening a log on the
+    /// blocking thread pool
+    pub fn blocking_shared(&self) -> Result<LockGuard, StorageError> {
+        let file = self.file.try_clone()?;
+        let started = Instant::now();
+        while !self.try_acquire(&file, |file| file.try_lock_shared(), started)? {
+            thread::sleep(LOCK_RETRY);
+        }
+        Ok(LockGuard { file })
+    }
+
+    async fn acquire(&self, try_lock: impl Fn(&File) -> Result<(), TryLockError>) -> Result<LockGuard, StorageError> {
+        let file = self.file.try_clone()?;
+        let started = Instant::now();
+        while !self.try_acquire(&file, &try_lock, started)? {
+            tokio::time::sleep(LOCK_RETRY).await;
+        }
+        Ok(LockGuard { file })
+    }
+
+    /// Try the lock once; false means try again after a while
+    fn try_acquire(
+        &self,
+        file: &File,
+        try_lock: impl Fn(&File) -> Result<(), TryLockError>,
+        started: Instant,
+    ) -> Result<bool, StorageError> {
+        match try_lock(file) {
+            Ok(()) => Ok(true),
+            Err(TryLockError::WouldBlock) if started.elapsed() < LOCK_TIMEOUT => Ok(false),
+            Err(TryLockError::WouldBlock) => Err(StorageError::Locked(self.path.display().to_string())),
+            Err(TryLockError::Error(e)) => Err(StorageError::Io(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exclusive_lock_blocks_other_holders() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log.adi");
+        let ours = LogLock::open(&log).unwrap();
+        let theirs = LogLock::open(&log).unwrap();
+
+        let guard = ours.exclusive().await.unwrap();
+        assert!(matches!(theirs.shared().await, Err(StorageError::Locked(_))));
+        assert!(matches!(theirs.blocking_shared(), Err(StorageError::Locked(_))));
+        drop(guard);
+
+        let _reader = ours.shared().await.unwrap();
+        assert!(theirs.shared().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_for_the_lock_leaves_the_runtime_free() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("log.adi");
+        let ours = LogLock::open(&log).unwrap();
+        let theirs = LogLock::open(&log).unwrap();
+
+        // On this single-threaded runtime the release only runs if waiting
+        // for the lock yields
+        let guard = ours.exclusive().await.unwrap();
+        let release = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            drop(guard);
+        };
+        let (_, waited) = tokio::join!(release, theirs.exclusive());
+        assert!(waited.is_ok());
+    }
+}
@@ -1,88 +1,662 @@
-use std::path::PathBuf;
-use sqlx::Sqlite;
-use tokio::sync::Mutex;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
+use tokio::sync::RwLock;
 
 use super::{
+    Backup, BackupConfig, BackupReason, ExternalChanges, FsckReport, JournalRecord, Revision, SearchHit,
     Storage, StorageError, StorageFormat,
 };
-use crate::storage::{JsonStorage, AdifStorage, SqliteStorage};
+use super::backup;
+use super::fields::{self, FieldDefinition, FieldQuery};
+use super::fsck;
+use super::journal;
+use super::mirror::{Mirror, MirrorStatus};
+use super::reconcile::{self, ConfirmationSource, ReconcileReport};
+use crate::storage::{JsonStorage, JsonLinesStorage, AdifStorage, SqliteStorage, InMemoryStorage};
 
 
 use crate::LogEntry;
 
+/// Front end to the log shared by everything that reads or writes it.
+///
+/// Clones share the same log, so a clone can be handed to each task that
+/// needs one. Reads run side by side; a change waits for reads in progress
+/// and holds the log until it and its mirrors are written.
+#[derive(Clone)]
 pub struct StorageManager {
-    storage: Arc<Mutex<Box<dyn Storage>>>,
+    log: Arc<RwLock<Log>>,
     format: StorageFormat,
     path: PathBuf,
+    backups: BackupConfig,
+    /// Entries added, changed or removed since the last backup
+    changed_since_backup: Arc<AtomicU32>,
+}
+
+/// The log and the other logs every change is copied to, locked together
+/// so mirrors see changes in the order they were made
+struct Log {
+    primary: Box<dyn Storage>,
+    mirrors: Vec<Mirror>,
+}
+
+impl Log {
+    async fn replicate(&mut self, ids: &[String]) {
+        for mirror in &mut self.mirrors {
+            mirror.replicate(&*self.primary, ids.iter().cloned()).await;
+        }
+    }
+
+    async fn sync_fields(&mut self) {
+        for mirror in &mut self.mirrors {
+            mirror.sync_fields(&*self.primary).await;
+        }
+    }
+
+    async fn resync(&mut self) -> Result<usize, StorageError> {
+        let mut count = 0;
+        for mirror in &mut self.mirrors {
+            count += mirror.resync(&*self.primary).await?;
+        }
+        Ok(count)
+    }
 }
 
 impl StorageManager {
     pub async fn new(format: StorageFormat, path: PathBuf) -> Result<Self, StorageError> {
-        let storage: Box<dyn Storage> = match format {
-            StorageFormat::Json => Box::new(JsonStorage::new(&path)?),
-            StorageFormat::Adif => Box::new(AdifStorage::new(&path)?),
-            StorageFormat::Sqlite => Box::new(SqliteStorage::new(&path).await?),
-        };
+        let primary = Self::open(format, &path).await?;
 
         Ok(Self {
-            storage: Arc::new(Mutex::new(storage)),
+            log: Arc::new(RwLock::new(Log { primary, mirrors: Vec::new() })),
             format,
+            backups: BackupConfig::beside(&path),
             path,
+            changed_since_backup: Arc::new(AtomicU32::new(0)),
+        })
+    }
+
+    /// Open a backend of the given format
+    pub(crate) async fn open(format: StorageFormat, path: &PathBuf) -> Result<Box<dyn Storage>, StorageError> {
+        Ok(match format {
+            StorageFormat::Json => Box::new(Self::open_blocking(path, JsonStorage::new).await?),
+            StorageFormat::JsonLines => Box::new(JsonLinesStorage::new(path)?),
+            StorageFormat::Adif => Box::new(Self::open_blocking(path, AdifStorage::new).await?),
+            StorageFormat::Sqlite => Box::new(SqliteStorage::new(path).await?),
+            StorageFormat::Memory => Box::new(InMemoryStorage::new(path)?),
         })
     }
 
-    pub async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        let mut storage = self.storage.lock().await;
-        storage.save_entry(entry).await
+    /// Open a locked file backend on the blocking thread pool, since it may
+    /// wait for another amlog instance to let go of the log
+    async fn open_blocking<S: Send + 'static>(
+        path: &Path,
+        open: fn(&PathBuf) -> Result<S, StorageError>,
+    ) -> Result<S, StorageError> {
+        let path = path.to_path_buf();
+        tokio::task::spawn_blocking(move || open(&path))
+            .await
+            .map_err(|e| StorageError::Backend(e.to_string()))?
+    }
+
+    /// Use other backup settings than the defaults
+    pub fn with_backups(mut self, config: BackupConfig) -> Self {
+        self.backups = config;
+        self
+    }
+
+    /// Copy every change to another log as well. The mirror is not filled
+    /// until the next `resync_mirrors`.
+    pub async fn add_mirror(&self, format: StorageFormat, path: PathBuf) -> Result<(), StorageError> {
+        let mut log = self.log.write().await;
+        if path == self.path || log.mirrors.iter().any(|mirror| mirror.status().path == path) {
+            return Err(StorageError::Backend(format!("{} is already in use", path.display())));
+        }
+        let storage = Self::open(format, &path).await?;
+        log.mirrors.push(Mirror::new(format, storage));
+        Ok(())
+    }
+
+    /// Bring every mirror in line with the log. Returns the number of
+    /// entries written or removed across all mirrors.
+    pub async fn resync_mirrors(&self) -> Result<usize, StorageError> {
+        self.log.write().await.resync().await
+    }
+
+    /// Write again what mirrors missed
+    pub async fn retry_mirrors(&self) {
+        self.log.write().await.replicate(&[]).await;
+    }
+
+    pub async fn mirrors(&self) -> Vec<MirrorStatus> {
+        self.log.read().await.mirrors.iter().map(Mirror::status).collect()
+    }
+
+    /// Take a backup now and remove those beyond the retention limit
+    pub async fn backup(&self, reason: BackupReason) -> Result<Backup, StorageError> {
+        let log = self.log.read().await;
+        self.write_backup(&*log.primary, reason).await
+    }
+
+    async fn write_backup(&self, primary: &dyn Storage, reason: BackupReason) -> Result<Backup, StorageError> {
+        fs::create_dir_all(&self.backups.dir)?;
+        let backup = Backup::new(&self.backups.dir, &self.path, reason);
+        // Written under another name first so a failed backup is never listed
+        let partial = backup::suffixed(&backup.path, ".partial");
+        primary.backup(&partial).await?;
+        backup::rename_with_sidecars(&partial, &backup.path)?;
+        backup::prune(&self.backups.dir, &self.path, self.backups.keep)?;
+        self.changed_since_backup.store(0, Ordering::Relaxed);
+        Ok(backup)
+    }
+
+    /// Backups of the log, newest first
+    pub fn backups(&self) -> Result<Vec<Backup>, StorageError> {
+        backup::list(&self.backups.dir, &self.path)
+    }
+
+    /// Find a backup by file name or path
+    pub fn find_backup(&self, name: &str) -> Result<Backup, StorageError> {
+        backup::find(&self.backups.dir, &self.path, name)
+    }
+
+    /// Replace the log with a backup. The log as it was is backed up first,
+    /// and that backup is returned so the restore can be reverted.
+    pub async fn restore_backup(&self, backup: &Backup) -> Result<Backup, StorageError> {
+        // Copy the backup before pruning can remove it
+        let incoming = backup::suffixed(&self.path, ".restore");
+        fs::copy(&backup.path, &incoming)?;
+        backup::copy_sidecars(&backup.path, &incoming)?;
+
+        let mut log = self.log.write().await;
+        let previous = self.write_backup(&*log.primary, BackupReason::PreRestore).await?;
+        log.primary.close().await?;
+        backup::rename_with_sidecars(&incoming, &self.path)?;
+        match Self::open(self.format, &self.path).await {
+            Ok(restored) => log.primary = restored,
+            Err(e) => {
+                // Put the log back the way it was
+                fs::copy(&previous.path, &self.path)?;
+                backup::copy_sidecars(&previous.path, &self.path)?;
+                log.primary = Self::open(self.format, &self.path).await?;
+                return Err(e);
+            }
+        }
+        log.resync().await?;
+        Ok(previous)
+    }
+
+    /// Take a periodic backup once enough entries have been added, changed
+    /// or removed, here or by another station
+    pub async fn backup_if_due(&self) -> Result<Option<Backup>, StorageError> {
+        if self.backups.every == 0 || self.changed_since_backup.load(Ordering::Relaxed) < self.backups.every {
+            return Ok(None);
+        }
+        self.backup(BackupReason::Periodic).await.map(Some)
+    }
+
+    /// Create or replace an entry, journaling the change. Returns the entry
+    /// as stored, with its timestamps filled in.
+    pub async fn save_entry(&self, mut entry: LogEntry) -> Result<LogEntry, StorageError> {
+        let mut log = self.log.write().await;
+        fields::normalize_entry(&log.primary.field_definitions().await?, &mut entry)?;
+        let entry = self.save_journaled(&mut log.primary, entry).await?;
+        log.replicate(std::slice::from_ref(&entry.id)).await;
+        Ok(entry)
+    }
+
+    /// Count changed entries towards the next periodic backup
+    fn count_changes(&self, changes: usize) {
+        self.changed_since_backup.fetch_add(changes as u32, Ordering::Relaxed);
+    }
+
+    async fn save_journaled(&self, storage: &mut Box<dyn Storage>, mut entry: LogEntry) -> Result<LogEntry, StorageError> {
+        let before = storage.get_entry(&entry.id).await?;
+        if let Some(before) = before.as_ref().filter(|b| journal::diff_entries(Some(b), Some(&entry)).is_empty()) {
+            return Ok(before.clone());
+        }
+        Self::stamp(before.as_ref(), &mut entry);
+        if let Some(before) = &before {
+            Self::keep_revision(storage, before).await?;
+        }
+        storage.save_entry(entry.clone()).await?;
+        Self::record(storage, before, Some(entry.clone())).await?;
+        self.count_changes(1);
+        Ok(entry)
+    }
+
+    /// Carry the creation time over from the stored version and mark the
+    /// entry as changed now
+    fn stamp(before: Option<&LogEntry>, entry: &mut LogEntry) {
+        let now = chrono::Utc::now();
+        entry.created_at = before.and_then(|b| b.created_at).or(entry.created_at).or(Some(now));
+        entry.updated_at = Some(now);
+    }
+
+    /// Add the stored version of an entry to its revision history
+    async fn keep_revision(storage: &mut Box<dyn Storage>, current: &LogEntry) -> Result<(), StorageError> {
+        let number = Self::next_revision(&**storage, &current.id).await?;
+        storage.add_revision(Revision::new(number, current.clone())).await
+    }
+
+    /// Number the next revision of an entry will have
+    async fn next_revision(storage: &dyn Storage, id: &str) -> Result<u32, StorageError> {
+        Ok(storage.revisions(id).await?.last().map_or(1, |revision| revision.number + 1))
+    }
+
+    /// Journal a change that has been applied. Changes that leave the entry
+    /// as it was are not recorded.
+    async fn record(
+        storage: &mut Box<dyn Storage>,
+        before: Option<LogEntry>,
+        after: Option<LogEntry>,
+    ) -> Result<(), StorageError> {
+        if journal::diff_entries(before.as_ref(), after.as_ref()).is_empty() {
+            return Ok(());
+        }
+        let seq = Self::next_seq(&**storage).await?;
+        storage.append_journal(JournalRecord::new(seq, before, after)).await
+    }
+
+    /// Sequence number for the next change: after the last change still in
+    /// effect, dropping the redo history
+    async fn next_seq(storage: &dyn Storage) -> Result<u64, StorageError> {
+        Ok(storage.last_seq().await? + 1)
+    }
+
+    /// Save many entries the way `save_journaled` saves one, but read the
+    /// log and journal once and write the entries, their revisions and
+    /// journal records in one go. Returns the ids of the entries that
+    /// changed.
+    async fn save_journaled_batch(&self, storage: &mut Box<dyn Storage>, entries: Vec<LogEntry>) -> Result<Vec<String>, StorageError> {
+        let mut stored: HashMap<String, LogEntry> = storage.list_entries().await?
+            .into_iter()
+            .map(|entry| (entry.id.clone(), entry))
+            .collect();
+        let mut seq = Self::next_seq(&**storage).await?;
+        let mut changed = Vec::new();
+        let mut revisions: Vec<Revision> = Vec::new();
+        let mut records = Vec::new();
+        for mut entry in entries {
+            let before = stored.get(&entry.id).cloned();
+            if before.as_ref().is_some_and(|b| journal::diff_entries(Some(b), Some(&entry)).is_empty()) {
+                continue;
+            }
+            Self::stamp(before.as_ref(), &mut entry);
+            if let Some(before) = &before {
+                // An entry saved twice in the batch has its first revision here already
+                let number = match revisions.iter().rev().find(|revision| revision.entry_id == before.id) {
+                    Some(revision) => revision.number + 1,
+                    None => Self::next_revision(&**storage, &before.id).await?,
+                };
+                revisions.push(Revision::new(number, before.clone()));
+            }
+            records.push(JournalRecord::new(seq, before, Some(entry.clone())));
+            seq += 1;
+            stored.insert(entry.id.clone(), entry.clone());
+            changed.push(entry);
+        }
+
+        let ids: Vec<String> = changed.iter().map(|entry| entry.id.clone()).collect();
+        storage.save_batch(changed, revisions, records).await?;
+        self.count_changes(ids.len());
+        Ok(ids)
+    }
+
+    /// Replace the whole log with `entries` in one step, journaling each
+    /// entry that differs from its version in `before` so the rewrite can
+    /// be undone like any other change
+    async fn replace_journaled(
+        storage: &mut Box<dyn Storage>,
+        mut before: HashMap<String, LogEntry>,
+        entries: Vec<LogEntry>,
+    ) -> Result<(), StorageError> {
+        let mut seq = Self::next_seq(&**storage).await?;
+        let mut records = Vec::new();
+        let mut replaced = Vec::with_capacity(entries.len());
+        for mut entry in entries {
+            let previous = before.remove(&entry.id);
+            if !journal::diff_entries(previous.as_ref(), Some(&entry)).is_empty() {
+                Self::stamp(previous.as_ref(), &mut entry);
+                if let Some(previous) = &previous {
+                    Self::keep_revision(storage, previous).await?;
+                }
+                records.push(JournalRecord::new(seq, previous, Some(entry.clone())));
+                seq += 1;
+            }
+            replaced.push(entry);
+        }
+
+        storage.replace_entries(replaced).await?;
+        for record in records {
+            storage.append_journal(record).await?;
+        }
+        Ok(())
+    }
+
+    /// Put an entry back the way a journal image has it; `None` removes it
+    async fn restore(storage: &mut Box<dyn Storage>, id: &str, image: Option<LogEntry>) -> Result<(), StorageError> {
+        let current = storage.get_entry(id).await?;
+        if let Some(current) = &current {
+            Self::keep_revision(storage, current).await?;
+        }
+        match image {
+            Some(entry) => storage.save_entry(entry).await,
+            None if current.is_some() => storage.delete_entry(id).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Revert the latest change still in effect. Returns the undone record,
+    /// or `None` when there is nothing to undo.
+    pub async fn undo(&self) -> Result<Option<JournalRecord>, StorageError> {
+        let mut log = self.log.write().await;
+        let Some(record) = log.primary.journal_record(log.primary.last_seq().await?).await? else {
+            return Ok(None);
+        };
+        Self::restore(&mut log.primary, &record.entry_id, record.before.clone()).await?;
+        log.primary.mark_journal(record.seq, true).await?;
+        log.replicate(std::slice::from_ref(&record.entry_id)).await;
+        Ok(Some(record))
+    }
+
+    /// Re-apply the earliest undone change
+    pub async fn redo(&self) -> Result<Option<JournalRecord>, StorageError> {
+        let mut log = self.log.write().await;
+        let next = log.primary.last_seq().await? + 1;
+        let Some(record) = log.primary.journal_record(next).await?.filter(|r| r.undone) else {
+            return Ok(None);
+        };
+        Self::restore(&mut log.primary, &record.entry_id, record.after.clone()).await?;
+        log.primary.mark_journal(record.seq, false).await?;
+        log.replicate(std::slice::from_ref(&record.entry_id)).await;
+        Ok(Some(record))
+    }
+
+    /// Check the log for damaged records, duplicate ids and impossible
+    /// values. With `repair`, the log is backed up and what can be fixed
+    /// is; `custom_fields` names the custom fields something uses.
+    pub async fn fsck(&self, custom_fields: &[String], repair: bool) -> Result<FsckReport, StorageError> {
+        if !repair {
+            let log = self.log.read().await;
+            let scan = log.primary.scan().await?;
+            let custom_fields = Self::with_defined(&*log.primary, custom_fields).await?;
+            let check = fsck::check(&scan, &custom_fields, chrono::Utc::now());
+            let mut problems = check.problems;
+            problems.extend(fsck::check_history(&log.primary.damaged_history()));
+            return Ok(FsckReport {
+                records: scan.entries.len() + scan.unreadable.len(),
+                repaired: false,
+                problems,
+            });
+        }
+
+        // Nothing may change between the check and the repair
+        let mut log = self.log.write().await;
+        let scan = log.primary.scan().await?;
+        let custom_fields = Self::with_defined(&*log.primary, custom_fields).await?;
+        let check = fsck::check(&scan, &custom_fields, chrono::Utc::now());
+        let rewrite = check.needs_rewrite();
+        let history = log.primary.damaged_history();
+        let mut problems = check.problems.clone();
+        problems.extend(fsck::check_history(&history));
+        let report = FsckReport {
+            records: scan.entries.len() + scan.unreadable.len(),
+            repaired: check.fixable() > 0 || !history.is_empty(),
+            problems,
+        };
+        if !report.repaired {
+            return Ok(report);
+        }
+
+        self.write_backup(&*log.primary, BackupReason::PreRepair).await?;
+        if !history.is_empty() {
+            let lines: Vec<String> = history.into_iter().map(|line| line.raw).collect();
+            fsck::keep_lost(&self.path, &lines)?;
+            log.primary.drop_damaged_history().await?;
+        }
+        if check.fixable() == 0 {
+            return Ok(report);
+        }
+        if rewrite {
+            // Records are dropped or renamed, which only a rewrite can do
+            fsck::keep_lost(&self.path, &check.removed)?;
+            let before = scan.entries.into_iter().map(|entry| (entry.id.clone(), entry)).collect();
+            Self::replace_journaled(&mut log.primary, before, check.entries).await?;
+        } else {
+            // Value fixes are ordinary changes that can be undone
+            for entry in check.entries.into_iter().filter(|entry| check.changed.contains(&entry.id)) {
+                self.save_journaled(&mut log.primary, entry).await?;
+            }
+        }
+        log.resync().await?;
+        Ok(report)
+    }
+
+    /// Custom field names something uses, along with those defined for the log
+    async fn with_defined(storage: &dyn Storage, custom_fields: &[String]) -> Result<Vec<String>, StorageError> {
+        let mut names = custom_fields.to_vec();
+        names.extend(storage.field_definitions().await?.into_iter().map(|definition| definition.name));
+        Ok(names)
+    }
+
+    /// Merge changes other programs made to the log file
+    pub async fn refresh(&self) -> Result<ExternalChanges, StorageError> {
+        let mut log = self.log.write().await;
+        let changes = log.primary.refresh().await?;
+        let ids: Vec<String> = changes.added.iter().chain(&changes.modified).chain(&changes.removed).cloned().collect();
+        log.replicate(&ids).await;
+        Ok(changes)
+    }
+
+    /// Problems met while opening the log, for the user to see
+    pub async fn warnings(&self) -> Vec<String> {
+        let log = self.log.read().await;
+        let mut warnings = log.primary.warnings();
+        let damaged = log.primary.damaged_history().len();
+        if damaged > 0 {
+            warnings.push(format!(
+                "Skipped {} damaged lines of the undo journal and revision history; run amlog fsck --fix",
+                damaged,
+            ));
+        }
+        warnings
+    }
+
+    /// The operation journal, oldest first
+    pub async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError> {
+        self.log.read().await.primary.journal().await
+    }
+
+    /// Earlier versions of an entry, oldest first
+    pub async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError> {
+        self.log.read().await.primary.revisions(id).await
+    }
+
+    /// Bring back an earlier version of an entry. The version it replaces
+    /// becomes a revision and the change can be undone like any other.
+    pub async fn restore_revision(&self, id: &str, number: u32) -> Result<LogEntry, StorageError> {
+        let mut log = self.log.write().await;
+        let revision = log.primary.revisions(id).await?
+            .into_iter()
+            .find(|revision| revision.number == number)
+            .ok_or_else(|| StorageError::NotFound(format!("revision {} of {}", number, id)))?;
+        let entry = self.save_journaled(&mut log.primary, revision.entry).await?;
+        log.replicate(std::slice::from_ref(&entry.id)).await;
+        Ok(entry)
     }
 
     pub async fn list_entries(&self) -> Result<Vec<LogEntry>, StorageError> {
-        let storage = self.storage.lock().await;
-        storage.list_entries().await
+        self.log.read().await.primary.list_entries().await
+    }
+
+    /// Entries matching a full-text query, best match first
+    pub async fn search_text(&self, query: &str) -> Result<Vec<SearchHit>, StorageError> {
+        self.log.read().await.primary.search_text(query).await
+    }
+
+    /// Custom fields registered for the log, in the order they were added
+    pub async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError> {
+        self.log.read().await.primary.field_definitions().await
+    }
+
+    /// Register a custom field or change its type. Values already logged
+    /// are put in the field's form as a change that can be undone; if any
+    /// does not fit, nothing changes.
+    pub async fn define_field(&self, definition: FieldDefinition) -> Result<(), StorageError> {
+        let mut log = self.log.write().await;
+        let mut changed = Vec::new();
+        for mut entry in log.primary.list_entries().await? {
+            let Some(value) = entry.custom_fields.get(&definition.name) else {
+                continue;
+            };
+            let normalized = definition.normalize(value)?;
+            if normalized != *value {
+                entry.custom_fields.insert(definition.name.clone(), normalized);
+                changed.push(entry);
+            }
+        }
+        let ids = self.save_journaled_batch(&mut log.primary, changed).await?;
+        log.primary.define_field(definition).await?;
+        log.sync_fields().await;
+        log.replicate(&ids).await;
+        Ok(())
+    }
+
+    /// Drop a custom field definition. Values already logged are kept.
+    pub async fn remove_field(&self, name: &str) -> Result<(), StorageError> {
+        let mut log = self.log.write().await;
+        log.primary.remove_field(name).await?;
+        log.sync_fields().await;
+        Ok(())
+    }
+
+    /// Entries selected and ordered by custom field values
+    pub async fn query_fields(&self, query: &FieldQuery) -> Result<Vec<LogEntry>, StorageError> {
+        self.log.read().await.primary.query_fields(query).await
+    }
+
+    pub async fn delete_entry(&self, id: &str) -> Result<(), StorageError> {
+        let mut log = self.log.write().await;
+        let before = log.primary.get_entry(id).await?;
+        if let Some(before) = &before {
+            Self::keep_revision(&mut log.primary, before).await?;
+        }
+        log.primary.delete_entry(id).await?;
+        if before.is_some() {
+            self.count_changes(1);
+        }
+        Self::record(&mut log.primary, before, None).await?;
+        log.replicate(&[id.to_string()]).await;
+        Ok(())
+    }
+
+    /// Store an entry as another station sent it. The change keeps its
+    /// timestamps and the version it replaces becomes a revision, but it is
+    /// not journaled: undo here only reverts changes made here, and the
+    /// redo history survives.
+    pub async fn apply_remote(&self, mut entry: LogEntry) -> Result<LogEntry, StorageError> {
+        let mut log = self.log.write().await;
+        fields::normalize_entry(&log.primary.field_definitions().await?, &mut entry)?;
+        if let Some(before) = log.primary.get_entry(&entry.id).await? {
+            if journal::diff_entries(Some(&before), Some(&entry)).is_empty() {
+                return Ok(before);
+            }
+            Self::keep_revision(&mut log.primary, &before).await?;
+        }
+        log.primary.save_entry(entry.clone()).await?;
+        log.replicate(std::slice::from_ref(&entry.id)).await;
+        self.count_changes(1);
+        Ok(entry)
     }
 
-    pub async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError> {
-        let mut storage = self.storage.lock().await;
-        storage.delete_entry(id).await
+    /// Remove an entry another station deleted, without journaling it
+    pub async fn apply_remote_delete(&self, id: &str) -> Result<(), StorageError> {
+        let mut log = self.log.write().await;
+        let Some(before) = log.primary.get_entry(id).await? else {
+            return Ok(());
+        };
+        Self::keep_revision(&mut log.primary, &before).await?;
+        log.primary.delete_entry(id).await?;
+        log.replicate(&[id.to_string()]).await;
+        self.count_changes(1);
+        Ok(())
     }
 
-    pub async fn add_entry(&mut self, entry: LogEntry) -> Result<(), StorageError> {
-        let mut storage = self.storage.lock().await;
-        storage.add_entry(entry).await
+    /// Add a new entry, journaling the change. Returns the entry as stored.
+    pub async fn add_entry(&self, mut entry: LogEntry) -> Result<LogEntry, StorageError> {
+        let mut log = self.log.write().await;
+        fields::normalize_entry(&log.primary.field_definitions().await?, &mut entry)?;
+        Self::stamp(None, &mut entry);
+        log.primary.add_entry(entry.clone()).await?;
+        Self::record(&mut log.primary, None, Some(entry.clone())).await?;
+        log.replicate(std::slice::from_ref(&entry.id)).await;
+        self.count_changes(1);
+        Ok(entry)
     }
     pub async fn export_adif(&self) -> Result<String, StorageError> {
-        let entries = self.list_entries().await?;
-        
+        let log = self.log.read().await;
+        let definitions = log.primary.field_definitions().await?;
         match self.format {
-            StorageFormat::Adif => {
-                let storage = self.storage.lock().await;
+            // Typed custom fields need a header declaring them
+            StorageFormat::Adif if definitions.is_empty() => {
                 // Since we're already using ADIF storage, just read the file
-                std::fs::read_to_string(storage.path())
+                std::fs::read_to_string(log.primary.path())
                     .map_err(StorageError::Io)
             },
             _ => {
                 // Convert entries to ADIF format
-                // let adif_storage = AdifStorage::new(&PathBuf::from(""))?;
-                Ok(AdifStorage::entries_to_adif(&entries))
+                Ok(AdifStorage::export(&log.primary.list_entries().await?, &definitions))
             }
         }
     }
 
-    pub async fn import_adif(&mut self, content: &str) -> Result<(), StorageError> {
-        // let temp_storage = AdifStorage::new(&PathBuf::from(""))?;
+    /// Import the QSOs of an ADIF file. USERDEF fields it declares that the
+    /// log does not have yet are registered; if a value does not fit its
+    /// field, nothing is imported.
+    pub async fn import_adif(&self, content: &str) -> Result<(), StorageError> {
         let entries = AdifStorage::adif_to_entries(content);
 
-        let mut storage = self.storage.lock().await;
-        if let Ok(entries) = entries {
-            for log_entry in entries {
-                storage.save_entry(log_entry).await?;
+        let mut log = self.log.write().await;
+        if let Ok(mut entries) = entries {
+            let mut definitions = log.primary.field_definitions().await?;
+            let added: Vec<FieldDefinition> = AdifStorage::userdefs(content).into_iter()
+                .filter(|userdef| fields::find(&definitions, &userdef.name).is_none())
+                .collect();
+            definitions.extend(added.iter().cloned());
+            for entry in &mut entries {
+                fields::normalize_entry(&definitions, entry)?;
+            }
+
+            for definition in added {
+                log.primary.define_field(definition).await?;
             }
+            log.sync_fields().await;
+            let ids = self.save_journaled_batch(&mut log.primary, entries).await?;
+            log.replicate(&ids).await;
         }
 
         Ok(())
     }
 
+    /// Apply a LoTW/eQSL confirmation report to existing entries. Only the
+    /// QSL-received fields of matching QSOs change; nothing is created.
+    pub async fn reconcile_confirmations(
+        &self,
+        content: &str,
+        source: ConfirmationSource,
+        tolerance: chrono::Duration,
+    ) -> Result<ReconcileReport, StorageError> {
+        let mut log = self.log.write().await;
+        let entries = log.primary.list_entries().await?;
+        let (updates, report) = reconcile::reconcile(&entries, content, source, tolerance);
+
+        let ids = self.save_journaled_batch(&mut log.primary, updates).await?;
+        log.replicate(&ids).await;
+        Ok(report)
+    }
+
     pub fn get_format(&self) -> StorageFormat {
         self.format
     }
@@ -90,4 +664,417 @@ impl StorageManager {
     pub fn get_path(&self) -> &PathBuf {
         &self.path
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Frequency;
+    use crate::storage::formats::fixtures::qso;
+    use crate::storage::JournalOp;
+
+    #[tokio::test]
+    async fn test_undo_redo_across_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        for (format, file) in [(StorageFormat::Sqlite, "log.db"), (StorageFormat::Adif, "log.adi"), (StorageFormat::Json, "log.json"), (StorageFormat::JsonLines, "log.jsonl")] {
+            let path = dir.path().join(file);
+            {
+                let manager = StorageManager::new(format, path.clone()).await.unwrap();
+                manager.save_entry(qso("1", "W1AW", "CW")).await.unwrap();
+                manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+                manager.delete_entry("1").await.unwrap();
+            }
+
+            let manager = StorageManager::new(format, path.clone()).await.unwrap();
+            assert_eq!(manager.journal().await.unwrap().len(), 3);
+            let undone = manager.undo().await.unwrap().unwrap();
+            assert_eq!(undone.op, JournalOp::Delete);
+            assert_eq!(manager.undo().await.unwrap().unwrap().op, JournalOp::Update);
+            let entries = manager.list_entries().await.unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].mode, "CW");
+
+            let manager = StorageManager::new(format, path).await.unwrap();
+            assert_eq!(manager.redo().await.unwrap().unwrap().op, JournalOp::Update);
+            assert_eq!(manager.list_entries().await.unwrap()[0].mode, "SSB");
+
+            // A new change drops the remaining redo history
+            manager.save_entry(qso("1", "W1AW", "FM")).await.unwrap();
+            assert!(manager.redo().await.unwrap().is_none());
+            assert_eq!(manager.journal().await.unwrap().len(), 3);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_revisions_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        for (format, file) in [(StorageFormat::Sqlite, "log.db"), (StorageFormat::Adif, "log.adi"), (StorageFormat::Json, "log.json"), (StorageFormat::JsonLines, "log.jsonl")] {
+            let path = dir.path().join(file);
+            let manager = StorageManager::new(format, path.clone()).await.unwrap();
+            let created = manager.add_entry(qso("1", "W1AW", "CW")).await.unwrap();
+            assert!(created.created_at.is_some());
+            manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+            // Saving without changes keeps no revision
+            manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+
+            let manager = StorageManager::new(format, path).await.unwrap();
+            let current = manager.list_entries().await.unwrap().remove(0);
+            assert_eq!(current.created_at, created.created_at);
+            assert!(current.updated_at >= created.updated_at);
+            let revisions = manager.revisions("1").await.unwrap();
+            assert_eq!(revisions.len(), 1);
+            assert_eq!(revisions[0].entry.mode, "CW");
+
+            let restored = manager.restore_revision("1", 1).await.unwrap();
+            assert_eq!(restored.mode, "CW");
+            assert_eq!(restored.created_at, created.created_at);
+            let revisions = manager.revisions("1").await.unwrap();
+            assert_eq!(revisions.iter().map(|r| r.number).collect::<Vec<_>>(), vec![1, 2]);
+            assert_eq!(revisions[1].entry.mode, "SSB");
+
+            // Restoring is an ordinary change that can be undone
+            manager.undo().await.unwrap();
+            assert_eq!(manager.list_entries().await.unwrap()[0].mode, "SSB");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconciled_confirmations_keep_revisions() {
+        use chrono::TimeZone;
+
+        let dir = tempfile::tempdir().unwrap();
+        let manager = StorageManager::new(StorageFormat::Json, dir.path().join("log.json")).await.unwrap();
+        let entry = LogEntry {
+            frequency: Frequency::from_hz(14_025_000),
+            timestamp: chrono::Utc.with_ymd_and_hms(2024, 5, 1, 12, 5, 0).unwrap(),
+            ..qso("1", "DL1ABC", "CW")
+        };
+        let created = manager.add_entry(entry).await.unwrap();
+
+        let report = "<PROGRAMID:4>LoTW <eoh>
+<CALL:6>DL1ABC <BAND:3>20M <MODE:2>CW <QSO_DATE:8>20240501 <TIME_ON:4>1205
+<QSL_RCVD:1>Y <QSLRDATE:8>20240510 <eor>";
+        let report = manager
+            .reconcile_confirmations(report, ConfirmationSource::Lotw, chrono::Duration::minutes(30))
+            .await
+            .unwrap();
+        assert_eq!(report.confirmed, vec!["1".to_string()]);
+
+        let confirmed = manager.list_entries().await.unwrap().remove(0);
+        assert!(confirmed.lotw.is_confirmed());
+        assert!(confirmed.updated_at > created.updated_at);
+        let revisions = manager.revisions("1").await.unwrap();
+        assert_eq!(revisions.len(), 1);
+        assert!(!revisions[0].entry.lotw.is_confirmed());
+        assert_eq!(manager.journal().await.unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_backup_and_restore() {
+        let dir = tempfile::tempdir().unwrap();
+        for (format, file) in [(StorageFormat::Sqlite, "log.db"), (StorageFormat::Adif, "log.adi"), (StorageFormat::Json, "log.json"), (StorageFormat::JsonLines, "log.jsonl")] {
+            let path = dir.path().join(file);
+            let config = BackupConfig { dir: dir.path().join(format!("{}-backups", file)), every: 2, keep: 3 };
+            let manager = StorageManager::new(format, path.clone()).await.unwrap().with_backups(config);
+
+            manager.add_entry(qso("1", "W1AW", "CW")).await.unwrap();
+            assert!(manager.backup_if_due().await.unwrap().is_none());
+            manager.add_entry(qso("2", "K1ABC", "SSB")).await.unwrap();
+            let periodic = manager.backup_if_due().await.unwrap().unwrap();
+            assert_eq!(periodic.reason, BackupReason::Periodic);
+            assert!(manager.backup_if_due().await.unwrap().is_none());
+
+            manager.delete_entry("1").await.unwrap();
+            manager.save_entry(qso("2", "K1ABC", "FM")).await.unwrap();
+            let previous = manager.restore_backup(&periodic).await.unwrap();
+            assert_eq!(previous.reason, BackupReason::PreRestore);
+            let mut entries = manager.list_entries().await.unwrap();
+            entries.sort_by(|a, b| a.id.cmp(&b.id));
+            assert_eq!(entries.iter().map(|e| e.mode.as_str()).collect::<Vec<_>>(), vec!["CW", "SSB"]);
+            // The journal comes back with the log, so undo matches it
+            assert_eq!(manager.journal().await.unwrap().len(), 2);
+
+            // The restore can be reverted from the backup it took
+            let previous = manager.find_backup(&previous.name()).unwrap();
+            manager.restore_backup(&previous).await.unwrap();
+            let entries = manager.list_entries().await.unwrap();
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].mode, "FM");
+
+            // Only the newest backups are kept
+            manager.backup(BackupReason::Manual).await.unwrap();
+            let backups = manager.backups().unwrap();
+            assert_eq!(backups.len(), 3);
+            assert!(!backups.contains(&periodic));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_periodic_backups_count_every_change() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = BackupConfig { dir: dir.path().join("backups"), every: 4, keep: 0 };
+        let manager = StorageManager::new(StorageFormat::Json, dir.path().join("log.json")).await.unwrap().with_backups(config);
+
+        manager.save_entry(qso("1", "W1AW", "CW")).await.unwrap();
+        manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+        manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+        manager.apply_remote(qso("2", "K1ABC", "FT8")).await.unwrap();
+        assert!(manager.backup_if_due().await.unwrap().is_none());
+        manager.import_adif(&AdifStorage::entries_to_adif(&[qso("3", "N0CALL", "CW")])).await.unwrap();
+        assert!(manager.backup_if_due().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_failed_batch_leaves_no_revisions_or_journal() {
+        use sqlx::Connection;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.db");
+        let manager = StorageManager::new(StorageFormat::Sqlite, path.clone()).await.unwrap();
+        manager.add_entry(qso("1", "W1AW", "CW")).await.unwrap();
+
+        // The journal record of the second entry cannot be written
+        let mut connection = sqlx::SqliteConnection::connect(&format!("sqlite://{}", path.display())).await.unwrap();
+        sqlx::query("CREATE TRIGGER fail BEFORE INSERT ON journal WHEN NEW.entry_id = '2' BEGIN SELECT RAISE(ABORT, 'full'); END")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        connection.close().await.unwrap();
+
+        let adif = AdifStorage::entries_to_adif(&[qso("1", "W1AW", "SSB"), qso("2", "K1ABC", "FT8")]);
+        assert!(manager.import_adif(&adif).await.is_err());
+        let entries = manager.list_entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].mode, "CW");
+        assert!(manager.revisions("1").await.unwrap().is_empty());
+        assert_eq!(manager.journal().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fsck_repairs_damaged_log() {
+        use sqlx::Connection;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.db");
+        let manager = StorageManager::new(StorageFormat::Sqlite, path.clone()).await.unwrap();
+        manager.add_entry(LogEntry { frequency: Frequency::from_hz(14_074_000), ..qso("1", "W1AW", "CW") }).await.unwrap();
+        manager.add_entry(LogEntry { frequency: Frequency::from_hz(14_074_000), band: Some("40m".into()), ..qso("2", "K1ABC", "CW") }).await.unwrap();
+
+        // A damaged timestamp makes the whole log fail to load
+        let mut connection = sqlx::SqliteConnection::connect(&format!("sqlite://{}", path.display())).await.unwrap();
+        sqlx::query("UPDATE log_entries SET timestamp = 'yesterday' WHERE id = '1'")
+            .execute(&mut connection)
+            .await
+            .unwrap();
+        connection.close().await.unwrap();
+        assert!(manager.list_entries().await.is_err());
+
+        let report = manager.fsck(&[], false).await.unwrap();
+        assert_eq!(report.records, 2);
+        assert_eq!(report.problems.iter().map(|p| p.kind).collect::<Vec<_>>(),
+            vec![fsck::ProblemKind::Unreadable, fsck::ProblemKind::BandMismatch]);
+        assert!(!report.repaired);
+        assert_eq!(manager.backups().unwrap().len(), 0);
+
+        let report = manager.fsck(&[], true).await.unwrap();
+        assert!(report.repaired);
+        let entries = manager.list_entries().await.unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].band.as_deref(), Some("20m"));
+        let lost = std::fs::read_to_string(fsck::lost_found_path(&path)).unwrap();
+        assert!(lost.contains("yesterday"));
+        assert_eq!(manager.backups().unwrap()[0].reason, BackupReason::PreRepair);
+        assert!(manager.fsck(&[], false).await.unwrap().problems.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fsck_moves_damaged_journal_lines_to_lost_found() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.json");
+        {
+            let manager = StorageManager::new(StorageFormat::Json, path.clone()).await.unwrap();
+            manager.save_entry(qso("1", "W1AW", "CW")).await.unwrap();
+            manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+        }
+        let journal_path = journal::JournalFile::path_for(&path);
+        let content = std::fs::read_to_string(&journal_path).unwrap();
+        std::fs::write(&journal_path, content.replacen("{", "{damaged", 1)).unwrap();
+
+        // The log still opens, with a warning, and undo works on what is left
+        let manager = StorageManager::new(StorageFormat::Json, path.clone()).await.unwrap();
+        assert_eq!(manager.warnings().await.len(), 1);
+        assert_eq!(manager.journal().await.unwrap().len(), 1);
+
+        let report = manager.fsck(&[], false).await.unwrap();
+        assert_eq!(report.problems.iter().map(|p| p.kind).collect::<Vec<_>>(), vec![fsck::ProblemKind::DamagedHistory]);
+        assert!(manager.fsck(&[], true).await.unwrap().repaired);
+        assert!(std::fs::read_to_string(fsck::lost_found_path(&path)).unwrap().contains("{damaged"));
+
+        let manager = StorageManager::new(StorageFormat::Json, path).await.unwrap();
+        assert!(manager.warnings().await.is_empty());
+        assert_eq!(manager.undo().await.unwrap().unwrap().op, JournalOp::Update);
+        assert_eq!(manager.list_entries().await.unwrap()[0].mode, "CW");
+    }
+
+    #[tokio::test]
+    async fn test_remote_changes_are_not_journaled() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = StorageManager::new(StorageFormat::Sqlite, dir.path().join("log.db")).await.unwrap();
+        manager.save_entry(qso("1", "W1AW", "CW")).await.unwrap();
+        manager.save_entry(qso("1", "W1AW", "SSB")).await.unwrap();
+        manager.undo().await.unwrap();
+
+        manager.apply_remote(qso("2", "K1ABC", "FT8")).await.unwrap();
+        manager.apply_remote(qso("1", "W1AW", "RTTY")).await.unwrap();
+        manager.apply_remote_delete("2").await.unwrap();
+        assert_eq!(manager.journal().await.unwrap().len(), 2);
+        assert_eq!(manager.revisions("1").await.unwrap().len(), 3);
+
+        // Redo still applies the local change, and undo never reaches the
+        // remote ones
+        assert_eq!(manager.redo().await.unwrap().unwrap().op, JournalOp::Update);
+        assert_eq!(manager.undo().await.unwrap().unwrap().op, JournalOp::Update);
+        assert_eq!(manager.undo().await.unwrap().unwrap().op, JournalOp::Create);
+        assert!(manager.undo().await.unwrap().is_none());
+        assert!(manager.list_entries().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fsck_value_fixes_can_be_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = StorageManager::new(StorageFormat::JsonLines, dir.path().join("log.jsonl")).await.unwrap();
+        let mut entry = LogEntry { frequency: Frequency::from_hz(-7_074_000), band: None, ..qso("1", "W1AW", "CW") };
+        entry.custom_fields.insert("CLASS".to_string(), "1D".to_string());
+        manager.add_entry(entry).await.unwrap();
+
+        let report = manager.fsck(&[], true).await.unwrap();
+        assert_eq!(report.problems.len(), 2);
+        assert_eq!(report.fixable(), 1);
+        let fixed = manager.list_entries().await.unwrap().remove(0);
+        assert_eq!(fixed.frequency, Frequency::from_hz(7_074_000));
+        assert_eq!(fixed.custom_fields.get("CLASS").map(String::as_str), Some("1D"));
+
+        manager.undo().await.unwrap();
+        assert_eq!(manager.list_entries().await.unwrap()[0].frequency, Frequency::from_hz(-7_074_000));
+    }
+
+    #[tokio::test]
+    async fn test_fsck_rewrite_keeps_adif_header_and_can_be_undone() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log.adi");
+        let header = "Exported by another logger\n<USERDEF1:5:S>PAPER\n<EOH>";
+        std::fs::write(&path, format!(
+            "{}\n<CALL:4>K1AB<QSO_DATE:8>20240327<EOR>\n\
+            <CALL:4>W1AW<QSO_DATE:8>20240327<TIME_ON:4>1430<FREQ:6>-7.074<MODE:2>CW<PAPER:2>no<EOR>\n",
+            header,
+        )).unwrap();
+        let manager = StorageManager::new(StorageFormat::Adif, path.clone()).await.unwrap();
+
+        let report = manager.fsck(&[], true).await.unwrap();
+        assert!(report.repaired);
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with(header));
+        assert!(!content.contains("K1AB"));
+        assert!(std::fs::read_to_string(fsck::lost_found_path(&path)).unwrap().contains("K1AB"));
+        let fixed = manager.list_entries().await.unwrap().remove(0);
+        assert_eq!(fixed.frequency, Frequency::from_hz(7_074_000));
+        assert_eq!(fixed.custom_fields.get("PAPER").map(String::as_str), Some("no"));
+
+        // The value fix made by the rewrite is journaled
+        assert_eq!(manager.undo().await.unwrap().unwrap().op, JournalOp::Update);
+        assert_eq!(manager.list_entries().await.unwrap()[0].frequency, Frequency::from_hz(-7_074_000));
+    }
+
+    #[tokio::test]
+    async fn test_mirrors_follow_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let adif = dir.path().join("mirror.adi");
+        let json = dir.path().join("mirror").join("log.json");
+        let manager = StorageManager::new(StorageFormat::Sqlite, dir.path().join("log.db")).await.unwrap();
+        manager.add_mirror(StorageFormat::Adif, adif.clone()).await.unwrap();
+        manager.add_mirror(StorageFormat::Json, json.clone()).await.unwrap();
+        assert!(manager.add_mirror(StorageFormat::Json, json.clone()).await.is_err());
+
+        manager.add_entry(qso("1", "W1AW", "CW")).await.unwrap();
+        manager.add_entry(qso("2", "K1ABC", "SSB")).await.unwrap();
+        manager.save_entry(qso("2", "K1ABC", "FM")).await.unwrap();
+        manager.delete_entry("1").await.unwrap();
+        let mirrored = [
+            AdifStorage::new(&adif).unwrap().list_entries().await.unwrap(),
+            JsonStorage::new(&json).unwrap().list_entries().await.unwrap(),
+        ];
+        for entries in mirrored {
+            assert_eq!(entries.iter().map(|e| (e.id.as_str(), e.mode.as_str())).collect::<Vec<_>>(), vec![("2", "FM")]);
+        }
+
+        // A mirror that cannot be written falls behind without failing the save
+        fs::remove_dir_all(json.parent().unwrap()).unwrap();
+        manager.add_entry(qso("3", "N0CALL", "CW")).await.unwrap();
+        let status = manager.mirrors().await;
+        assert_eq!((status[0].pending, status[1].pending), (0, 1));
+        assert!(status[1].last_error.is_some());
+
+        fs::create_dir_all(json.parent().unwrap()).unwrap();
+        manager.retry_mirrors().await;
+        assert!(manager.mirrors().await.iter().all(|mirror| mirror.pending == 0 && mirror.last_error.is_none()));
+        assert_eq!(JsonStorage::new(&json).unwrap().list_entries().await.unwrap().len(), 2);
+
+        // Undo reaches the mirrors too
+        manager.undo().await.unwrap();
+        assert_eq!(JsonStorage::new(&json).unwrap().list_entries().await.unwrap().len(), 1);
+        drop(manager);
+
+        // On the next start a lost mirror is rewritten and a current one left alone
+        fs::remove_file(&adif).unwrap();
+        let manager = StorageManager::new(StorageFormat::Sqlite, dir.path().join("log.db")).await.unwrap();
+        manager.add_mirror(StorageFormat::Adif, adif.clone()).await.unwrap();
+        manager.add_mirror(StorageFormat::Json, json.clone()).await.unwrap();
+        assert_eq!(manager.resync_mirrors().await.unwrap(), 1);
+        assert_eq!(manager.resync_mirrors().await.unwrap(), 0);
+        assert_eq!(AdifStorage::new(&adif).unwrap().list_entries().await.unwrap()[0].mode, "FM");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_clones_share_the_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = StorageManager::new(StorageFormat::Sqlite, dir.path().join("log.db")).await.unwrap();
+
+        let tasks: Vec<_> = (0..8).map(|i| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager.add_entry(qso(&i.to_string(), "W1AW", "CW")).await.unwrap();
+                manager.list_entries().await.unwrap().len()
+            })
+        }).collect();
+        for task in tasks {
+            assert!(task.await.unwrap() >= 1);
+        }
+
+        assert_eq!(manager.list_entries().await.unwrap().len(), 8);
+        // Every change was journaled in turn
+        let seqs: Vec<u64> = manager.journal().await.unwrap().iter().map(|record| record.seq).collect();
+        assert_eq!(seqs, (1..=8).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_reads_share_the_log_and_writes_wait_for_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = StorageManager::new(StorageFormat::Sqlite, dir.path().join("log.db")).await.unwrap();
+        manager.add_entry(qso("1", "W1AW", "CW")).await.unwrap();
+
+        // A long read, such as an export, holds the log for reading
+        let export = manager.log.read().await;
+        let wait = std::time::Duration::from_millis(200);
+        let entries = tokio::time::timeout(wait, manager.list_entries()).await;
+        assert_eq!(entries.expect("a read waited for another read").unwrap().len(), 1);
+
+        let writer = manager.clone();
+        let save = tokio::spawn(async move { writer.add_entry(qso("2", "K1ABC", "CW")).await });
+        tokio::time::sleep(wait).await;
+        assert!(!save.is_finished());
+
+        // The write goes ahead once the read is over
+        drop(export);
+        tokio::time::timeout(wait, save).await.unwrap().unwrap().unwrap();
+        assert_eq!(manager.list_entries().await.unwrap().len(), 2);
+    }
+}
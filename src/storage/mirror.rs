@@ -0,0 +1,205 @@
+//! Extra copies of the log in other formats, kept up to date with every
+//! change to the primary log
+//!
+//! A mirror is written after the primary and always from what the primary
+//! has stored, so a write that fails only leaves the mirror behind. The ids
+//! it missed are kept and written again on the next change or retry, and a
+//! resync compares the whole log to catch anything else.
+
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::path::{Path, PathBuf};
+
+use super::journal;
+use super::{Storage, StorageError, StorageFormat};
+
+/// Mirrors listed in `AMLOG_MIRROR`, e.g. `adif,json:/backup/logbook.json`.
+/// A format without a path is mirrored to its usual file in `data_dir`.
+pub fn from_env(data_dir: &Path) -> Result<Vec<(StorageFormat, PathBuf)>, StorageError> {
+    match env::var("AMLOG_MIRROR") {
+        Ok(value) => parse_list(&value, data_dir),
+        Err(_) => Ok(Vec::new()),
+    }
+}
+
+fn parse_list(value: &str, data_dir: &Path) -> Result<Vec<(StorageFormat, PathBuf)>, StorageError> {
+    value.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(|item| {
+            let (name, path) = match item.split_once(':') {
+                Some((name, path)) => (name, Some(PathBuf::from(path))),
+                None => (item, None),
+            };
+            let format = StorageFormat::from_name(name)
+                .ok_or_else(|| StorageError::Validation(format!("unknown mirror format '{}'", name)))?;
+            Ok((format, path.unwrap_or_else(|| data_dir.join(format.file_name()))))
+        })
+        .collect()
+}
+
+/// State of a mirror as shown to the user
+#[derive(Debug, Clone, PartialEq)]
+pub struct MirrorStatus {
+    pub format: StorageFormat,
+    pub path: PathBuf,
+    /// Entries the mirror has not caught up with
+    pub pending: usize,
+    pub last_error: Option<String>,
+}
+
+pub(crate) struct Mirror {
+    storage: Box<dyn Storage>,
+    format: StorageFormat,
+    /// Ids whose latest change has not reached the mirror
+    pending: BTreeSet<String>,
+    last_error: Option<String>,
+}
+
+impl Mirror {
+    pub fn new(format: StorageFormat, storage: Box<dyn Storage>) -> Self {
+        Self { storage, format, pending: BTreeSet::new(), last_error: None }
+    }
+
+    pub fn status(&self) -> MirrorStatus {
+        MirrorStatus {
+            format: self.format,
+            path: self.storage.path().clone(),
+            pending: self.pending.len(),
+            last_error: self.last_error.clone(),
+        }
+    }
+
+    /// Copy the current state of the given entries, along with any still
+    /// pending, from the primary. Failures are kept for the next attempt.
+    pub async fn replicate<I, S>(&mut self, primary: &dyn Storage, ids: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.pending.extend(ids.into_iter().map(Into::into));
+        let mut saves = Vec::new();
+        for id in std::mem::take(&mut self.pending) {
+            let result = match primary.get_entry(&id).await {
+                Ok(Some(entry)) => {
+                    saves.push(entry);
+                    Ok(())
+                }
+                Ok(None) => self.remove(&id).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                self.last_error = Some(e.to_string());
+                self.pending.insert(id);
+            }
+        }
+        // Entries that still exist are written together
+        if !saves.is_empty() {
+            let ids: Vec<String> = saves.iter().map(|entry| entry.id.clone()).collect();
+            if let Err(e) = self.storage.save_entries(saves).await {
+                self.last_error = Some(e.to_string());
+                self.pending.extend(ids);
+            }
+        }
+        if self.pending.is_empty() {
+            self.last_error = None;
+        }
+    }
+
+    async fn remove(&mut self, id: &str) -> Result<(), StorageError> {
+        if self.storage.get_entry(id).await?.is_some() {
+            self.storage.delete_entry(id).await?;
+        }
+        Ok(())
+    }
+
+    /// Give the mirror the primary's custom field definitions
+    pub async fn sync_fields(&mut self, primary: &dyn Storage) {
+        if let Err(e) = self.copy_fields(primary).await {
+            self.last_error = Some(e.to_string());
+        }
+    }
+
+    async fn copy_fields(&mut self, primary: &dyn Storage) -> Result<(), StorageError> {
+        let expected = primary.field_definitions().await?;
+        let actual = self.storage.field_definitions().await?;
+        for definition in actual.iter().filter(|d| !expected.iter().any(|e| e.name == d.name)) {
+            self.storage.remove_field(&definition.name).await?;
+        }
+        for definition in expected.into_iter().filter(|d| !actual.contains(d)) {
+            self.storage.define_field(definition).await?;
+        }
+        Ok(())
+    }
+
+    /// Compare the mirror with the primary and write every entry that
+    /// differs. Entries are compared field by field rather than by
+    /// `updated_at`, which not every change moves on. Returns the number of
+    /// entries brought up to date.
+    pub async fn resync(&mut self, primary: &dyn Storage) -> Result<usize, StorageError> {
+        // Edits made to the mirror file by other programs are overwritten below
+        self.storage.refresh().await?;
+        self.copy_fields(primary).await?;
+        let expected = primary.list_entries().await?;
+        let actual = self.storage.list_entries().await?;
+        let mut stale: HashMap<String, _> = actual.iter().map(|entry| (entry.id.clone(), entry)).collect();
+
+        let mut ids = Vec::new();
+        for entry in &expected {
+            let current = stale.remove(&entry.id)
+                .is_some_and(|mirrored| journal::diff_entries(Some(mirrored), Some(entry)).is_empty());
+            if !current {
+                ids.push(entry.id.clone());
+            }
+        }
+        // Whatever is left is gone from the primary
+        ids.extend(stale.into_keys());
+
+        let count = ids.len();
+        self.replicate(primary, ids).await;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::InMemoryStorage;
+    use crate::LogEntry;
+
+    #[test]
+    fn test_mirror_list() {
+        let dir = Path::new("/data");
+        assert_eq!(parse_list("adif, json:/backup/log.json", dir).unwrap(), vec![
+            (StorageFormat::Adif, dir.join("logbook.adi")),
+            (StorageFormat::Json, PathBuf::from("/backup/log.json")),
+        ]);
+        assert!(parse_list("", dir).unwrap().is_empty());
+        assert!(matches!(parse_list("csv", dir), Err(StorageError::Validation(_))));
+    }
+
+    #[tokio::test]
+    async fn test_resync_compares_whole_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut primary = InMemoryStorage::new(&dir.path().join("primary.mem")).unwrap();
+        let mut mirror = Mirror::new(
+            StorageFormat::Memory,
+            Box::new(InMemoryStorage::new(&dir.path().join("mirror.mem")).unwrap()),
+        );
+        let entry = LogEntry {
+            id: "1".to_string(),
+            callsign: "W1AW".into(),
+            mode: "CW".into(),
+            updated_at: Some(chrono::Utc::now()),
+            ..Default::default()
+        };
+        primary.add_entry(entry.clone()).await.unwrap();
+        assert_eq!(mirror.resync(&primary).await.unwrap(), 1);
+        assert_eq!(mirror.resync(&primary).await.unwrap(), 0);
+
+        // A change that left updated_at alone still reaches the mirror
+        primary.save_entry(LogEntry { mode: "SSB".into(), ..entry }).await.unwrap();
+        assert_eq!(mirror.resync(&primary).await.unwrap(), 1);
+        assert_eq!(mirror.storage.get_entry("1").await.unwrap().unwrap().mode, "SSB");
+    }
+}
@@ -1,18 +1,34 @@
 //! Storage handling for amateur radio logs
 //! Supports multiple backends including JSON, ADIF
 
+pub mod backup;
 mod error;
+pub mod fields;
 mod manager;
 pub mod formats;
+pub mod fsck;
+pub mod journal;
+pub mod lock;
+pub mod mirror;
+pub mod reconcile;
+pub mod revision;
+pub mod search;
 mod types;
 
+pub use backup::{Backup, BackupConfig, BackupReason};
 pub use error::StorageError;
+pub use fields::{Comparison, FieldDefinition, FieldQuery, FieldType};
+pub use fsck::FsckReport;
+pub use journal::{JournalOp, JournalRecord};
 pub use manager::StorageManager;
-pub use types::{Storage, StorageFormat};
+pub use mirror::MirrorStatus;
+pub use revision::Revision;
+pub use search::SearchHit;
+pub use types::{ExternalChanges, Storage, StorageFormat, StorageValidator};
 
 // Re-export concrete implementations
 pub use formats::json::JsonStorage;
+pub use formats::jsonl::JsonLinesStorage;
 pub use formats::adif::AdifStorage;
 pub use formats::sqlite::SqliteStorage;
-// Constants
-pub(crate) const DEFAULT_BUFFER_SIZE: usize = 1024;
\ No newline at end of file
+pub use formats::memory::InMemoryStorage;
\ No newline at end of file
@@ -0,0 +1,275 @@
+//! Reconcile confirmation reports (LoTW `lotwreport.adi`, eQSL inbox
+//! downloads) against the log, updating only QSL-received fields
+
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+
+use crate::app::band::{band_for_frequency, same_band};
+use crate::app::{QslConfirmation, QslStatus};
+use crate::storage::AdifStorage;
+use crate::LogEntry;
+
+/// Service a confirmation report was downloaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationSource {
+    Lotw,
+    Eqsl,
+}
+
+impl ConfirmationSource {
+    /// Guess the source from the report header's PROGRAMID
+    pub fn detect(content: &str) -> Option<Self> {
+        let program = AdifStorage::header_field(content, "PROGRAMID")?.to_uppercase();
+        if program.contains("LOTW") {
+            Some(ConfirmationSource::Lotw)
+        } else if program.contains("EQSL") {
+            Some(ConfirmationSource::Eqsl)
+        } else {
+            None
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ConfirmationSource::Lotw => "LoTW",
+            ConfirmationSource::Eqsl => "eQSL",
+        }
+    }
+
+    fn confirmation<'a>(&self, entry: &'a mut LogEntry) -> &'a mut QslConfirmation {
+        match self {
+            ConfirmationSource::Lotw => &mut entry.lotw,
+            ConfirmationSource::Eqsl => &mut entry.eqsl,
+        }
+    }
+}
+
+/// One confirmed QSO from a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReportRecord {
+    pub callsign: String,
+    pub band: Option<String>,
+    pub mode: String,
+    pub submode: Option<String>,
+    pub timestamp: DateTime<Utc>,
+    pub received_date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ReconcileReport {
+    /// Ids of entries that gained a confirmation
+    pub confirmed: Vec<String>,
+    /// Report records matching an entry that was already confirmed
+    pub already_confirmed: usize,
+    /// Report records with no matching QSO, for manual review
+    pub unmatched: Vec<ReportRecord>,
+    /// Report records that were not confirmations (e.g. LoTW QSL_RCVD=N)
+    pub skipped: usize,
+}
+
+/// Parse the confirmation records of a report. Records that are not
+/// confirmations or lack a callsign/date are counted in the second value.
+pub fn parse_report(content: &str, source: ConfirmationSource) -> (Vec<ReportRecord>, usize) {
+    let mut records = Vec::new();
+    let mut skipped = 0;
+
+    for fields in AdifStorage::adif_to_records(content) {
+        match record_from_fields(&fields, source) {
+            Some(record) => records.push(record),
+            None => skipped += 1,
+        }
+    }
+    (records, skipped)
+}
+
+fn record_from_fields(fields: &HashMap<String, String>, source: ConfirmationSource) -> Option<ReportRecord> {
+    // LoTW lists unconfirmed uploads too; eQSL inbox records are all confirmations
+    if source == ConfirmationSource::Lotw
+        && !fields.get("QSL_RCVD").is_some_and(|v| v.eq_ignore_ascii_case("Y"))
+    {
+        return None;
+    }
+
+    let timestamp = AdifStorage::parse_qso_datetime(
+        fields.get("QSO_DATE")?,
+        fields.get("TIME_ON").map(String::as_str).unwrap_or("000000"),
+    )?;
+    let band = fields.get("BAND").cloned().or_else(|| {
+        fields.get("FREQ")
+            .and_then(|f| f.trim().parse().ok())
+            .and_then(band_for_frequency)
+            .map(str::to_string)
+    });
+
+    Some(ReportRecord {
+        callsign: fields.get("CALL")?.trim().to_uppercase(),
+        band,
+        mode: fields.get("MODE").cloned().unwrap_or_default(),
+        submode: fields.get("SUBMODE").cloned(),
+        timestamp,
+        received_date: fields.get("QSLRDATE")
+            .and_then(|d| NaiveDate::parse_from_str(d.trim(), "%Y%m%d").ok()),
+    })
+}
+
+/// Modes match when mode or submode agree, treating USB/LSB as SSB
+fn modes_match(entry: &LogEntry, record: &ReportRecord) -> bool {
+    fn normalize(mode: &str) -> String {
+        match mode.trim().to_uppercase().as_str() {
+            "USB" | "LSB" => "SSB".to_string(),
+            other => other.to_string(),
+        }
+    }
+
+    let ours: Vec<String> = [Some(entry.mode.as_str()), entry.submode.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(normalize)
+        .collect();
+    [Some(record.mode.as_str()), record.submode.as_deref()]
+        .into_iter()
+        .flatten()
+        .map(normalize)
+        .any(|mode| ours.contains(&mode))
+}
+
+/// Find the QSO a report record confirms: same call, band and mode, closest
+/// in time within `tolerance`
+pub fn find_match<'a>(entries: &'a [LogEntry], record: &ReportRecord, tolerance: Duration) -> Option<&'a LogEntry> {
+    entries
+        .iter()
+        .filter(|entry| entry.callsign.trim().eq_ignore_ascii_case(&record.callsign))
+        .filter(|entry| match (entry.band_name(), &record.band) {
+            (Some(ours), Some(theirs)) => same_band(&ours, theirs),
+            _ => true,
+        })
+        .filter(|entry| modes_match(entry, record))
+        .filter(|entry| (entry.timestamp - record.timestamp).abs() <= tolerance)
+        .min_by_key(|entry| (entry.timestamp - record.timestamp).abs())
+}
+
+/// Match a report against the log. Returns the entries to save, with only
+/// their confirmation fields changed, and a summary for the user.
+pub fn reconcile(
+    entries: &[LogEntry],
+    content: &str,
+    source: ConfirmationSource,
+    tolerance: Duration,
+) -> (Vec<LogEntry>, ReconcileReport) {
+    let (records, skipped) = parse_report(content, source);
+    let mut report = ReconcileReport {
+        skipped,
+        ..Default::default()
+    };
+    let mut updates: HashMap<String, LogEntry> = HashMap::new();
+    let today = Utc::now().date_naive();
+
+    for record in records {
+        let Some(entry) = find_match(entries, &record, tolerance) else {
+            report.unmatched.push(record);
+            continue;
+        };
+
+        let updated = updates.entry(entry.id.clone()).or_insert_with(|| entry.clone());
+        let confirmation = source.confirmation(updated);
+        if confirmation.is_confirmed() {
+            report.already_confirmed += 1;
+            continue;
+        }
+        confirmation.received = Some(QslStatus::Yes);
+        confirmation.received_date = Some(record.received_date.unwrap_or(today));
+        report.confirmed.push(entry.id.clone());
+    }
+
+    let changed = updates
+        .into_values()
+        .filter(|entry| report.confirmed.contains(&entry.id))
+        .collect();
+    (changed, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::formats::fixtures::qso_on;
+    use chrono::TimeZone;
+
+    const LOTW_REPORT: &str = "ARRL Logbook of the World Status Report
+<PROGRAMID:4>LoTW
+<APP_LoTW_NUMREC:1>3
+<eoh>
+<CALL:6>DL1ABC <BAND:3>20M <MODE:2>CW <QSO_DATE:8>20240501 <TIME_ON:6>120500
+<QSL_RCVD:1>Y <QSLRDATE:8>20240510 <eor>
+<CALL:5>K1ABC <BAND:3>40M <MODE:3>FT8 <QSO_DATE:8>20240502 <TIME_ON:4>0100
+<QSL_RCVD:1>Y <QSLRDATE:8>20240511 <eor>
+<CALL:5>N0XYZ <BAND:3>20M <MODE:3>SSB <QSO_DATE:8>20240503 <TIME_ON:4>1500
+<QSL_RCVD:1>N <eor>
+";
+
+    #[test]
+    fn test_detect_source() {
+        assert_eq!(ConfirmationSource::detect(LOTW_REPORT), Some(ConfirmationSource::Lotw));
+        assert_eq!(ConfirmationSource::detect("<PROGRAMID:7>eQSL.cc<EOH>"), Some(ConfirmationSource::Eqsl));
+        assert_eq!(ConfirmationSource::detect("<CALL:4>W1AW<EOR>"), None);
+    }
+
+    #[test]
+    fn test_reconcile_lotw_report() {
+        let mut notes_entry = LogEntry {
+            timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 20, 0).unwrap(),
+            ..qso_on("a", "DL1ABC", 14.025, "CW")
+        };
+        notes_entry.notes = Some("keep me".to_string());
+        let entries = vec![
+            notes_entry,
+            // Same call on another band must not be confirmed
+            LogEntry {
+                timestamp: Utc.with_ymd_and_hms(2024, 5, 1, 12, 5, 0).unwrap(),
+                ..qso_on("b", "DL1ABC", 7.025, "CW")
+            },
+            // Outside the time tolerance
+            LogEntry {
+                timestamp: Utc.with_ymd_and_hms(2024, 5, 2, 3, 0, 0).unwrap(),
+                ..qso_on("c", "K1ABC", 7.074, "FT8")
+            },
+            LogEntry {
+                timestamp: Utc.with_ymd_and_hms(2024, 5, 3, 15, 0, 0).unwrap(),
+                ..qso_on("d", "N0XYZ", 14.250, "SSB")
+            },
+        ];
+
+        let (updates, report) = reconcile(&entries, LOTW_REPORT, ConfirmationSource::Lotw, Duration::minutes(30));
+        assert_eq!(report.confirmed, vec!["a".to_string()]);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.unmatched.len(), 1);
+        assert_eq!(report.unmatched[0].callsign, "K1ABC");
+
+        assert_eq!(updates.len(), 1);
+        let updated = &updates[0];
+        assert!(updated.lotw.is_confirmed());
+        assert_eq!(updated.lotw.received_date, NaiveDate::from_ymd_opt(2024, 5, 10));
+        assert_eq!(updated.notes.as_deref(), Some("keep me"));
+        assert!(!updated.qsl.is_confirmed());
+
+        // Reconciling again changes nothing
+        let mut entries = entries;
+        entries[0] = updated.clone();
+        let (updates, report) = reconcile(&entries, LOTW_REPORT, ConfirmationSource::Lotw, Duration::minutes(30));
+        assert!(updates.is_empty());
+        assert_eq!(report.already_confirmed, 1);
+    }
+
+    #[test]
+    fn test_modes_match_sideband() {
+        let ours = LogEntry { timestamp: Utc::now(), ..qso_on("a", "W1AW", 14.2, "USB") };
+        let record = ReportRecord {
+            callsign: "W1AW".into(),
+            band: Some("20m".into()),
+            mode: "SSB".into(),
+            submode: None,
+            timestamp: Utc::now(),
+            received_date: None,
+        };
+        assert!(modes_match(&ours, &record));
+    }
+}
@@ -0,0 +1,104 @@
+//! Per-QSO revision history: every version of an entry that has been
+//! replaced or deleted, kept so changes can be audited and restored
+//!
+//! Unlike the journal, revisions are never discarded; undoing or restoring
+//! an older version adds another revision.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use super::fsck::Unreadable;
+use super::{journal, StorageError};
+use crate::LogEntry;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub entry_id: String,
+    /// 1 for the first version replaced, counting up
+    pub number: u32,
+    /// When this version was replaced
+    pub saved_at: DateTime<Utc>,
+    pub entry: LogEntry,
+}
+
+impl Revision {
+    pub fn new(number: u32, entry: LogEntry) -> Self {
+        Self { entry_id: entry.id.clone(), number, saved_at: Utc::now(), entry }
+    }
+}
+
+/// Revisions kept as JSON lines next to a file-based log
+pub struct RevisionFile {
+    path: PathBuf,
+    revisions: Vec<Revision>,
+    /// Lines that could not be read, left for fsck
+    damaged: Vec<Unreadable>,
+}
+
+impl RevisionFile {
+    /// Revision path for a log file, e.g. `logbook.adi.revisions.jsonl`
+    pub fn path_for(log_path: &Path) -> PathBuf {
+        let mut name = log_path.file_name().unwrap_or_default().to_os_string();
+        name.push(".revisions.jsonl");
+        log_path.with_file_name(name)
+    }
+
+    pub fn open(path: PathBuf) -> Result<Self, StorageError> {
+        let lines = if path.exists() { journal::read_lines(&path)? } else { journal::Lines::default() };
+        Ok(Self { path, revisions: lines.items, damaged: lines.damaged })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Revisions of one entry, oldest first
+    pub fn for_entry(&self, id: &str) -> Vec<Revision> {
+        self.revisions.iter().filter(|r| r.entry_id == id).cloned().collect()
+    }
+
+    pub fn append(&mut self, revision: Revision) -> Result<(), StorageError> {
+        self.append_all(vec![revision])
+    }
+
+    /// Append several revisions with one write
+    pub fn append_all(&mut self, revisions: Vec<Revision>) -> Result<(), StorageError> {
+        if revisions.is_empty() {
+            return Ok(());
+        }
+        let mut text = String::new();
+        for revision in &revisions {
+            text.push_str(&serde_json::to_string(revision)?);
+            text.push('\n');
+        }
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        file.write_all(text.as_bytes())?;
+        self.revisions.extend(revisions);
+        Ok(())
+    }
+
+    /// Lines that could not be read when the file was opened
+    pub fn damaged(&self) -> &[Unreadable] {
+        &self.damaged
+    }
+
+    /// Rewrite the file without its damaged lines
+    pub fn drop_damaged(&mut self) -> Result<(), StorageError> {
+        if self.damaged.is_empty() {
+            return Ok(());
+        }
+        let mut content = String::new();
+        for revision in &self.revisions {
+            content.push_str(&serde_json::to_string(revision)?);
+            content.push('\n');
+        }
+        let temp_path = self.path.with_extension("jsonl.tmp");
+        fs::write(&temp_path, content)?;
+        fs::rename(&temp_path, &self.path)?;
+        self.damaged.clear();
+        Ok(())
+    }
+}
@@ -0,0 +1,219 @@
+//! Full-text search over the free-text parts of a QSO: notes, name, QTH
+//! and custom field values
+//!
+//! A query is a list of words that must all appear, in any of those
+//! fields. A word ending in `*` matches as a prefix, and words in double
+//! quotes must appear together in one field. Words are split and compared
+//! the way SQLite's `unicode61` tokenizer does (letters and digits,
+//! ignoring case), so the in-memory search and the FTS5 index agree on
+//! what matches, and matches are highlighted the same way for both.
+
+use std::cmp::Ordering;
+use std::ops::Range;
+
+use crate::LogEntry;
+
+/// An entry that matched, with the text that matched
+#[derive(Debug, Clone)]
+pub struct SearchHit {
+    pub entry: LogEntry,
+    /// Higher is a better match
+    pub score: f64,
+    pub matches: Vec<FieldMatch>,
+}
+
+/// A field of a matching entry and the byte ranges of the matched words
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldMatch {
+    pub field: String,
+    pub text: String,
+    pub ranges: Vec<Range<usize>>,
+}
+
+/// Words or phrases that must all appear
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Term {
+    /// Lowercase words, more than one for a quoted phrase
+    words: Vec<String>,
+    /// The last word matches as a prefix
+    prefix: bool,
+}
+
+impl Query {
+    pub fn parse(input: &str) -> Self {
+        let mut terms = Vec::new();
+        for (index, part) in input.split('"').enumerate() {
+            if index % 2 == 1 {
+                // Inside quotes: one phrase
+                let prefix = part.trim_end().ends_with('*');
+                let words: Vec<String> = tokens(part).into_iter().map(|(_, word)| word).collect();
+                if !words.is_empty() {
+                    terms.push(Term { words, prefix });
+                }
+                continue;
+            }
+            for word in part.split_whitespace() {
+                let prefix = word.ends_with('*');
+                // "QTH-less" is two words to the tokenizer, so it is a phrase here too
+                let words: Vec<String> = tokens(word).into_iter().map(|(_, word)| word).collect();
+                if !words.is_empty() {
+                    terms.push(Term { words, prefix });
+                }
+            }
+        }
+        Self { terms }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+
+    /// The query in FTS5 syntax, with every word quoted so nothing the
+    /// user types is taken as an operator
+    pub fn to_fts(&self) -> String {
+        self.terms.iter()
+            .map(|term| {
+                let phrase = format!("\"{}\"", term.words.join(" "));
+                if term.prefix { phrase + "*" } else { phrase }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+/// Words with their byte ranges, lowercased
+type Words = Vec<(Range<usize>, String)>;
+
+fn tokens(text: &str) -> Words {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    for (pos, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(pos),
+            (false, Some(from)) => {
+                tokens.push((from..pos, text[from..pos].to_lowercase()));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    tokens
+}
+
+/// The fields searched, by name
+pub fn searchable_fields(entry: &LogEntry) -> Vec<(String, &str)> {
+    let mut fields: Vec<(String, &str)> = [("notes", &entry.notes), ("name", &entry.name), ("qth", &entry.qth)]
+        .into_iter()
+        .filter_map(|(field, value)| value.as_deref().map(|value| (field.to_string(), value)))
+        .collect();
+    let mut custom: Vec<(&String, &String)> = entry.custom_fields.iter().collect();
+    custom.sort();
+    fields.extend(custom.into_iter().map(|(name, value)| (name.clone(), value.as_str())));
+    fields
+}
+
+/// Where `term` occurs among `words`, as ranges of the words matched
+fn occurrences(term: &Term, words: &Words) -> Vec<Range<usize>> {
+    let n = term.words.len();
+    if words.len() < n {
+        return Vec::new();
+    }
+    (0..=words.len() - n)
+        .filter(|&start| {
+            term.words.iter().enumerate().all(|(i, word)| {
+                let candidate = &words[start + i].1;
+                if term.prefix && i == n - 1 { candidate.starts_with(word.as_str()) } else { candidate == word }
+            })
+        })
+        .map(|start| words[start].0.start..words[start + n - 1].0.end)
+        .collect()
+}
+
+/// Check an entry against the query. Every term has to occur somewhere;
+/// the score is the number of occurrences, so an entry that mentions the
+/// words more often ranks higher.
+pub fn match_entry(query: &Query, entry: &LogEntry) -> Option<SearchHit> {
+    if query.is_empty() {
+        return None;
+    }
+    let fields: Vec<(String, &str, Words)> = searchable_fields(entry)
+        .into_iter()
+        .map(|(field, text)| (field, text, tokens(text)))
+        .collect();
+
+    let mut score = 0.0;
+    let mut matches: Vec<FieldMatch> = Vec::new();
+    for term in &query.terms {
+        let mut found = false;
+        for (field, text, words) in &fields {
+            let ranges = occurrences(term, words);
+            if ranges.is_empty() {
+                continue;
+            }
+            found = true;
+            score += ranges.len() as f64;
+            match matches.iter_mut().find(|m| &m.field == field) {
+                Some(existing) => existing.ranges.extend(ranges),
+                None => matches.push(FieldMatch { field: field.clone(), text: text.to_string(), ranges }),
+            }
+        }
+        if !found {
+            return None;
+        }
+    }
+    for field in &mut matches {
+        field.ranges.sort_by_key(|range| range.start);
+        field.ranges.dedup();
+    }
+    Some(SearchHit { entry: entry.clone(), score, matches })
+}
+
+/// Entries matching the query, best match first and newest first among
+/// equally good matches
+pub fn rank(query: &Query, entries: Vec<LogEntry>) -> Vec<SearchHit> {
+    let mut hits: Vec<SearchHit> = entries.iter().filter_map(|entry| match_entry(query, entry)).collect();
+    sort_hits(&mut hits);
+    hits
+}
+
+pub fn sort_hits(hits: &mut [SearchHit]) {
+    hits.sort_by(|a, b| {
+        b.score.partial_cmp(&a.score)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.entry.timestamp.cmp(&a.entry.timestamp))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_query_and_highlights() {
+        let query = Query::parse("antenna TEST* \"met at\"");
+        assert_eq!(query.to_fts(), "\"antenna\" \"test\"* \"met at\"");
+        // Operators and stray quotes are just words
+        assert_eq!(Query::parse("qth-less OR (\"dx").to_fts(), "\"qth less\" \"or\" \"dx\"");
+
+        let mut entry = LogEntry {
+            notes: Some("Antenna testing; met at the hamfest".to_string()),
+            ..Default::default()
+        };
+        entry.custom_fields.insert("SRX_STRING".to_string(), "antenna".to_string());
+        let hit = match_entry(&query, &entry).unwrap();
+        assert_eq!(hit.score, 4.0);
+        let notes = &hit.matches[0];
+        let highlighted: Vec<&str> = notes.ranges.iter().map(|range| &notes.text[range.clone()]).collect();
+        assert_eq!(highlighted, vec!["Antenna", "testing", "met at"]);
+        assert_eq!(hit.matches[1].field, "SRX_STRING");
+
+        assert!(match_entry(&Query::parse("antenna dipole"), &entry).is_none());
+        assert!(match_entry(&Query::parse("test"), &entry).is_none());
+        assert!(match_entry(&Query::parse("  "), &entry).is_none());
+    }
+}
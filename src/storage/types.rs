@@ -1,13 +1,83 @@
 use async_trait::async_trait;
-use std::path::PathBuf;
+use std::fs;
+use std::path::{Path, PathBuf};
 use crate::LogEntry;
-use super::StorageError;
+use super::backup;
+use super::fields::{FieldDefinition, FieldQuery};
+use super::fsck::{Scan, Unreadable};
+use super::lock::LogLock;
+use super::search::{self, SearchHit};
+use super::{JournalRecord, Revision, StorageError};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum StorageFormat {
     Json,
+    JsonLines,
     Adif,
     Sqlite,
+    /// Kept in memory and lost on exit
+    Memory,
+}
+
+impl StorageFormat {
+    /// Format chosen with `AMLOG_STORAGE` (sqlite, json, jsonl or adif),
+    /// SQLite when unset or unknown
+    pub fn from_env() -> Self {
+        std::env::var("AMLOG_STORAGE").ok()
+            .and_then(|v| Self::from_name(&v))
+            .unwrap_or(StorageFormat::Sqlite)
+    }
+
+    /// Format by name: sqlite, json, jsonl or adif
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "sqlite" => Some(StorageFormat::Sqlite),
+            "json" => Some(StorageFormat::Json),
+            "jsonl" => Some(StorageFormat::JsonLines),
+            "adif" => Some(StorageFormat::Adif),
+            _ => None,
+        }
+    }
+
+    /// Log file name in the data directory
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            StorageFormat::Json => "logbook.json",
+            StorageFormat::JsonLines => "logbook.jsonl",
+            StorageFormat::Adif => "logbook.adi",
+            StorageFormat::Sqlite => "logbook.db",
+            StorageFormat::Memory => "logbook.mem",
+        }
+    }
+}
+
+/// Entries another program added, changed or removed since the log was
+/// last read
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ExternalChanges {
+    pub added: Vec<String>,
+    pub modified: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl ExternalChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
+
+    /// Whether the entry was changed or removed
+    pub fn touches(&self, id: &str) -> bool {
+        self.modified.iter().chain(&self.removed).any(|changed| changed == id)
+    }
+
+    pub fn summary(&self) -> String {
+        let parts: Vec<String> = [("added", &self.added), ("changed", &self.modified), ("removed", &self.removed)]
+            .into_iter()
+            .filter(|(_, ids)| !ids.is_empty())
+            .map(|(what, ids)| format!("{} {}", ids.len(), what))
+            .collect();
+        parts.join(", ")
+    }
 }
 
 #[async_trait]
@@ -15,6 +85,34 @@ pub trait Storage: Send + Sync {
     /// Save a new log entry
     async fn save_entry(&mut self, entry: LogEntry) -> Result<(), StorageError>;
 
+    /// Save many entries at once, creating or replacing each. Backends that
+    /// can should write them in one go rather than one at a time.
+    async fn save_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        for entry in entries {
+            self.save_entry(entry).await?;
+        }
+        Ok(())
+    }
+
+    /// Save entries along with the versions they replace and their journal
+    /// records. Backends that can should write it all in one transaction,
+    /// so a failure leaves the log, its revisions and journal as they were.
+    async fn save_batch(
+        &mut self,
+        entries: Vec<LogEntry>,
+        revisions: Vec<Revision>,
+        records: Vec<JournalRecord>,
+    ) -> Result<(), StorageError> {
+        for revision in revisions {
+            self.add_revision(revision).await?;
+        }
+        self.save_entries(entries).await?;
+        for record in records {
+            self.append_journal(record).await?;
+        }
+        Ok(())
+    }
+
     /// Retrieve a specific entry by ID
     async fn get_entry(&self, id: &str) -> Result<Option<LogEntry>, StorageError>;
 
@@ -27,13 +125,116 @@ pub trait Storage: Send + Sync {
     /// Delete an entry
     async fn delete_entry(&mut self, id: &str) -> Result<(), StorageError>;
 
-    /// Add an entry to the undo buffer
-    /// This is used to store deleted entries for potential undo
+    /// Add a new entry; fails with `EntryExists` if the id is taken
     async fn add_entry(&mut self, entry: LogEntry) -> Result<(), StorageError>;
 
+    /// The operation journal, oldest record first
+    async fn journal(&self) -> Result<Vec<JournalRecord>, StorageError>;
+
+    /// Append to the journal, discarding records with the same or a later
+    /// sequence number
+    async fn append_journal(&mut self, record: JournalRecord) -> Result<(), StorageError>;
+
+    /// Flag a journal record as undone or redone
+    async fn mark_journal(&mut self, seq: u64, undone: bool) -> Result<(), StorageError>;
+
+    /// Sequence number of the latest change still in effect; 0 when none
+    /// is. Backends that can should answer without reading the journal.
+    async fn last_seq(&self) -> Result<u64, StorageError> {
+        Ok(self.journal().await?.iter().rev().find(|r| !r.undone).map_or(0, |r| r.seq))
+    }
+
+    /// One journal record by sequence number
+    async fn journal_record(&self, seq: u64) -> Result<Option<JournalRecord>, StorageError> {
+        Ok(self.journal().await?.into_iter().find(|r| r.seq == seq))
+    }
+
+    /// Lines of the journal and revision history that could not be read
+    /// and were skipped
+    fn damaged_history(&self) -> Vec<Unreadable> {
+        Vec::new()
+    }
+
+    /// Rewrite the journal and revision history without the damaged lines
+    async fn drop_damaged_history(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
+    /// Earlier versions of an entry, oldest first
+    async fn revisions(&self, id: &str) -> Result<Vec<Revision>, StorageError>;
+
+    /// Keep a version of an entry that is about to be replaced or deleted
+    async fn add_revision(&mut self, revision: Revision) -> Result<(), StorageError>;
+
+    /// Custom fields registered for this log, in the order they were added
+    async fn field_definitions(&self) -> Result<Vec<FieldDefinition>, StorageError>;
+
+    /// Register a custom field, replacing any definition of the same name
+    async fn define_field(&mut self, definition: FieldDefinition) -> Result<(), StorageError>;
+
+    /// Drop a custom field definition; values already stored are kept
+    async fn remove_field(&mut self, name: &str) -> Result<(), StorageError>;
+
+    /// Entries selected and ordered by custom field values. Backends
+    /// without an index run the query in memory.
+    async fn query_fields(&self, query: &FieldQuery) -> Result<Vec<LogEntry>, StorageError> {
+        query.apply(&self.field_definitions().await?, self.list_entries().await?)
+    }
+
+    /// Pick up changes other programs made to the log; backends that are
+    /// only written by amlog have none
+    async fn refresh(&mut self) -> Result<ExternalChanges, StorageError> {
+        Ok(ExternalChanges::default())
+    }
+
+    /// Read every record as stored, duplicates included, setting aside
+    /// those that cannot be read instead of failing
+    async fn scan(&self) -> Result<Scan, StorageError> {
+        Ok(Scan { entries: self.list_entries().await?, unreadable: Vec::new() })
+    }
+
+    /// Entries whose notes, name, QTH or custom fields match `query`, best
+    /// match first. Backends without an index search the entries in memory.
+    async fn search_text(&self, query: &str) -> Result<Vec<SearchHit>, StorageError> {
+        let query = search::Query::parse(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(search::rank(&query, self.list_entries().await?))
+    }
+
+    /// Write a complete copy of the log to `dest`. File-based logs are
+    /// copied with their sidecars under a shared lock so no writer is
+    /// halfway through.
+    async fn backup(&self, dest: &Path) -> Result<(), StorageError> {
+        let lock = LogLock::open(self.path())?;
+        let _guard = lock.shared().await?;
+        fs::copy(self.path(), dest)?;
+        backup::copy_sidecars(self.path(), dest)
+    }
+
+    /// Problems met while opening the log that did not stop it opening,
+    /// e.g. a damaged file that was partly recovered
+    fn warnings(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Let go of the log file before it is replaced
+    async fn close(&mut self) -> Result<(), StorageError> {
+        Ok(())
+    }
+
     /// Clear all entries
     async fn clear(&mut self) -> Result<(), StorageError>;
 
+    /// Replace every entry in the log with `entries` in one step, as fsck
+    /// does when it rewrites the log. Backends that can should leave the
+    /// log as it was when this fails.
+    async fn replace_entries(&mut self, entries: Vec<LogEntry>) -> Result<(), StorageError> {
+        self.clear().await?;
+        self.save_entries(entries).await
+    }
+
     /// Get the storage format
     fn format(&self) -> StorageFormat;
 
@@ -0,0 +1,176 @@
+// src/sync/client.rs
+use std::time::Duration;
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use super::{nonce, prove, read_message, verify, write_message, Envelope, Message, SyncError, SyncEvent, SyncHandle, SyncOp};
+
+/// How long to wait before reconnecting to the hub
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Where a station is in the hub's history, kept across reconnects
+struct ClientState {
+    station: String,
+    secret: Option<String>,
+    epoch: Option<String>,
+    since: u64,
+    /// Local operations the hub has not echoed back yet
+    outbox: Vec<Envelope>,
+}
+
+impl ClientState {
+    fn queue(&mut self, op: SyncOp) -> Envelope {
+        let envelope = Envelope {
+            id: uuid::Uuid::new_v4().to_string(),
+            seq: 0,
+            origin: self.station.clone(),
+            op,
+        };
+        self.outbox.push(envelope.clone());
+        envelope
+    }
+}
+
+/// Connect to the hub at `addr`, reconnecting whenever the link drops
+pub fn connect(addr: &str, station: &str, secret: Option<&str>) -> SyncHandle {
+    connect_with_retry(addr, station, secret, RETRY_INTERVAL)
+}
+
+pub fn connect_with_retry(addr: &str, station: &str, secret: Option<&str>, retry: Duration) -> SyncHandle {
+    let (outgoing, local_ops) = mpsc::unbounded_channel();
+    let (events, incoming) = mpsc::unbounded_channel();
+    let state = ClientState {
+        station: station.to_string(),
+        secret: secret.map(str::to_string),
+        epoch: None,
+        since: 0,
+        outbox: Vec::new(),
+    };
+    tokio::spawn(run(addr.to_string(), state, local_ops, events, retry));
+
+    SyncHandle {
+        station: station.to_string(),
+        local_addr: None,
+        outgoing,
+        incoming,
+    }
+}
+
+async fn run(
+    addr: String,
+    mut state: ClientState,
+    mut local_ops: UnboundedReceiver<SyncOp>,
+    events: UnboundedSender<SyncEvent>,
+    retry: Duration,
+) {
+    loop {
+        if let Ok(stream) = TcpStream::connect(&addr).await {
+            match session(stream, &addr, &mut state, &mut local_ops, &events).await {
+                // The application has gone away
+                Ok(false) => return,
+                // Retrying will not help until someone fixes the secret
+                Err(SyncError::Auth(reason)) => {
+                    let _ = events.send(SyncEvent::Rejected(reason));
+                    return;
+                }
+                Ok(true) | Err(_) => {
+                    let _ = events.send(SyncEvent::Disconnected(addr.clone()));
+                }
+            }
+        }
+
+        // Keep collecting local changes while the hub is out of reach
+        let wait = tokio::time::sleep(retry);
+        tokio::pin!(wait);
+        loop {
+            tokio::select! {
+                _ = &mut wait => break,
+                op = local_ops.recv() => match op {
+                    Some(op) => {
+                        state.queue(op);
+                    }
+                    None => return,
+                },
+            }
+        }
+        if events.is_closed() {
+            return;
+        }
+    }
+}
+
+/// Exchange operations until the connection drops (`Ok(true)`) or the
+/// application closes its handle (`Ok(false)`)
+async fn session(
+    stream: TcpStream,
+    addr: &str,
+    state: &mut ClientState,
+    local_ops: &mut UnboundedReceiver<SyncOp>,
+    events: &UnboundedSender<SyncEvent>,
+) -> Result<bool, SyncError> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+
+    let challenge = read_message(&mut reader, &mut buf).await?.ok_or_else(|| SyncError::Protocol("closed before challenge".to_string()))?;
+    let Message::Challenge { nonce: hub_nonce } = challenge else {
+        return Err(SyncError::Protocol("expected challenge".to_string()));
+    };
+    let our_nonce = nonce();
+    let hello = Message::Hello {
+        station: state.station.clone(),
+        epoch: state.epoch.clone(),
+        since: state.since,
+        nonce: our_nonce.clone(),
+        proof: state.secret.as_deref().map(|secret| prove(secret, &hub_nonce)),
+    };
+    write_message(&mut writer, &hello).await?;
+
+    let welcome = read_message(&mut reader, &mut buf).await?.ok_or_else(|| SyncError::Protocol("closed before welcome".to_string()))?;
+    let (epoch, proof) = match welcome {
+        Message::Welcome { epoch, proof } => (epoch, proof),
+        Message::Rejected { reason } => return Err(SyncError::Auth(reason)),
+        _ => return Err(SyncError::Protocol("expected welcome".to_string())),
+    };
+    if !verify(state.secret.as_deref(), &our_nonce, proof.as_deref()) {
+        return Err(SyncError::Auth(format!("{} does not know the sync secret", addr)));
+    }
+    if state.epoch.as_ref() != Some(&epoch) {
+        // The hub restarted and will replay its whole journal
+        state.since = 0;
+        state.epoch = Some(epoch);
+    }
+    let _ = events.send(SyncEvent::Connected(addr.to_string()));
+
+    for envelope in state.outbox.clone() {
+        write_message(&mut writer, &Message::Op { envelope }).await?;
+    }
+
+    loop {
+        tokio::select! {
+            op = local_ops.recv() => {
+                let Some(op) = op else {
+                    return Ok(false);
+                };
+                let envelope = state.queue(op);
+                write_message(&mut writer, &Message::Op { envelope }).await?;
+            }
+            message = read_message(&mut reader, &mut buf) => {
+                let Some(message) = message? else {
+                    return Ok(true);
+                };
+                let Message::Op { envelope } = message else {
+                    return Err(SyncError::Protocol("unexpected message".to_string()));
+                };
+                state.since = state.since.max(envelope.seq);
+                let before = state.outbox.len();
+                state.outbox.retain(|queued| queued.id != envelope.id);
+                // Our own operations come back as acknowledgements only
+                if state.outbox.len() == before && events.send(SyncEvent::Op(envelope)).is_err() {
+                    return Ok(false);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+// src/sync/hub.rs
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc::{self, UnboundedSender};
+
+use super::{nonce, prove, read_message, verify, write_message, Envelope, Message, SyncError, SyncEvent, SyncHandle, SyncOp};
+use crate::LogEntry;
+
+/// Operations superseded by a later one for the same QSO that may pile up
+/// before the journal is compacted
+const COMPACT_SLACK: usize = 1024;
+
+/// How many operation ids are remembered to spot a station resending
+const MAX_SEEN: usize = 100_000;
+
+/// The latest operation for every QSO the hub has numbered since it
+/// started, plus the stations currently connected
+struct HubState {
+    epoch: String,
+    journal: Vec<Envelope>,
+    /// Sequence number of the latest operation for each QSO
+    latest: HashMap<String, u64>,
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    next_seq: u64,
+    clients: HashMap<u64, UnboundedSender<Envelope>>,
+    next_client: u64,
+}
+
+type Shared = Arc<Mutex<HubState>>;
+
+impl HubState {
+    /// Number a new operation and send it to every connected station,
+    /// including the one it came from as an acknowledgement. Returns `None`
+    /// for an operation the hub already has.
+    fn record(&mut self, mut envelope: Envelope) -> Option<Envelope> {
+        if !self.seen.insert(envelope.id.clone()) {
+            return None;
+        }
+        self.seen_order.push_back(envelope.id.clone());
+        if self.seen_order.len() > MAX_SEEN {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+
+        self.next_seq += 1;
+        envelope.seq = self.next_seq;
+        self.latest.insert(envelope.op.entry_id().to_string(), envelope.seq);
+        self.journal.push(envelope.clone());
+        if self.journal.len() > self.latest.len() * 2 + COMPACT_SLACK {
+            self.compact();
+        }
+        self.clients.retain(|_, client| client.send(envelope.clone()).is_ok());
+        Some(envelope)
+    }
+
+    /// Drop operations a later one for the same QSO supersedes. A station
+    /// catching up still ends with every QSO in its latest state.
+    fn compact(&mut self) {
+        let latest = &self.latest;
+        self.journal.retain(|envelope| latest.get(envelope.op.entry_id()) == Some(&envelope.seq));
+    }
+}
+
+/// Run the hub on `addr`. The current log is offered to every station
+/// that joins, so a fresh station receives the whole log. Stations must
+/// know `secret`; without one the hub only listens on a loopback address.
+pub async fn listen(addr: &str, station: &str, secret: Option<&str>, entries: Vec<LogEntry>) -> Result<SyncHandle, SyncError> {
+    let listener = TcpListener::bind(addr).await?;
+    let local_addr = listener.local_addr()?;
+    if secret.is_none() && !local_addr.ip().is_loopback() {
+        return Err(SyncError::Auth(format!(
+            "set AMLOG_SYNC_SECRET to accept stations on {}",
+            local_addr
+        )));
+    }
+
+    let mut state = HubState {
+        epoch: uuid::Uuid::new_v4().to_string(),
+        journal: Vec::new(),
+        latest: HashMap::new(),
+        seen: HashSet::new(),
+        seen_order: VecDeque::new(),
+        next_seq: 0,
+        clients: HashMap::new(),
+        next_client: 0,
+    };
+    for entry in entries {
+        state.record(Envelope {
+            id: uuid::Uuid::new_v4().to_string(),
+            seq: 0,
+            origin: station.to_string(),
+            op: SyncOp::upsert(entry),
+        });
+    }
+    let state: Shared = Arc::new(Mutex::new(state));
+
+    let (outgoing, mut local_ops) = mpsc::unbounded_channel::<SyncOp>();
+    let (events, incoming) = mpsc::unbounded_channel();
+
+    // Changes made at the hub itself
+    let local_state = state.clone();
+    let origin = station.to_string();
+    tokio::spawn(async move {
+        while let Some(op) = local_ops.recv().await {
+            local_state.lock().unwrap().record(Envelope {
+                id: uuid::Uuid::new_v4().to_string(),
+                seq: 0,
+                origin: origin.clone(),
+                op,
+            });
+        }
+    });
+
+    let secret = secret.map(str::to_string);
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let state = state.clone();
+            let station_events = events.clone();
+            let secret = secret.clone();
+            tokio::spawn(async move {
+                let _ = serve_station(stream, state, secret, station_events).await;
+            });
+            if events.is_closed() {
+                break;
+            }
+        }
+    });
+
+    Ok(SyncHandle {
+        station: station.to_string(),
+        local_addr: Some(local_addr),
+        outgoing,
+        incoming,
+    })
+}
+
+async fn serve_station(stream: TcpStream, state: Shared, secret: Option<String>, events: UnboundedSender<SyncEvent>) -> Result<(), SyncError> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut buf = Vec::new();
+
+    let challenge = nonce();
+    write_message(&mut writer, &Message::Challenge { nonce: challenge.clone() }).await?;
+    let hello = read_message(&mut reader, &mut buf).await?.ok_or_else(|| SyncError::Protocol("closed before hello".to_string()))?;
+    let Message::Hello { station, epoch, since, nonce: station_nonce, proof } = hello else {
+        return Err(SyncError::Protocol("expected hello".to_string()));
+    };
+    if !verify(secret.as_deref(), &challenge, proof.as_deref()) {
+        let reason = format!("{} does not know the sync secret", station);
+        write_message(&mut writer, &Message::Rejected { reason: reason.clone() }).await?;
+        return Err(SyncError::Auth(reason));
+    }
+    let proof = secret.as_deref().map(|secret| prove(secret, &station_nonce));
+
+    // Register and take the backlog under one lock so nothing falls between
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let (client_id, hub_epoch, backlog) = {
+        let mut state = state.lock().unwrap();
+        let since = if epoch.as_deref() == Some(state.epoch.as_str()) { since } else { 0 };
+        let backlog: Vec<Envelope> = state.journal.iter().filter(|e| e.seq > since).cloned().collect();
+        let client_id = state.next_client;
+        state.next_client += 1;
+        state.clients.insert(client_id, tx);
+        (client_id, state.epoch.clone(), backlog)
+    };
+
+    let _ = events.send(SyncEvent::Connected(station.clone()));
+    let writer_task = tokio::spawn(async move {
+        write_message(&mut writer, &Message::Welcome { epoch: hub_epoch, proof }).await?;
+        for envelope in backlog {
+            write_message(&mut writer, &Message::Op { envelope }).await?;
+        }
+        while let Some(envelope) = rx.recv().await {
+            write_message(&mut writer, &Message::Op { envelope }).await?;
+        }
+        Ok::<_, SyncError>(())
+    });
+
+    let result = async {
+        while let Some(message) = read_message(&mut reader, &mut buf).await? {
+            match message {
+                Message::Op { envelope } => {
+                    let mut state = state.lock().unwrap();
+                    match state.record(envelope.clone()) {
+                        Some(recorded) => {
+                            let _ = events.send(SyncEvent::Op(recorded));
+                        }
+                        // Resent after a reconnect: acknowledge it again, as
+                        // received if a later operation has replaced it
+                        None => {
+                            let known = state.journal.iter().find(|e| e.id == envelope.id).cloned().unwrap_or(envelope);
+                            if let Some(client) = state.clients.get(&client_id) {
+                                let _ = client.send(known);
+                            }
+                        }
+                    }
+                }
+                _ => return Err(SyncError::Protocol("unexpected message".to_string())),
+            }
+        }
+        Ok(())
+    }
+    .await;
+
+    state.lock().unwrap().clients.remove(&client_id);
+    writer_task.abort();
+    let _ = events.send(SyncEvent::Disconnected(station));
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> HubState {
+        HubState {
+            epoch: "test".to_string(),
+            journal: Vec::new(),
+            latest: HashMap::new(),
+            seen: HashSet::new(),
+            seen_order: VecDeque::new(),
+            next_seq: 0,
+            clients: HashMap::new(),
+            next_client: 0,
+        }
+    }
+
+    fn op(entry_id: &str) -> Envelope {
+        Envelope {
+            id: uuid::Uuid::new_v4().to_string(),
+            seq: 0,
+            origin: "TEST".to_string(),
+            op: SyncOp::Delete { id: entry_id.to_string() },
+        }
+    }
+
+    #[test]
+    fn test_journal_keeps_latest_operation_per_qso() {
+        let mut state = state();
+        state.record(op("kept"));
+        for _ in 0..COMPACT_SLACK * 2 {
+            state.record(op("busy"));
+        }
+        assert!(state.journal.len() <= COMPACT_SLACK + 2);
+        assert!(state.journal.iter().any(|e| e.op.entry_id() == "kept"));
+        let last = state.journal.iter().filter(|e| e.op.entry_id() == "busy").map(|e| e.seq).max();
+        assert_eq!(last, Some(COMPACT_SLACK as u64 * 2 + 1));
+
+        // A resend is recognised even after its operation was compacted away
+        let first = state.journal[0].clone();
+        assert!(state.record(first).is_none());
+    }
+}
+
@@ -0,0 +1,348 @@
+//! Multi-operator log sharing over the local network
+//!
+//! One instance runs the hub (`AMLOG_SYNC_LISTEN`) and the other stations
+//! connect to it (`AMLOG_SYNC_CONNECT`). Every add, edit and delete travels
+//! as an operation keyed by the QSO's UUID. The hub numbers operations and
+//! keeps them, so a station that reconnects receives everything it missed,
+//! and stations keep their own operations until the hub has echoed them.
+//!
+//! Stations prove they know `AMLOG_SYNC_SECRET` by answering a challenge
+//! from the hub, and the hub answers one from the station in turn. Without
+//! a secret the hub only listens on a loopback address.
+
+mod client;
+mod hub;
+
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::env;
+use std::net::SocketAddr;
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+
+pub use client::{connect, connect_with_retry};
+pub use hub::listen;
+
+use crate::LogEntry;
+
+#[derive(Error, Debug)]
+pub enum SyncError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid sync message: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Protocol error: {0}")]
+    Protocol(String),
+
+    #[error("Authentication failed: {0}")]
+    Auth(String),
+}
+
+/// Longest message a peer may send; a QSO is a few kilobytes at most
+const MAX_MESSAGE_LEN: usize = 1024 * 1024;
+
+/// A change to the shared log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SyncOp {
+    Upsert { entry: Box<LogEntry> },
+    Delete { id: String },
+}
+
+impl SyncOp {
+    pub fn upsert(entry: LogEntry) -> Self {
+        SyncOp::Upsert { entry: Box::new(entry) }
+    }
+
+    pub fn entry_id(&self) -> &str {
+        match self {
+            SyncOp::Upsert { entry } => &entry.id,
+            SyncOp::Delete { id } => id,
+        }
+    }
+}
+
+/// An operation as it travels between stations. `seq` is assigned by the
+/// hub; zero means the hub has not seen it yet.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Envelope {
+    pub id: String,
+    pub seq: u64,
+    pub origin: String,
+    pub op: SyncOp,
+}
+
+/// Wire protocol: one JSON message per line
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    /// First message from the hub: a nonce for the station to sign
+    Challenge { nonce: String },
+    /// The station's reply: the hub epoch and last sequence it saw, its
+    /// signature of the hub's nonce and a nonce for the hub to sign
+    Hello { station: String, epoch: Option<String>, since: u64, nonce: String, proof: Option<String> },
+    /// Hub reply; a new epoch means the hub restarted and replays everything
+    Welcome { epoch: String, proof: Option<String> },
+    /// The hub turned the station away
+    Rejected { reason: String },
+    Op { envelope: Envelope },
+}
+
+/// Read one message, refusing lines longer than `MAX_MESSAGE_LEN`. `buf`
+/// keeps a partly read line, so this can be cancelled in `select!` and
+/// called again. `None` means the peer closed the connection.
+async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R, buf: &mut Vec<u8>) -> Result<Option<Message>, SyncError> {
+    let limit = (MAX_MESSAGE_LEN + 1).saturating_sub(buf.len()) as u64;
+    (&mut *reader).take(limit).read_until(b'\n', buf).await?;
+    if buf.last() != Some(&b'\n') {
+        if buf.len() > MAX_MESSAGE_LEN {
+            return Err(SyncError::Protocol("message too long".to_string()));
+        }
+        return Ok(None);
+    }
+    let message = serde_json::from_slice(buf);
+    buf.clear();
+    Ok(Some(message?))
+}
+
+async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, message: &Message) -> Result<(), SyncError> {
+    let mut line = serde_json::to_string(message)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Sign `nonce` with the shared secret
+fn prove(secret: &str, nonce: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(nonce.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Check a peer's signature of `nonce`; with no secret anything passes
+fn verify(secret: Option<&str>, nonce: &str, proof: Option<&str>) -> bool {
+    let Some(secret) = secret else {
+        return true;
+    };
+    let Some(proof) = proof.and_then(|proof| hex::decode(proof).ok()) else {
+        return false;
+    };
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(nonce.as_bytes());
+    mac.verify_slice(&proof).is_ok()
+}
+
+fn nonce() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// What the sync tasks report back to the application
+#[derive(Debug, Clone)]
+pub enum SyncEvent {
+    Op(Envelope),
+    Connected(String),
+    Disconnected(String),
+    /// The hub and this station do not share a secret; the station stops
+    /// trying to connect
+    Rejected(String),
+}
+
+/// The application's end of a running hub or client
+pub struct SyncHandle {
+    pub station: String,
+    /// Address the hub listens on
+    pub local_addr: Option<SocketAddr>,
+    outgoing: UnboundedSender<SyncOp>,
+    incoming: UnboundedReceiver<SyncEvent>,
+}
+
+impl SyncHandle {
+    /// Send a local change to the other stations
+    pub fn publish(&self, op: SyncOp) {
+        // The tasks only stop when the handle is dropped
+        let _ = self.outgoing.send(op);
+    }
+
+    /// Next event without waiting, for the UI loop
+    pub fn try_next(&mut self) -> Option<SyncEvent> {
+        self.incoming.try_recv().ok()
+    }
+
+    pub async fn recv(&mut self) -> Option<SyncEvent> {
+        self.incoming.recv().await
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyncRole {
+    Hub(String),
+    Client(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct SyncConfig {
+    pub station: String,
+    pub role: SyncRole,
+    pub secret: Option<String>,
+}
+
+impl SyncConfig {
+    /// Read `AMLOG_SYNC_LISTEN` or `AMLOG_SYNC_CONNECT`, and the shared
+    /// `AMLOG_SYNC_SECRET`; `None` when neither address is set
+    pub fn from_env() -> Option<Self> {
+        let role = match (env::var("AMLOG_SYNC_LISTEN"), env::var("AMLOG_SYNC_CONNECT")) {
+            (Ok(addr), _) => SyncRole::Hub(addr),
+            (_, Ok(addr)) => SyncRole::Client(addr),
+            _ => return None,
+        };
+        Some(Self {
+            station: station_from_env().unwrap_or_else(|| "amlog".to_string()),
+            role,
+            secret: env::var("AMLOG_SYNC_SECRET").ok().filter(|s| !s.is_empty()),
+        })
+    }
+}
+
+/// Name this station puts on the QSOs it logs (`AMLOG_STATION`)
+pub fn station_from_env() -> Option<String> {
+    env::var("AMLOG_STATION")
+        .ok()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+}
+
+/// Start the hub or client described by `config`; the hub offers
+/// `entries` to every station that joins
+pub async fn start(config: &SyncConfig, entries: Vec<LogEntry>) -> Result<SyncHandle, SyncError> {
+    match &config.role {
+        SyncRole::Hub(addr) => listen(addr, &config.station, config.secret.as_deref(), entries).await,
+        SyncRole::Client(addr) => Ok(connect(addr, &config.station, config.secret.as_deref())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::formats::fixtures::qso;
+    use std::time::Duration;
+    use tokio::time::timeout;
+
+    /// Next operation, skipping connection events
+    async fn next_op(handle: &mut SyncHandle) -> Envelope {
+        timeout(Duration::from_secs(5), async {
+            loop {
+                match handle.recv().await {
+                    Some(SyncEvent::Op(envelope)) => return envelope,
+                    Some(_) => continue,
+                    None => panic!("sync stopped"),
+                }
+            }
+        })
+        .await
+        .expect("timed out waiting for an operation")
+    }
+
+    async fn connected(handle: &mut SyncHandle) {
+        timeout(Duration::from_secs(5), async {
+            while !matches!(handle.recv().await, Some(SyncEvent::Connected(_))) {}
+        })
+        .await
+        .expect("timed out waiting for connection");
+    }
+
+    #[tokio::test]
+    async fn test_operations_reach_every_station() {
+        let mut hub = listen("127.0.0.1:0", "HUB", None, Vec::new()).await.unwrap();
+        let addr = hub.local_addr.unwrap().to_string();
+        let mut alpha = connect(&addr, "ALPHA", None);
+        let mut bravo = connect(&addr, "BRAVO", None);
+        connected(&mut alpha).await;
+        connected(&mut bravo).await;
+
+        let entry = qso("1", "W1AW", "FT8");
+        alpha.publish(SyncOp::upsert(entry.clone()));
+
+        let at_hub = next_op(&mut hub).await;
+        assert_eq!(at_hub.origin, "ALPHA");
+        assert_eq!(at_hub.op.entry_id(), entry.id);
+        let at_bravo = next_op(&mut bravo).await;
+        assert_eq!(at_bravo.seq, at_hub.seq);
+
+        hub.publish(SyncOp::Delete { id: entry.id.clone() });
+        let deleted = next_op(&mut alpha).await;
+        assert!(matches!(deleted.op, SyncOp::Delete { ref id } if *id == entry.id));
+        assert_eq!(deleted.origin, "HUB");
+    }
+
+    #[tokio::test]
+    async fn test_late_station_catches_up() {
+        let existing = qso("1", "K1ABC", "FT8");
+        let hub = listen("127.0.0.1:0", "HUB", None, vec![existing.clone()]).await.unwrap();
+        hub.publish(SyncOp::upsert(qso("2", "N1XYZ", "FT8")));
+
+        let mut late = connect(&hub.local_addr.unwrap().to_string(), "LATE", None);
+        let first = next_op(&mut late).await;
+        let second = next_op(&mut late).await;
+        assert_eq!(first.op.entry_id(), existing.id);
+        assert!(matches!(second.op, SyncOp::Upsert { ref entry } if entry.callsign == "N1XYZ"));
+        assert!(second.seq > first.seq);
+    }
+
+    #[tokio::test]
+    async fn test_offline_changes_are_sent_on_reconnect() {
+        // Reserve a port, then start the station before the hub exists
+        let addr = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().to_string();
+        let station = connect_with_retry(&addr, "FIELD", None, Duration::from_millis(50));
+        let entry = qso("1", "VE3XX", "FT8");
+        station.publish(SyncOp::upsert(entry.clone()));
+
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let mut hub = listen(&addr, "HUB", None, Vec::new()).await.unwrap();
+        let received = next_op(&mut hub).await;
+        assert_eq!(received.op.entry_id(), entry.id);
+        assert_eq!(received.origin, "FIELD");
+    }
+
+    #[tokio::test]
+    async fn test_stations_must_share_the_secret() {
+        let mut hub = listen("127.0.0.1:0", "HUB", Some("73 de hub"), Vec::new()).await.unwrap();
+        let addr = hub.local_addr.unwrap().to_string();
+
+        let mut intruder = connect(&addr, "INTRUDER", Some("guess"));
+        let rejected = timeout(Duration::from_secs(5), intruder.recv()).await.unwrap();
+        assert!(matches!(rejected, Some(SyncEvent::Rejected(_))));
+        let mut anonymous = connect(&addr, "ANON", None);
+        let rejected = timeout(Duration::from_secs(5), anonymous.recv()).await.unwrap();
+        assert!(matches!(rejected, Some(SyncEvent::Rejected(_))));
+
+        let mut member = connect(&addr, "MEMBER", Some("73 de hub"));
+        connected(&mut member).await;
+        let entry = qso("1", "W1AW", "FT8");
+        member.publish(SyncOp::upsert(entry.clone()));
+        assert_eq!(next_op(&mut hub).await.op.entry_id(), entry.id);
+    }
+
+    #[tokio::test]
+    async fn test_hub_without_secret_stays_on_loopback() {
+        let open = listen("0.0.0.0:0", "HUB", None, Vec::new()).await;
+        assert!(matches!(open, Err(SyncError::Auth(_))));
+        assert!(listen("0.0.0.0:0", "HUB", Some("secret"), Vec::new()).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_read_message_refuses_long_lines() {
+        let long = vec![b'x'; MAX_MESSAGE_LEN + 10];
+        let mut reader = &long[..];
+        let mut buf = Vec::new();
+        assert!(matches!(read_message(&mut reader, &mut buf).await, Err(SyncError::Protocol(_))));
+
+        let mut reader = &b"{\"type\":\"challenge\",\"nonce\":\"abc\"}\n"[..];
+        let message = read_message(&mut reader, &mut Vec::new()).await.unwrap();
+        assert!(matches!(message, Some(Message::Challenge { ref nonce }) if nonce == "abc"));
+    }
+}
@@ -3,14 +3,16 @@ mod layout;
 
 use ratatui::{
     Frame,
-    layout::{Layout, Direction, Constraint, Rect},
+    layout::{Layout, Direction, Constraint},
     style::{Color, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Clear},
+    widgets::{Block, Borders, Paragraph},
 };
-use crate::app::{App, AppMode};
+use crate::app::{App, AppMode, PromptAction};
 
-use self::views::{draw_form, draw_log_list};
+use self::views::{
+    draw_awards, draw_backups, draw_contest, draw_detail, draw_form, draw_fsck, draw_help, draw_history, draw_log_list, draw_prompt, draw_qsl, draw_reconcile, draw_search, draw_stats,
+};
 pub use layout::centered_rect;
 
 pub fn draw(f: &mut Frame, app: &App) {
@@ -21,11 +23,16 @@ pub fn draw(f: &mut Frame, app: &App) {
             Constraint::Min(1),     // Content
             Constraint::Length(1),  // Status bar
         ])
-        .split(f.size());
+        .split(f.area());
 
     // Draw title
     let version = env!("CARGO_PKG_VERSION");
-    let title = Paragraph::new(format!("amlog v{}", version))
+    let mut title = format!("amlog v{}", version);
+    for summary in [app.sync_summary(), app.activation_summary()].into_iter().flatten() {
+        title.push_str(" | ");
+        title.push_str(&summary);
+    }
+    let title = Paragraph::new(title)
         .block(Block::default().borders(Borders::ALL));
     f.render_widget(title, chunks[0]);
 
@@ -36,6 +43,57 @@ pub fn draw(f: &mut Frame, app: &App) {
         },
         AppMode::Normal => {
             draw_log_list(f, app, chunks[1]);
+        },
+        AppMode::Detail => {
+            draw_log_list(f, app, chunks[1]);
+            if let Some(entry) = app.get_selected_entry() {
+                draw_detail(f, entry, app.callbook_result.as_ref(), &app.revisions, app.revision_cursor, chunks[1]);
+            }
+        },
+        AppMode::Help => {
+            draw_log_list(f, app, chunks[1]);
+            draw_help(f, app, chunks[1]);
+        },
+        AppMode::Stats => {
+            draw_stats(f, app.get_entries(), chunks[1]);
+        },
+        AppMode::Qsl => {
+            draw_qsl(f, app, chunks[1]);
+        },
+        AppMode::Prompt => {
+            match &app.prompt {
+                Some(prompt) if prompt.action.returns_to_contest() => draw_contest(f, app, chunks[1]),
+                Some(prompt) if prompt.action == PromptAction::RestoreBackup => draw_backups(f, app, chunks[1]),
+                Some(prompt) if prompt.action == PromptAction::RepairLog => draw_fsck(f, app, chunks[1]),
+                _ => draw_log_list(f, app, chunks[1]),
+            }
+            if let Some(prompt) = &app.prompt {
+                draw_prompt(f, prompt, chunks[1]);
+            }
+        },
+        AppMode::Awards => {
+            draw_awards(f, app, chunks[1]);
+        },
+        AppMode::Contest => {
+            draw_contest(f, app, chunks[1]);
+        },
+        AppMode::History => {
+            draw_history(f, app, chunks[1]);
+        },
+        AppMode::Backups => {
+            draw_backups(f, app, chunks[1]);
+        },
+        AppMode::Fsck => {
+            draw_fsck(f, app, chunks[1]);
+        },
+        AppMode::Search => {
+            draw_log_list(f, app, chunks[1]);
+            draw_search(f, app, chunks[1]);
+        },
+        AppMode::Reconcile => {
+            if let Some((source, report)) = &app.reconcile_result {
+                draw_reconcile(f, *source, report, chunks[1]);
+            }
         }
     }
 
@@ -51,26 +109,4 @@ pub fn draw(f: &mut Frame, app: &App) {
         ]));
         f.render_widget(status, chunks[2]);
     }
-
-    if !app.deleted_entries.is_empty() {
-        let status = app.status_message.as_ref().map(|(msg, is_error)| {
-            if *is_error {
-                (msg.clone(), true)
-            } else {
-                (format!("{} ({} deletions in buffer)", msg, app.deleted_entries.len()), false)
-            }
-        });
-        
-        if let Some((message, is_error)) = status {
-            let style = if is_error {
-                Style::default().fg(Color::Red)
-            } else {
-                Style::default().fg(Color::Green)
-            };
-            let status = Paragraph::new(Line::from(vec![
-                Span::styled(&message, style)
-            ]));
-            f.render_widget(status, chunks[2]);
-        }
-    }
 }
@@ -0,0 +1,120 @@
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, List, ListItem, Row, Table, Cell},
+};
+use crate::app::App;
+use crate::app::mode::ModeCategory;
+use crate::awards::AwardProgress;
+
+pub fn draw_awards(f: &mut Frame, app: &App, area: Rect) {
+    let Some(progress) = app.current_award() else {
+        let empty = Paragraph::new("No award definitions loaded")
+            .block(Block::default().borders(Borders::ALL).title("Awards"));
+        f.render_widget(empty, area);
+        return;
+    };
+
+    let constraints = if app.award_drill_down {
+        [Constraint::Length(3), Constraint::Percentage(50), Constraint::Min(3)]
+    } else {
+        [Constraint::Length(3), Constraint::Min(3), Constraint::Length(0)]
+    };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints(constraints)
+        .split(area);
+
+    let definition = &progress.definition;
+    let summary = Line::from(vec![
+        Span::styled(&definition.name, Style::default().fg(Color::Yellow)),
+        Span::raw(" | Worked: "),
+        Span::styled(progress.worked(None, None).to_string(), Style::default().fg(Color::Cyan)),
+        Span::raw(" | Confirmed: "),
+        Span::styled(progress.confirmed(None, None).to_string(), Style::default().fg(Color::Green)),
+        Span::raw(format!(" / {}", definition.target)),
+    ]);
+    let summary = Paragraph::new(summary)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Awards (Tab: next award, arrows: select cell, Enter: drill down, Esc: back)"));
+    f.render_widget(summary, chunks[0]);
+
+    draw_matrix(f, app, &progress, chunks[1]);
+
+    if app.award_drill_down {
+        draw_drill_down(f, app, &progress, chunks[2]);
+    }
+}
+
+fn draw_matrix(f: &mut Frame, app: &App, progress: &AwardProgress, area: Rect) {
+    let cell_text = |band: Option<&str>, mode: Option<ModeCategory>| {
+        let worked = progress.worked(band, mode);
+        if worked == 0 {
+            "-".to_string()
+        } else {
+            format!("{}/{}", worked, progress.confirmed(band, mode))
+        }
+    };
+
+    let mut bands: Vec<Option<String>> = progress.bands().into_iter().map(Some).collect();
+    bands.push(None);
+    let mut modes: Vec<Option<ModeCategory>> = ModeCategory::ALL.iter().copied().map(Some).collect();
+    modes.push(None);
+
+    let selected = Style::default().bg(Color::Yellow).fg(Color::Black).add_modifier(Modifier::BOLD);
+    let rows: Vec<Row> = bands.iter().enumerate().map(|(row, band)| {
+        let mut cells = vec![Cell::from(band.clone().unwrap_or_else(|| "All".to_string()))];
+        cells.extend(modes.iter().enumerate().map(|(column, mode)| {
+            let cell = Cell::from(cell_text(band.as_deref(), *mode));
+            if row == app.award_row && column == app.award_column {
+                cell.style(selected)
+            } else {
+                cell
+            }
+        }));
+        Row::new(cells)
+    }).collect();
+
+    let mut header = vec![Cell::from("Band")];
+    header.extend(modes.iter().map(|mode| Cell::from(mode.map_or("All", |m| m.label()))));
+
+    let table = Table::new(rows, [Constraint::Length(8); 5])
+        .header(Row::new(header).style(Style::default().fg(Color::Cyan)))
+        .block(Block::default().borders(Borders::ALL).title("Worked/Confirmed by band and mode"));
+    f.render_widget(table, area);
+}
+
+fn draw_drill_down(f: &mut Frame, app: &App, progress: &AwardProgress, area: Rect) {
+    let (band, mode) = app.award_cell(progress);
+    let values = progress.values(band.as_deref(), mode);
+
+    let items: Vec<ListItem> = values.iter().map(|value| {
+        let qsos: Vec<String> = value.entry_ids.iter()
+            .filter_map(|id| app.find_entry(id))
+            .map(|entry| format!("{} {}", entry.callsign, entry.timestamp.format("%Y-%m-%d")))
+            .collect();
+        let (mark, style) = if value.confirmed {
+            ("✓", Style::default().fg(Color::Green))
+        } else {
+            ("·", Style::default().fg(Color::Gray))
+        };
+        ListItem::new(Line::from(vec![
+            Span::styled(format!("{} {:<10}", mark, value.value), style),
+            Span::raw(qsos.join(", ")),
+        ]))
+    }).collect();
+
+    let title = format!(
+        "{} on {} / {} ({})",
+        progress.definition.name,
+        band.as_deref().unwrap_or("all bands"),
+        mode.map_or("all modes", |m| m.label()),
+        values.len()
+    );
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(list, area);
+}
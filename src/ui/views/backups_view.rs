@@ -0,0 +1,56 @@
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, List, ListItem, ListState},
+};
+use crate::app::App;
+use crate::storage::BackupReason;
+
+pub fn draw_backups(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),     // Backups
+            Constraint::Length(3),  // Key help
+        ])
+        .split(area);
+
+    let items: Vec<ListItem> = app.backups
+        .iter()
+        .map(|backup| {
+            let reason_style = match backup.reason {
+                BackupReason::Manual => Style::default().fg(Color::Green),
+                BackupReason::PreRestore => Style::default().fg(Color::Cyan),
+                BackupReason::Corrupt => Style::default().fg(Color::Red),
+                _ => Style::default(),
+            };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!("{} ", backup.taken_at.format("%Y-%m-%d %H:%M:%S"))),
+                Span::styled(format!("{:<12}", backup.reason.as_str()), reason_style),
+                Span::styled(backup.name(), Style::default().fg(Color::DarkGray)),
+            ]))
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select((!app.backups.is_empty()).then_some(app.backup_cursor));
+
+    let list = List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Backups ({}, times in UTC)", app.backups.len())))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        )
+        .highlight_symbol("➤ ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    let help = Paragraph::new("j/k: move | b: back up now | Enter: restore | Esc: back")
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(help, chunks[1]);
+}
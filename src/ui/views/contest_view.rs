@@ -0,0 +1,200 @@
+use chrono::Utc;
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Row, Table},
+};
+use crate::app::App;
+use crate::contest::{exchange_value, ContestSession};
+
+pub fn draw_contest(f: &mut Frame, app: &App, area: Rect) {
+    let Some(session) = &app.contest else {
+        return;
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3), // Entry line
+            Constraint::Length(4), // Score and rate
+            Constraint::Min(3),    // Recent QSOs
+        ])
+        .split(area);
+
+    draw_entry_line(f, app, chunks[0]);
+    draw_score(f, app, session, chunks[1]);
+    if session.definition.bonuses.is_empty() {
+        draw_recent(f, app, session, chunks[2]);
+    } else {
+        let columns = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Min(40), Constraint::Length(52)])
+            .split(chunks[2]);
+        draw_recent(f, app, session, columns[0]);
+        draw_bonuses(f, session, columns[1]);
+    }
+}
+
+/// Compact single-line form: one small box per field
+fn draw_entry_line(f: &mut Frame, app: &App, area: Rect) {
+    let form = &app.contest_form;
+    let dupe = app.contest_dupe();
+    let unknown = app.contest_unknown_exchange();
+
+    let widths: Vec<Constraint> = form.fields.iter()
+        .map(|field| match field.label.as_str() {
+            "Callsign" => Constraint::Length(16),
+            "Frequency" => Constraint::Length(12),
+            _ => Constraint::Length(field.label.len().max(6) as u16 + 4),
+        })
+        .chain(std::iter::once(Constraint::Min(0)))
+        .collect();
+    let boxes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints(widths)
+        .split(area);
+
+    for (i, field) in form.fields.iter().enumerate() {
+        let is_call = field.label == "Callsign";
+        let unknown = unknown.as_ref().is_some_and(|(label, _)| *label == field.label);
+        let (title, border) = match dupe {
+            Some(earlier) if is_call => (
+                match &earlier.station {
+                    Some(station) => format!("{} DUPE ({} {})", field.label, station, earlier.timestamp.format("%H:%M")),
+                    None => format!("{} DUPE ({})", field.label, earlier.timestamp.format("%H:%M")),
+                },
+                Style::default().fg(Color::Red),
+            ),
+            _ if unknown => (format!("{} ?", field.label), Style::default().fg(Color::Red)),
+            _ if i == form.current_field => (field.label.clone(), Style::default().fg(Color::Yellow)),
+            _ => (field.label.clone(), Style::default()),
+        };
+        let text = Paragraph::new(field.value.as_str())
+            .block(Block::default().borders(Borders::ALL).border_style(border).title(title));
+        f.render_widget(text, boxes[i]);
+    }
+
+    if let Some(field) = form.fields.get(form.current_field) {
+        let area = boxes[form.current_field];
+        f.set_cursor_position((area.x + 1 + field.cursor_position as u16, area.y + 1));
+    }
+}
+
+fn draw_score(f: &mut Frame, app: &App, session: &ContestSession, area: Rect) {
+    let entries = app.get_entries();
+    let score = session.score(entries);
+    let now = Utc::now();
+    let last_10 = session.rate(entries, now, 10);
+    let last_60 = session.rate(entries, now, 60);
+
+    let multipliers: Vec<String> = score.multipliers.iter()
+        .filter(|m| !m.track_only)
+        .map(|m| format!("{} {}", m.name, m.count))
+        .collect();
+    let tracked: Vec<String> = score.multipliers.iter()
+        .filter(|m| m.track_only)
+        .map(|m| match m.possible {
+            Some(possible) => format!("{} {}/{}", m.name, m.count, possible),
+            None => format!("{} {}", m.name, m.count),
+        })
+        .collect();
+    let mut extras = tracked;
+    if let Some(level) = session.power_level() {
+        extras.push(format!("Power x{}", level.factor));
+    }
+    if score.bonus > 0 {
+        extras.push(format!("Bonus {}", score.bonus));
+    }
+
+    let lines = vec![
+        Line::from(vec![
+            Span::raw("QSOs: "),
+            Span::styled(score.qsos.to_string(), Style::default().fg(Color::Yellow)),
+            Span::raw(format!(" (dupes {}) | Points: ", score.dupes)),
+            Span::styled(score.points.to_string(), Style::default().fg(Color::Cyan)),
+            Span::raw(" | Mults: "),
+            Span::styled(score.multiplier_total().to_string(), Style::default().fg(Color::Cyan)),
+            Span::raw(if multipliers.is_empty() { String::new() } else { format!(" ({})", multipliers.join(", ")) }),
+            Span::raw(" | Score: "),
+            Span::styled(score.total().to_string(), Style::default().fg(Color::Green).add_modifier(Modifier::BOLD)),
+        ]),
+        Line::from(vec![
+            Span::raw("Rate: "),
+            Span::styled(format!("{}", last_10), Style::default().fg(Color::Yellow)),
+            Span::raw(format!(" in last 10 min ({}/h) | ", last_10 * 6)),
+            Span::styled(format!("{}", last_60), Style::default().fg(Color::Yellow)),
+            Span::raw(" in last 60 min | Next serial: "),
+            Span::styled(format!("{:03}", session.next_serial()), Style::default().fg(Color::Cyan)),
+            Span::raw(if extras.is_empty() { String::new() } else { format!(" | {}", extras.join(" | ")) }),
+        ]),
+    ];
+
+    let title = format!(
+        "{} (Enter: log, Space/Tab: next field, Ctrl-B: bonus, Ctrl-P: power, Ctrl-W: Cabrillo, Esc: leave, Ctrl-X: end)",
+        session.definition.name
+    );
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).title(title));
+    f.render_widget(paragraph, area);
+}
+
+fn draw_recent(f: &mut Frame, app: &App, session: &ContestSession, area: Rect) {
+    let mut qsos: Vec<_> = session.qsos(app.get_entries()).collect();
+    qsos.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+
+    let exchange = &session.definition.exchange;
+    let rows: Vec<Row> = qsos.iter()
+        .take(area.height.saturating_sub(3) as usize)
+        .map(|entry| {
+            let mut cells = vec![
+                entry.timestamp.format("%H:%M").to_string(),
+                entry.callsign.to_string(),
+                entry.band_name().map(String::from).unwrap_or_default(),
+                entry.mode.to_string(),
+                entry.stx.map(|n| format!("{:03}", n)).unwrap_or_default(),
+                entry.station.clone().unwrap_or_default(),
+            ];
+            cells.extend(exchange.iter().map(|field| exchange_value(entry, &field.field).unwrap_or_default()));
+            Row::new(cells)
+        })
+        .collect();
+
+    let mut header = vec!["UTC".to_string(), "Call".to_string(), "Band".to_string(), "Mode".to_string(), "Sent".to_string(), "Stn".to_string()];
+    header.extend(exchange.iter().map(|field| field.label.clone()));
+    let mut widths = vec![Constraint::Length(6), Constraint::Length(12), Constraint::Length(6), Constraint::Length(6), Constraint::Length(5), Constraint::Length(8)];
+    widths.extend(exchange.iter().map(|_| Constraint::Length(8)));
+
+    let table = Table::new(rows, widths)
+        .header(Row::new(header).style(Style::default().fg(Color::Cyan)))
+        .block(Block::default().borders(Borders::ALL).title(format!("Contest log ({})", qsos.len())));
+    f.render_widget(table, area);
+}
+
+fn draw_bonuses(f: &mut Frame, session: &ContestSession, area: Rect) {
+    let claimed = session.claimed_bonuses();
+    let rows: Vec<Row> = session.definition.bonuses.iter()
+        .map(|bonus| {
+            let count = claimed.iter()
+                .find(|(b, _)| b.id == bonus.id)
+                .map_or(0, |(_, count)| *count);
+            let style = if count > 0 {
+                Style::default().fg(Color::Green)
+            } else {
+                Style::default().fg(Color::Gray)
+            };
+            Row::new(vec![
+                bonus.id.clone(),
+                format!("{}/{}", count, bonus.max_count),
+                (bonus.points * count).to_string(),
+            ])
+            .style(style)
+        })
+        .collect();
+
+    let table = Table::new(rows, [Constraint::Length(22), Constraint::Length(6), Constraint::Length(6)])
+        .header(Row::new(vec!["Bonus", "Claim", "Pts"]).style(Style::default().fg(Color::Cyan)))
+        .block(Block::default().borders(Borders::ALL).title("Bonus points"));
+    f.render_widget(table, area);
+}
@@ -5,10 +5,19 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Clear},
 };
-use chrono::Timelike;
-use crate::app::LogEntry;
+use crate::app::{LogEntry, QslConfirmation, Rst};
+use crate::callbook::CallbookRecord;
+use crate::portable::{pota, sota};
+use crate::storage::{journal, Revision};
 
-pub fn draw_detail(f: &mut Frame, entry: &LogEntry, area: Rect) {
+pub fn draw_detail(
+    f: &mut Frame,
+    entry: &LogEntry,
+    callbook: Option<&CallbookRecord>,
+    revisions: &[Revision],
+    revision_cursor: Option<usize>,
+    area: Rect,
+) {
     let detail_area = super::super::centered_rect(70, 80, area);
     f.render_widget(Clear, detail_area);
 
@@ -19,18 +28,31 @@ pub fn draw_detail(f: &mut Frame, entry: &LogEntry, area: Rect) {
             Constraint::Length(3),  // Basic info
             Constraint::Length(3),  // Signal info
             Constraint::Length(3),  // Time info
-            Constraint::Min(3),     // Notes
+            Constraint::Length(3),  // Station info
+            Constraint::Length(3),  // Propagation and references
+            Constraint::Length(3),  // Contest exchange
+            Constraint::Length(5),  // QSL status
+            Constraint::Length(3),  // Callbook lookup
+            Constraint::Length(3),  // Notes
+            Constraint::Min(4),     // Revisions
         ])
         .split(detail_area);
 
     // Basic QSO Information
     let basic_info = Line::from(vec![
         Span::raw("Callsign: "),
-        Span::styled(&entry.callsign, Style::default().fg(Color::Yellow)),
+        Span::styled(entry.callsign.as_str(), Style::default().fg(Color::Yellow)),
         Span::raw(" | Frequency: "),
-        Span::styled(format!("{:.3} MHz", entry.frequency), Style::default().fg(Color::Yellow)),
+        Span::styled(format!("{} MHz", entry.frequency), Style::default().fg(Color::Yellow)),
         Span::raw(" | Mode: "),
-        Span::styled(&entry.mode, Style::default().fg(Color::Yellow)),
+        Span::styled(entry.mode.as_str(), Style::default().fg(Color::Yellow)),
+        Span::raw(" | Submode: "),
+        Span::styled(entry.submode.as_deref().unwrap_or("---"), Style::default().fg(Color::Yellow)),
+        Span::raw(" | RX: "),
+        Span::styled(
+            entry.freq_rx.map_or("---".to_string(), |f| format!("{} MHz", f)),
+            Style::default().fg(Color::Yellow)
+        ),
     ]);
     let basic = Paragraph::new(basic_info)
         .block(Block::default().borders(Borders::ALL).title("QSO Details"));
@@ -39,13 +61,12 @@ pub fn draw_detail(f: &mut Frame, entry: &LogEntry, area: Rect) {
     // Signal Information
     let signal_info = Line::from(vec![
         Span::raw("RST Sent: "),
-        Span::styled(
-            entry.rst_sent.as_deref().unwrap_or("---"),
-            Style::default().fg(Color::Green)
-        ),
+        Span::styled(report(entry.rst_sent.as_ref()), Style::default().fg(Color::Green)),
         Span::raw(" | RST Received: "),
+        Span::styled(report(entry.rst_received.as_ref()), Style::default().fg(Color::Green)),
+        Span::raw(" | Power: "),
         Span::styled(
-            entry.rst_received.as_deref().unwrap_or("---"),
+            entry.power.map_or("---".to_string(), |p| p.to_string()),
             Style::default().fg(Color::Green)
         ),
     ]);
@@ -65,14 +86,240 @@ pub fn draw_detail(f: &mut Frame, entry: &LogEntry, area: Rect) {
             entry.timestamp.format("%H:%M:%S UTC").to_string(),
             Style::default().fg(Color::Cyan)
         ),
+        Span::raw(" | Time Off: "),
+        Span::styled(
+            entry.time_off.map_or("---".to_string(), |t| t.format("%H:%M:%S UTC").to_string()),
+            Style::default().fg(Color::Cyan)
+        ),
+        Span::raw(" | Created: "),
+        Span::styled(audit_time(entry.created_at), Style::default().fg(Color::Cyan)),
+        Span::raw(" | Updated: "),
+        Span::styled(audit_time(entry.updated_at), Style::default().fg(Color::Cyan)),
     ]);
     let time = Paragraph::new(time_info)
         .block(Block::default().borders(Borders::ALL).title("Time Information"));
     f.render_widget(time, chunks[2]);
 
+    // Station Information
+    let station = Paragraph::new(station_line(
+        entry.name.as_deref(),
+        entry.qth.as_deref(),
+        entry.state.as_deref(),
+        entry.country.as_deref(),
+        entry.grid.as_deref(),
+    ))
+        .block(Block::default().borders(Borders::ALL).title("Station"));
+    f.render_widget(station, chunks[3]);
+
+    // Propagation and Location References
+    let value_style = Style::default().fg(Color::Cyan);
+    let references = Line::from(vec![
+        Span::raw("Prop: "),
+        Span::styled(entry.prop_mode.as_deref().unwrap_or("---"), value_style),
+        Span::raw(" | Sat: "),
+        Span::styled(
+            match (&entry.sat_name, &entry.sat_mode) {
+                (Some(name), Some(mode)) => format!("{} ({})", name, mode),
+                (Some(name), None) => name.clone(),
+                _ => "---".to_string(),
+            },
+            value_style
+        ),
+        Span::raw(" | CQ/ITU: "),
+        Span::styled(
+            format!("{}/{}", optional_number(entry.cq_zone), optional_number(entry.itu_zone)),
+            value_style
+        ),
+        Span::raw(" | Cont: "),
+        Span::styled(entry.cont.as_deref().unwrap_or("---"), value_style),
+        Span::raw(" | County: "),
+        Span::styled(entry.county.as_deref().unwrap_or("---"), value_style),
+        Span::raw(" | IOTA: "),
+        Span::styled(entry.iota.as_deref().unwrap_or("---"), value_style),
+        Span::raw(" | SOTA: "),
+        Span::styled(summits_text(entry), value_style),
+        Span::raw(" | POTA: "),
+        Span::styled(parks_text(entry), value_style),
+    ]);
+    let references = Paragraph::new(references)
+        .block(Block::default().borders(Borders::ALL).title("Propagation & References"));
+    f.render_widget(references, chunks[4]);
+
+    // Contest Exchange
+    let exchange = Line::from(vec![
+        Span::raw("Contest: "),
+        Span::styled(entry.contest_id.as_deref().unwrap_or("---"), value_style),
+        Span::raw(" | Sent #: "),
+        Span::styled(optional_number(entry.stx), value_style),
+        Span::raw(" | Rcvd #: "),
+        Span::styled(optional_number(entry.srx), value_style),
+        Span::raw(" | Logged at: "),
+        Span::styled(entry.station.as_deref().unwrap_or("---"), value_style),
+        Span::raw(" | Operator: "),
+        Span::styled(entry.operator.as_deref().unwrap_or("---"), value_style),
+    ]);
+    let exchange = Paragraph::new(exchange)
+        .block(Block::default().borders(Borders::ALL).title("Contest & Station"));
+    f.render_widget(exchange, chunks[5]);
+
+    // QSL Status
+    let qsl_lines = vec![
+        qsl_line(
+            "Paper",
+            &entry.qsl,
+            match (entry.qsl_sent_via, entry.qsl_rcvd_via) {
+                (None, None) => None,
+                (sent, rcvd) => Some(format!(
+                    " (via {}/{})",
+                    sent.map_or("---", |v| v.label()),
+                    rcvd.map_or("---", |v| v.label())
+                )),
+            },
+        ),
+        qsl_line("LoTW", &entry.lotw, None),
+        qsl_line("eQSL", &entry.eqsl, None),
+    ];
+    let qsl = Paragraph::new(qsl_lines)
+        .block(Block::default().borders(Borders::ALL).title("QSL"));
+    f.render_widget(qsl, chunks[6]);
+
+    // Callbook Lookup
+    let (lookup_line, lookup_title) = match callbook {
+        Some(record) => (
+            station_line(
+                record.name.as_deref(),
+                record.qth.as_deref(),
+                record.state.as_deref(),
+                record.country.as_deref(),
+                record.grid.as_deref(),
+            ),
+            format!("Callbook: {} (a: apply to entry)", record.source),
+        ),
+        None => (
+            Line::from(Span::styled("Press 'l' to look up this callsign", Style::default().fg(Color::Gray))),
+            "Callbook".to_string(),
+        ),
+    };
+    let lookup = Paragraph::new(lookup_line)
+        .block(Block::default().borders(Borders::ALL).title(lookup_title));
+    f.render_widget(lookup, chunks[7]);
+
     // Notes Section
     let notes = Paragraph::new(entry.notes.as_deref().unwrap_or("No notes"))
         .block(Block::default().borders(Borders::ALL).title("Notes"))
         .wrap(ratatui::widgets::Wrap { trim: true });
-    f.render_widget(notes, chunks[3]);
+    f.render_widget(notes, chunks[8]);
+
+    // Revision history: what changed between the selected version and the next
+    let (revision_lines, revision_title) = match revision_cursor.and_then(|i| revisions.get(i).map(|r| (i, r))) {
+        Some((index, revision)) => {
+            let next = revisions.get(index + 1).map_or(entry, |r| &r.entry);
+            let mut lines = vec![Line::from(Span::styled(
+                format!(
+                    "Revision {} of {}, replaced {}",
+                    revision.number,
+                    revisions.len(),
+                    revision.saved_at.format("%Y-%m-%d %H:%M:%S UTC"),
+                ),
+                Style::default().fg(Color::Gray),
+            ))];
+            let changes = journal::diff_entries(Some(&revision.entry), Some(next));
+            if changes.is_empty() {
+                lines.push(Line::from("No field changes"));
+            }
+            for (field, old, new) in changes {
+                lines.push(Line::from(vec![
+                    Span::raw(format!("{}: ", field)),
+                    Span::styled(if old.is_empty() { "---".to_string() } else { old }, Style::default().fg(Color::Red)),
+                    Span::raw(" -> "),
+                    Span::styled(if new.is_empty() { "---".to_string() } else { new }, Style::default().fg(Color::Green)),
+                ]));
+            }
+            (lines, "Revisions ([/]: select, r: restore)".to_string())
+        }
+        None => (
+            vec![Line::from(Span::styled("No earlier versions", Style::default().fg(Color::Gray)))],
+            "Revisions".to_string(),
+        ),
+    };
+    let history = Paragraph::new(revision_lines)
+        .block(Block::default().borders(Borders::ALL).title(revision_title));
+    f.render_widget(history, chunks[9]);
+}
+
+fn audit_time(time: Option<chrono::DateTime<chrono::Utc>>) -> String {
+    time.map_or("---".to_string(), |t| t.format("%Y-%m-%d %H:%M").to_string())
+}
+
+/// Parks worked, and the parks we were in for park-to-park QSOs
+fn parks_text(entry: &LogEntry) -> String {
+    let hunted = pota::hunted_parks(entry).join(",");
+    let activated = pota::activated_parks(entry).join(",");
+    match (hunted.is_empty(), activated.is_empty()) {
+        (true, true) => "---".to_string(),
+        (false, true) => hunted,
+        (true, false) => format!("from {}", activated),
+        (false, false) => format!("{} from {} (P2P)", hunted, activated),
+    }
+}
+
+/// Summit worked, and our own summit for summit-to-summit QSOs
+fn summits_text(entry: &LogEntry) -> String {
+    match (sota::chased_summit(entry), sota::activated_summit(entry)) {
+        (None, None) => "---".to_string(),
+        (Some(theirs), None) => theirs,
+        (None, Some(mine)) => format!("from {}", mine),
+        (Some(theirs), Some(mine)) => format!("{} from {} (S2S)", theirs, mine),
+    }
+}
+
+/// A signal report with what kind of report it is, e.g. `-10 (dB)`
+fn report(rst: Option<&Rst>) -> String {
+    match rst {
+        None => "---".to_string(),
+        Some(Rst::Other(text)) => text.clone(),
+        Some(rst) => format!("{} ({})", rst, rst.kind()),
+    }
+}
+
+fn optional_number(value: Option<u32>) -> String {
+    value.map_or("---".to_string(), |v| v.to_string())
+}
+
+fn qsl_line(service: &str, confirmation: &QslConfirmation, via: Option<String>) -> Line<'static> {
+    let style = Style::default().fg(Color::Green);
+    let status = |status: Option<crate::app::QslStatus>, date: Option<chrono::NaiveDate>| match (status, date) {
+        (Some(status), Some(date)) => format!("{} {}", status.label(), date.format("%Y-%m-%d")),
+        (Some(status), None) => status.label().to_string(),
+        (None, _) => "---".to_string(),
+    };
+    Line::from(vec![
+        Span::raw(format!("{:<6}sent: ", service)),
+        Span::styled(status(confirmation.sent, confirmation.sent_date), style),
+        Span::raw(" | rcvd: "),
+        Span::styled(status(confirmation.received, confirmation.received_date), style),
+        Span::raw(via.unwrap_or_default()),
+    ])
+}
+
+fn station_line<'a>(
+    name: Option<&'a str>,
+    qth: Option<&'a str>,
+    state: Option<&'a str>,
+    country: Option<&'a str>,
+    grid: Option<&'a str>,
+) -> Line<'a> {
+    let style = Style::default().fg(Color::Magenta);
+    Line::from(vec![
+        Span::raw("Name: "),
+        Span::styled(name.unwrap_or("---"), style),
+        Span::raw(" | QTH: "),
+        Span::styled(qth.unwrap_or("---"), style),
+        Span::raw(" | State: "),
+        Span::styled(state.unwrap_or("---"), style),
+        Span::raw(" | Country: "),
+        Span::styled(country.unwrap_or("---"), style),
+        Span::raw(" | Grid: "),
+        Span::styled(grid.unwrap_or("---"), style),
+    ])
 }
\ No newline at end of file
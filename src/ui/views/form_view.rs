@@ -17,6 +17,9 @@ pub fn draw_form(f: &mut Frame, app: &App, area: Rect) {
         _ => "Log Entry Form",
     };
 
+    // Labels of custom fields can be longer than the usual ones
+    let width = app.form.fields.iter().map(|field| field.label.len()).max().unwrap_or(0).max(12);
+
     // Create form lines with current field highlighted
     let lines: Vec<Line> = app.form.fields.iter().enumerate()
         .map(|(i, field)| {
@@ -27,7 +30,7 @@ pub fn draw_form(f: &mut Frame, app: &App, area: Rect) {
             };
             
             Line::from(vec![
-                Span::styled(format!("{:<12}", field.label), style),
+                Span::styled(format!("{:<width$}", field.label), style),
                 Span::raw(": "),
                 Span::styled(&field.value, style),
             ])
@@ -42,8 +45,8 @@ pub fn draw_form(f: &mut Frame, app: &App, area: Rect) {
 
     // Draw cursor at current field position
     if let Some(field) = app.form.fields.get(app.form.current_field) {
-        let x = 14 + field.cursor_position as u16;
+        let x = (width + 2 + field.cursor_position) as u16;
         let y = app.form.current_field as u16 + 1;
-        f.set_cursor(form_area.x + x, form_area.y + y);
+        f.set_cursor_position((form_area.x + x, form_area.y + y));
     }
 }
\ No newline at end of file
@@ -0,0 +1,56 @@
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, List, ListItem, ListState},
+};
+use crate::app::App;
+
+pub fn draw_fsck(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),     // Problems
+            Constraint::Length(3),  // Key help
+        ])
+        .split(area);
+
+    let problems = app.fsck_report.as_ref().map_or(&[][..], |report| &report.problems);
+    let items: Vec<ListItem> = problems
+        .iter()
+        .map(|problem| {
+            let (fix, fix_style) = match &problem.fix {
+                Some(fix) => (fix.as_str(), Style::default().fg(Color::Green)),
+                None if problem.kind.is_warning() => ("left as is", Style::default().fg(Color::Yellow)),
+                None => ("fix by hand", Style::default().fg(Color::Red)),
+            };
+            ListItem::new(Line::from(vec![
+                Span::styled(format!("{:<20}", problem.kind.as_str()), Style::default().fg(Color::Cyan)),
+                Span::raw(format!("{}: {} ", problem.location, problem.detail)),
+                Span::styled(format!("[{}]", fix), fix_style),
+            ]))
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select((!problems.is_empty()).then_some(app.fsck_cursor));
+
+    let title = app.fsck_report.as_ref().map_or("Integrity check".to_string(), |report| {
+        format!("Integrity check ({})", report.summary())
+    });
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        )
+        .highlight_symbol("➤ ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    let help = Paragraph::new("j/k: move | f: fix what can be fixed | Esc: back")
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(help, chunks[1]);
+}
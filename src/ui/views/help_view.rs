@@ -53,9 +53,61 @@ pub fn draw_help(f: &mut Frame, _app: &App, area: Rect) {
             Span::raw("e      - "),
             Span::styled("Edit selected entry", Style::default().fg(Color::Yellow)),
         ]),
+        Line::from(vec![
+            Span::raw("v      - "),
+            Span::styled("View entry details (l: callbook lookup, a: apply, [/]: revisions, r: restore)", Style::default().fg(Color::Yellow)),
+        ]),
         Line::from(vec![
             Span::raw("d      - "),
-            Span::styled("View entry details", Style::default().fg(Color::Yellow)),
+            Span::styled("Delete selected entry", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("u      - "),
+            Span::styled("Undo last change (add, edit or delete)", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("C-r    - "),
+            Span::styled("Redo", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("H      - "),
+            Span::styled("History of changes", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("B      - "),
+            Span::styled("Backups (b: back up now, Enter: restore)", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("F      - "),
+            Span::styled("Check the log for damage and bad values (f: fix)", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("/      - "),
+            Span::styled("Search notes, names, QTH and custom fields (Enter: view)", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("s      - "),
+            Span::styled("Toggle statistics", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("A      - "),
+            Span::styled("Award progress (DXCC, WAS, WAZ, VUCC, VUCC Satellite, WPX)", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("C      - "),
+            Span::styled("Contest mode, e.g. ARRL-FIELD-DAY or WFD (start or resume)", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("P      - "),
+            Span::styled("Start or end a POTA/SOTA activation", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("U      - "),
+            Span::styled("Write POTA upload files and SOTA CSV v2 files", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("Q      - "),
+            Span::styled("QSL manager (mark sent, queue, export)", Style::default().fg(Color::Yellow)),
         ]),
         Line::from(""),
         Line::from(vec![
@@ -74,6 +126,14 @@ pub fn draw_help(f: &mut Frame, _app: &App, area: Rect) {
             Span::raw("Enter  - "),
             Span::styled("Save entry", Style::default().fg(Color::Yellow)),
         ]),
+        Line::from(vec![
+            Span::raw("C-l    - "),
+            Span::styled("Look up callsign and fill station fields", Style::default().fg(Color::Yellow)),
+        ]),
+        Line::from(vec![
+            Span::raw("C-t    - "),
+            Span::styled("Satellite preset (cycles the catalogue)", Style::default().fg(Color::Yellow)),
+        ]),
         Line::from(vec![
             Span::raw("Esc    - "),
             Span::styled("Cancel/Return", Style::default().fg(Color::Yellow)),
@@ -91,6 +151,10 @@ pub fn draw_help(f: &mut Frame, _app: &App, area: Rect) {
             Span::raw("x      - "),
             Span::styled("Export to ADIF", Style::default().fg(Color::Yellow)),
         ]),
+        Line::from(vec![
+            Span::raw("R      - "),
+            Span::styled("Reconcile a LoTW/eQSL confirmation report", Style::default().fg(Color::Yellow)),
+        ]),
     ];
 
     let help_content = Paragraph::new(help_text)
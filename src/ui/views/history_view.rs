@@ -0,0 +1,69 @@
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, List, ListItem, ListState},
+};
+use crate::app::App;
+use crate::storage::JournalOp;
+
+pub fn draw_history(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Min(3),     // Journal
+            Constraint::Length(3),  // Key help
+        ])
+        .split(area);
+
+    let items: Vec<ListItem> = app.history
+        .iter()
+        .map(|record| {
+            let op_style = match record.op {
+                JournalOp::Create => Style::default().fg(Color::Green),
+                JournalOp::Update => Style::default().fg(Color::Cyan),
+                JournalOp::Delete => Style::default().fg(Color::Red),
+            };
+            let changes = match record.op {
+                JournalOp::Update => record.changed_fields().join(", "),
+                _ => String::new(),
+            };
+            let line = Line::from(vec![
+                Span::raw(format!(
+                    "{:>5} {} ",
+                    record.seq,
+                    record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                )),
+                Span::styled(format!("{:<7}", record.op.as_str()), op_style),
+                Span::raw(format!("{:<10} {}", record.callsign(), changes)),
+            ]);
+            if record.undone {
+                ListItem::new(line).style(Style::default().fg(Color::DarkGray).add_modifier(Modifier::CROSSED_OUT))
+            } else {
+                ListItem::new(line)
+            }
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select((!app.history.is_empty()).then_some(app.history_cursor));
+
+    let undone = app.history.iter().filter(|r| r.undone).count();
+    let list = List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!("History ({} changes, {} undone)", app.history.len(), undone)))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        )
+        .highlight_symbol("➤ ");
+    f.render_stateful_widget(list, chunks[0], &mut list_state);
+
+    let help = Paragraph::new("j/k: move | u: undo | C-r: redo | Enter: show QSO | Esc: back")
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(help, chunks[1]);
+}
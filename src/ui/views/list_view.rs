@@ -5,22 +5,26 @@ use ratatui::{
     text::{Line, Span},
     widgets::{Block, Borders, List, ListItem, ListState},
 };
-use crate::app::App;
+use crate::app::{App, Rst};
 
 pub fn draw_log_list(f: &mut Frame, app: &App, area: Rect) {
     let items: Vec<ListItem> = app.get_entries()
         .iter()
         .map(|entry| {
-            ListItem::new(Line::from(vec![
+            let mut spans = vec![
                 Span::raw(format!(
-                    "{} - {} on {:.3}MHz {} RST: {}",
+                    "{} - {} on {}MHz {} RST: {}",
                     entry.timestamp.format("%Y-%m-%d %H:%M"),
                     entry.callsign,
                     entry.frequency,
                     entry.mode,
-                    entry.rst_sent.as_deref().unwrap_or("---")
+                    entry.rst_sent.as_ref().map_or("---".to_string(), Rst::to_string)
                 ))
-            ]))
+            ];
+            if let Some(station) = &entry.station {
+                spans.push(Span::styled(format!(" [{}]", station), Style::default().fg(Color::Gray)));
+            }
+            ListItem::new(Line::from(spans))
         })
         .collect();
 
@@ -3,14 +3,28 @@ mod list_view;
 mod detail_view;
 mod stats_view;
 mod help_view;
+mod qsl_view;
+mod awards_view;
+mod contest_view;
+mod history_view;
+mod backups_view;
+mod fsck_view;
+mod prompt_view;
+mod reconcile_view;
 mod search_view;
 
 // Only export what we're currently using
 pub(super) use form_view::draw_form;
 pub(super) use list_view::draw_log_list;
-
-// Keep these private until they're implemented
 pub(crate) use detail_view::draw_detail;
 pub(crate) use stats_view::draw_stats;
 pub(crate) use help_view::draw_help;
+pub(crate) use qsl_view::draw_qsl;
+pub(crate) use awards_view::draw_awards;
+pub(crate) use contest_view::draw_contest;
+pub(crate) use history_view::draw_history;
+pub(crate) use backups_view::draw_backups;
+pub(crate) use fsck_view::draw_fsck;
+pub(crate) use prompt_view::draw_prompt;
+pub(crate) use reconcile_view::draw_reconcile;
 pub(crate) use search_view::draw_search;
\ No newline at end of file
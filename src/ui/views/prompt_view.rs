@@ -0,0 +1,27 @@
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, Clear},
+};
+use crate::app::Prompt;
+
+pub fn draw_prompt(f: &mut Frame, prompt: &Prompt, area: Rect) {
+    let prompt_area = super::super::centered_rect(60, 20, area);
+    let prompt_area = Rect {
+        height: prompt_area.height.min(3),
+        ..prompt_area
+    };
+    f.render_widget(Clear, prompt_area);
+
+    let line = Line::from(vec![
+        Span::styled(&prompt.input, Style::default().fg(Color::Yellow)),
+        Span::styled("_", Style::default().fg(Color::Gray)),
+    ]);
+    let paragraph = Paragraph::new(line)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!("{} (Enter to confirm, Esc to cancel)", prompt.label)));
+    f.render_widget(paragraph, prompt_area);
+}
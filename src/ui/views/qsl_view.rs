@@ -0,0 +1,89 @@
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style, Modifier},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, List, ListItem, ListState},
+};
+use crate::app::{App, QslConfirmation};
+
+pub fn draw_qsl(f: &mut Frame, app: &App, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Filter and marking options
+            Constraint::Min(3),     // QSO list
+            Constraint::Length(3),  // Key help
+        ])
+        .split(area);
+
+    let visible = app.qsl_visible();
+
+    let header = Line::from(vec![
+        Span::raw("Filter: "),
+        Span::styled(app.qsl_filter.label(), Style::default().fg(Color::Yellow)),
+        Span::raw(" | Date: "),
+        Span::styled(app.qsl_date.format("%Y-%m-%d").to_string(), Style::default().fg(Color::Cyan)),
+        Span::raw(" | Via: "),
+        Span::styled(app.qsl_via.label(), Style::default().fg(Color::Cyan)),
+        Span::raw(" | Selected: "),
+        Span::styled(app.qsl_selected.len().to_string(), Style::default().fg(Color::Green)),
+    ]);
+    let header = Paragraph::new(header)
+        .block(Block::default().borders(Borders::ALL).title("QSL Manager"));
+    f.render_widget(header, chunks[0]);
+
+    let entries = app.get_entries();
+    let items: Vec<ListItem> = visible
+        .iter()
+        .map(|idx| {
+            let entry = &entries[*idx];
+            let marker = if app.qsl_selected.contains(&entry.id) { "[x]" } else { "[ ]" };
+            ListItem::new(Line::from(vec![
+                Span::raw(format!(
+                    "{} {} {:<10} {:>9.3} {:<6}",
+                    marker,
+                    entry.timestamp.format("%Y-%m-%d %H:%M"),
+                    entry.callsign,
+                    entry.frequency,
+                    entry.mode,
+                )),
+                Span::styled(format!(" Paper {}", status_pair(&entry.qsl)), Style::default().fg(Color::Green)),
+                Span::raw(entry.qsl_sent_via.map_or(String::new(), |v| format!(" ({})", v.label()))),
+                Span::styled(format!(" LoTW {}", status_pair(&entry.lotw)), Style::default().fg(Color::Cyan)),
+                Span::styled(format!(" eQSL {}", status_pair(&entry.eqsl)), Style::default().fg(Color::Magenta)),
+            ]))
+        })
+        .collect();
+
+    let mut list_state = ListState::default();
+    list_state.select(app.qsl_cursor());
+
+    let list = List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title(format!("QSOs ({})", visible.len())))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD)
+        )
+        .highlight_symbol("➤ ");
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
+
+    let help = Paragraph::new(
+        "Space: select | a: select all | f: filter | v: via | +/-: date | m: mark sent | Q: queue | x: export queue | Esc: back"
+    )
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(help, chunks[2]);
+}
+
+/// Compact sent/received indicator, e.g. `Y/-`
+fn status_pair(confirmation: &QslConfirmation) -> String {
+    format!(
+        "{}/{}",
+        confirmation.sent.map_or("-", |s| s.as_adif()),
+        confirmation.received.map_or("-", |s| s.as_adif()),
+    )
+}
@@ -0,0 +1,53 @@
+use ratatui::{
+    Frame,
+    layout::{Layout, Direction, Constraint, Rect},
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::{Block, Borders, Paragraph, List, ListItem},
+};
+use crate::storage::reconcile::{ConfirmationSource, ReconcileReport};
+
+pub fn draw_reconcile(f: &mut Frame, source: ConfirmationSource, report: &ReconcileReport, area: Rect) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),  // Summary
+            Constraint::Min(3),     // Unmatched confirmations
+        ])
+        .split(area);
+
+    let summary = Line::from(vec![
+        Span::raw("Newly confirmed: "),
+        Span::styled(report.confirmed.len().to_string(), Style::default().fg(Color::Green)),
+        Span::raw(" | Already confirmed: "),
+        Span::styled(report.already_confirmed.to_string(), Style::default().fg(Color::Cyan)),
+        Span::raw(" | Unmatched: "),
+        Span::styled(report.unmatched.len().to_string(), Style::default().fg(Color::Red)),
+        Span::raw(" | Not confirmations: "),
+        Span::styled(report.skipped.to_string(), Style::default().fg(Color::Gray)),
+    ]);
+    let summary = Paragraph::new(summary)
+        .block(Block::default().borders(Borders::ALL).title(format!("{} Reconciliation", source.label())));
+    f.render_widget(summary, chunks[0]);
+
+    let items: Vec<ListItem> = report.unmatched.iter().map(|record| {
+        ListItem::new(Line::from(vec![
+            Span::styled(
+                record.timestamp.format("%Y-%m-%d %H:%M").to_string(),
+                Style::default().fg(Color::Gray)
+            ),
+            Span::raw(" | "),
+            Span::styled(&record.callsign, Style::default().fg(Color::Yellow)),
+            Span::raw(" | "),
+            Span::styled(record.band.as_deref().unwrap_or("---"), Style::default().fg(Color::Cyan)),
+            Span::raw(" | "),
+            Span::styled(record.submode.as_deref().unwrap_or(&record.mode), Style::default().fg(Color::Green)),
+        ]))
+    }).collect();
+
+    let list = List::new(items)
+        .block(Block::default()
+            .borders(Borders::ALL)
+            .title("Unmatched confirmations - review manually (Esc: back)"));
+    f.render_widget(list, chunks[1]);
+}
@@ -1,13 +1,19 @@
 use ratatui::{
     Frame,
     layout::{Layout, Direction, Constraint, Rect},
-    style::{Color, Style},
+    style::{Color, Style, Modifier},
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, List, ListItem, Clear},
+    widgets::{Block, Borders, Paragraph, List, ListItem, ListState, Clear},
 };
-use crate::app::{App, LogEntry};
+use crate::app::App;
+use crate::storage::search::FieldMatch;
 
-pub fn draw_search(f: &mut Frame, app: &App, search_text: &str, results: &[LogEntry], area: Rect) {
+/// Characters of context shown before the first match in a field
+const CONTEXT: usize = 20;
+/// Characters shown of each matching field
+const SNIPPET: usize = 60;
+
+pub fn draw_search(f: &mut Frame, app: &App, area: Rect) {
     let search_area = super::super::centered_rect(70, 80, area);
     f.render_widget(Clear, search_area);
 
@@ -22,35 +28,73 @@ pub fn draw_search(f: &mut Frame, app: &App, search_text: &str, results: &[LogEn
     // Search input
     let search_line = Line::from(vec![
         Span::raw("🔍 "),
-        Span::styled(search_text, Style::default().fg(Color::Yellow)),
+        Span::styled(&app.search_query, Style::default().fg(Color::Yellow)),
         Span::styled("_", Style::default().fg(Color::Gray)),
     ]);
-    
+
     let search_input = Paragraph::new(search_line)
         .block(Block::default()
             .borders(Borders::ALL)
-            .title("Search (Callsign, Mode, or Frequency)"));
+            .title("Search notes, name, QTH and custom fields (\"phrase\", prefix*)"));
     f.render_widget(search_input, chunks[0]);
 
-    // Results
-    let items: Vec<ListItem> = results.iter().map(|entry| {
-        ListItem::new(Line::from(vec![
+    // Results, best match first, with the matching words picked out
+    let items: Vec<ListItem> = app.search_results.iter().map(|hit| {
+        let entry = &hit.entry;
+        let mut lines = vec![Line::from(vec![
             Span::styled(
                 entry.timestamp.format("%Y-%m-%d %H:%M").to_string(),
                 Style::default().fg(Color::Gray)
             ),
             Span::raw(" | "),
-            Span::styled(&entry.callsign, Style::default().fg(Color::Yellow)),
+            Span::styled(entry.callsign.as_str(), Style::default().fg(Color::Yellow)),
             Span::raw(" | "),
-            Span::styled(format!("{:.3}MHz", entry.frequency), Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{}MHz", entry.frequency), Style::default().fg(Color::Cyan)),
             Span::raw(" | "),
-            Span::styled(&entry.mode, Style::default().fg(Color::Green)),
-        ]))
+            Span::styled(entry.mode.as_str(), Style::default().fg(Color::Green)),
+        ])];
+        lines.extend(hit.matches.iter().map(|field| Line::from(snippet(field))));
+        ListItem::new(lines)
     }).collect();
 
+    let mut list_state = ListState::default();
+    list_state.select((!app.search_results.is_empty()).then_some(app.search_cursor));
+
     let results_list = List::new(items)
         .block(Block::default()
             .borders(Borders::ALL)
-            .title(format!("Results ({})", results.len())));
-    f.render_widget(results_list, chunks[1]);
-}
\ No newline at end of file
+            .title(format!("Results ({}) | Up/Down: move | Enter: view | Esc: back", app.search_results.len())))
+        .highlight_style(Style::default().add_modifier(Modifier::BOLD))
+        .highlight_symbol("➤ ");
+    f.render_stateful_widget(results_list, chunks[1], &mut list_state);
+}
+
+/// Part of a field around its first match, with every match highlighted
+fn snippet(field: &FieldMatch) -> Vec<Span<'_>> {
+    let text = &field.text;
+    let highlight = Style::default().bg(Color::Yellow).fg(Color::Black).add_modifier(Modifier::BOLD);
+    let first = field.ranges.first().map_or(0, |range| range.start);
+    // Cut on character boundaries; the ranges always fall on them
+    let start = text[..first].char_indices().rev().nth(CONTEXT - 1).map_or(0, |(i, _)| i);
+    let end = text[start..].char_indices().nth(SNIPPET).map_or(text.len(), |(i, _)| start + i);
+
+    let mut spans = vec![Span::styled(format!("    {}: ", field.field), Style::default().fg(Color::DarkGray))];
+    if start > 0 {
+        spans.push(Span::raw("…"));
+    }
+    let mut pos = start;
+    for range in &field.ranges {
+        let (from, to) = (range.start.max(pos), range.end.min(end));
+        if from >= to {
+            continue;
+        }
+        spans.push(Span::raw(&text[pos..from]));
+        spans.push(Span::styled(&text[from..to], highlight));
+        pos = to;
+    }
+    spans.push(Span::raw(&text[pos..end]));
+    if end < text.len() {
+        spans.push(Span::raw("…"));
+    }
+    spans
+}
@@ -50,7 +50,7 @@ fn draw_summary_stats(f: &mut Frame, entries: &[LogEntry], area: Rect) {
 fn draw_mode_chart(f: &mut Frame, entries: &[LogEntry], area: Rect) {
     let mut mode_counts: HashMap<String, u64> = HashMap::new();
     for entry in entries {
-        *mode_counts.entry(entry.mode.clone()).or_default() += 1;
+        *mode_counts.entry(entry.mode.to_string()).or_default() += 1;
     }
 
     let mut mode_data: Vec<(&str, u64)> = mode_counts
@@ -74,18 +74,8 @@ fn draw_band_chart(f: &mut Frame, entries: &[LogEntry], area: Rect) {
     let mut band_counts: HashMap<String, u64> = HashMap::new();
     
     for entry in entries {
-        let band = match entry.frequency {
-            f if f >= 1.8 && f <= 2.0 => "160m",
-            f if f >= 3.5 && f <= 4.0 => "80m",
-            f if f >= 7.0 && f <= 7.3 => "40m",
-            f if f >= 14.0 && f <= 14.35 => "20m",
-            f if f >= 21.0 && f <= 21.45 => "15m",
-            f if f >= 28.0 && f <= 29.7 => "10m",
-            f if f >= 50.0 && f <= 54.0 => "6m",
-            f if f >= 144.0 && f <= 148.0 => "2m",
-            _ => "Other",
-        };
-        *band_counts.entry(band.to_string()).or_default() += 1;
+        let band = entry.band_name().map_or_else(|| "Other".to_string(), String::from);
+        *band_counts.entry(band).or_default() += 1;
     }
 
     let mut band_data: Vec<(&str, u64)> = band_counts